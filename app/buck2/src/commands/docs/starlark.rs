@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+mod html;
 mod markdown;
 
 use async_trait::async_trait;
@@ -23,6 +24,8 @@ use dupe::Dupe;
 use gazebo::prelude::*;
 use starlark::docs::Doc;
 
+use crate::commands::docs::starlark::html::generate_html_site;
+use crate::commands::docs::starlark::html::HtmlSiteOptions;
 use crate::commands::docs::starlark::markdown::generate_markdown_files;
 use crate::commands::docs::starlark::markdown::MarkdownFileOptions;
 
@@ -31,6 +34,7 @@ use crate::commands::docs::starlark::markdown::MarkdownFileOptions;
 enum DocsOutputFormatArg {
     Json,
     MarkdownFiles,
+    HtmlSite,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -51,6 +55,9 @@ pub(crate) struct DocsStarlarkCommand {
     #[clap(flatten)]
     markdown_file_opts: MarkdownFileOptions,
 
+    #[clap(flatten)]
+    html_site_opts: HtmlSiteOptions,
+
     #[clap(
         long = "format",
         help = "how to format the returned documentation",
@@ -115,6 +122,9 @@ impl StreamingCommand for DocsStarlarkCommand {
             DocsOutputFormatArg::MarkdownFiles => {
                 generate_markdown_files(&self.markdown_file_opts, docs)?;
             }
+            DocsOutputFormatArg::HtmlSite => {
+                generate_html_site(&self.html_site_opts, docs)?;
+            }
         }
 
         ExitResult::success()
@@ -0,0 +1,607 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Render docs as a static HTML site, with cross links between symbols and a search index.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use buck2_core::fs::fs_util;
+use itertools::Itertools;
+use pulldown_cmark::Event;
+use pulldown_cmark::Options;
+use pulldown_cmark::Parser;
+use pulldown_cmark::Tag;
+use starlark::docs::Doc;
+use starlark::docs::DocItem;
+use starlark::docs::MarkdownFlavor;
+use starlark::docs::RenderMarkdown;
+
+use crate::commands::docs::starlark::markdown::item_ordering;
+use crate::commands::docs::starlark::markdown::output_path_for_doc;
+
+const SEARCH_JS: &str = include_str!("html/search.js");
+const STYLE_CSS: &str = include_str!("html/style.css");
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+pub(crate) struct HtmlSiteOptions {
+    /// Directory to write the HTML documentation site to
+    #[structopt(long = "--html-destination-dir", required_if_eq("format", "html_site"))]
+    destination_dir: Option<PathBuf>,
+    /// Title of the HTML documentation site
+    #[structopt(long = "--html-title", default_value = "Starlark API")]
+    title: String,
+}
+
+/// A heading within a page. Every heading gets an anchor, and is an entry in the search index.
+#[derive(Debug)]
+struct Heading {
+    /// The symbol name in the heading, without any type annotation.
+    name: String,
+    /// The anchor of the heading, unique within the page.
+    slug: String,
+}
+
+/// A single HTML page, containing all the docs which would be in the same markdown file.
+struct Page {
+    /// Path relative to the root of the site, separated with `/`.
+    path: String,
+    title: String,
+    /// Names of the top level symbols in this page.
+    symbols: Vec<String>,
+    markdown: String,
+    headings: Vec<Heading>,
+}
+
+#[derive(serde::Serialize)]
+struct SearchEntry<'a> {
+    name: &'a str,
+    page: &'a str,
+    url: String,
+}
+
+/// Every file in the site, keyed by its path relative to the root of the site.
+struct HtmlSite {
+    files: BTreeMap<String, String>,
+}
+
+impl HtmlSite {
+    fn new(title: &str, docs: Vec<Doc>) -> anyhow::Result<HtmlSite> {
+        let mut pages: BTreeMap<String, Vec<Doc>> = BTreeMap::new();
+        for doc in docs.into_iter().sorted_by(item_ordering) {
+            let path =
+                output_path_for_doc(Path::new("native"), Path::new("starlark"), &doc, "html")?;
+            let path = path.iter().map(|c| c.to_string_lossy()).join("/");
+            pages.entry(path).or_default().push(doc);
+        }
+
+        let pages: Vec<Page> = pages
+            .into_iter()
+            .map(|(path, docs)| Page::new(path, docs))
+            .collect();
+
+        // Symbols which are linked to from code. The first page defining a symbol wins.
+        let mut links = HashMap::new();
+        for page in &pages {
+            for symbol in &page.symbols {
+                if let Some(heading) = page.headings.iter().find(|h| &h.name == symbol) {
+                    links
+                        .entry(symbol.clone())
+                        .or_insert_with(|| format!("{}#{}", url_path(&page.path), heading.slug));
+                }
+            }
+        }
+
+        let mut files = BTreeMap::new();
+        for page in &pages {
+            let content = markdown_to_html(&page.markdown, &page.headings, &links, &page.path);
+            files.insert(
+                page.path.clone(),
+                html_document(title, &page.title, &page.path, &content),
+            );
+        }
+        files.insert("index.html".to_owned(), index_html(title, &pages));
+        files.insert("search-index.js".to_owned(), search_index_js(&pages)?);
+        files.insert("search.js".to_owned(), SEARCH_JS.to_owned());
+        files.insert("style.css".to_owned(), STYLE_CSS.to_owned());
+        Ok(HtmlSite { files })
+    }
+}
+
+impl Page {
+    fn new(path: String, docs: Vec<Doc>) -> Page {
+        let title = match docs.first().and_then(|d| d.id.location.as_ref()) {
+            Some(location) => location.path.clone(),
+            None if docs.len() == 1 => docs[0].id.name.clone(),
+            None => path.trim_end_matches(".html").to_owned(),
+        };
+        let symbols = docs
+            .iter()
+            .filter(|d| !matches!(d.item, DocItem::Module(_)))
+            .map(|d| d.id.name.clone())
+            .collect();
+        // Same as the markdown files, so both outputs read the same.
+        let markdown = docs
+            .iter()
+            .filter_map(|d| d.render_markdown_opt(MarkdownFlavor::DocFile))
+            .join("\n\n---\n");
+        let headings = headings(&markdown);
+        Page {
+            path,
+            title,
+            symbols,
+            markdown,
+            headings,
+        }
+    }
+}
+
+fn parser(markdown: &str) -> Parser<'_, '_> {
+    Parser::new_ext(markdown, Options::ENABLE_TABLES)
+}
+
+/// Find all the headings in the markdown, in order, giving each a unique anchor.
+fn headings(markdown: &str) -> Vec<Heading> {
+    let mut res = Vec::new();
+    let mut used = HashSet::new();
+    let mut current: Option<String> = None;
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::Heading(..)) => current = Some(String::new()),
+            Event::Text(s) | Event::Code(s) => {
+                if let Some(current) = &mut current {
+                    current.push_str(&s);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                let text = current.take().unwrap_or_default();
+                // Headings for properties look like `name : type`.
+                let name = text
+                    .split(" : ")
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_owned();
+                let slug = unique_slug(&name, &mut used);
+                res.push(Heading { name, slug });
+            }
+            _ => {}
+        }
+    }
+    res
+}
+
+fn unique_slug(name: &str, used: &mut HashSet<String>) -> String {
+    let base: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let mut slug = base.clone();
+    let mut i = 1;
+    while !used.insert(slug.clone()) {
+        slug = format!("{}-{}", base, i);
+        i += 1;
+    }
+    slug
+}
+
+/// Relative path from a page to the root of the site.
+fn root_prefix(page_path: &str) -> String {
+    "../".repeat(page_path.matches('/').count())
+}
+
+/// Percent-encode a path relative to the root of the site, so it can be used in a URL.
+/// Paths come from symbol and file names, which may contain any character.
+fn url_path(path: &str) -> String {
+    let mut res = String::new();
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'/' | b'-' | b'_' | b'.' | b'~') {
+            res.push(b as char);
+        } else {
+            write!(res, "%{:02X}", b).unwrap();
+        }
+    }
+    res
+}
+
+/// Whether a link in a docstring is safe to render: relative, or to a web page.
+/// Anything else, e.g. `javascript:`, could run code when the docs are viewed.
+fn is_safe_link(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => matches!(
+            scheme.to_ascii_lowercase().as_str(),
+            "http" | "https" | "mailto"
+        ),
+        _ => true,
+    }
+}
+
+fn escape_html(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Escape code, linking any known symbols to where they are documented.
+///
+/// Dotted names link their longest known prefix, so `RunInfo.type` links to `RunInfo`.
+fn linkify_code(code: &str, links: &HashMap<String, String>, root: &str) -> String {
+    fn is_ident(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
+
+    let mut out = String::new();
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        if !(c.is_ascii_alphabetic() || c == '_') {
+            escape_html(&rest[..c.len_utf8()], &mut out);
+            rest = &rest[c.len_utf8()..];
+            // Don't link the middle of an identifier, e.g. `x2foo`.
+            if is_ident(c) {
+                let len = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+                out.push_str(&rest[..len]);
+                rest = &rest[len..];
+            }
+            continue;
+        }
+        let len = rest
+            .find(|c| !is_ident(c) && c != '.')
+            .unwrap_or(rest.len());
+        let token = rest[..len].trim_end_matches('.');
+        let linked = token
+            .match_indices('.')
+            .map(|(i, _)| &token[..i])
+            .chain([token])
+            .rev()
+            .find_map(|prefix| links.get(prefix).map(|url| (prefix, url)));
+        let unlinked = match linked {
+            Some((prefix, url)) => {
+                write!(out, "<a href=\"{}{}\">", root, url).unwrap();
+                escape_html(prefix, &mut out);
+                out.push_str("</a>");
+                &token[prefix.len()..]
+            }
+            None => token,
+        };
+        escape_html(unlinked, &mut out);
+        rest = &rest[token.len()..];
+    }
+    out
+}
+
+/// Convert the markdown for a page to HTML. Headings get the anchors from `headings`
+/// (which must have been computed from the same markdown), and code links to known symbols.
+/// Docstrings are not trusted: raw HTML in them is escaped, and unsafe links are dropped.
+fn markdown_to_html(
+    markdown: &str,
+    headings: &[Heading],
+    links: &HashMap<String, String>,
+    page_path: &str,
+) -> String {
+    let root = root_prefix(page_path);
+    let mut headings = headings.iter();
+    let mut in_code_block = false;
+    let events = parser(markdown).map(|event| match event {
+        Event::Start(Tag::Heading(level, ..)) => {
+            let slug = headings.next().map_or("", |h| h.slug.as_str());
+            Event::Html(format!("<h{} id=\"{}\">", level as usize, slug).into())
+        }
+        Event::End(Tag::Heading(level, ..)) => {
+            Event::Html(format!("</h{}>\n", level as usize).into())
+        }
+        Event::Start(Tag::CodeBlock(kind)) => {
+            in_code_block = true;
+            Event::Start(Tag::CodeBlock(kind))
+        }
+        Event::End(Tag::CodeBlock(kind)) => {
+            in_code_block = false;
+            Event::End(Tag::CodeBlock(kind))
+        }
+        Event::Text(s) if in_code_block => Event::Html(linkify_code(&s, links, &root).into()),
+        Event::Code(s) => {
+            Event::Html(format!("<code>{}</code>", linkify_code(&s, links, &root)).into())
+        }
+        Event::Html(s) => Event::Text(s),
+        Event::Start(Tag::Link(_, url, _) | Tag::Image(_, url, _))
+        | Event::End(Tag::Link(_, url, _) | Tag::Image(_, url, _))
+            if !is_safe_link(&url) =>
+        {
+            Event::Text("".into())
+        }
+        event => event,
+    });
+    let mut res = String::new();
+    pulldown_cmark::html::push_html(&mut res, events);
+    res
+}
+
+fn html_document(site_title: &str, title: &str, page_path: &str, content: &str) -> String {
+    let root = root_prefix(page_path);
+    let mut res = String::new();
+    res.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    escape_html(title, &mut res);
+    res.push_str(" - ");
+    escape_html(site_title, &mut res);
+    write!(
+        res,
+        "</title>\n\
+         <link rel=\"stylesheet\" href=\"{root}style.css\">\n\
+         <script src=\"{root}search-index.js\"></script>\n\
+         <script src=\"{root}search.js\"></script>\n\
+         </head>\n<body>\n<nav>\n<a class=\"site-title\" href=\"{root}index.html\">",
+    )
+    .unwrap();
+    escape_html(site_title, &mut res);
+    write!(
+        res,
+        "</a>\n<input id=\"search\" type=\"search\" placeholder=\"Search\" data-root=\"{root}\">\n\
+         <ul id=\"search-results\"></ul>\n</nav>\n<main>\n{content}</main>\n</body>\n</html>\n",
+    )
+    .unwrap();
+    res
+}
+
+/// The landing page, listing every page (grouped by directory) and the symbols in it.
+fn index_html(title: &str, pages: &[Page]) -> String {
+    let mut content = String::new();
+    content.push_str("<h1>");
+    escape_html(title, &mut content);
+    content.push_str("</h1>\n");
+    let mut dirs: BTreeMap<&str, Vec<&Page>> = BTreeMap::new();
+    for page in pages {
+        let dir = page.path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        dirs.entry(dir).or_default().push(page);
+    }
+    for (dir, pages) in dirs {
+        content.push_str("<h2>");
+        escape_html(dir, &mut content);
+        content.push_str("</h2>\n<ul>\n");
+        for page in pages {
+            write!(content, "<li><a href=\"{}\">", url_path(&page.path)).unwrap();
+            escape_html(&page.title, &mut content);
+            content.push_str("</a>");
+            if page.symbols.len() > 1 {
+                content.push_str("<ul class=\"symbols\">");
+                for heading in page
+                    .symbols
+                    .iter()
+                    .filter_map(|s| page.headings.iter().find(|h| &h.name == s))
+                {
+                    write!(
+                        content,
+                        "<li><a href=\"{}#{}\">",
+                        url_path(&page.path),
+                        heading.slug
+                    )
+                    .unwrap();
+                    escape_html(&heading.name, &mut content);
+                    content.push_str("</a></li>");
+                }
+                content.push_str("</ul>");
+            }
+            content.push_str("</li>\n");
+        }
+        content.push_str("</ul>\n");
+    }
+    html_document(title, title, "index.html", &content)
+}
+
+/// The search index is a script rather than JSON, so that the site also works from `file://` URLs.
+fn search_index_js(pages: &[Page]) -> anyhow::Result<String> {
+    let entries: Vec<SearchEntry> = pages
+        .iter()
+        .flat_map(|page| {
+            page.headings.iter().map(move |h| SearchEntry {
+                name: &h.name,
+                page: &page.title,
+                url: format!("{}#{}", url_path(&page.path), h.slug),
+            })
+        })
+        .collect();
+    Ok(format!(
+        "const SEARCH_INDEX = {};\n",
+        serde_json::to_string(&entries)?
+    ))
+}
+
+/// Render the docs to HTML, and write the site to the destination directory.
+pub(crate) fn generate_html_site(opts: &HtmlSiteOptions, docs: Vec<Doc>) -> anyhow::Result<()> {
+    let destination_dir = opts
+        .destination_dir
+        .as_ref()
+        .expect("clap enforces when --format=html_site");
+
+    let abs_destination = if destination_dir.is_relative() {
+        std::env::current_dir()?.join(destination_dir)
+    } else {
+        destination_dir.to_owned()
+    };
+
+    let site = HtmlSite::new(&opts.title, docs)?;
+    for (relative_path, contents) in &site.files {
+        let path = abs_destination.join(relative_path);
+        if let Some(p) = path.parent() {
+            fs_util::create_dir_all(p)?;
+        }
+        fs_util::write(&path, contents)?;
+    }
+    buck2_client_ctx::eprintln!(
+        "Wrote {} files to {}",
+        site.files.len(),
+        abs_destination.display()
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use starlark::docs::*;
+
+    use super::*;
+
+    #[test]
+    fn linkify_code_links_known_symbols() {
+        let links = hashmap! {
+            "FooInfo".to_owned() => "native/FooInfo.html#FooInfo".to_owned(),
+            "ctx.actions".to_owned() => "native/ctx.actions.html#ctx.actions".to_owned(),
+        };
+        assert_eq!(
+            "x: [<a href=\"../native/FooInfo.html#FooInfo\">FooInfo</a>.type, &quot;MyFooInfo&quot;]",
+            linkify_code("x: [FooInfo.type, \"MyFooInfo\"]", &links, "../")
+        );
+        assert_eq!(
+            "<a href=\"native/ctx.actions.html#ctx.actions\">ctx.actions</a>.write(), ctx.label",
+            linkify_code("ctx.actions.write(), ctx.label", &links, "")
+        );
+    }
+
+    #[test]
+    fn site_cross_links_and_indexes_symbols() -> anyhow::Result<()> {
+        let docs = vec![
+            Doc {
+                id: Identifier {
+                    name: "FooInfo".to_owned(),
+                    location: None,
+                },
+                custom_attrs: hashmap! { "directory".to_owned() => "providers".to_owned() },
+                item: DocItem::Object(Object {
+                    docs: DocString::from_docstring(DocStringKind::Rust, "A provider"),
+                    members: vec![(
+                        "value".to_owned(),
+                        Member::Property(Property {
+                            docs: None,
+                            typ: Some(Type {
+                                raw_type: "int".to_owned(),
+                            }),
+                        }),
+                    )],
+                }),
+            },
+            Doc {
+                id: Identifier {
+                    name: "make_foo".to_owned(),
+                    location: Some(Location {
+                        path: "root//foo:foo.bzl".to_owned(),
+                        position: None,
+                    }),
+                },
+                custom_attrs: HashMap::new(),
+                item: DocItem::Function(Function {
+                    docs: None,
+                    params: vec![],
+                    ret: Return {
+                        docs: None,
+                        typ: Some(Type {
+                            raw_type: "FooInfo.type".to_owned(),
+                        }),
+                    },
+                }),
+            },
+        ];
+
+        let site = HtmlSite::new("Test", docs)?;
+        assert_eq!(
+            vec![
+                "index.html",
+                "native/providers/FooInfo.html",
+                "search-index.js",
+                "search.js",
+                "starlark/root/foo/foo.bzl.html",
+                "style.css",
+            ],
+            site.files.keys().collect::<Vec<_>>()
+        );
+
+        let foo_info = &site.files["native/providers/FooInfo.html"];
+        assert!(foo_info.contains("<h1 id=\"FooInfo\">FooInfo</h1>"));
+        assert!(foo_info.contains("<h2 id=\"value\">value : <code>int</code></h2>"));
+        assert!(foo_info.contains("href=\"../../style.css\""));
+
+        let foo_bzl = &site.files["starlark/root/foo/foo.bzl.html"];
+        assert!(foo_bzl.contains("<h2 id=\"make_foo\">make_foo</h2>"));
+        assert!(foo_bzl.contains(
+            "def make_foo() -&gt; <a href=\"../../../native/providers/FooInfo.html#FooInfo\">FooInfo</a>.type"
+        ));
+
+        let search_index = &site.files["search-index.js"];
+        assert!(search_index.contains(
+            r#"{"name":"value","page":"FooInfo","url":"native/providers/FooInfo.html#value"}"#
+        ));
+        assert!(search_index.contains(
+            r#"{"name":"make_foo","page":"root//foo:foo.bzl","url":"starlark/root/foo/foo.bzl.html#make_foo"}"#
+        ));
+
+        let index = &site.files["index.html"];
+        assert!(index.contains("<a href=\"starlark/root/foo/foo.bzl.html\">root//foo:foo.bzl</a>"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn site_escapes_names_and_docstrings() -> anyhow::Result<()> {
+        let docs = vec![Doc {
+            id: Identifier {
+                name: "Bad\"Info <b>".to_owned(),
+                location: None,
+            },
+            custom_attrs: HashMap::new(),
+            item: DocItem::Object(Object {
+                docs: DocString::from_docstring(
+                    DocStringKind::Rust,
+                    "Hi <script>alert(1)</script> [safe](https://example.com) [bad](javascript:alert(1))",
+                ),
+                members: vec![],
+            }),
+        }];
+
+        let site = HtmlSite::new("Test", docs)?;
+        let page = &site.files["native/Bad\"Info <b>.html"];
+        assert!(!page.contains("<script>"), "{}", page);
+        assert!(
+            page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"),
+            "{}",
+            page
+        );
+        assert!(
+            page.contains("<a href=\"https://example.com\">safe</a>"),
+            "{}",
+            page
+        );
+        assert!(!page.contains("javascript:"), "{}", page);
+
+        let index = &site.files["index.html"];
+        assert!(
+            index.contains(
+                "<a href=\"native/Bad%22Info%20%3Cb%3E.html\">Bad&quot;Info &lt;b&gt;</a>"
+            ),
+            "{}",
+            index
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+/**
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+// Search over the symbols in `SEARCH_INDEX`, which is defined by `search-index.js`.
+
+function search(query) {
+  query = query.trim().toLowerCase();
+  if (query === "") {
+    return [];
+  }
+  const rank = (entry) => (entry.name.toLowerCase().startsWith(query) ? 0 : 1);
+  return SEARCH_INDEX.filter((entry) =>
+    entry.name.toLowerCase().includes(query),
+  )
+    .sort(
+      (a, b) =>
+        rank(a) - rank(b) ||
+        a.name.length - b.name.length ||
+        a.name.localeCompare(b.name),
+    )
+    .slice(0, 50);
+}
+
+document.addEventListener("DOMContentLoaded", () => {
+  const $search = document.getElementById("search");
+  const $results = document.getElementById("search-results");
+  const root = $search.dataset.root;
+
+  $search.addEventListener("input", () => {
+    $results.replaceChildren(
+      ...search($search.value).map((entry) => {
+        const $link = document.createElement("a");
+        $link.href = root + entry.url;
+        $link.textContent = entry.name;
+        const $page = document.createElement("span");
+        $page.className = "search-page";
+        $page.textContent = entry.page;
+        const $item = document.createElement("li");
+        $item.append($link, " ", $page);
+        return $item;
+      }),
+    );
+  });
+});
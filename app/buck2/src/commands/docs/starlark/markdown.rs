@@ -55,33 +55,53 @@ impl MarkdownOutput {
         Ok(contents)
     }
 
-    /// Convert a buck style path (foo//bar:baz.bzl) to path, failing if someone attempts to traverse upward
-    fn path_from_location(location: &str) -> anyhow::Result<PathBuf> {
-        Ok(Path::new(&location.replace("//", "/").replace(':', "/")).to_path_buf())
-    }
-
     /// Get the output path for the markdown for a given [`Doc`], whether it's in a starlark file, or a native symbol.
     fn markdown_path_for_doc(opts: &MarkdownFileOptions, doc: &Doc) -> anyhow::Result<PathBuf> {
-        let subdir = output_subdir_for_doc(doc)?;
-        let path = match &doc.id.location {
-            Some(loc) => opts
-                .starlark_subdir
-                .join(subdir.as_path())
-                .join(Self::path_from_location(&loc.path)?),
-            None => match &doc.item {
-                // Functions all go in one file. Objects get their on file (e.g. each provider,
-                // Artifact, etc)
-                DocItem::Module(_) | DocItem::Function(_) | DocItem::Property(_) => {
-                    opts.native_subdir.join(subdir.as_path()).join("native")
-                }
-                DocItem::Object(_) => opts.native_subdir.join(subdir.as_path()).join(&doc.id.name),
-            },
-        };
-        let path = path.with_extension(match path.extension() {
-            None => "md".to_owned(),
-            Some(e) => format!("{}.md", e.to_str().expect("path if not UTF-8")),
-        });
-        Ok(path)
+        output_path_for_doc(&opts.native_subdir, &opts.starlark_subdir, doc, "md")
+    }
+}
+
+/// Convert a buck style path (foo//bar:baz.bzl) to path, failing if someone attempts to traverse upward
+fn path_from_location(location: &str) -> anyhow::Result<PathBuf> {
+    Ok(Path::new(&location.replace("//", "/").replace(':', "/")).to_path_buf())
+}
+
+/// Get the output path for a given [`Doc`], whether it's in a starlark file, or a native symbol.
+/// The given extension is appended to the file name.
+pub(super) fn output_path_for_doc(
+    native_subdir: &Path,
+    starlark_subdir: &Path,
+    doc: &Doc,
+    extension: &str,
+) -> anyhow::Result<PathBuf> {
+    let subdir = output_subdir_for_doc(doc)?;
+    let path = match &doc.id.location {
+        Some(loc) => starlark_subdir
+            .join(subdir.as_path())
+            .join(path_from_location(&loc.path)?),
+        None => match &doc.item {
+            // Functions all go in one file. Objects get their on file (e.g. each provider,
+            // Artifact, etc)
+            DocItem::Module(_) | DocItem::Function(_) | DocItem::Property(_) => {
+                native_subdir.join(subdir.as_path()).join("native")
+            }
+            DocItem::Object(_) => native_subdir.join(subdir.as_path()).join(&doc.id.name),
+        },
+    };
+    let path = path.with_extension(match path.extension() {
+        None => extension.to_owned(),
+        Some(e) => format!("{}.{}", e.to_str().expect("path if not UTF-8"), extension),
+    });
+    Ok(path)
+}
+
+/// Order docs within a file: modules first, then everything else by name.
+pub(super) fn item_ordering(l: &Doc, r: &Doc) -> Ordering {
+    match (&l.item, &r.item) {
+        (DocItem::Module(_), DocItem::Module(_)) => l.id.name.cmp(&r.id.name),
+        (DocItem::Module(_), _) => Ordering::Less,
+        (_, DocItem::Module(_)) => Ordering::Greater,
+        _ => l.id.name.cmp(&r.id.name),
     }
 }
 
@@ -96,15 +116,6 @@ pub(crate) fn generate_markdown_files(
         .expect("clap enforces when --format=markdown_files");
     let mut outputs = HashMap::new();
 
-    fn item_ordering(l: &Doc, r: &Doc) -> Ordering {
-        match (&l.item, &r.item) {
-            (DocItem::Module(_), DocItem::Module(_)) => l.id.name.cmp(&r.id.name),
-            (DocItem::Module(_), _) => Ordering::Less,
-            (_, DocItem::Module(_)) => Ordering::Greater,
-            _ => l.id.name.cmp(&r.id.name),
-        }
-    }
-
     for doc in docs.into_iter().sorted_by(item_ordering) {
         let markdown_path = MarkdownOutput::markdown_path_for_doc(opts, &doc)?;
         let markdown_file = outputs
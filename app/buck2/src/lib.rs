@@ -29,6 +29,7 @@ use buck2_audit::AuditCommand;
 use buck2_client::args::expand_argfiles_with_context;
 use buck2_client::args::ArgExpansionContext;
 use buck2_client::commands::aquery::AqueryCommand;
+use buck2_client::commands::bsp::BspCommand;
 use buck2_client::commands::build::BuildCommand;
 use buck2_client::commands::bxl::BxlCommand;
 use buck2_client::commands::clean::CleanCommand;
@@ -283,6 +284,7 @@ pub(crate) enum CommandKind {
     #[clap(subcommand)]
     Log(LogCommand),
     Lsp(LspCommand),
+    Bsp(BspCommand),
     Subscribe(SubscribeCommand),
 }
 
@@ -450,6 +452,7 @@ impl CommandKind {
             CommandKind::Install(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Log(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Lsp(cmd) => cmd.exec(matches, command_ctx),
+            CommandKind::Bsp(cmd) => cmd.exec(matches, command_ctx),
             CommandKind::Subscribe(cmd) => cmd.exec(matches, command_ctx),
         }
     }
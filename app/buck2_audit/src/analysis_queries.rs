@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::io::Write;
 
 use async_trait::async_trait;
@@ -27,6 +28,7 @@ use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use dupe::Dupe;
 use gazebo::prelude::*;
+use indexmap::IndexMap;
 
 use crate::AuditSubcommand;
 
@@ -50,6 +52,18 @@ pub struct AuditAnalysisQueriesCommand {
         help = "Enable to print the outputs for the targets in the resolved queries"
     )]
     include_outputs: bool,
+
+    /// Output in JSON format: a map from target to a map from query to the targets it resolved to
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(serde::Serialize)]
+struct JsonQueryResult {
+    target: String,
+    /// The default outputs of `target`, only present with `--include-outputs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    outputs: Option<Vec<String>>,
 }
 
 #[async_trait]
@@ -79,6 +93,7 @@ impl AuditSubcommand for AuditAnalysisQueriesCommand {
                     resolve_target_patterns(&cells, &parsed_patterns, &ctx.file_ops()).await?;
 
                 let mut stdout = stdout.as_writer();
+                let mut json_results = IndexMap::new();
 
                 for (package, spec) in resolved_pattern.specs {
                     match spec {
@@ -92,6 +107,31 @@ impl AuditSubcommand for AuditAnalysisQueriesCommand {
                                     ctx.get_configured_target_node(&configured_target).await?;
                                 let node = node.require_compatible()?;
                                 let query_results = resolve_queries(&ctx, &node).await?;
+                                if self.json {
+                                    let queries = query_results
+                                        .iter()
+                                        .map(|(query, result)| {
+                                            let targets = result
+                                                .iter()
+                                                .map(|(target, providers)| JsonQueryResult {
+                                                    target: target.unconfigured().to_string(),
+                                                    outputs: self.include_outputs.then(|| {
+                                                        providers
+                                                            .provider_collection()
+                                                            .default_info()
+                                                            .default_outputs()
+                                                            .iter()
+                                                            .map(|output| output.to_string())
+                                                            .collect()
+                                                    }),
+                                                })
+                                                .collect::<Vec<_>>();
+                                            (query.clone(), targets)
+                                        })
+                                        .collect::<BTreeMap<_, _>>();
+                                    json_results.insert(label.to_string(), queries);
+                                    continue;
+                                }
                                 writeln!(stdout, "{}:", label)?;
                                 for (query, result) in &query_results {
                                     writeln!(stdout, "  {}", query)?;
@@ -114,6 +154,10 @@ impl AuditSubcommand for AuditAnalysisQueriesCommand {
                     }
                 }
 
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&json_results)?)?;
+                }
+
                 Ok(())
             })
             .await
@@ -123,3 +167,44 @@ impl AuditSubcommand for AuditAnalysisQueriesCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_query_result() {
+        let results = IndexMap::from([(
+            "root//foo:bar".to_owned(),
+            BTreeMap::from([(
+                "deps(:baz)".to_owned(),
+                vec![
+                    JsonQueryResult {
+                        target: "root//foo:baz".to_owned(),
+                        outputs: Some(vec!["<build artifact baz.o>".to_owned()]),
+                    },
+                    JsonQueryResult {
+                        target: "root//foo:qux".to_owned(),
+                        outputs: None,
+                    },
+                ],
+            )]),
+        )]);
+        assert_eq!(
+            serde_json::json!({
+                "root//foo:bar": {
+                    "deps(:baz)": [
+                        {
+                            "target": "root//foo:baz",
+                            "outputs": ["<build artifact baz.o>"],
+                        },
+                        {
+                            "target": "root//foo:qux",
+                        },
+                    ],
+                },
+            }),
+            serde_json::to_value(&results).unwrap()
+        );
+    }
+}
@@ -16,6 +16,7 @@ use buck2_core::configuration::bound_id::BoundConfigurationId;
 use buck2_core::configuration::data::ConfigurationData;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use indexmap::IndexMap;
 use itertools::Itertools;
 
 use crate::AuditSubcommand;
@@ -35,6 +36,11 @@ pub struct AuditConfigurationsCommand {
         help = "configurations to audit (example: `cell//package:target-105fe3389fc7e436`). If none provided, will print information about all known configurations."
     )]
     configs: Vec<String>,
+
+    /// Output in JSON format: a map from configuration name to a map from constraint setting to
+    /// constraint value
+    #[clap(long)]
+    json: bool,
 }
 
 #[async_trait]
@@ -47,18 +53,24 @@ impl AuditSubcommand for AuditConfigurationsCommand {
     ) -> anyhow::Result<()> {
         let mut stdout = stdout.as_writer();
 
-        if self.configs.is_empty() {
-            for cfg in ConfigurationData::iter_existing()
+        let configs = if self.configs.is_empty() {
+            ConfigurationData::iter_existing()
                 .filter(|c| c.is_bound())
                 .sorted_by_cached_key(|c| c.full_name().to_owned())
-            {
-                print_cfg(&mut stdout, &cfg)?;
-            }
+                .collect()
+        } else {
+            self.configs
+                .iter()
+                .map(|cfg| ConfigurationData::lookup_bound(BoundConfigurationId::parse(cfg)?))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+
+        if self.json {
+            let configs = json_configurations(&configs)?;
+            writeln!(stdout, "{}", serde_json::to_string_pretty(&configs)?)?;
         } else {
-            for cfg in &self.configs {
-                let cfg = BoundConfigurationId::parse(cfg)?;
-                let cfg = ConfigurationData::lookup_bound(cfg)?;
-                print_cfg(&mut stdout, &cfg)?;
+            for cfg in &configs {
+                print_cfg(&mut stdout, cfg)?;
             }
         }
 
@@ -70,6 +82,24 @@ impl AuditSubcommand for AuditConfigurationsCommand {
     }
 }
 
+/// Map from configuration name to a map from constraint setting to constraint value.
+fn json_configurations(
+    configs: &[ConfigurationData],
+) -> anyhow::Result<IndexMap<String, IndexMap<String, String>>> {
+    configs
+        .iter()
+        .map(|cfg| {
+            let constraints = cfg
+                .data()?
+                .constraints
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<IndexMap<_, _>>();
+            anyhow::Ok((cfg.full_name().to_owned(), constraints))
+        })
+        .collect()
+}
+
 fn print_cfg(stdout: &mut impl Write, cfg: &ConfigurationData) -> anyhow::Result<()> {
     writeln!(stdout, "{}:", cfg.full_name())?;
     let data = cfg.data()?;
@@ -89,3 +119,38 @@ fn print_cfg(stdout: &mut impl Write, cfg: &ConfigurationData) -> anyhow::Result
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use buck2_core::configuration::constraints::ConstraintKey;
+    use buck2_core::configuration::constraints::ConstraintValue;
+    use buck2_core::configuration::data::ConfigurationDataData;
+    use buck2_core::target::label::TargetLabel;
+    use dupe::Dupe;
+
+    use super::*;
+
+    #[test]
+    fn test_json_configurations() -> anyhow::Result<()> {
+        let cfg = ConfigurationData::from_platform(
+            "cfg:linux".to_owned(),
+            ConfigurationDataData {
+                constraints: BTreeMap::from([(
+                    ConstraintKey(TargetLabel::testing_parse("root//constraints:os")),
+                    ConstraintValue(TargetLabel::testing_parse("root//constraints:linux")),
+                )]),
+            },
+        )?;
+        let json = serde_json::to_value(json_configurations(&[cfg.dupe()])?)?;
+        assert_eq!(1, json.as_object().unwrap().len());
+        assert_eq!(
+            serde_json::json!({
+                "root//constraints:os": "root//constraints:linux",
+            }),
+            json[cfg.full_name()]
+        );
+        Ok(())
+    }
+}
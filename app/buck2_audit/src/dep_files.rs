@@ -30,6 +30,7 @@ use buck2_server_ctx::ctx::ServerCommandDiceContext;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
+use indexmap::IndexMap;
 
 use crate::AuditSubcommand;
 
@@ -50,6 +51,17 @@ pub struct AuditDepFilesCommand {
 
     #[clap(help = "Action identifier")]
     identifier: Option<String>,
+
+    /// Output in JSON format: an object with the `untagged` paths, and the `tagged` paths keyed
+    /// by tag
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(serde::Serialize)]
+struct JsonDepFiles {
+    untagged: Vec<String>,
+    tagged: IndexMap<String, Vec<String>>,
 }
 
 #[async_trait]
@@ -117,22 +129,40 @@ impl AuditSubcommand for AuditDepFilesCommand {
 
                 let mut stdout = stdout.as_writer();
 
-                for (path, ..) in dirs
+                let untagged = dirs
                     .untagged
                     .ordered_walk()
                     .with_paths()
                     .filter_map(|(p, e)| Some((p, e.into_leaf()?)))
-                {
-                    writeln!(stdout, "untagged\t{}", path)?;
-                }
+                    .map(|(path, ..)| path.to_string())
+                    .collect::<Vec<_>>();
+
+                let tagged = dirs
+                    .tagged
+                    .iter()
+                    .map(|(tag, dir)| {
+                        let paths = dir
+                            .ordered_walk()
+                            .with_paths()
+                            .filter_map(|(p, e)| Some((p, e.into_leaf()?)))
+                            .map(|(path, ..)| path.to_string())
+                            .collect::<Vec<_>>();
+                        (tag.to_string(), paths)
+                    })
+                    .collect::<IndexMap<_, _>>();
+
+                if self.json {
+                    let dep_files = JsonDepFiles { untagged, tagged };
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&dep_files)?)?;
+                } else {
+                    for path in untagged {
+                        writeln!(stdout, "untagged\t{}", path)?;
+                    }
 
-                for (tag, dir) in dirs.tagged.iter() {
-                    for (path, ..) in dir
-                        .ordered_walk()
-                        .with_paths()
-                        .filter_map(|(p, e)| Some((p, e.into_leaf()?)))
-                    {
-                        writeln!(stdout, "{}\t{}", tag, path)?;
+                    for (tag, paths) in tagged {
+                        for path in paths {
+                            writeln!(stdout, "{}\t{}", tag, path)?;
+                        }
                     }
                 }
 
@@ -145,3 +175,25 @@ impl AuditSubcommand for AuditDepFilesCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_dep_files() {
+        let dep_files = JsonDepFiles {
+            untagged: vec!["foo/main.c".to_owned()],
+            tagged: IndexMap::from([("headers".to_owned(), vec!["foo/lib.h".to_owned()])]),
+        };
+        assert_eq!(
+            serde_json::json!({
+                "untagged": ["foo/main.c"],
+                "tagged": {
+                    "headers": ["foo/lib.h"],
+                },
+            }),
+            serde_json::to_value(&dep_files).unwrap()
+        );
+    }
+}
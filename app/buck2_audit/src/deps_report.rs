@@ -0,0 +1,514 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_build_api::calculation::MissingTargetBehavior;
+use buck2_build_api::configure_targets::load_compatible_patterns;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::provider::label::ConfiguredProvidersLabel;
+use buck2_core::target::label::ConfiguredTargetLabel;
+use buck2_node::attrs::configured_traversal::ConfiguredAttrTraversal;
+use buck2_node::attrs::fmt_context::AttrFmtContext;
+use buck2_node::attrs::inspect_options::AttrInspectOptions;
+use buck2_node::attrs::serialize::AttrSerializeWithContextExt;
+use buck2_node::nodes::configured::ConfiguredTargetNode;
+use buck2_node::nodes::unconfigured::RuleKind;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use indexmap::IndexMap;
+use regex::Regex;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-deps-report",
+    about = "Report every target reachable from the given targets in the configured graph, with \
+    selected attribute values, the kind of dependency edge and the shortest path from a root. \
+    Useful to audit licenses of third-party code linked into a binary."
+)]
+pub struct AuditDepsReportCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Target patterns to start the traversal from"
+    )]
+    patterns: Vec<String>,
+
+    /// Attributes to include for each reachable target. Can be passed multiple times.
+    #[clap(
+        long = "attribute",
+        short = 'a',
+        value_name = "ATTRIBUTE",
+        default_value = "licenses"
+    )]
+    attributes: Vec<String>,
+
+    /// Only report targets whose unconfigured label matches this regular expression, for example
+    /// `^fbsource//third-party/`. All targets are still traversed.
+    #[clap(long, value_name = "REGEX")]
+    filter: Option<String>,
+
+    /// Do not follow execution deps. Execution deps are tools run during the build, and are
+    /// usually not shipped as part of the output.
+    #[clap(long)]
+    exclude_exec_deps: bool,
+
+    /// Output in JSON format: a list of objects with the `target`, its `rule_type`, the `kind`
+    /// and `attribute` of the edge it was reached through, its `depth`, the `path` from a root
+    /// and the requested `attributes`
+    #[clap(long)]
+    json: bool,
+}
+
+/// The kind of edge through which a target was first reached.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DepKind {
+    /// The target is one of the targets the traversal started from.
+    Root,
+    Dep,
+    ExecDep,
+    ToolchainDep,
+}
+
+impl fmt::Display for DepKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            DepKind::Root => "root",
+            DepKind::Dep => "dep",
+            DepKind::ExecDep => "exec_dep",
+            DepKind::ToolchainDep => "toolchain_dep",
+        };
+        f.write_str(s)
+    }
+}
+
+/// A target reached by the traversal, in the order it was reached.
+struct Reached {
+    node: ConfiguredTargetNode,
+    /// Index of the target it was first reached from.
+    parent: Option<usize>,
+    kind: DepKind,
+    /// The attribute of the parent that declared the edge, if we could find it.
+    attribute: Option<String>,
+    depth: usize,
+}
+
+#[derive(serde::Serialize)]
+struct DepsReportEntry {
+    target: String,
+    rule_type: String,
+    kind: DepKind,
+    attribute: Option<String>,
+    depth: usize,
+    path: Vec<String>,
+    attributes: IndexMap<String, serde_json::Value>,
+}
+
+/// Collects, for each dep of a node, the kind of the edge and the attribute declaring it.
+#[derive(Default)]
+struct DepEdges<'n> {
+    attribute: &'n str,
+    edges: HashMap<ConfiguredTargetLabel, (DepKind, &'n str)>,
+}
+
+impl<'n> DepEdges<'n> {
+    fn collect(node: &'n ConfiguredTargetNode) -> anyhow::Result<Self> {
+        let mut edges = DepEdges::default();
+        for attr in node.attrs(AttrInspectOptions::All) {
+            edges.attribute = attr.name;
+            attr.traverse(node.label().pkg(), &mut edges)?;
+        }
+        Ok(edges)
+    }
+
+    fn insert(&mut self, dep: &ConfiguredProvidersLabel, kind: DepKind) {
+        self.edges
+            .entry(dep.target().dupe())
+            .or_insert((kind, self.attribute));
+    }
+}
+
+impl<'a, 'n> ConfiguredAttrTraversal<'a> for DepEdges<'n> {
+    fn dep(&mut self, dep: &'a ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.insert(dep, DepKind::Dep);
+        Ok(())
+    }
+
+    fn exec_dep(&mut self, dep: &'a ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.insert(dep, DepKind::ExecDep);
+        Ok(())
+    }
+
+    fn toolchain_dep(&mut self, dep: &'a ConfiguredProvidersLabel) -> anyhow::Result<()> {
+        self.insert(dep, DepKind::ToolchainDep);
+        Ok(())
+    }
+}
+
+/// Breadth first traversal from `roots`, so that every target is reached through a shortest path.
+fn traverse(
+    roots: impl IntoIterator<Item = ConfiguredTargetNode>,
+    exclude_exec_deps: bool,
+) -> anyhow::Result<Vec<Reached>> {
+    let mut reached = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+
+    for root in roots {
+        if !visited.insert(root.label().dupe()) {
+            continue;
+        }
+        queue.push_back(reached.len());
+        reached.push(Reached {
+            node: root,
+            parent: None,
+            kind: DepKind::Root,
+            attribute: None,
+            depth: 0,
+        });
+    }
+
+    while let Some(index) = queue.pop_front() {
+        let node = reached[index].node.dupe();
+        let depth = reached[index].depth;
+        let edges = DepEdges::collect(&node)?;
+
+        let target_deps = node.target_deps().map(|dep| {
+            let kind = if dep.rule_kind() == RuleKind::Toolchain {
+                DepKind::ToolchainDep
+            } else {
+                DepKind::Dep
+            };
+            (dep, kind)
+        });
+        let exec_deps = node.exec_deps().map(|dep| (dep, DepKind::ExecDep));
+
+        for (dep, fallback_kind) in target_deps.chain(exec_deps) {
+            if visited.contains(dep.label()) {
+                continue;
+            }
+            // Deps that don't come from an attribute (for example the exec deps of the
+            // execution platform) are classified by where the node stores them.
+            let (kind, attribute) = match edges.edges.get(dep.label()) {
+                Some((kind, attribute)) => (*kind, Some((*attribute).to_owned())),
+                None => (fallback_kind, None),
+            };
+            if exclude_exec_deps && kind == DepKind::ExecDep {
+                continue;
+            }
+            visited.insert(dep.label().dupe());
+            queue.push_back(reached.len());
+            reached.push(Reached {
+                node: dep.dupe(),
+                parent: Some(index),
+                kind,
+                attribute,
+                depth: depth + 1,
+            });
+        }
+    }
+
+    Ok(reached)
+}
+
+fn path_from_root(reached: &[Reached], mut index: usize) -> Vec<String> {
+    let mut path = vec![reached[index].node.label().to_string()];
+    while let Some(parent) = reached[index].parent {
+        path.push(reached[parent].node.label().to_string());
+        index = parent;
+    }
+    path.reverse();
+    path
+}
+
+/// Render an attribute value for the tabulated output: strings as is, and anything else as JSON.
+fn display_attribute(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Null => "-".to_owned(),
+        v => v.to_string(),
+    }
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditDepsReportCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: Box<dyn ServerCommandContextTrait>,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        let filter = self.filter.as_deref().map(Regex::new).transpose()?;
+
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let parsed_patterns = parse_patterns_from_cli_args::<TargetPatternExtra>(
+                    &ctx,
+                    &self
+                        .patterns
+                        .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
+                    server_ctx.working_dir(),
+                )
+                .await?;
+                let target_platform =
+                    target_platform_from_client_context(&client_ctx, server_ctx, &ctx).await?;
+                let roots = load_compatible_patterns(
+                    &ctx,
+                    parsed_patterns,
+                    target_platform,
+                    MissingTargetBehavior::Fail,
+                )
+                .await?;
+
+                let reached =
+                    traverse(roots.iter().map(|node| node.dupe()), self.exclude_exec_deps)?;
+
+                let mut entries = Vec::new();
+                for (index, target) in reached.iter().enumerate() {
+                    let label = target.node.label();
+                    if let Some(filter) = &filter {
+                        if !filter.is_match(&label.unconfigured().to_string()) {
+                            continue;
+                        }
+                    }
+
+                    let fmt_ctx = AttrFmtContext {
+                        package: Some(label.pkg()),
+                    };
+                    let mut attributes = IndexMap::new();
+                    for name in &self.attributes {
+                        let value = match target.node.get(name, AttrInspectOptions::All) {
+                            Some(attr) => serde_json::to_value(attr.value.as_serialize(&fmt_ctx))?,
+                            None => serde_json::Value::Null,
+                        };
+                        attributes.insert(name.clone(), value);
+                    }
+
+                    entries.push(DepsReportEntry {
+                        target: label.to_string(),
+                        rule_type: target.node.rule_type().to_string(),
+                        kind: target.kind,
+                        attribute: target.attribute.clone(),
+                        depth: target.depth,
+                        path: path_from_root(&reached, index),
+                        attributes,
+                    });
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&entries)?)?;
+                } else {
+                    write!(stdout, "target\tkind\tdepth")?;
+                    for name in &self.attributes {
+                        write!(stdout, "\t{}", name)?;
+                    }
+                    writeln!(stdout, "\tpath")?;
+
+                    for entry in &entries {
+                        write!(stdout, "{}\t{}\t{}", entry.target, entry.kind, entry.depth)?;
+                        for value in entry.attributes.values() {
+                            write!(stdout, "\t{}", display_attribute(value))?;
+                        }
+                        writeln!(stdout, "\t{}", entry.path.join(" -> "))?;
+                    }
+                }
+
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use buck2_core::bzl::ImportPath;
+    use buck2_core::collections::ordered_map::OrderedMap;
+    use buck2_core::collections::unordered_map::UnorderedMap;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::configuration::pair::ConfigurationNoExec;
+    use buck2_core::provider::label::ProvidersLabel;
+    use buck2_core::provider::label::ProvidersName;
+    use buck2_core::target::label::TargetLabel;
+    use buck2_node::attrs::attr::testing::AttributeExt;
+    use buck2_node::attrs::attr::Attribute;
+    use buck2_node::attrs::attr_type::attr_literal::AttrLiteral;
+    use buck2_node::attrs::attr_type::dep::DepAttr;
+    use buck2_node::attrs::attr_type::dep::DepAttrTransition;
+    use buck2_node::attrs::attr_type::dep::DepAttrType;
+    use buck2_node::attrs::attr_type::AttrType;
+    use buck2_node::attrs::coerced_attr::CoercedAttr;
+    use buck2_node::configuration::execution::ExecutionPlatformResolution;
+    use buck2_node::configuration::resolved::ResolvedConfiguration;
+    use buck2_node::nodes::unconfigured::testing::TargetNodeExt;
+    use buck2_node::nodes::unconfigured::TargetNode;
+    use buck2_node::provider_id_set::ProviderIdSet;
+    use buck2_node::rule_type::RuleType;
+    use buck2_node::rule_type::StarlarkRuleType;
+
+    use super::*;
+
+    /// A node whose `deps` are declared by its `runtime_deps` attribute, while its `exec_deps`
+    /// are not declared by any attribute, like the exec deps of an execution platform.
+    fn node(
+        name: &str,
+        deps: Vec<ConfiguredTargetNode>,
+        exec_deps: Vec<ConfiguredTargetNode>,
+    ) -> ConfiguredTargetNode {
+        let cfg = ConfigurationData::testing_new();
+        let label = TargetLabel::testing_parse(&format!("root//pkg:{}", name));
+        let rule_type = RuleType::Starlark(Arc::new(StarlarkRuleType {
+            import_path: ImportPath::testing_new("root//pkg:rules.bzl"),
+            name: "some_rule".to_owned(),
+        }));
+        let runtime_deps = CoercedAttr::Literal(AttrLiteral::List(
+            deps.iter()
+                .map(|dep| {
+                    CoercedAttr::Literal(AttrLiteral::Dep(Box::new(DepAttr {
+                        attr_type: DepAttrType::new(
+                            ProviderIdSet::EMPTY,
+                            DepAttrTransition::Identity,
+                        ),
+                        label: ProvidersLabel::new(
+                            dep.label().unconfigured().dupe(),
+                            ProvidersName::Default,
+                        ),
+                    })))
+                })
+                .collect(),
+        ));
+
+        ConfiguredTargetNode::new(
+            label.configure(cfg.dupe()),
+            TargetNode::testing_new(
+                label,
+                rule_type,
+                vec![(
+                    "runtime_deps",
+                    Attribute::testing_new(
+                        None,
+                        AttrType::list(AttrType::dep(ProviderIdSet::EMPTY)),
+                    ),
+                    runtime_deps,
+                )],
+            ),
+            ResolvedConfiguration::new(ConfigurationNoExec::new(cfg), UnorderedMap::new()),
+            OrderedMap::new(),
+            ExecutionPlatformResolution::new(None, Vec::new()),
+            deps,
+            exec_deps,
+            OrderedMap::new(),
+        )
+    }
+
+    /// `bin -> {a, b}`, `a -> d -> c`, `b -> c`, and `bin` exec deps on `tool -> tool_lib`.
+    fn graph() -> ConfiguredTargetNode {
+        let c = node("c", vec![], vec![]);
+        let d = node("d", vec![c.dupe()], vec![]);
+        let a = node("a", vec![d], vec![]);
+        let b = node("b", vec![c], vec![]);
+        let tool = node("tool", vec![node("tool_lib", vec![], vec![])], vec![]);
+        node("bin", vec![a, b], vec![tool])
+    }
+
+    fn find<'a>(reached: &'a [Reached], name: &str) -> Option<(usize, &'a Reached)> {
+        reached
+            .iter()
+            .enumerate()
+            .find(|(_, r)| r.node.label().name().as_str() == name)
+    }
+
+    fn path_names(reached: &[Reached], index: usize) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut index = Some(index);
+        while let Some(i) = index {
+            names.push(reached[i].node.label().name().as_str().to_owned());
+            index = reached[i].parent;
+        }
+        names.reverse();
+        names
+    }
+
+    #[test]
+    fn test_shortest_path() -> anyhow::Result<()> {
+        let reached = traverse([graph()], false)?;
+        assert_eq!(reached.len(), 7);
+
+        let (index, c) = find(&reached, "c").unwrap();
+        assert_eq!(c.depth, 2);
+        assert_eq!(path_names(&reached, index), vec!["bin", "b", "c"]);
+        assert_eq!(
+            path_from_root(&reached, index),
+            path_names(&reached, index)
+                .iter()
+                .map(|name| find(&reached, name).unwrap().1.node.label().to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let (index, d) = find(&reached, "d").unwrap();
+        assert_eq!(d.depth, 2);
+        assert_eq!(path_names(&reached, index), vec!["bin", "a", "d"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_edge_kinds() -> anyhow::Result<()> {
+        let reached = traverse([graph()], false)?;
+
+        let (_, bin) = find(&reached, "bin").unwrap();
+        assert_eq!(bin.kind, DepKind::Root);
+        assert_eq!(bin.attribute, None);
+
+        let (_, a) = find(&reached, "a").unwrap();
+        assert_eq!(a.kind, DepKind::Dep);
+        assert_eq!(a.attribute.as_deref(), Some("runtime_deps"));
+
+        // Not declared by an attribute, so classified by where the node stores it.
+        let (_, tool) = find(&reached, "tool").unwrap();
+        assert_eq!(tool.kind, DepKind::ExecDep);
+        assert_eq!(tool.attribute, None);
+
+        let (index, tool_lib) = find(&reached, "tool_lib").unwrap();
+        assert_eq!(tool_lib.kind, DepKind::Dep);
+        assert_eq!(path_names(&reached, index), vec!["bin", "tool", "tool_lib"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_exclude_exec_deps() -> anyhow::Result<()> {
+        let reached = traverse([graph()], true)?;
+        assert_eq!(reached.len(), 5);
+        assert!(find(&reached, "tool").is_none());
+        assert!(find(&reached, "tool_lib").is_none());
+        Ok(())
+    }
+}
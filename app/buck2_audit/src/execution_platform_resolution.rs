@@ -28,6 +28,7 @@ use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use buck2_server_ctx::pattern::PatternParser;
 use indent_write::io::IndentWriter;
+use indexmap::IndexMap;
 
 use crate::AuditSubcommand;
 
@@ -52,6 +53,27 @@ pub struct AuditExecutionPlatformResolutionCommand {
 
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to analyze")]
     patterns: Vec<String>,
+
+    /// Output in JSON format: a map from configured target to its execution platform resolution
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum JsonExecutionPlatformResolution {
+    Resolved {
+        execution_platform: String,
+        execution_platform_configuration: String,
+        exec_deps: Vec<String>,
+        toolchain_deps: Vec<String>,
+        /// Execution platforms that were considered and rejected, with the reason.
+        skipped: IndexMap<String, String>,
+    },
+    Failed {
+        #[serde(rename = "$error")]
+        error: String,
+    },
 }
 
 #[async_trait]
@@ -109,6 +131,28 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
 
                 let mut stdout = stdout.as_writer();
 
+                if self.json {
+                    let mut results = IndexMap::new();
+                    for configured_target in configured_patterns {
+                        let configured_node = ctx.get_configured_target_node(&configured_target).await?;
+                        let configured_node = configured_node.require_compatible()?;
+                        let resolution = configured_node.execution_platform_resolution();
+                        let result = match resolution.platform() {
+                            Ok(platform) => JsonExecutionPlatformResolution::Resolved {
+                                execution_platform: platform.id().to_string(),
+                                execution_platform_configuration: platform.cfg().to_string(),
+                                exec_deps: configured_node.exec_deps().map(|dep| dep.label().to_string()).collect(),
+                                toolchain_deps: configured_node.toolchain_deps().map(|dep| dep.label().to_string()).collect(),
+                                skipped: resolution.skipped().iter().map(|(label, reason)| (label.clone(), format!("{:#}", reason))).collect(),
+                            },
+                            Err(e) => JsonExecutionPlatformResolution::Failed { error: format!("{:#}", e) },
+                        };
+                        results.insert(configured_target.to_string(), result);
+                    }
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&results)?)?;
+                    return Ok(());
+                }
+
                 for configured_target in configured_patterns {
                     let configured_node = ctx.get_configured_target_node(&configured_target).await?;
                     let configured_node = configured_node.require_compatible()?;
@@ -144,3 +188,50 @@ impl AuditSubcommand for AuditExecutionPlatformResolutionCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_execution_platform_resolution() {
+        let results = IndexMap::from([
+            (
+                "root//foo:bar (cfg:linux)".to_owned(),
+                JsonExecutionPlatformResolution::Resolved {
+                    execution_platform: "root//platforms:default".to_owned(),
+                    execution_platform_configuration: "cfg:linux".to_owned(),
+                    exec_deps: Vec::new(),
+                    toolchain_deps: vec!["root//toolchains:cxx (cfg:linux)".to_owned()],
+                    skipped: IndexMap::from([(
+                        "root//platforms:windows".to_owned(),
+                        "incompatible".to_owned(),
+                    )]),
+                },
+            ),
+            (
+                "root//foo:baz (cfg:linux)".to_owned(),
+                JsonExecutionPlatformResolution::Failed {
+                    error: "No compatible execution platform".to_owned(),
+                },
+            ),
+        ]);
+        assert_eq!(
+            serde_json::json!({
+                "root//foo:bar (cfg:linux)": {
+                    "execution_platform": "root//platforms:default",
+                    "execution_platform_configuration": "cfg:linux",
+                    "exec_deps": [],
+                    "toolchain_deps": ["root//toolchains:cxx (cfg:linux)"],
+                    "skipped": {
+                        "root//platforms:windows": "incompatible",
+                    },
+                },
+                "root//foo:baz (cfg:linux)": {
+                    "$error": "No compatible execution platform",
+                },
+            }),
+            serde_json::to_value(&results).unwrap()
+        );
+    }
+}
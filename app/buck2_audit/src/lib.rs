@@ -33,9 +33,11 @@ use crate::config::AuditConfigCommand;
 use crate::configurations::AuditConfigurationsCommand;
 use crate::deferred_materializer::DeferredMaterializerCommand;
 use crate::dep_files::AuditDepFilesCommand;
+use crate::deps_report::AuditDepsReportCommand;
 use crate::execution_platform_resolution::AuditExecutionPlatformResolutionCommand;
 use crate::includes::AuditIncludesCommand;
 use crate::output::command::AuditOutputCommand;
+use crate::pattern::AuditPatternCommand;
 use crate::prelude::AuditPreludeCommand;
 use crate::providers::AuditProvidersCommand;
 use crate::starlark::StarlarkCommand;
@@ -48,9 +50,11 @@ mod config;
 mod configurations;
 pub mod deferred_materializer;
 mod dep_files;
+mod deps_report;
 mod execution_platform_resolution;
 mod includes;
 pub mod output;
+mod pattern;
 mod prelude;
 mod providers;
 pub mod server;
@@ -73,8 +77,10 @@ pub enum AuditCommand {
     #[clap(subcommand)]
     Starlark(StarlarkCommand),
     DepFiles(AuditDepFilesCommand),
+    DepsReport(AuditDepsReportCommand),
     DeferredMaterializer(DeferredMaterializerCommand),
     Output(AuditOutputCommand),
+    Pattern(AuditPatternCommand),
 }
 
 /// `buck2 audit` subcommands have a somewhat unique approach to make it really easy to
@@ -121,9 +127,11 @@ impl AuditCommand {
             AuditCommand::ExecutionPlatformResolution(cmd) => cmd,
             AuditCommand::Starlark(cmd) => cmd,
             AuditCommand::DepFiles(cmd) => cmd,
+            AuditCommand::DepsReport(cmd) => cmd,
             AuditCommand::DeferredMaterializer(cmd) => cmd,
             AuditCommand::Visibility(cmd) => cmd,
             AuditCommand::Output(cmd) => cmd,
+            AuditCommand::Pattern(cmd) => cmd,
         }
     }
 }
@@ -0,0 +1,600 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_client_ctx::common::CommonCommandOptions;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::data::HasIoProvider;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::file_ops::FileOps;
+use buck2_common::find_buildfile::find_buildfile;
+use buck2_common::git_ignores::HasGitIgnores;
+use buck2_common::ignores::IgnoreSet;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_common::package_boundary::HasPackageBoundaryExceptions;
+use buck2_common::package_listing::dice::HasPackageListingResolver;
+use buck2_common::package_listing::resolver::PackageListingResolver;
+use buck2_common::target_aliases::HasTargetAliasResolver;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::CellAlias;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::package::PackageLabel;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::name::TargetName;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::PatternParser;
+use dice::DiceComputations;
+use dupe::Dupe;
+
+use crate::AuditSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "audit-pattern",
+    about = "Explain how target patterns and aliases are resolved: \
+        cell resolution, alias expansion, packages matched and directories skipped"
+)]
+pub struct AuditPatternCommand {
+    #[clap(flatten)]
+    common_opts: CommonCommandOptions,
+
+    #[clap(long = "json", help = "Output in JSON format")]
+    json: bool,
+
+    #[clap(name = "TARGET_PATTERNS", help = "Target pattern(s) to explain.")]
+    patterns: Vec<String>,
+}
+
+#[derive(Default, serde::Serialize)]
+struct PatternExplanation {
+    pattern: String,
+    /// Aliases visited and the target they resolved to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alias: Option<Vec<String>>,
+    /// Cell alias written in the pattern, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell_alias: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cell_root: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parsed: Option<String>,
+    packages: Vec<PackageExplanation>,
+    /// Directories skipped while expanding recursive pattern.
+    ignored: Vec<IgnoredDir>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct PackageExplanation {
+    package: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_file: Option<String>,
+    /// Package the directory belongs to when there's no build file in it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    enclosing_package: Option<String>,
+    /// Package is covered by `project.package_boundary_exceptions`.
+    package_boundary_exception: bool,
+    /// Number of targets defined in the package, for non-recursive patterns.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    targets: Option<usize>,
+    /// Whether the target from the pattern exists in the package.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_found: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct IgnoredDir {
+    path: String,
+    reason: String,
+}
+
+/// Cell alias part of a pattern like `cell//foo:bar`, `None` for `//foo:bar` or `foo:bar`.
+fn pattern_cell_alias(pattern: &str) -> Option<&str> {
+    match pattern.split_once("//") {
+        Some((alias, _)) if !alias.is_empty() => Some(alias),
+        _ => None,
+    }
+}
+
+/// Why a directory which exists on disk is not traversed, checked in the same order
+/// as directory listing does. `None` if the directory is not skipped, which happens
+/// when it was created after the listing was computed.
+fn skipped_dir_reason(
+    file_name: &str,
+    nested_cell: Option<CellName>,
+    project_ignore: bool,
+    git_ignore: bool,
+) -> Option<String> {
+    if FileName::new(file_name).is_err() {
+        Some("invalid file name".to_owned())
+    } else if let Some(cell) = nested_cell {
+        Some(format!("belongs to cell `{}`", cell))
+    } else if project_ignore {
+        Some("matched by `project.ignore`".to_owned())
+    } else if git_ignore {
+        Some("matched by `.gitignore` or `.ignore`".to_owned())
+    } else {
+        None
+    }
+}
+
+/// Whether the pattern may be interpreted as an alias, see `resolve_target_alias`.
+fn may_be_alias(pattern: &str) -> bool {
+    !pattern.is_empty()
+        && pattern
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+async fn explain_package(
+    ctx: &DiceComputations,
+    cells: &CellResolver,
+    package: PackageLabel,
+    target: Option<&TargetName>,
+    load: bool,
+) -> PackageExplanation {
+    let mut explanation = PackageExplanation {
+        package: package.to_string(),
+        build_file: None,
+        enclosing_package: None,
+        package_boundary_exception: false,
+        targets: None,
+        target_found: None,
+        error: None,
+    };
+    let res: anyhow::Result<()> = try {
+        let path = package.as_cell_path();
+        explanation.package_boundary_exception = ctx.get_package_boundary_exception(path).await?;
+        let listing = ctx.file_ops().read_dir(path).await?;
+        explanation.build_file =
+            find_buildfile(cells.get(path.cell())?.buildfiles(), &listing.included)
+                .map(|f| f.to_string());
+        if explanation.build_file.is_none() {
+            explanation.enclosing_package = ctx
+                .get_package_listing_resolver()
+                .get_enclosing_package(path)
+                .await
+                .ok()
+                .map(|p| p.to_string());
+        } else if load {
+            let result = ctx.get_interpreter_results(package.dupe()).await?;
+            explanation.targets = Some(result.targets().len());
+            if let Some(target) = target {
+                explanation.target_found = Some(result.get_target(target.as_ref()).is_some());
+            }
+        }
+    };
+    if let Err(e) = res {
+        explanation.error = Some(format!("{:#}", e));
+    }
+    explanation
+}
+
+/// Walk the directory tree like `find_package_roots` does, additionally recording
+/// directories which were not traversed because they are ignored.
+async fn explain_recursive(
+    ctx: &DiceComputations,
+    cells: &CellResolver,
+    root: CellPath,
+    explanation: &mut PatternExplanation,
+) -> anyhow::Result<()> {
+    let file_ops = ctx.file_ops();
+    let io = ctx.global_data().get_io_provider();
+    let project_ignore = IgnoreSet::from_ignore_spec(
+        ctx.get_legacy_config_property(root.cell(), "project", "ignore")
+            .await?
+            .as_deref()
+            .unwrap_or(""),
+    )?;
+
+    if file_ops.is_ignored(root.as_ref()).await? {
+        let nested_cell = cells
+            .get_cell_path(&cells.resolve_path(root.as_ref())?)?
+            .cell();
+        explanation.ignored.push(IgnoredDir {
+            path: root.to_string(),
+            reason: skipped_dir_reason(
+                root.path().file_name().map_or("", |name| name.as_str()),
+                Some(nested_cell).filter(|c| *c != root.cell()),
+                project_ignore.is_match(root.path()),
+                false,
+            )
+            .unwrap_or_else(|| "matched by `project.ignore`".to_owned()),
+        });
+        return Ok(());
+    }
+
+    let mut queue = vec![root];
+    while let Some(dir) = queue.pop() {
+        let listing = file_ops.read_dir(dir.as_ref()).await?.included;
+        if find_buildfile(cells.get(dir.cell())?.buildfiles(), &listing).is_some() {
+            explanation.packages.push(
+                explain_package(
+                    ctx,
+                    cells,
+                    PackageLabel::from_cell_path(dir.as_ref()),
+                    None,
+                    false,
+                )
+                .await,
+            );
+        }
+
+        let included: HashSet<&str> = listing.iter().map(|e| e.file_name.as_str()).collect();
+        let project_path = cells
+            .get(dir.cell())?
+            .path()
+            .as_project_relative_path()
+            .join(dir.path());
+        let git_ignores = ctx.get_git_ignores(dir.as_ref()).await?;
+        for entry in io.read_dir(project_path.clone()).await? {
+            if !entry.file_type.is_dir() || included.contains(entry.file_name.as_str()) {
+                continue;
+            }
+            let (path, reason) = match FileName::new(entry.file_name.as_str()) {
+                Ok(name) => {
+                    let path = dir.join(name);
+                    let nested_cell = cells.get_cell_path(&project_path.join(name))?.cell();
+                    let reason = skipped_dir_reason(
+                        name.as_str(),
+                        Some(nested_cell).filter(|c| *c != dir.cell()),
+                        project_ignore.is_match(path.path()),
+                        git_ignores
+                            .as_ref()
+                            .map_or(false, |g| g.is_ignored(path.path(), true)),
+                    );
+                    (path.to_string(), reason)
+                }
+                Err(_) => (
+                    match dir.path().is_empty() {
+                        true => format!("{}{}", dir, entry.file_name),
+                        false => format!("{}/{}", dir, entry.file_name),
+                    },
+                    skipped_dir_reason(&entry.file_name, None, false, false),
+                ),
+            };
+            if let Some(reason) = reason {
+                explanation.ignored.push(IgnoredDir { path, reason });
+            }
+        }
+
+        for entry in listing.iter().rev() {
+            if entry.file_type.is_dir() {
+                queue.push(dir.join(ForwardRelativePath::unchecked_new(&entry.file_name)));
+            }
+        }
+    }
+
+    explanation
+        .packages
+        .sort_by(|a, b| a.package.cmp(&b.package));
+    Ok(())
+}
+
+async fn explain_pattern(
+    ctx: &DiceComputations,
+    cells: &CellResolver,
+    cwd: &CellPath,
+    parser: &PatternParser,
+    pattern: &str,
+) -> PatternExplanation {
+    let mut explanation = PatternExplanation {
+        pattern: pattern.to_owned(),
+        ..Default::default()
+    };
+    let res: anyhow::Result<()> = try {
+        if may_be_alias(pattern) {
+            explanation.alias = ctx
+                .target_alias_resolver_for_cell(cwd.cell())
+                .await?
+                .resolve_alias_chain(pattern)?;
+        }
+
+        // Alias targets are resolved in the cell of the working directory.
+        let unaliased = match &explanation.alias {
+            Some(chain) => chain.last().map_or(pattern, |s| s.as_str()),
+            None => pattern,
+        };
+        let cell_name = match pattern_cell_alias(unaliased) {
+            Some(alias) => {
+                explanation.cell_alias = Some(alias.to_owned());
+                cells
+                    .get(cwd.cell())?
+                    .cell_alias_resolver()
+                    .resolve(&CellAlias::new(alias.to_owned()))?
+            }
+            None => cwd.cell(),
+        };
+        let cell = cells.get(cell_name)?;
+        explanation.cell = Some(cell_name.to_string());
+        explanation.cell_root = Some(cell.path().to_string());
+
+        let parsed = parser.parse_pattern::<TargetPatternExtra>(pattern)?;
+        explanation.parsed = Some(parsed.to_string());
+
+        match parsed {
+            ParsedPattern::Target(package, target, TargetPatternExtra) => {
+                explanation
+                    .packages
+                    .push(explain_package(ctx, cells, package, Some(&target), true).await);
+            }
+            ParsedPattern::Package(package) => {
+                explanation
+                    .packages
+                    .push(explain_package(ctx, cells, package, None, true).await);
+            }
+            ParsedPattern::Recursive(path) => {
+                explain_recursive(ctx, cells, path, &mut explanation).await?;
+            }
+        }
+    };
+    if let Err(e) = res {
+        explanation.error = Some(format!("{:#}", e));
+    }
+    explanation
+}
+
+fn write_explanation(
+    stdout: &mut impl Write,
+    explanation: &PatternExplanation,
+) -> anyhow::Result<()> {
+    writeln!(stdout, "Pattern `{}`", explanation.pattern)?;
+    if let Some(alias) = &explanation.alias {
+        writeln!(stdout, "  alias: {}", alias.join(" -> "))?;
+    }
+    if let Some(cell) = &explanation.cell {
+        match &explanation.cell_alias {
+            Some(alias) => writeln!(stdout, "  cell: `{}//` resolved to cell `{}`", alias, cell)?,
+            None => writeln!(stdout, "  cell: `{}` (working directory cell)", cell)?,
+        }
+    }
+    if let Some(cell_root) = &explanation.cell_root {
+        writeln!(stdout, "  cell root: `{}`", cell_root)?;
+    }
+    if let Some(parsed) = &explanation.parsed {
+        writeln!(stdout, "  parsed: `{}`", parsed)?;
+    }
+    if explanation.parsed.is_some() && explanation.packages.is_empty() {
+        writeln!(stdout, "  packages: none")?;
+    }
+    for package in &explanation.packages {
+        writeln!(stdout, "  package `{}`", package.package)?;
+        match (&package.build_file, &package.enclosing_package) {
+            (Some(build_file), _) => writeln!(stdout, "    build file: `{}`", build_file)?,
+            (None, Some(enclosing)) => writeln!(
+                stdout,
+                "    no build file, directory belongs to package `{}`",
+                enclosing
+            )?,
+            (None, None) => writeln!(stdout, "    no build file")?,
+        }
+        if package.package_boundary_exception {
+            writeln!(
+                stdout,
+                "    covered by `project.package_boundary_exceptions`"
+            )?;
+        }
+        if let Some(targets) = package.targets {
+            writeln!(stdout, "    targets: {}", targets)?;
+        }
+        match package.target_found {
+            Some(true) => writeln!(stdout, "    target: found")?,
+            Some(false) => writeln!(stdout, "    target: not found")?,
+            None => {}
+        }
+        if let Some(error) = &package.error {
+            writeln!(stdout, "    error: {}", error)?;
+        }
+    }
+    for ignored in &explanation.ignored {
+        writeln!(
+            stdout,
+            "  skipped ignored directory `{}` ({})",
+            ignored.path, ignored.reason
+        )?;
+    }
+    if let Some(error) = &explanation.error {
+        writeln!(stdout, "  error: {}", error)?;
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl AuditSubcommand for AuditPatternCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: Box<dyn ServerCommandContextTrait>,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let cells = ctx.get_cell_resolver().await?;
+                let cwd = cells.get_cell_path(server_ctx.working_dir())?;
+                let parser = PatternParser::new(&ctx, server_ctx.working_dir()).await?;
+
+                let mut explanations = Vec::with_capacity(self.patterns.len());
+                for pattern in &self.patterns {
+                    explanations.push(explain_pattern(&ctx, &cells, &cwd, &parser, pattern).await);
+                }
+
+                let mut stdout = stdout.as_writer();
+                if self.json {
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&explanations)?)?;
+                } else {
+                    for explanation in &explanations {
+                        write_explanation(&mut stdout, explanation)?;
+                    }
+                }
+                Ok(())
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &CommonCommandOptions {
+        &self.common_opts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_cell_alias() {
+        assert_eq!(Some("cell"), pattern_cell_alias("cell//foo:bar"));
+        assert_eq!(Some("cell"), pattern_cell_alias("cell//..."));
+        assert_eq!(None, pattern_cell_alias("//foo:bar"));
+        assert_eq!(None, pattern_cell_alias("foo:bar"));
+        assert_eq!(None, pattern_cell_alias("..."));
+    }
+
+    #[test]
+    fn test_skipped_dir_reason() {
+        let cell = CellName::testing_new("other");
+        assert_eq!(
+            Some("invalid file name".to_owned()),
+            skipped_dir_reason("..", None, false, false)
+        );
+        assert_eq!(
+            Some("belongs to cell `other`".to_owned()),
+            skipped_dir_reason("other", Some(cell), true, true)
+        );
+        assert_eq!(
+            Some("matched by `project.ignore`".to_owned()),
+            skipped_dir_reason("gen", None, true, true)
+        );
+        assert_eq!(
+            Some("matched by `.gitignore` or `.ignore`".to_owned()),
+            skipped_dir_reason("node_modules", None, false, true)
+        );
+        assert_eq!(None, skipped_dir_reason("src", None, false, false));
+    }
+
+    fn package(package: &str, build_file: Option<&str>) -> PackageExplanation {
+        PackageExplanation {
+            package: package.to_owned(),
+            build_file: build_file.map(str::to_owned),
+            enclosing_package: None,
+            package_boundary_exception: false,
+            targets: None,
+            target_found: None,
+            error: None,
+        }
+    }
+
+    fn write(explanation: &PatternExplanation) -> String {
+        let mut out = Vec::new();
+        write_explanation(&mut out, explanation).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_explain_package() {
+        let explanation = PatternExplanation {
+            pattern: "other//foo:bar".to_owned(),
+            cell_alias: Some("other".to_owned()),
+            cell: Some("other".to_owned()),
+            cell_root: Some("other".to_owned()),
+            parsed: Some("other//foo:bar".to_owned()),
+            packages: vec![PackageExplanation {
+                targets: Some(2),
+                target_found: Some(false),
+                ..package("other//foo", Some("BUCK"))
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            "Pattern `other//foo:bar`\n\
+             \x20 cell: `other//` resolved to cell `other`\n\
+             \x20 cell root: `other`\n\
+             \x20 parsed: `other//foo:bar`\n\
+             \x20 package `other//foo`\n\
+             \x20   build file: `BUCK`\n\
+             \x20   targets: 2\n\
+             \x20   target: not found\n",
+            write(&explanation)
+        );
+    }
+
+    #[test]
+    fn test_explain_recursive() {
+        let explanation = PatternExplanation {
+            pattern: "//foo/...".to_owned(),
+            cell: Some("root".to_owned()),
+            cell_root: Some("".to_owned()),
+            parsed: Some("root//foo/...".to_owned()),
+            packages: vec![
+                package("root//foo", Some("BUCK")),
+                package("root//foo/bar", Some("BUCK.v2")),
+            ],
+            ignored: vec![IgnoredDir {
+                path: "root//foo/node_modules".to_owned(),
+                reason: "matched by `.gitignore` or `.ignore`".to_owned(),
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            "Pattern `//foo/...`\n\
+             \x20 cell: `root` (working directory cell)\n\
+             \x20 cell root: ``\n\
+             \x20 parsed: `root//foo/...`\n\
+             \x20 package `root//foo`\n\
+             \x20   build file: `BUCK`\n\
+             \x20 package `root//foo/bar`\n\
+             \x20   build file: `BUCK.v2`\n\
+             \x20 skipped ignored directory `root//foo/node_modules` \
+             (matched by `.gitignore` or `.ignore`)\n",
+            write(&explanation)
+        );
+        assert_eq!(
+            serde_json::json!({
+                "pattern": "//foo/...",
+                "cell": "root",
+                "cell_root": "",
+                "parsed": "root//foo/...",
+                "packages": [
+                    {
+                        "package": "root//foo",
+                        "build_file": "BUCK",
+                        "package_boundary_exception": false,
+                    },
+                    {
+                        "package": "root//foo/bar",
+                        "build_file": "BUCK.v2",
+                        "package_boundary_exception": false,
+                    },
+                ],
+                "ignored": [
+                    {
+                        "path": "root//foo/node_modules",
+                        "reason": "matched by `.gitignore` or `.ignore`",
+                    },
+                ],
+            }),
+            serde_json::to_value(&explanation).unwrap()
+        );
+    }
+}
@@ -32,6 +32,8 @@ use dupe::Dupe;
 use futures::stream::FuturesOrdered;
 use futures::StreamExt;
 use gazebo::prelude::*;
+use indexmap::IndexMap;
+use serde_json::json;
 
 use crate::AuditSubcommand;
 
@@ -63,6 +65,11 @@ pub struct AuditProvidersCommand {
         conflicts_with_all=&["list", "quiet"]
     )]
     print_debug: bool,
+
+    /// Output in JSON format: a map from target to a map from provider name to the provider's
+    /// value, a list of provider names with `--list`, or a list of targets with `--quiet`
+    #[clap(long)]
+    json: bool,
 }
 
 #[async_trait]
@@ -156,12 +163,33 @@ impl AuditProvidersCommand {
         let mut stderr = server_ctx.stderr()?;
 
         let mut at_least_one_error = false;
+        let mut json_results = IndexMap::new();
         while let Some((target, result)) = futs.next().await {
             match result {
                 Ok(v) => {
                     let v: FrozenProviderCollectionValue = v.require_compatible()?;
 
-                    if self.quiet {
+                    if self.json {
+                        let collection = v.provider_collection();
+                        let value = if self.quiet {
+                            serde_json::Value::Null
+                        } else if self.list {
+                            let mut provider_names = collection.provider_names();
+                            provider_names.sort();
+                            json!(provider_names)
+                        } else {
+                            let providers = collection
+                                .provider_ids()
+                                .into_iter()
+                                .filter_map(|id| {
+                                    let value = collection.get_provider_raw(id)?;
+                                    Some((id.name().to_owned(), value.to_string()))
+                                })
+                                .collect::<IndexMap<_, _>>();
+                            json!(providers)
+                        };
+                        json_results.insert(target.to_string(), value);
+                    } else if self.quiet {
                         writeln!(&mut stdout, "{}", target)?
                     } else if self.list {
                         let mut provider_names = v.provider_collection().provider_names();
@@ -195,6 +223,9 @@ impl AuditProvidersCommand {
                     }
                 }
                 Err(e) => {
+                    if self.json {
+                        json_results.insert(target.to_string(), json_error(&e));
+                    }
                     write!(
                         &mut stderr,
                         "{}: failed:\n{}",
@@ -206,6 +237,19 @@ impl AuditProvidersCommand {
             }
         }
 
+        if self.json {
+            if self.quiet {
+                let targets = json_quiet_targets(&json_results);
+                writeln!(&mut stdout, "{}", serde_json::to_string_pretty(&targets)?)?;
+            } else {
+                writeln!(
+                    &mut stdout,
+                    "{}",
+                    serde_json::to_string_pretty(&json_results)?
+                )?;
+            }
+        }
+
         stdout.flush()?;
         stderr.flush()?;
 
@@ -216,3 +260,54 @@ impl AuditProvidersCommand {
         }
     }
 }
+
+/// Entry of a target whose providers failed to evaluate.
+fn json_error(e: &anyhow::Error) -> serde_json::Value {
+    json!({ "$error": format!("{:#}", e) })
+}
+
+/// Only the targets whose providers evaluated successfully, like the text output.
+fn json_quiet_targets(results: &IndexMap<String, serde_json::Value>) -> Vec<&str> {
+    results
+        .iter()
+        .filter(|(_, v)| v.get("$error").is_none())
+        .map(|(k, _)| k.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_results() {
+        let results = IndexMap::from([
+            (
+                "root//foo:bar (cfg:linux)".to_owned(),
+                json!(IndexMap::from([(
+                    "DefaultInfo".to_owned(),
+                    "DefaultInfo(sub_targets={})".to_owned()
+                )])),
+            ),
+            (
+                "root//foo:baz (cfg:linux)".to_owned(),
+                json_error(&anyhow::anyhow!("Failed")),
+            ),
+        ]);
+        assert_eq!(
+            json!({
+                "root//foo:bar (cfg:linux)": {
+                    "DefaultInfo": "DefaultInfo(sub_targets={})",
+                },
+                "root//foo:baz (cfg:linux)": {
+                    "$error": "Failed",
+                },
+            }),
+            serde_json::to_value(&results).unwrap()
+        );
+        assert_eq!(
+            vec!["root//foo:bar (cfg:linux)"],
+            json_quiet_targets(&results)
+        );
+    }
+}
@@ -7,6 +7,8 @@
  * of this source tree.
  */
 
+use std::io::Write;
+
 use async_trait::async_trait;
 use buck2_build_api::calculation::load_patterns;
 use buck2_build_api::calculation::MissingTargetBehavior;
@@ -38,6 +40,8 @@ enum VisibilityCommandError {
         "Internal Error: The dependency `{0}` of the target `{1}` was not found during the traversal."
     )]
     DepNodeNotFound(String, String),
+    #[error("{0} visibility errors found")]
+    VisibilityErrors(usize),
 }
 
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
@@ -51,13 +55,29 @@ pub struct AuditVisibilityCommand {
 
     #[clap(name = "TARGET_PATTERNS", help = "Target pattern(s) to analyze.")]
     patterns: Vec<String>,
+
+    /// Output in JSON format: an object with a `violations` list, where each violation has the
+    /// `target` whose `dep` is not visible to it
+    #[clap(long)]
+    json: bool,
+}
+
+#[derive(serde::Serialize)]
+struct JsonVisibilityViolation {
+    target: String,
+    dep: String,
+}
+
+#[derive(serde::Serialize)]
+struct JsonVisibilityResult {
+    violations: Vec<JsonVisibilityViolation>,
 }
 
 impl AuditVisibilityCommand {
     async fn verify_visibility(
         ctx: DiceTransaction,
         targets: TargetSet<TargetNode>,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<Vec<VisibilityError>> {
         struct Delegate {
             targets: TargetSet<TargetNode>,
         }
@@ -111,16 +131,7 @@ impl AuditVisibilityCommand {
             }
         }
 
-        for err in &visibility_errors {
-            buck2_client_ctx::eprintln!("{}", err)?;
-        }
-
-        if !visibility_errors.is_empty() {
-            return Err(anyhow::anyhow!("{}", 1));
-        }
-
-        buck2_client_ctx::eprintln!("audit visibility succeeded")?;
-        Ok(())
+        Ok(visibility_errors)
     }
 }
 
@@ -129,7 +140,7 @@ impl AuditSubcommand for AuditVisibilityCommand {
     async fn server_execute(
         &self,
         server_ctx: Box<dyn ServerCommandContextTrait>,
-        _stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
         _client_ctx: ClientContext,
     ) -> anyhow::Result<()> {
         server_ctx
@@ -158,7 +169,40 @@ impl AuditSubcommand for AuditVisibilityCommand {
                     }
                 }
 
-                AuditVisibilityCommand::verify_visibility(ctx, nodes).await?;
+                let visibility_errors =
+                    AuditVisibilityCommand::verify_visibility(ctx, nodes).await?;
+
+                if self.json {
+                    let result = JsonVisibilityResult {
+                        violations: visibility_errors
+                            .iter()
+                            .map(|err| match err {
+                                VisibilityError::NotVisibleTo(dep, target) => {
+                                    JsonVisibilityViolation {
+                                        target: target.to_string(),
+                                        dep: dep.to_string(),
+                                    }
+                                }
+                            })
+                            .collect(),
+                    };
+                    let mut stdout = stdout.as_writer();
+                    writeln!(stdout, "{}", serde_json::to_string_pretty(&result)?)?;
+                } else {
+                    for err in &visibility_errors {
+                        buck2_client_ctx::eprintln!("{}", err)?;
+                    }
+                }
+
+                if !visibility_errors.is_empty() {
+                    return Err(
+                        VisibilityCommandError::VisibilityErrors(visibility_errors.len()).into(),
+                    );
+                }
+
+                if !self.json {
+                    buck2_client_ctx::eprintln!("audit visibility succeeded")?;
+                }
                 Ok(())
             })
             .await
@@ -168,3 +212,29 @@ impl AuditSubcommand for AuditVisibilityCommand {
         &self.common_opts
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_visibility_result() {
+        let result = JsonVisibilityResult {
+            violations: vec![JsonVisibilityViolation {
+                target: "root//foo:bar".to_owned(),
+                dep: "root//baz:qux".to_owned(),
+            }],
+        };
+        assert_eq!(
+            serde_json::json!({
+                "violations": [
+                    {
+                        "target": "root//foo:bar",
+                        "dep": "root//baz:qux",
+                    },
+                ],
+            }),
+            serde_json::to_value(&result).unwrap()
+        );
+    }
+}
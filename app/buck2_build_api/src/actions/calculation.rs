@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashSet;
 use std::iter::zip;
 use std::sync::Arc;
 use std::time::Instant;
@@ -17,6 +18,7 @@ use async_trait::async_trait;
 use buck2_common::result::SharedResult;
 use buck2_common::result::ToSharedResultExt;
 use buck2_common::result::ToUnsharedResultExt;
+use buck2_core::cells::cell_path::CellPath;
 use buck2_data::ToProtoMessage;
 use buck2_events::dispatch::current_span;
 use buck2_events::dispatch::span_async;
@@ -24,6 +26,8 @@ use buck2_execute::execute::kind::CommandExecutionKind;
 use buck2_execute::execute::result::CommandExecutionReport;
 use buck2_execute::execute::result::CommandExecutionStatus;
 use buck2_execute::output_size::OutputSize;
+use buck2_interpreter::path::PackageFilePath;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use derive_more::Display;
 use dice::DiceComputations;
 use dice::Key;
@@ -43,9 +47,11 @@ use crate::actions::build_listener::HasBuildSignals;
 use crate::actions::build_listener::NodeDuration;
 use crate::actions::execute::action_executor::ActionOutputs;
 use crate::actions::execute::action_executor::HasActionExecutor;
+use crate::actions::invalidation::HasActionInvalidationTracker;
 use crate::actions::key::ActionKey;
 use crate::actions::RegisteredAction;
 use crate::artifact_groups::calculation::ensure_artifact_group_staged;
+use crate::deferred::base_deferred_key::BaseDeferredKey;
 use crate::deferred::calculation::DeferredCalculation;
 use crate::keep_going;
 
@@ -78,12 +84,51 @@ async fn build_action_impl(
             });
         }
 
+        if let Some(tracker) = ctx.per_transaction_data().get_action_invalidation_tracker() {
+            tracker.redirect(key, action.key());
+        }
+
         return res;
     }
 
     build_action_no_redirect(ctx, action).await
 }
 
+/// The files DICE evaluated to define the action `key`: the build file of its owner, the
+/// `PACKAGE` files above it, and the `.bzl` files it loads.
+async fn definition_files(
+    ctx: &DiceComputations,
+    key: &ActionKey,
+) -> anyhow::Result<Vec<CellPath>> {
+    let label = match key.owner() {
+        BaseDeferredKey::TargetLabel(label) => label,
+        // These are not defined by a build file.
+        BaseDeferredKey::AnonTarget(_) | BaseDeferredKey::BxlLabel(_) => return Ok(Vec::new()),
+    };
+
+    let package = label.pkg();
+    let eval_result = ctx.get_interpreter_results(package.dupe()).await?;
+    let mut files = vec![eval_result.buildfile_path().path()];
+    files.extend(
+        package
+            .as_cell_path()
+            .ancestors()
+            .map(|dir| dir.join(PackageFilePath::PACKAGE_FILE_NAME)),
+    );
+
+    let mut visited = HashSet::new();
+    let mut loads = eval_result.imports().to_vec();
+    while let Some(import) = loads.pop() {
+        if !visited.insert(import.clone()) {
+            continue;
+        }
+        files.push(import.path().clone());
+        let module = ctx.get_loaded_module_from_import_path(&import).await?;
+        loads.extend(module.imports().cloned());
+    }
+    Ok(files)
+}
+
 async fn build_action_no_redirect(
     ctx: &DiceComputations,
     action: Arc<RegisteredAction>,
@@ -105,6 +150,19 @@ async fn build_action_no_redirect(
         results
     };
 
+    let invalidation = match ctx.per_transaction_data().get_action_invalidation_tracker() {
+        Some(tracker) => {
+            tracker
+                .attribute(
+                    action.key(),
+                    &materialized_inputs,
+                    definition_files(ctx, action.key()),
+                )
+                .await
+        }
+        None => None,
+    };
+
     let start_event = buck2_data::ActionExecutionStart {
         key: Some(action.key().as_proto()),
         kind: action.kind().into(),
@@ -112,6 +170,7 @@ async fn build_action_no_redirect(
             category: action.category().as_str().to_owned(),
             identifier: action.identifier().unwrap_or("").to_owned(),
         }),
+        invalidation,
     };
 
     let executor = ctx
@@ -0,0 +1,412 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Attribution of executed actions to the file changes that invalidated them.
+//!
+//! Every file watcher sync starts a new generation, and we remember the last generation in which
+//! each path changed and each action ran. When an action runs again, we attribute it to the paths
+//! among its source inputs that changed since it last ran, plus whatever the actions producing its
+//! other inputs were attributed to if they ran since. Those are the same edges DICE follows to
+//! decide that the action has to run again, and since inputs are only resolved once the actions
+//! producing them have finished, dependencies are always attributed before their dependents.
+//!
+//! When none of those changed, the action was redefined, and we attribute it to the changed files
+//! among those DICE evaluated to define it: the build file of its owner and the `.bzl` files that
+//! build file loads.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use buck2_core::cells::cell_path::CellPath;
+use dashmap::DashMap;
+use dice::UserComputationData;
+use dupe::Dupe;
+use indexmap::IndexMap;
+
+use crate::actions::artifact::artifact_type::BaseArtifactKind;
+use crate::actions::key::ActionKey;
+use crate::artifact_groups::ArtifactGroup;
+use crate::artifact_groups::ArtifactGroupValues;
+
+/// The file changes picked up by a file watcher sync.
+#[derive(Debug)]
+pub enum FileChanges {
+    /// The file watcher lost track of changes, so everything was invalidated.
+    FreshInstance,
+    Paths(HashSet<CellPath>),
+}
+
+/// Tracks which file changes each action executed by the daemon is attributed to.
+///
+/// This is shared by all the commands of the daemon, so changes synced by one command are still
+/// attributed when the actions they invalidated only run in a later one. By default, commands that
+/// would see different file states don't run concurrently, so actions are attributed against the
+/// changes of the command running them.
+#[derive(Default)]
+pub struct ActionInvalidationTracker {
+    changes: Mutex<ChangeLog>,
+    executions: DashMap<ActionKey, Execution>,
+}
+
+#[derive(Default)]
+struct ChangeLog {
+    generation: u64,
+    /// The last generation each path changed in, since the last fresh instance.
+    changed: HashMap<CellPath, u64>,
+    /// The last generation in which the file watcher lost track of changes.
+    fresh_instance: Option<u64>,
+}
+
+impl ChangeLog {
+    fn changed_since(&self, path: &CellPath, generation: u64) -> bool {
+        self.changed
+            .get(path)
+            .map_or(false, |changed| *changed > generation)
+    }
+}
+
+#[derive(Clone)]
+struct Execution {
+    generation: u64,
+    changed_inputs: Arc<BTreeSet<CellPath>>,
+    changed_build_files: Arc<BTreeSet<CellPath>>,
+}
+
+impl ActionInvalidationTracker {
+    /// Record the changes of a file watcher sync, which starts a new generation.
+    pub fn record_file_changes(&self, changes: &FileChanges) {
+        let mut log = self.changes.lock().unwrap();
+        log.generation += 1;
+        let generation = log.generation;
+        match changes {
+            FileChanges::FreshInstance => {
+                // Everything changed, so there is no point in remembering what did before.
+                log.fresh_instance = Some(generation);
+                log.changed.clear();
+            }
+            FileChanges::Paths(paths) => {
+                for path in paths {
+                    log.changed.insert(path.clone(), generation);
+                }
+            }
+        }
+    }
+
+    /// Attribute the action `key`, about to run with `inputs`, to the changes that invalidated
+    /// it. `definition_files` lists the files the action's definition was evaluated from, and is
+    /// only awaited if no input changed. Returns `None` if the action did not run before.
+    pub(crate) async fn attribute(
+        &self,
+        key: &ActionKey,
+        inputs: &IndexMap<ArtifactGroup, ArtifactGroupValues>,
+        definition_files: impl Future<Output = anyhow::Result<Vec<CellPath>>>,
+    ) -> Option<buck2_data::ActionInvalidation> {
+        let previous = self.executions.get(key).map(|e| e.generation);
+        let mut changed_inputs = BTreeSet::new();
+        let mut changed_build_files = BTreeSet::new();
+
+        let (generation, fresh_instance) = {
+            let log = self.changes.lock().unwrap();
+            let fresh_instance = match (previous, log.fresh_instance) {
+                (Some(previous), Some(fresh_instance)) => fresh_instance > previous,
+                _ => false,
+            };
+            if let (Some(previous), false) = (previous, fresh_instance) {
+                self.collect_changed_inputs(
+                    &log,
+                    previous,
+                    inputs,
+                    &mut changed_inputs,
+                    &mut changed_build_files,
+                );
+            }
+            (log.generation, fresh_instance)
+        };
+
+        if let (Some(previous), false) = (previous, fresh_instance) {
+            if changed_inputs.is_empty() && changed_build_files.is_empty() {
+                // This is best effort: if the definition can't be evaluated, the action won't
+                // run either.
+                let files = definition_files.await.unwrap_or_default();
+                let log = self.changes.lock().unwrap();
+                changed_build_files.extend(
+                    files
+                        .into_iter()
+                        .filter(|path| log.changed_since(path, previous)),
+                );
+            }
+        }
+
+        let invalidation = previous.map(|_| buck2_data::ActionInvalidation {
+            changed_inputs: changed_inputs.iter().map(|p| p.to_string()).collect(),
+            changed_build_files: changed_build_files.iter().map(|p| p.to_string()).collect(),
+            fresh_instance,
+        });
+
+        self.executions.insert(
+            key.dupe(),
+            Execution {
+                generation,
+                changed_inputs: Arc::new(changed_inputs),
+                changed_build_files: Arc::new(changed_build_files),
+            },
+        );
+
+        invalidation
+    }
+
+    fn collect_changed_inputs(
+        &self,
+        log: &ChangeLog,
+        previous: u64,
+        inputs: &IndexMap<ArtifactGroup, ArtifactGroupValues>,
+        changed_inputs: &mut BTreeSet<CellPath>,
+        changed_build_files: &mut BTreeSet<CellPath>,
+    ) {
+        for values in inputs.values() {
+            for (artifact, value) in values.iter() {
+                match artifact.as_parts() {
+                    (BaseArtifactKind::Source(source), projected) => {
+                        let mut path = source.get_path().to_cell_path();
+                        if let Some(projected) = projected {
+                            path = path.join(projected);
+                        }
+                        if value.is_dir() {
+                            changed_inputs.extend(
+                                log.changed
+                                    .iter()
+                                    .filter(|(c, g)| **g > previous && c.starts_with(path.as_ref()))
+                                    .map(|(c, _)| c.clone()),
+                            );
+                        } else if log.changed_since(&path, previous) {
+                            changed_inputs.insert(path);
+                        }
+                    }
+                    (BaseArtifactKind::Build(build), _) => {
+                        let upstream = self.executions.get(build.key()).map(|e| e.clone());
+                        if let Some(upstream) = upstream {
+                            if upstream.generation > previous {
+                                changed_inputs.extend(upstream.changed_inputs.iter().cloned());
+                                changed_build_files
+                                    .extend(upstream.changed_build_files.iter().cloned());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record that `key` resolved to the action `dest`, so that consumers of `key`'s outputs
+    /// inherit `dest`'s attribution.
+    pub(crate) fn redirect(&self, key: &ActionKey, dest: &ActionKey) {
+        let execution = self.executions.get(dest).map(|e| e.clone());
+        if let Some(execution) = execution {
+            self.executions.insert(key.dupe(), execution);
+        }
+    }
+}
+
+pub trait SetActionInvalidationTracker {
+    fn set_action_invalidation_tracker(&mut self, tracker: Arc<ActionInvalidationTracker>);
+}
+
+impl SetActionInvalidationTracker for UserComputationData {
+    fn set_action_invalidation_tracker(&mut self, tracker: Arc<ActionInvalidationTracker>) {
+        self.data.set(tracker);
+    }
+}
+
+pub trait HasActionInvalidationTracker {
+    fn get_action_invalidation_tracker(&self) -> Option<&Arc<ActionInvalidationTracker>>;
+}
+
+impl HasActionInvalidationTracker for UserComputationData {
+    fn get_action_invalidation_tracker(&self) -> Option<&Arc<ActionInvalidationTracker>> {
+        self.data.get::<Arc<ActionInvalidationTracker>>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::buck_path::path::BuckPath;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::paths::CellRelativePathBuf;
+    use buck2_core::configuration::data::ConfigurationData;
+    use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+    use buck2_core::package::package_relative_path::PackageRelativePath;
+    use buck2_core::package::PackageLabel;
+    use buck2_core::target::label::ConfiguredTargetLabel;
+    use buck2_execute::artifact_value::ArtifactValue;
+    use buck2_execute::digest_config::DigestConfig;
+
+    use super::*;
+    use crate::actions::artifact::artifact_type::testing::BuildArtifactTestingExt;
+    use crate::actions::artifact::artifact_type::Artifact;
+    use crate::actions::artifact::build_artifact::BuildArtifact;
+    use crate::actions::artifact::source_artifact::SourceArtifact;
+    use crate::deferred::types::testing::DeferredIdExt;
+    use crate::deferred::types::DeferredId;
+
+    fn cell_path(path: &str) -> CellPath {
+        CellPath::new(
+            CellName::testing_new("root"),
+            CellRelativePathBuf::unchecked_new(path.to_owned()),
+        )
+    }
+
+    fn build_artifact(id: u32) -> BuildArtifact {
+        BuildArtifact::testing_new(
+            ConfiguredTargetLabel::testing_parse("root//pkg:foo", ConfigurationData::testing_new()),
+            ForwardRelativePathBuf::unchecked_new(format!("out{}", id)),
+            DeferredId::testing_new(id),
+        )
+    }
+
+    fn inputs(
+        artifacts: impl IntoIterator<Item = Artifact>,
+    ) -> IndexMap<ArtifactGroup, ArtifactGroupValues> {
+        artifacts
+            .into_iter()
+            .map(|artifact| {
+                let value = ArtifactValue::file(DigestConfig::testing_default().empty_file());
+                (
+                    ArtifactGroup::Artifact(artifact.dupe()),
+                    ArtifactGroupValues::from_artifact(artifact, value),
+                )
+            })
+            .collect()
+    }
+
+    fn source(path: &str) -> Artifact {
+        Artifact::from(SourceArtifact::new(BuckPath::testing_new(
+            PackageLabel::testing_new("root", "pkg"),
+            PackageRelativePath::unchecked_new(path),
+        )))
+    }
+
+    fn paths(paths: &[&str]) -> FileChanges {
+        FileChanges::Paths(paths.iter().map(|p| cell_path(p)).collect())
+    }
+
+    async fn no_definition() -> anyhow::Result<Vec<CellPath>> {
+        Ok(Vec::new())
+    }
+
+    #[tokio::test]
+    async fn test_attribute() {
+        let compile = build_artifact(0);
+        let link = build_artifact(1);
+        let other = build_artifact(2);
+        let tracker = ActionInvalidationTracker::default();
+
+        // Nothing to attribute the first time an action runs.
+        tracker.record_file_changes(&paths(&[]));
+        let compile_inputs = inputs([source("pkg/src.c"), source("pkg/other.c")]);
+        let link_inputs = inputs([Artifact::from(compile.dupe())]);
+        let other_inputs = inputs([source("pkg/other.c")]);
+        for (key, inputs) in [
+            (compile.key(), &compile_inputs),
+            (link.key(), &link_inputs),
+            (other.key(), &other_inputs),
+        ] {
+            assert_eq!(tracker.attribute(key, inputs, no_definition()).await, None);
+        }
+
+        tracker.record_file_changes(&paths(&["pkg/src.c", "pkg/BUCK"]));
+
+        let compiled = tracker
+            .attribute(compile.key(), &compile_inputs, no_definition())
+            .await
+            .unwrap();
+        assert_eq!(compiled.changed_inputs, vec!["root//pkg/src.c".to_owned()]);
+        assert!(compiled.changed_build_files.is_empty());
+
+        // Dependents inherit the attribution of the actions producing their inputs.
+        let linked = tracker
+            .attribute(link.key(), &link_inputs, no_definition())
+            .await
+            .unwrap();
+        assert_eq!(linked.changed_inputs, vec!["root//pkg/src.c".to_owned()]);
+
+        // Without a changed input, we fall back to the changed files defining the action.
+        let redefined = tracker
+            .attribute(other.key(), &other_inputs, async {
+                Ok(vec![cell_path("pkg/BUCK"), cell_path("defs.bzl")])
+            })
+            .await
+            .unwrap();
+        assert!(redefined.changed_inputs.is_empty());
+        assert_eq!(
+            redefined.changed_build_files,
+            vec!["root//pkg/BUCK".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changes_accumulate_until_the_action_runs() {
+        let compile = build_artifact(0);
+        let compile_inputs = inputs([source("pkg/a.c"), source("pkg/b.c")]);
+        let tracker = ActionInvalidationTracker::default();
+
+        tracker.record_file_changes(&paths(&[]));
+        tracker
+            .attribute(compile.key(), &compile_inputs, no_definition())
+            .await;
+
+        // Changes synced by commands that did not run the action are not lost.
+        tracker.record_file_changes(&paths(&["pkg/a.c"]));
+        tracker.record_file_changes(&paths(&["pkg/b.c"]));
+        let compiled = tracker
+            .attribute(compile.key(), &compile_inputs, no_definition())
+            .await
+            .unwrap();
+        assert_eq!(
+            compiled.changed_inputs,
+            vec!["root//pkg/a.c".to_owned(), "root//pkg/b.c".to_owned()]
+        );
+
+        // But they don't carry over to the next time it runs.
+        tracker.record_file_changes(&paths(&["pkg/b.c"]));
+        let compiled = tracker
+            .attribute(compile.key(), &compile_inputs, no_definition())
+            .await
+            .unwrap();
+        assert_eq!(compiled.changed_inputs, vec!["root//pkg/b.c".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_fresh_instance() {
+        let compile = build_artifact(0);
+        let tracker = ActionInvalidationTracker::default();
+
+        tracker.record_file_changes(&paths(&[]));
+        tracker
+            .attribute(compile.key(), &inputs([]), no_definition())
+            .await;
+
+        tracker.record_file_changes(&FileChanges::FreshInstance);
+        let attributed = tracker
+            .attribute(compile.key(), &inputs([]), no_definition())
+            .await
+            .unwrap();
+        assert!(attributed.fresh_instance);
+
+        tracker.record_file_changes(&paths(&[]));
+        let attributed = tracker
+            .attribute(compile.key(), &inputs([]), no_definition())
+            .await
+            .unwrap();
+        assert!(!attributed.fresh_instance);
+    }
+}
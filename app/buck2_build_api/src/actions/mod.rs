@@ -29,6 +29,7 @@ pub mod build_listener;
 pub mod calculation;
 pub mod execute;
 pub mod impls;
+pub mod invalidation;
 pub mod key;
 pub(crate) mod registry;
 
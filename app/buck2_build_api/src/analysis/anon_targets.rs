@@ -41,6 +41,7 @@ use buck2_data::ToProtoMessage;
 use buck2_events::dispatch::get_dispatcher;
 use buck2_events::dispatch::span_async;
 use buck2_execute::digest_config::HasDigestConfig;
+use buck2_interpreter::starlark_debug::hook_starlark_debugger;
 use buck2_interpreter::starlark_promise::StarlarkPromise;
 use buck2_interpreter::types::label::Label;
 use buck2_interpreter_for_build::attrs::coerce::attr_type::AttrTypeInnerExt;
@@ -366,6 +367,8 @@ impl AnonTargetKey {
                 let analysis_registry = {
                     let mut eval = Evaluator::new(&env);
                     eval.set_print_handler(&print);
+                    let _debug =
+                        hook_starlark_debugger(&mut eval, || format!("analysis of {}", self.0));
 
                     // No attributes are allowed to contain macros or other stuff, so an empty resolution context works
                     let resolution_ctx = RuleAnalysisAttrResolutionContext {
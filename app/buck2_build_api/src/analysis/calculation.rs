@@ -381,8 +381,8 @@ pub async fn profile_analysis(
     .context("profile_data not set (internal error)")
 }
 
-fn all_deps(node: ConfiguredTargetNode) -> LabelIndexedSet<ConfiguredTargetNode> {
-    let mut stack = vec![node];
+fn all_deps(nodes: Vec<ConfiguredTargetNode>) -> LabelIndexedSet<ConfiguredTargetNode> {
+    let mut stack = nodes;
     let mut visited = LabelIndexedSet::new();
     while let Some(node) = stack.pop() {
         if visited.insert(node.dupe()) {
@@ -395,6 +395,40 @@ fn all_deps(node: ConfiguredTargetNode) -> LabelIndexedSet<ConfiguredTargetNode>
 pub async fn profile_analysis_recursively(
     ctx: &DiceComputations,
     target: &ConfiguredTargetLabel,
+) -> anyhow::Result<StarlarkProfileDataAndStats> {
+    let node = ctx
+        .get_configured_target_node(target)
+        .await?
+        .require_compatible()?;
+
+    profile_analysis_of_nodes_recursively(ctx, vec![node]).await
+}
+
+/// Like `profile_analysis_recursively`, but for many targets, merging the profiles of all the
+/// analysis they need. Incompatible targets are skipped.
+pub async fn profile_analysis_recursively_aggregated(
+    ctx: &DiceComputations,
+    targets: &[ConfiguredTargetLabel],
+) -> anyhow::Result<StarlarkProfileDataAndStats> {
+    let mut futures = targets
+        .iter()
+        .map(|target| ctx.get_configured_target_node(target))
+        .collect::<FuturesOrdered<_>>();
+
+    let mut nodes = Vec::new();
+    while let Some(node) = futures.next().await {
+        match node? {
+            MaybeCompatible::Compatible(node) => nodes.push(node),
+            MaybeCompatible::Incompatible(_) => {}
+        }
+    }
+
+    profile_analysis_of_nodes_recursively(ctx, nodes).await
+}
+
+async fn profile_analysis_of_nodes_recursively(
+    ctx: &DiceComputations,
+    nodes: Vec<ConfiguredTargetNode>,
 ) -> anyhow::Result<StarlarkProfileDataAndStats> {
     // Self check.
     let profile_mode = ctx.get_profile_mode_for_intermediate_analysis().await?;
@@ -405,12 +439,7 @@ pub async fn profile_analysis_recursively(
         return Err(ProfileAnalysisError::RecursiveProfileConfiguredIncorrectly.into());
     }
 
-    let node = ctx
-        .get_configured_target_node(target)
-        .await?
-        .require_compatible()?;
-
-    let all_deps = all_deps(node);
+    let all_deps = all_deps(nodes);
 
     let mut futures = all_deps
         .iter()
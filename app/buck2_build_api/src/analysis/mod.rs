@@ -19,6 +19,7 @@ use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_core::unsafe_send_future::UnsafeSendFuture;
 use buck2_events::dispatch::get_dispatcher;
 use buck2_execute::digest_config::HasDigestConfig;
+use buck2_interpreter::starlark_debug::hook_starlark_debugger;
 use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
 use buck2_interpreter::starlark_profiler::StarlarkProfileModeOrInstrumentation;
 use buck2_interpreter::starlark_profiler::StarlarkProfiler;
@@ -334,6 +335,7 @@ async fn run_analysis_with_env_underlying(
     let analysis_registry = {
         let mut eval = Evaluator::new(&env);
         eval.set_print_handler(&print);
+        let _debug = hook_starlark_debugger(&mut eval, || format!("analysis of {}", node.label()));
 
         let ctx = env.heap().alloc_typed(AnalysisContext::new(
             eval.heap(),
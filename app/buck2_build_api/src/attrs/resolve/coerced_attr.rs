@@ -20,7 +20,7 @@ enum CoercedAttrResolveError {
     AttrCannotBeConvertedToValue(String),
 }
 
-pub(crate) trait CoercedAttrResolveExt {
+pub trait CoercedAttrResolveExt {
     fn to_value<'v>(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>>;
 }
 
@@ -9,7 +9,7 @@
 
 pub(crate) mod attr_literal;
 pub mod attr_type;
-pub(crate) mod coerced_attr;
+pub mod coerced_attr;
 pub mod configured_attr;
 pub mod ctx;
 #[cfg(test)]
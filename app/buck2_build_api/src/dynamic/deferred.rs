@@ -17,6 +17,7 @@ use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersName;
 use buck2_data::ToProtoMessage;
 use buck2_events::dispatch::get_dispatcher;
+use buck2_interpreter::starlark_debug::hook_starlark_debugger;
 use buck2_interpreter::types::label::Label;
 use buck2_interpreter_for_build::interpreter::print_handler::EventDispatcherPrintHandler;
 use dupe::Dupe;
@@ -192,6 +193,8 @@ impl Deferred for DynamicLambda {
         let analysis_registry = {
             let mut eval = Evaluator::new(&env);
             eval.set_print_handler(&print);
+            let _debug =
+                hook_starlark_debugger(&mut eval, || format!("dynamic_output of {}", self.owner));
 
             let data = TupleRef::from_value(self.attributes_lambda.owned_value(env.frozen_heap()))
                 .unwrap();
@@ -9,10 +9,12 @@
 
 use std::iter::empty;
 use std::iter::once;
+use std::str::FromStr;
 
 use allocative::Allocative;
 use anyhow::Context as _;
 use buck2_build_api_derive::internal_provider;
+use dupe::Dupe;
 use either::Either;
 use starlark::any::ProvidesStaticType;
 use starlark::coerce::Coerce;
@@ -93,6 +95,45 @@ pub struct ExternalRunnerTestInfoGen<V> {
     /// This is of type {str.type: CommandExecutorConfig}
     #[provider(field_type = "DictType<String, StarlarkCommandExecutorConfig>")]
     executor_overrides: V,
+
+    /// The format of the coverage data this test produces when run with `buck2 test --coverage`.
+    /// Buck2 sets up the environment so the data ends up in a directory it collects. Tests that
+    /// don't set this don't collect coverage.
+    /// This is of type str.type, one of `lcov`, `llvm`, `gcov` or `python`.
+    #[provider(field_type = "String")]
+    coverage_format: V,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Unknown coverage format `{0}`, expected one of `lcov`, `llvm`, `gcov` or `python`")]
+pub struct UnknownCoverageFormat(String);
+
+/// The format of the coverage data a test produces, set via `coverage_format`.
+#[derive(Clone, Copy, Dupe, Debug, PartialEq, Eq)]
+pub enum TestCoverageFormat {
+    /// LCOV tracefiles, written by the test to `$BUCK2_COVERAGE_DIR`.
+    Lcov,
+    /// LLVM raw profiles, written via `LLVM_PROFILE_FILE`. The first element of `command` must be
+    /// the instrumented binary.
+    Llvm,
+    /// gcov data files, written via `GCOV_PREFIX`.
+    Gcov,
+    /// coverage.py data files, written via `COVERAGE_FILE`.
+    Python,
+}
+
+impl FromStr for TestCoverageFormat {
+    type Err = UnknownCoverageFormat;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lcov" => Ok(Self::Lcov),
+            "llvm" => Ok(Self::Llvm),
+            "gcov" => Ok(Self::Gcov),
+            "python" => Ok(Self::Python),
+            _ => Err(UnknownCoverageFormat(s.to_owned())),
+        }
+    }
 }
 
 // NOTE: All the methods here unwrap because we validate at freeze time.
@@ -143,6 +184,10 @@ impl FrozenExternalRunnerTestInfo {
             .map(|v| StarlarkCommandExecutorConfig::from_value(v.to_value()).unwrap())
     }
 
+    pub fn coverage_format(&self) -> Option<TestCoverageFormat> {
+        unpack_opt_coverage_format(self.coverage_format.to_value()).unwrap()
+    }
+
     pub fn visit_artifacts(
         &self,
         visitor: &mut dyn CommandLineArtifactVisitor,
@@ -337,6 +382,18 @@ fn unpack_opt_executor<'v>(
     Ok(Some(executor))
 }
 
+fn unpack_opt_coverage_format(format: Value) -> anyhow::Result<Option<TestCoverageFormat>> {
+    if format.is_none() {
+        return Ok(None);
+    }
+
+    let format = format
+        .unpack_str()
+        .with_context(|| format!("Expected a str, got: `{}`", format))?;
+
+    Ok(Some(format.parse()?))
+}
+
 fn check_all<I, T>(it: I) -> anyhow::Result<()>
 where
     I: IntoIterator<Item = anyhow::Result<T>>,
@@ -370,6 +427,8 @@ where
     NoneOr::<bool>::unpack_value(info.run_from_project_root.to_value())
         .context("`run_from_project_root` must be a bool if provided")?;
     unpack_opt_executor(info.default_executor.to_value()).context("Invalid `default_executor`")?;
+    unpack_opt_coverage_format(info.coverage_format.to_value())
+        .context("Invalid `coverage_format`")?;
     info.test_type
         .to_value()
         .unpack_str()
@@ -390,6 +449,7 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
         #[starlark(default = NoneType)] run_from_project_root: Value<'v>,
         #[starlark(default = NoneType)] default_executor: Value<'v>,
         #[starlark(default = NoneType)] executor_overrides: Value<'v>,
+        #[starlark(default = NoneType)] coverage_format: Value<'v>,
     ) -> anyhow::Result<ExternalRunnerTestInfo<'v>> {
         let res = ExternalRunnerTestInfo {
             test_type: r#type,
@@ -401,6 +461,7 @@ fn external_runner_test_info_creator(globals: &mut GlobalsBuilder) {
             run_from_project_root,
             default_executor,
             executor_overrides,
+            coverage_format,
         };
         validate_external_runner_test_info(&res)?;
         Ok(res)
@@ -437,6 +498,7 @@ mod tests {
                 ExternalRunnerTestInfo(type = "foo", labels = ("foo",))
                 ExternalRunnerTestInfo(type = "foo", use_project_relative_paths = True)
                 ExternalRunnerTestInfo(type = "foo", run_from_project_root = True)
+                ExternalRunnerTestInfo(type = "foo", coverage_format = "llvm")
             "#
         );
         let mut tester = tester();
@@ -587,6 +649,16 @@ mod tests {
             "`executor_overrides`",
         );
 
+        tester.run_starlark_bzl_test_expecting_error(
+            indoc!(
+                r#"
+            def test():
+                ExternalRunnerTestInfo(type = "foo", coverage_format = "jacoco")
+            "#
+            ),
+            "`coverage_format`",
+        );
+
         Ok(())
     }
 
@@ -9,6 +9,7 @@
 
 pub mod calculation;
 pub mod lookup;
+pub mod watch_inputs;
 
 use buck2_node::attrs::coerced_attr::CoercedAttr;
 
@@ -0,0 +1,92 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Files a build depends on, for `--watch`.
+
+use std::collections::HashSet;
+
+use buck2_common::pattern::resolve::ResolvedPattern;
+use buck2_common::watch_inputs::WatchInputs;
+use buck2_core::pattern::PackageSpec;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::pattern::PatternType;
+use buck2_core::target::label::TargetLabel;
+use buck2_interpreter::path::PackageFilePath;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use dice::DiceComputations;
+use dupe::Dupe;
+
+/// Collect the files building the targets matched by `patterns` depends on:
+/// directories of all packages in the unconfigured dependency graph of the targets,
+/// `PACKAGE` files above them, and `.bzl` files they load.
+///
+/// Unconfigured dependencies include all `select()` branches, so this is a superset
+/// of what the build depends on.
+pub async fn collect_watch_inputs<T: PatternType>(
+    ctx: &DiceComputations,
+    patterns: &[ParsedPattern<T>],
+    resolved: &ResolvedPattern<T>,
+) -> anyhow::Result<WatchInputs> {
+    let mut inputs = WatchInputs::default();
+    for pattern in patterns {
+        // New packages under recursive patterns add targets to the build.
+        if let ParsedPattern::Recursive(dir) = pattern {
+            inputs.add_dir(dir.clone());
+        }
+    }
+
+    let mut queue = Vec::new();
+    for (package, spec) in &resolved.specs {
+        match spec {
+            PackageSpec::All => {
+                let eval_result = ctx.get_interpreter_results(package.dupe()).await?;
+                queue.extend(
+                    eval_result
+                        .targets()
+                        .values()
+                        .map(|node| node.label().dupe()),
+                );
+            }
+            PackageSpec::Targets(targets) => queue.extend(
+                targets
+                    .iter()
+                    .map(|(name, _)| TargetLabel::new(package.dupe(), name.as_ref())),
+            ),
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut packages = HashSet::new();
+    let mut imports = HashSet::new();
+    while let Some(label) = queue.pop() {
+        if !visited.insert(label.dupe()) {
+            continue;
+        }
+        let package = label.pkg();
+        let eval_result = ctx.get_interpreter_results(package.dupe()).await?;
+        if packages.insert(package.dupe()) {
+            inputs.add_dir(package.to_cell_path());
+            for dir in package.as_cell_path().ancestors() {
+                inputs.add_file(dir.join(PackageFilePath::PACKAGE_FILE_NAME));
+            }
+            let mut loads = eval_result.imports().to_vec();
+            while let Some(import) = loads.pop() {
+                if !imports.insert(import.clone()) {
+                    continue;
+                }
+                inputs.add_file(import.path().clone());
+                let module = ctx.get_loaded_module_from_import_path(&import).await?;
+                loads.extend(module.imports().cloned());
+            }
+        }
+        let node = eval_result.resolve_target(label.name())?;
+        queue.extend(node.deps().map(|dep| dep.dupe()));
+    }
+    Ok(inputs)
+}
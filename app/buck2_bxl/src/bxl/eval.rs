@@ -34,6 +34,7 @@ use buck2_events::dispatch::with_dispatcher;
 use buck2_execute::digest_config::HasDigestConfig;
 use buck2_interpreter::file_loader::LoadedModule;
 use buck2_interpreter::path::StarlarkModulePath;
+use buck2_interpreter::starlark_debug::hook_starlark_debugger;
 use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
 use buck2_interpreter::starlark_profiler::StarlarkProfileModeOrInstrumentation;
 use buck2_interpreter::starlark_profiler::StarlarkProfiler;
@@ -147,6 +148,8 @@ pub async fn eval(
                     let bxl_function_name = key.label().name.clone();
                     let frozen_callable = get_bxl_callable(key.label(), &bxl_module)?;
                     eval.set_print_handler(&print);
+                    let _debug =
+                        hook_starlark_debugger(&mut eval, || format!("bxl {}", key.label()));
 
                     let materializations = *key.materializations();
 
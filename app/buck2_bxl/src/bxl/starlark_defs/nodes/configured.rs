@@ -275,6 +275,25 @@ fn configured_target_node_value_methods(builder: &mut MethodsBuilder) {
         Ok(this.0.rule_type().to_string())
     }
 
+    /// Returns a dict of values set with `write_package_value` in `PACKAGE` files
+    /// applicable to this target node.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_package_values(ctx):
+    ///     node = ctx.configured_targets("my_cell//bin:the_binary")
+    ///     ctx.output.print(node.package_values().get("ownership.team"))
+    /// ```
+    fn package_values<'v>(
+        this: &StarlarkConfiguredTargetNode,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        this.0
+            .package_values()
+            .to_configured_attr()
+            .to_value(this.0.label().pkg().dupe(), heap)
+    }
+
     /// Returns a List of all the sources used by this node.
     ///
     /// Sample usage:
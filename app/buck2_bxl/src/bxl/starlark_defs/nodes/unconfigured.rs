@@ -8,6 +8,7 @@
  */
 
 use allocative::Allocative;
+use buck2_build_api::attrs::resolve::coerced_attr::CoercedAttrResolveExt;
 use buck2_interpreter::types::target_label::StarlarkTargetLabel;
 use buck2_node::attrs::inspect_options::AttrInspectOptions;
 use buck2_node::nodes::unconfigured::TargetNode;
@@ -106,6 +107,19 @@ fn target_node_value_methods(builder: &mut MethodsBuilder) {
         Ok(heap.alloc(AllocStruct(attrs)))
     }
 
+    /// Returns a dict of values set with `write_package_value` in `PACKAGE` files
+    /// applicable to this target node.
+    ///
+    /// Sample usage:
+    /// ```text
+    /// def _impl_package_values(ctx):
+    ///     target_node = ctx.uquery().eval("owner('path/to/file')")[0]
+    ///     ctx.output.print(target_node.package_values().get("ownership.team"))
+    /// ```
+    fn package_values<'v>(this: &StarlarkTargetNode, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        this.0.package_values().to_coerced_attr().to_value(heap)
+    }
+
     /// Gets the label from the unconfigured target node.
     ///
     /// Sample usage:
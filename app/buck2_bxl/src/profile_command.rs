@@ -128,7 +128,7 @@ impl ServerCommandTemplate for BxlProfileServerCommand {
                     }
                 };
 
-                get_profile_response(profile_data, &self.req, output)
+                get_profile_response(profile_data, &self.req, server_ctx.project_root(), output)
             }
             _ => {
                 return Err(anyhow::anyhow!(
@@ -62,6 +62,69 @@ impl From<LspRequest> for StreamingRequest {
     }
 }
 
+impl TryFrom<StreamingRequest> for BspRequest {
+    type Error = tonic::Status;
+
+    fn try_from(value: StreamingRequest) -> Result<Self, Self::Error> {
+        match value.request {
+            Some(streaming_request::Request::Bsp(req)) => Ok(req),
+            _ => Err(tonic::Status::invalid_argument(
+                "messages sent by client must be of type `BspRequest`",
+            )),
+        }
+    }
+}
+
+impl From<BspRequest> for StreamingRequest {
+    fn from(request: BspRequest) -> Self {
+        Self {
+            request: Some(streaming_request::Request::Bsp(request)),
+        }
+    }
+}
+
+impl TryFrom<StreamingRequest> for DapRequest {
+    type Error = tonic::Status;
+
+    fn try_from(value: StreamingRequest) -> Result<Self, Self::Error> {
+        match value.request {
+            Some(streaming_request::Request::Dap(req)) => Ok(req),
+            _ => Err(tonic::Status::invalid_argument(
+                "messages sent by client must be of type `DapRequest`",
+            )),
+        }
+    }
+}
+
+impl From<DapRequest> for StreamingRequest {
+    fn from(request: DapRequest) -> Self {
+        Self {
+            request: Some(streaming_request::Request::Dap(request)),
+        }
+    }
+}
+
+impl TryFrom<StreamingRequest> for StarlarkReplRequest {
+    type Error = tonic::Status;
+
+    fn try_from(value: StreamingRequest) -> Result<Self, Self::Error> {
+        match value.request {
+            Some(streaming_request::Request::StarlarkRepl(req)) => Ok(req),
+            _ => Err(tonic::Status::invalid_argument(
+                "messages sent by client must be of type `StarlarkReplRequest`",
+            )),
+        }
+    }
+}
+
+impl From<StarlarkReplRequest> for StreamingRequest {
+    fn from(request: StarlarkReplRequest) -> Self {
+        Self {
+            request: Some(streaming_request::Request::StarlarkRepl(request)),
+        }
+    }
+}
+
 impl TryFrom<StreamingRequest> for SubscriptionRequestWrapper {
     type Error = tonic::Status;
 
@@ -283,12 +346,17 @@ result_convert!(InstallResponse);
 result_convert!(MaterializeResponse);
 result_convert!(CleanStaleResponse);
 result_convert!(LspResponse);
+result_convert!(BspResponse);
+result_convert!(DapResponse);
+result_convert!(StarlarkReplResponse);
 result_convert!(AllocativeResponse);
 result_convert!(SubscriptionCommandResponse);
 result_convert!(TraceIoResponse);
 
 partial_result_convert!(StdoutBytes);
 partial_result_convert!(LspMessage);
+partial_result_convert!(DapMessage);
+partial_result_convert!(StarlarkReplMessage);
 partial_result_convert!(SubscriptionResponseWrapper);
 
 define_request!(KillRequest);
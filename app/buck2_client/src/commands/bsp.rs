@@ -0,0 +1,128 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use async_trait::async_trait;
+use buck2_cli_proto::BspRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::common::CommonBuildConfigurationOptions;
+use buck2_client_ctx::common::CommonConsoleOptions;
+use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::common::ConsoleType;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::stream_util::reborrow_stream_for_static;
+use buck2_client_ctx::streaming::StreamingCommand;
+use buck2_client_ctx::version::BuckVersion;
+use futures::stream::StreamExt;
+use lsp_server::Message;
+use lsp_server::Notification;
+use once_cell::sync::Lazy;
+use tokio_util::codec::FramedRead;
+
+use crate::commands::lsp::LspMessageDecoder;
+
+#[derive(Debug, clap::Parser)]
+#[clap(about = "Start a Build Server Protocol (BSP) server for IDEs")]
+pub struct BspCommand {
+    #[clap(
+        name = "TARGET_PATTERNS",
+        help = "Patterns of the targets to expose to the IDE",
+        default_value = "//..."
+    )]
+    patterns: Vec<String>,
+
+    #[clap(flatten)]
+    config_opts: CommonBuildConfigurationOptions,
+
+    #[clap(flatten)]
+    event_log_opts: CommonDaemonCommandOptions,
+}
+
+#[async_trait]
+impl StreamingCommand for BspCommand {
+    const COMMAND_NAME: &'static str = "bsp";
+
+    async fn exec_impl(
+        self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        mut ctx: ClientCommandContext,
+    ) -> ExitResult {
+        let client_context =
+            ctx.client_context(&self.config_opts, matches, self.sanitized_argv())?;
+
+        // BSP uses the same message framing as LSP.
+        let messages = FramedRead::new(ctx.stdin(), LspMessageDecoder).filter_map(|m| {
+            let m = m.and_then(|m| {
+                let bsp_json = serde_json::to_string(&m)?;
+                Ok(BspRequest {
+                    bsp_json,
+                    ..Default::default()
+                })
+            });
+
+            futures::future::ready(match m {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    let _ignored =
+                        buck2_client_ctx::eprintln!("Could not read message from stdin: `{}`", e);
+                    None
+                }
+            })
+        });
+
+        let init = BspRequest {
+            bsp_json: String::new(),
+            target_patterns: self.patterns,
+            buck2_version: BuckVersion::get().version().to_owned(),
+        };
+        let stream = futures::stream::once(futures::future::ready(init)).chain(messages);
+
+        reborrow_stream_for_static(
+            stream,
+            |stream| async move { buckd.with_flushing().bsp(client_context, stream).await },
+            // Shut the server down when the IDE closes stdin without sending `build/exit`.
+            || {
+                let exit = Message::Notification(Notification::new(
+                    "build/exit".to_owned(),
+                    serde_json::Value::Null,
+                ));
+                Some(BspRequest {
+                    bsp_json: serde_json::to_string(&exit).ok()?,
+                    ..Default::default()
+                })
+            },
+        )
+        .await??;
+
+        ExitResult::success()
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        // Stdout is the protocol channel, so only use the simple console.
+        static SIMPLE_CONSOLE: Lazy<CommonConsoleOptions> = Lazy::new(|| CommonConsoleOptions {
+            console_type: ConsoleType::Simple,
+            ui: vec![],
+            no_interactive_console: true,
+        });
+        &SIMPLE_CONSOLE
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.config_opts
+    }
+
+    fn should_show_waiting_message(&self) -> bool {
+        false
+    }
+}
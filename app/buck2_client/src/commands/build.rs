@@ -12,6 +12,8 @@ use std::collections::HashMap;
 use std::io;
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
 
 use anyhow::Context;
 use async_trait::async_trait;
@@ -178,6 +180,16 @@ pub struct BuildCommand {
     )]
     output_path: Option<OutPath>,
 
+    /// Keep running, and rebuild when the file watcher reports changes to the build inputs.
+    /// Stop with Ctrl-C.
+    #[clap(long)]
+    watch: bool,
+
+    /// Write the line coverage of the Starlark run by analysis of the requested targets and their
+    /// dependencies to this file, in LCOV format.
+    #[clap(long, value_name = "PATH")]
+    starlark_coverage: Option<PathArg>,
+
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to build")]
     patterns: Vec<String>,
 }
@@ -236,6 +248,47 @@ pub fn print_build_result(console: &FinalConsole, error_messages: &[String]) ->
     Ok(())
 }
 
+/// Report the result of one `--watch` cycle.
+///
+/// Returns the result to exit with if watching should stop, which is the case on Ctrl-C
+/// or on other client failures.
+pub(crate) fn finish_watch_cycle(
+    console: &FinalConsole,
+    cycle: u64,
+    duration: Duration,
+    result: ExitResult,
+    watch_token: &mut Option<String>,
+) -> Option<ExitResult> {
+    if let ExitResult::Err(e) = &result {
+        if e.downcast_ref::<FailureExitCode>().is_some() {
+            return Some(result);
+        }
+        if console
+            .print_error(&format!("Command failed: {:?}", e))
+            .is_err()
+        {
+            return Some(result);
+        }
+    }
+    // If the command failed before returning a token, wait for changes
+    // relative to the current daemon state.
+    watch_token.get_or_insert_with(String::new);
+    let status = if result.is_success() {
+        "succeeded"
+    } else {
+        "failed"
+    };
+    match console.print_stderr(&format!(
+        "Cycle {} {} in {:.1}s. Watching for changes, press Ctrl-C to stop.",
+        cycle,
+        status,
+        duration.as_secs_f64()
+    )) {
+        Ok(()) => None,
+        Err(e) => Some(ExitResult::Err(e)),
+    }
+}
+
 #[async_trait]
 impl StreamingCommand for BuildCommand {
     const COMMAND_NAME: &'static str = "build";
@@ -245,6 +298,49 @@ impl StreamingCommand for BuildCommand {
         buckd: &mut BuckdClientConnector,
         matches: &clap::ArgMatches,
         mut ctx: ClientCommandContext,
+    ) -> ExitResult {
+        if !self.watch {
+            return self.build_once(buckd, matches, &mut ctx, &mut None).await;
+        }
+        let console = self.common_opts.console_opts.final_console();
+        let mut watch_token = None;
+        let mut cycle = 0;
+        loop {
+            cycle += 1;
+            let start = Instant::now();
+            let result = self
+                .build_once(buckd, matches, &mut ctx, &mut watch_token)
+                .await;
+            if let Some(result) =
+                finish_watch_cycle(&console, cycle, start.elapsed(), result, &mut watch_token)
+            {
+                return result;
+            }
+        }
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+}
+
+impl BuildCommand {
+    /// Run a single build. With `--watch`, `watch_token` is the state to wait for
+    /// changes from, and is replaced with the state the build ran against.
+    async fn build_once(
+        &self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext,
+        watch_token: &mut Option<String>,
     ) -> ExitResult {
         let show_default_other_outputs = false;
         let context = ctx.client_context(
@@ -252,6 +348,12 @@ impl StreamingCommand for BuildCommand {
             matches,
             self.sanitized_argv(),
         )?;
+        let starlark_coverage_filename = self
+            .starlark_coverage
+            .as_ref()
+            .map(|p| p.resolve(&ctx.working_dir).into_string())
+            .transpose()?
+            .unwrap_or_default();
 
         let result = buckd
             .with_flushing()
@@ -275,9 +377,13 @@ impl StreamingCommand for BuildCommand {
                             || self.output_path.is_some(),
                         return_default_other_outputs: show_default_other_outputs,
                     }),
-                    build_opts: Some(self.build_opts.to_proto()),
+                    build_opts: Some(buck2_cli_proto::CommonBuildOptions {
+                        starlark_coverage_filename,
+                        ..self.build_opts.to_proto()
+                    }),
                     final_artifact_materializations: self.materializations.to_proto() as i32,
-                    target_universe: self.target_universe,
+                    target_universe: self.target_universe.clone(),
+                    watch_token: watch_token.take(),
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -301,6 +407,7 @@ impl StreamingCommand for BuildCommand {
         // Action errors will have already been printed, but any other type
         // of error will be printed below the FAILED line here.
         let response = result??;
+        *watch_token = Some(response.watch_token);
 
         print_build_result(&console, &response.error_messages)?;
 
@@ -343,18 +450,6 @@ impl StreamingCommand for BuildCommand {
 
         ExitResult::success()
     }
-
-    fn console_opts(&self) -> &CommonConsoleOptions {
-        &self.common_opts.console_opts
-    }
-
-    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
-        &self.common_opts.event_log_opts
-    }
-
-    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
-        &self.common_opts.config_opts
-    }
 }
 
 pub(crate) fn print_outputs(
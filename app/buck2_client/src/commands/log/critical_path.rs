@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::HashMap;
 use std::fmt;
 use std::time::Duration;
 
@@ -39,6 +40,9 @@ impl CriticalPathCommand {
             let (invocation, mut events) = log_path.unpack_stream().await?;
             buck2_client_ctx::eprintln!("Showing critical path from: {}", invocation)?;
 
+            // Execution stats of the command shown for each action, by action span.
+            let mut execution_stats = HashMap::new();
+
             while let Some(event) = events.try_next().await? {
                 match event {
                     StreamValue::Event(event) => match event.data {
@@ -47,11 +51,24 @@ impl CriticalPathCommand {
                                 Some(buck2_data::instant_event::Data::BuildGraphInfo(
                                     build_graph,
                                 )) => {
-                                    log_critical_path(&build_graph)?;
+                                    log_critical_path(&build_graph, &execution_stats)?;
                                 }
                                 _ => {}
                             }
                         }
+                        Some(buck2_data::buck_event::Data::SpanEnd(span)) => match span.data {
+                            Some(buck2_data::span_end_event::Data::ActionExecution(action)) => {
+                                if let Some(stats) = action
+                                    .commands
+                                    .last()
+                                    .and_then(|c| c.details.as_ref())
+                                    .and_then(|d| d.execution_stats)
+                                {
+                                    execution_stats.insert(event.span_id, stats);
+                                }
+                            }
+                            _ => {}
+                        },
                         _ => {}
                     },
                     _ => {}
@@ -65,9 +82,36 @@ impl CriticalPathCommand {
     }
 }
 
-fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> anyhow::Result<()> {
+fn log_critical_path(
+    critical_path: &buck2_data::BuildGraphExecutionInfo,
+    execution_stats: &HashMap<u64, buck2_data::CommandExecutionStats>,
+) -> anyhow::Result<()> {
     let target_display_options = TargetDisplayOptions::for_log();
 
+    // Execution stats are in the same order as in `buck2 log what-ran --format json`.
+    buck2_client_ctx::println!(
+        "{}",
+        [
+            "kind",
+            "name",
+            "category",
+            "identifier",
+            "total_duration_us",
+            "user_duration_us",
+            "potential_improvement_duration_us",
+            "cpu_instructions_user",
+            "cpu_instructions_kernel",
+            "peak_rss_bytes",
+            "user_cpu_time_us",
+            "system_cpu_time_us",
+            "syscall_read_bytes",
+            "syscall_write_bytes",
+            "block_read_bytes",
+            "block_write_bytes",
+        ]
+        .join("\t")
+    )?;
+
     for entry in &critical_path.critical_path2 {
         use buck2_data::critical_path_entry2::Entry;
 
@@ -162,8 +206,27 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
             }
         }
 
+        struct OptionalValue {
+            inner: Option<u64>,
+        }
+
+        impl fmt::Display for OptionalValue {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if let Some(inner) = self.inner {
+                    write!(f, "{}", inner)?;
+                }
+                Ok(())
+            }
+        }
+
+        let stats = entry
+            .span_id
+            .and_then(|span_id| execution_stats.get(&span_id))
+            .copied()
+            .unwrap_or_default();
+
         buck2_client_ctx::println!(
-            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
             kind,
             name,
             category,
@@ -171,6 +234,33 @@ fn log_critical_path(critical_path: &buck2_data::BuildGraphExecutionInfo) -> any
             OptionalDuration::new(entry.total_duration.clone())?,
             OptionalDuration::new(entry.user_duration.clone())?,
             OptionalDuration::new(entry.potential_improvement_duration.clone())?,
+            OptionalValue {
+                inner: stats.cpu_instructions_user
+            },
+            OptionalValue {
+                inner: stats.cpu_instructions_kernel
+            },
+            OptionalValue {
+                inner: stats.peak_rss_bytes
+            },
+            OptionalValue {
+                inner: stats.user_cpu_time_us
+            },
+            OptionalValue {
+                inner: stats.system_cpu_time_us
+            },
+            OptionalValue {
+                inner: stats.syscall_read_bytes
+            },
+            OptionalValue {
+                inner: stats.syscall_write_bytes
+            },
+            OptionalValue {
+                inner: stats.block_read_bytes
+            },
+            OptionalValue {
+                inner: stats.block_write_bytes
+            },
         )?;
     }
 
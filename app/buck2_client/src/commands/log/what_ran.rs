@@ -55,6 +55,12 @@ use crate::commands::log::LogCommandOutputFormat;
 /// To reproduce an action that ran locally, make sure your working directory is the project root
 /// (if unsure, use `buck2 root --kind project` to find it), then run the command. The command is
 /// already shell-quoted.
+///
+///
+/// With `--why`, an extra record explains why each build action ran: the changed source files
+/// among its inputs (directly, or through dependencies that were rebuilt), or failing that the
+/// changed build files, Starlark files and buckconfigs. Only changes picked up by the file watcher
+/// at the start of that invocation are considered.
 #[derive(Debug, clap::Parser)]
 pub struct WhatRanCommand {
     #[clap(flatten)]
@@ -81,6 +87,10 @@ pub struct WhatRanCommandCommon {
 
     #[clap(flatten)]
     pub options: WhatRanOptions,
+
+    /// Show which file changes caused each build action to run
+    #[clap(long)]
+    pub why: bool,
 }
 
 impl WhatRanCommand {
@@ -89,12 +99,18 @@ impl WhatRanCommand {
             common:
                 WhatRanCommandCommon {
                     event_log,
-                    mut output,
+                    output,
                     options,
+                    why,
                 },
             failed,
         } = self;
 
+        let mut output = WhatRanCommandOutput {
+            format: output,
+            why,
+        };
+
         let rt = runtime::Builder::new_current_thread()
             .enable_all()
             .build()?;
@@ -141,16 +157,73 @@ trait WhatRanComandImplementation: Default {
             }
         }
 
+        cmd.finish(output, options)
+    }
+
+    /// Called once all events have been received.
+    fn finish(
+        &mut self,
+        _output: &mut impl WhatRanOutputWriter,
+        _options: &WhatRanOptions,
+    ) -> anyhow::Result<()> {
         Ok(())
     }
 }
 
+fn action_execution_end(
+    data: &buck2_data::buck_event::Data,
+) -> Option<&buck2_data::ActionExecutionEnd> {
+    match data {
+        buck2_data::buck_event::Data::SpanEnd(span) => match &span.data {
+            Some(buck2_data::span_end_event::Data::ActionExecution(action)) => Some(action),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 /// The state for a WhatRan command. This is all the events we have seen that are
-/// WhatRanRelevantActions. This emits the actions immediately.
+/// WhatRanRelevantActions. This emits the actions immediately, except for local commands run by
+/// build actions, which are emitted when their action finishes so they include the resources they
+/// used.
 #[derive(Default)]
 pub struct WhatRanImpl {
     /// Maps action spans to their details.
     known_actions: HashMap<u64, Box<buck2_data::BuckEvent>>,
+    /// Maps action spans to the local commands they ran, known to be CommandReproducers.
+    #[allow(clippy::vec_box)]
+    pending_local_executions: HashMap<u64, Vec<Box<buck2_data::BuckEvent>>>,
+}
+
+impl WhatRanImpl {
+    fn emit_pending_local_executions(
+        &mut self,
+        span_id: u64,
+        action_end: Option<&buck2_data::ActionExecutionEnd>,
+        output: &mut impl WhatRanOutputWriter,
+        options: &WhatRanOptions,
+    ) -> anyhow::Result<()> {
+        let pending = match self.pending_local_executions.remove(&span_id) {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+
+        let stats = action_end
+            .map(what_ran::local_execution_stats)
+            .unwrap_or_default();
+
+        for repro in pending.iter() {
+            let repro = CommandReproducer::from_buck_data(
+                repro.data.as_ref().expect("Checked above"),
+                options,
+            )
+            .expect("Checked above");
+            let execution_stats = what_ran::reproducer_execution_stats(repro, &stats);
+            what_ran::emit_reproducer(self.get(span_id), repro, execution_stats, output)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl WhatRanState<u64> for WhatRanImpl {
@@ -174,6 +247,28 @@ impl WhatRanComandImplementation for WhatRanImpl {
         options: &WhatRanOptions,
     ) -> anyhow::Result<()> {
         if let Some(data) = &event.data {
+            if let Some(CommandReproducer::LocalExecute(..)) =
+                CommandReproducer::from_buck_data(data, options)
+            {
+                if let Some(WhatRanRelevantAction::ActionExecution(..)) = self.get(event.parent_id)
+                {
+                    self.pending_local_executions
+                        .entry(event.parent_id)
+                        .or_default()
+                        .push(event);
+                    return Ok(());
+                }
+            }
+
+            if let Some(action_end) = action_execution_end(data) {
+                self.emit_pending_local_executions(
+                    event.span_id,
+                    Some(action_end),
+                    output,
+                    options,
+                )?;
+            }
+
             what_ran::emit_event_if_relevant(event.parent_id, data, &*self, output, options)?;
 
             if WhatRanRelevantAction::from_buck_data(data).is_some() {
@@ -183,6 +278,24 @@ impl WhatRanComandImplementation for WhatRanImpl {
 
         Ok(())
     }
+
+    fn finish(
+        &mut self,
+        output: &mut impl WhatRanOutputWriter,
+        options: &WhatRanOptions,
+    ) -> anyhow::Result<()> {
+        // Actions that never finished, e.g. because the build was interrupted.
+        let mut span_ids = self
+            .pending_local_executions
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        span_ids.sort_unstable();
+        for span_id in span_ids {
+            self.emit_pending_local_executions(span_id, None, output, options)?;
+        }
+        Ok(())
+    }
 }
 
 /// The state for a WhatRan command when only showing actions that failed. This stores all the events
@@ -241,31 +354,26 @@ impl WhatRanComandImplementation for WhatFailedImpl {
                 return Ok(());
             }
 
-            match data {
-                buck2_data::buck_event::Data::SpanEnd(span) => match &span.data {
-                    Some(buck2_data::span_end_event::Data::ActionExecution(action))
-                        if action.failed =>
-                    {
-                        if let Some(entry) = self.known_actions.remove(&event.span_id) {
-                            let action = WhatRanRelevantAction::from_buck_data(
-                                entry.event.data.as_ref().expect("Checked above"),
-                            );
-
-                            for repro in entry.reproducers.iter() {
-                                what_ran::emit_reproducer(
-                                    action,
-                                    CommandReproducer::from_buck_data(
-                                        repro.data.as_ref().expect("Checked above"),
-                                        options,
-                                    )
-                                    .expect("Checked above"),
-                                    output,
-                                )?;
-                            }
+            match action_execution_end(data) {
+                Some(action_end) if action_end.failed => {
+                    if let Some(entry) = self.known_actions.remove(&event.span_id) {
+                        let action = WhatRanRelevantAction::from_buck_data(
+                            entry.event.data.as_ref().expect("Checked above"),
+                        );
+                        let stats = what_ran::local_execution_stats(action_end);
+
+                        for repro in entry.reproducers.iter() {
+                            let repro = CommandReproducer::from_buck_data(
+                                repro.data.as_ref().expect("Checked above"),
+                                options,
+                            )
+                            .expect("Checked above");
+                            let execution_stats =
+                                what_ran::reproducer_execution_stats(repro, &stats);
+                            what_ran::emit_reproducer(action, repro, execution_stats, output)?;
                         }
                     }
-                    _ => {}
-                },
+                }
                 _ => {}
             }
         }
@@ -274,20 +382,37 @@ impl WhatRanComandImplementation for WhatFailedImpl {
     }
 }
 
-/// An output that writes to stdout in a tabulated format.
-impl WhatRanOutputWriter for LogCommandOutputFormat {
+/// An output that writes to stdout in the requested format.
+struct WhatRanCommandOutput {
+    format: LogCommandOutputFormat,
+    /// Whether to include why each action ran.
+    why: bool,
+}
+
+impl WhatRanOutputWriter for WhatRanCommandOutput {
     fn emit_command(&mut self, command: WhatRanOutputCommand<'_>) -> anyhow::Result<()> {
-        match self {
-            Self::Tabulated => {
-                buck2_client_ctx::println!(
-                    "{}\t{}\t{}\t{}",
-                    command.reason(),
-                    command.identity(),
-                    command.repro().executor(),
-                    command.repro().as_human_readable()
-                )
+        match self.format {
+            LogCommandOutputFormat::Tabulated => {
+                if self.why {
+                    buck2_client_ctx::println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        command.reason(),
+                        command.identity(),
+                        command.repro().executor(),
+                        command.repro().as_human_readable(),
+                        display_invalidation(command.invalidation())
+                    )
+                } else {
+                    buck2_client_ctx::println!(
+                        "{}\t{}\t{}\t{}",
+                        command.reason(),
+                        command.identity(),
+                        command.repro().executor(),
+                        command.repro().as_human_readable()
+                    )
+                }
             }
-            Self::Json => {
+            LogCommandOutputFormat::Json => {
                 let reproducer = match command.repro() {
                     CommandReproducer::CacheQuery(cache_hit) => JsonReproducer::CacheQuery {
                         digest: &cache_hit.action_digest,
@@ -315,11 +440,19 @@ impl WhatRanOutputWriter for LogCommandOutputFormat {
                     },
                 };
 
+                let why = if self.why {
+                    Some(JsonWhy::from(command.invalidation()))
+                } else {
+                    None
+                };
+
                 let command = JsonCommand {
                     reason: command.reason(),
                     identity: command.identity(),
                     reproducer,
                     extra: command.extra().map(Into::into),
+                    execution_stats: command.execution_stats().map(Into::into),
+                    why,
                 };
 
                 buck2_client_ctx::stdio::print_with_writer(|mut w| {
@@ -327,13 +460,15 @@ impl WhatRanOutputWriter for LogCommandOutputFormat {
                     w.write(b"\n").map(|_| ())
                 })
             }
-            Self::Csv => {
+            LogCommandOutputFormat::Csv => {
                 #[derive(serde::Serialize)]
                 struct Record<'a> {
                     reason: &'a str,
                     identity: &'a str,
                     executor: String,
                     reproducer: String,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    why: Option<String>,
                 }
 
                 buck2_client_ctx::stdio::print_with_writer(|w| {
@@ -343,6 +478,11 @@ impl WhatRanOutputWriter for LogCommandOutputFormat {
                         identity: command.identity(),
                         executor: command.repro().executor(),
                         reproducer: command.repro().as_human_readable().to_string(),
+                        why: if self.why {
+                            Some(display_invalidation(command.invalidation()))
+                        } else {
+                            None
+                        },
                     })
                 })
             }
@@ -350,6 +490,26 @@ impl WhatRanOutputWriter for LogCommandOutputFormat {
     }
 }
 
+/// A one-line explanation of why an action ran, for the tabulated and CSV outputs.
+fn display_invalidation(invalidation: Option<&buck2_data::ActionInvalidation>) -> String {
+    let invalidation = match invalidation {
+        Some(invalidation) => invalidation,
+        None => return "-".to_owned(),
+    };
+    if invalidation.fresh_instance {
+        "file watcher lost track of changes".to_owned()
+    } else if !invalidation.changed_inputs.is_empty() {
+        format!("changed: {}", invalidation.changed_inputs.join(", "))
+    } else if !invalidation.changed_build_files.is_empty() {
+        format!(
+            "build files changed: {}",
+            invalidation.changed_build_files.join(", ")
+        )
+    } else {
+        "no changed inputs".to_owned()
+    }
+}
+
 fn into_index_map(platform: &Option<buck2_data::RePlatform>) -> IndexMap<&str, &str> {
     platform.as_ref().map_or_else(IndexMap::new, |p| {
         p.properties
@@ -366,6 +526,76 @@ struct JsonCommand<'a> {
     reproducer: JsonReproducer<'a>,
     #[serde(skip_serializing_if = "Option::is_none")]
     extra: Option<JsonExtra<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    execution_stats: Option<JsonExecutionStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    why: Option<JsonWhy<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct JsonWhy<'a> {
+    /// Whether Buck2 could attribute this action at all.
+    known: bool,
+    changed_inputs: &'a [String],
+    changed_build_files: &'a [String],
+    fresh_instance: bool,
+}
+
+impl<'a> From<Option<&'a buck2_data::ActionInvalidation>> for JsonWhy<'a> {
+    fn from(invalidation: Option<&'a buck2_data::ActionInvalidation>) -> Self {
+        match invalidation {
+            Some(invalidation) => Self {
+                known: true,
+                changed_inputs: &invalidation.changed_inputs,
+                changed_build_files: &invalidation.changed_build_files,
+                fresh_instance: invalidation.fresh_instance,
+            },
+            None => Self {
+                known: false,
+                changed_inputs: &[],
+                changed_build_files: &[],
+                fresh_instance: false,
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonExecutionStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_instructions_user: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu_instructions_kernel: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peak_rss_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_cpu_time_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_cpu_time_us: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    syscall_read_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    syscall_write_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_read_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_write_bytes: Option<u64>,
+}
+
+impl From<&buck2_data::CommandExecutionStats> for JsonExecutionStats {
+    fn from(stats: &buck2_data::CommandExecutionStats) -> Self {
+        Self {
+            cpu_instructions_user: stats.cpu_instructions_user,
+            cpu_instructions_kernel: stats.cpu_instructions_kernel,
+            peak_rss_bytes: stats.peak_rss_bytes,
+            user_cpu_time_us: stats.user_cpu_time_us,
+            system_cpu_time_us: stats.system_cpu_time_us,
+            syscall_read_bytes: stats.syscall_read_bytes,
+            syscall_write_bytes: stats.syscall_write_bytes,
+            block_read_bytes: stats.block_read_bytes,
+            block_write_bytes: stats.block_write_bytes,
+        }
+    }
 }
 
 mod json_reproducer {
@@ -425,6 +655,8 @@ mod tests {
             identity: "some/target",
             reproducer: JsonReproducer::Local { command, env },
             extra: None,
+            execution_stats: None,
+            why: None,
         }
     }
 
@@ -440,6 +672,8 @@ mod tests {
                 action_key: None,
             },
             extra: None,
+            execution_stats: None,
+            why: None,
         }
     }
 
@@ -518,4 +752,104 @@ mod tests {
         assert_eq!(expected, serde_json::to_string_pretty(&command)?);
         Ok(())
     }
+
+    #[test]
+    fn serialize_what_ran_command_with_execution_stats() -> anyhow::Result<()> {
+        let mut command = make_base_command();
+        command.execution_stats = Some(JsonExecutionStats::from(
+            &buck2_data::CommandExecutionStats {
+                cpu_instructions_user: Some(100),
+                peak_rss_bytes: Some(4096),
+                user_cpu_time_us: Some(20),
+                system_cpu_time_us: Some(10),
+                ..Default::default()
+            },
+        ));
+
+        let expected = r#"{
+  "reason": "test.run",
+  "identity": "some/target",
+  "reproducer": {
+    "executor": "Local",
+    "details": {
+      "command": [
+        "some",
+        "command"
+      ],
+      "env": {
+        "KEY": "val"
+      }
+    }
+  },
+  "execution_stats": {
+    "cpu_instructions_user": 100,
+    "peak_rss_bytes": 4096,
+    "user_cpu_time_us": 20,
+    "system_cpu_time_us": 10
+  }
+}"#;
+        assert_eq!(expected, serde_json::to_string_pretty(&command)?);
+        Ok(())
+    }
+
+    #[test]
+    fn serialize_what_ran_command_with_why() -> anyhow::Result<()> {
+        let mut command = make_base_command();
+        let invalidation = buck2_data::ActionInvalidation {
+            changed_inputs: vec!["root//foo/main.c".to_owned()],
+            ..Default::default()
+        };
+        command.why = Some(JsonWhy::from(Some(&invalidation)));
+
+        let expected = r#"{
+  "reason": "test.run",
+  "identity": "some/target",
+  "reproducer": {
+    "executor": "Local",
+    "details": {
+      "command": [
+        "some",
+        "command"
+      ],
+      "env": {
+        "KEY": "val"
+      }
+    }
+  },
+  "why": {
+    "known": true,
+    "changed_inputs": [
+      "root//foo/main.c"
+    ],
+    "changed_build_files": [],
+    "fresh_instance": false
+  }
+}"#;
+        assert_eq!(expected, serde_json::to_string_pretty(&command)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_invalidation() {
+        assert_eq!(display_invalidation(None), "-");
+        assert_eq!(
+            display_invalidation(Some(&buck2_data::ActionInvalidation {
+                changed_inputs: vec!["root//a.c".to_owned(), "root//b.h".to_owned()],
+                changed_build_files: vec!["root//BUCK".to_owned()],
+                fresh_instance: false,
+            })),
+            "changed: root//a.c, root//b.h"
+        );
+        assert_eq!(
+            display_invalidation(Some(&buck2_data::ActionInvalidation {
+                changed_build_files: vec!["root//BUCK".to_owned()],
+                ..Default::default()
+            })),
+            "build files changed: root//BUCK"
+        );
+        assert_eq!(
+            display_invalidation(Some(&Default::default())),
+            "no changed inputs"
+        );
+    }
 }
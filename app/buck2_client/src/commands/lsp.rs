@@ -15,6 +15,7 @@ use buck2_client_ctx::common::CommonBuildConfigurationOptions;
 use buck2_client_ctx::common::CommonConsoleOptions;
 use buck2_client_ctx::common::CommonDaemonCommandOptions;
 use buck2_client_ctx::common::ConsoleType;
+use buck2_client_ctx::content_length_codec::ContentLengthDecoder;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::stream_util::reborrow_stream_for_static;
@@ -102,46 +103,17 @@ impl StreamingCommand for LspCommand {
     }
 }
 
-struct LspMessageDecoder;
+pub(crate) struct LspMessageDecoder;
 
 impl Decoder for LspMessageDecoder {
     type Item = Message;
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // The LSP protocol allows at most 2 headers (Content-Length and Content-Type), but since a
-        // header is 2 pointers we allow ourselves quite a few more.
-        let mut headers_buff = [httparse::EMPTY_HEADER; 16];
-
-        let (headers_length, headers) =
-            match httparse::parse_headers(src, &mut headers_buff).context("Invalid headers")? {
-                httparse::Status::Complete(r) => r,
-                httparse::Status::Partial => return Ok(None),
-            };
-
-        let mut content_length: Option<usize> = None;
-
-        for h in headers {
-            if h.name.eq_ignore_ascii_case("Content-Length") {
-                content_length = Some(
-                    std::str::from_utf8(h.value)
-                        .context("Content-Length is not utf-8")?
-                        .parse()
-                        .context("Content-Length is not a number")?,
-                );
-                break;
-            }
-        }
-
-        let content_length = content_length.context("Content-Length is missing")?;
-
-        if src.len() < headers_length + content_length {
-            return Ok(None);
-        }
-
-        let _headers = src.split_to(headers_length);
-        let text = src.split_to(content_length);
-        Some(serde_json::from_slice(&text).context("Invalid request")).transpose()
+        ContentLengthDecoder
+            .decode(src)?
+            .map(|text| serde_json::from_slice(&text).context("Invalid request"))
+            .transpose()
     }
 }
 
@@ -8,6 +8,7 @@
  */
 
 pub mod aquery;
+pub mod bsp;
 pub mod build;
 pub mod bxl;
 pub mod clean;
@@ -96,6 +96,7 @@ enum BuckProfileMode {
     Bytecode,
     BytecodePairs,
     Typecheck,
+    Coverage,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -125,6 +126,17 @@ pub struct AnalysisLoadProfileOptions {
     /// and output the merged profile.
     #[clap(long, short = 'r')]
     recursive: bool,
+
+    /// Profile all the packages (for loading) or targets (for analysis) matched by the pattern,
+    /// e.g. `//...`, and output the merged profile.
+    /// In analysis profiling, the dependencies of the targets are profiled too.
+    #[clap(long)]
+    aggregate: bool,
+
+    /// With `--aggregate` and a flame graph mode, also write a table of the N functions
+    /// (e.g. macros or rule implementations) which are the most expensive to `top.csv`.
+    #[clap(long, value_name = "N", default_value = "50", requires = "aggregate")]
+    top_functions: u32,
 }
 
 #[derive(Debug, clap::Parser)]
@@ -148,6 +160,9 @@ pub struct ProfileCommonOptions {
     /// This is probably what you want when profiling analysis.
     ///
     /// `-allocated` means allocated memory, including memory which is later garbage collected.
+    ///
+    /// `coverage` writes the lines of Starlark executed in LCOV format. Combine it with
+    /// `analysis --recursive` to get the coverage of all the analysis needed for a target.
     #[clap(long, short = 'm', value_enum)]
     mode: BuckProfileMode,
 }
@@ -168,6 +183,7 @@ fn profile_mode_to_profile(mode: &BuckProfileMode) -> Profiler {
         BuckProfileMode::Bytecode => Profiler::Bytecode,
         BuckProfileMode::BytecodePairs => Profiler::BytecodePairs,
         BuckProfileMode::Typecheck => Profiler::Typecheck,
+        BuckProfileMode::Coverage => Profiler::Coverage,
     }
 }
 
@@ -203,6 +219,8 @@ impl StreamingCommand for ProfileSubcommand {
                     }),
                     recursive: opts.recursive,
                     action: action.into(),
+                    aggregate: opts.aggregate,
+                    top_functions: opts.top_functions,
                 };
 
                 buckd
@@ -244,6 +262,7 @@ impl StreamingCommand for ProfileSubcommand {
         let ProfileResponse {
             elapsed,
             total_retained_bytes,
+            errors,
         } = response;
 
         let elapsed = elapsed
@@ -260,6 +279,15 @@ impl StreamingCommand for ProfileSubcommand {
         )?;
         buck2_client_ctx::println!("Elapsed: {:.3}s", elapsed.as_secs_f64())?;
         buck2_client_ctx::println!("Total retained bytes: {}", total_retained_bytes)?;
+        if !errors.is_empty() {
+            for error in &errors {
+                buck2_client_ctx::eprintln!("{}", error)?;
+            }
+            buck2_client_ctx::eprintln!(
+                "{} packages or targets failed, and were left out of the profile",
+                errors.len()
+            )?;
+        }
 
         ExitResult::success()
     }
@@ -122,6 +122,7 @@ impl StreamingCommand for RunCommand {
                     build_opts: Some(self.build_opts.to_proto()),
                     final_artifact_materializations: Materializations::Materialize as i32,
                     target_universe: Vec::new(),
+                    watch_token: None,
                 },
                 ctx.stdin()
                     .console_interaction_stream(&self.common_opts.console_opts),
@@ -7,9 +7,13 @@
  * of this source tree.
  */
 
+use std::time::Instant;
+
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_cli_proto::coverage_report_options;
 use buck2_cli_proto::CounterWithExamples;
+use buck2_cli_proto::CoverageReportOptions;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestSessionOptions;
 use buck2_client_ctx::client_ctx::ClientCommandContext;
@@ -22,12 +26,15 @@ use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::NoPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::final_console::FinalConsole;
+use buck2_client_ctx::path_arg::PathArg;
 use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_client_ctx::subscribers::superconsole::test::StylizedCount;
 use buck2_client_ctx::subscribers::superconsole::test::TestHeader;
 use crossterm::style::Color;
+use dupe::Dupe;
 use gazebo::prelude::*;
 
+use crate::commands::build::finish_watch_cycle;
 use crate::commands::build::print_build_result;
 
 fn print_error_counter(
@@ -50,6 +57,14 @@ fn print_error_counter(
     }
     Ok(())
 }
+
+#[derive(Debug, Clone, Dupe, clap::ArgEnum)]
+#[clap(rename_all = "snake_case")]
+enum CoverageFormat {
+    Lcov,
+    Cobertura,
+}
+
 #[derive(Debug, clap::Parser)]
 #[clap(name = "test", about = "Build and test the specified targets")]
 pub struct TestCommand {
@@ -113,6 +128,35 @@ If include patterns are present, regardless of whether exclude patterns are pres
     #[clap(long, group = "re_options", alias = "unstable-force-tests-on-re")]
     unstable_allow_all_tests_on_re: bool,
 
+    /// Run all tests, even those whose passing results are cached (see
+    /// `buck2.test_result_cache`).
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Keep running, and rerun tests when the file watcher reports changes to their inputs.
+    /// Stop with Ctrl-C.
+    #[clap(long)]
+    watch: bool,
+
+    /// Collect coverage from tests that declare a `coverage_format`, and write a report merging
+    /// all of it once tests finish.
+    #[clap(long)]
+    coverage: bool,
+
+    /// The format of the coverage report.
+    #[clap(long, arg_enum, default_value = "lcov", requires = "coverage")]
+    coverage_format: CoverageFormat,
+
+    /// Where to write the coverage report. Defaults to `test.coverage_output`, or a file under
+    /// buck-out.
+    #[clap(long, value_name = "PATH", requires = "coverage")]
+    coverage_output: Option<PathArg>,
+
+    /// Write the line coverage of the Starlark run by analysis of the requested targets and their
+    /// dependencies to this file, in LCOV format.
+    #[clap(long, value_name = "PATH")]
+    starlark_coverage: Option<PathArg>,
+
     #[clap(name = "TARGET_PATTERNS", help = "Patterns to test")]
     patterns: Vec<String>,
 
@@ -133,6 +177,53 @@ impl StreamingCommand for TestCommand {
         buckd: &mut BuckdClientConnector,
         matches: &clap::ArgMatches,
         mut ctx: ClientCommandContext,
+    ) -> ExitResult {
+        if !self.watch {
+            return self.test_once(buckd, matches, &mut ctx, &mut None).await;
+        }
+        let console = self.common_opts.console_opts.final_console();
+        let mut watch_token = None;
+        let mut cycle = 0;
+        loop {
+            cycle += 1;
+            let start = Instant::now();
+            let result = self
+                .test_once(buckd, matches, &mut ctx, &mut watch_token)
+                .await;
+            if let Some(result) =
+                finish_watch_cycle(&console, cycle, start.elapsed(), result, &mut watch_token)
+            {
+                return result;
+            }
+        }
+    }
+
+    fn console_opts(&self) -> &CommonConsoleOptions {
+        &self.common_opts.console_opts
+    }
+
+    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
+        &self.common_opts.event_log_opts
+    }
+
+    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
+        &self.common_opts.config_opts
+    }
+
+    fn extra_superconsole_component(&self) -> Option<Box<dyn superconsole::Component>> {
+        Some(Box::new(TestHeader::new()))
+    }
+}
+
+impl TestCommand {
+    /// Run tests once. With `--watch`, `watch_token` is the state to wait for
+    /// changes from, and is replaced with the state the tests ran against.
+    async fn test_once(
+        &self,
+        buckd: &mut BuckdClientConnector,
+        matches: &clap::ArgMatches,
+        ctx: &mut ClientCommandContext,
+        watch_token: &mut Option<String>,
     ) -> ExitResult {
         let context = ctx.client_context(
             &self.common_opts.config_opts,
@@ -147,19 +238,42 @@ impl StreamingCommand for TestCommand {
                     target_patterns: self
                         .patterns
                         .map(|pat| buck2_data::TargetPattern { value: pat.clone() }),
-                    test_executor_args: self.test_executor_args,
-                    excluded_labels: self.exclude,
-                    included_labels: self.include,
+                    test_executor_args: self.test_executor_args.clone(),
+                    excluded_labels: self.exclude.clone(),
+                    included_labels: self.include.clone(),
                     always_exclude: self.always_exclude,
                     build_filtered_targets: self.build_filtered_targets,
                     // we don't currently have a different flag for this, so just use the build one.
                     concurrency: self.build_opts.num_threads.unwrap_or(0),
-                    build_opts: Some(self.build_opts.to_proto()),
+                    build_opts: Some(buck2_cli_proto::CommonBuildOptions {
+                        starlark_coverage_filename: self
+                            .starlark_coverage
+                            .as_ref()
+                            .map(|p| p.resolve(&ctx.working_dir).into_string())
+                            .transpose()?
+                            .unwrap_or_default(),
+                        ..self.build_opts.to_proto()
+                    }),
                     session_options: Some(TestSessionOptions {
                         allow_re: self.unstable_allow_compatible_tests_on_re
                             || self.unstable_allow_all_tests_on_re,
                         force_use_project_relative_paths: self.unstable_allow_all_tests_on_re,
                         force_run_from_project_root: self.unstable_allow_all_tests_on_re,
+                        disable_test_result_cache: self.no_cache,
+                        collect_coverage: self.coverage,
+                    }),
+                    watch_token: watch_token.take(),
+                    coverage_report: Some(CoverageReportOptions {
+                        format: match self.coverage_format {
+                            CoverageFormat::Lcov => coverage_report_options::Format::Lcov,
+                            CoverageFormat::Cobertura => coverage_report_options::Format::Cobertura,
+                        } as i32,
+                        output: self
+                            .coverage_output
+                            .as_ref()
+                            .map(|p| p.resolve(&ctx.working_dir).into_string())
+                            .transpose()?
+                            .unwrap_or_default(),
                     }),
                 },
                 ctx.stdin()
@@ -167,6 +281,7 @@ impl StreamingCommand for TestCommand {
                 &mut NoPartialResultHandler,
             )
             .await??;
+        *watch_token = Some(response.watch_token.clone());
 
         let statuses = response
             .test_statuses
@@ -237,6 +352,9 @@ impl StreamingCommand for TestCommand {
         if passed.count + failed.count + fatals.count + skipped.count == 0 {
             console.print_warning("NO TESTS RAN")?;
         }
+        if let Some(coverage_report) = &response.coverage_report {
+            console.print_success(&format!("Coverage report written to {}", coverage_report))?;
+        }
 
         if let Some(exit_code) = response.exit_code {
             ExitResult::status_extended(exit_code)
@@ -244,20 +362,4 @@ impl StreamingCommand for TestCommand {
             ExitResult::failure()
         }
     }
-
-    fn console_opts(&self) -> &CommonConsoleOptions {
-        &self.common_opts.console_opts
-    }
-
-    fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
-        &self.common_opts.event_log_opts
-    }
-
-    fn common_opts(&self) -> &CommonBuildConfigurationOptions {
-        &self.common_opts.config_opts
-    }
-
-    fn extra_superconsole_component(&self) -> Option<Box<dyn superconsole::Component>> {
-        Some(Box::new(TestHeader::new()))
-    }
 }
@@ -0,0 +1,58 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! The `Content-Length` header framing shared by LSP and DAP messages.
+
+use anyhow::Context as _;
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+/// Decodes a stream of messages, each preceded by headers including `Content-Length`, into
+/// the bodies of the messages.
+pub struct ContentLengthDecoder;
+
+impl Decoder for ContentLengthDecoder {
+    type Item = BytesMut;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // The LSP protocol allows at most 2 headers (Content-Length and Content-Type), but since a
+        // header is 2 pointers we allow ourselves quite a few more.
+        let mut headers_buff = [httparse::EMPTY_HEADER; 16];
+
+        let (headers_length, headers) =
+            match httparse::parse_headers(src, &mut headers_buff).context("Invalid headers")? {
+                httparse::Status::Complete(r) => r,
+                httparse::Status::Partial => return Ok(None),
+            };
+
+        let mut content_length: Option<usize> = None;
+
+        for h in headers {
+            if h.name.eq_ignore_ascii_case("Content-Length") {
+                content_length = Some(
+                    std::str::from_utf8(h.value)
+                        .context("Content-Length is not utf-8")?
+                        .parse()
+                        .context("Content-Length is not a number")?,
+                );
+                break;
+            }
+        }
+
+        let content_length = content_length.context("Content-Length is missing")?;
+
+        if src.len() < headers_length + content_length {
+            return Ok(None);
+        }
+
+        let _headers = src.split_to(headers_length);
+        Ok(Some(src.split_to(content_length)))
+    }
+}
@@ -375,7 +375,7 @@ impl PartialResultHandler for StdoutPartialResultHandler {
     }
 }
 
-/// Receives StdoutBytes, writes them to stdout.
+/// Receives JSON-RPC messages from the LSP or BSP server, writes them to stdout.
 struct LspPartialResultHandler;
 
 #[async_trait]
@@ -398,6 +398,67 @@ impl PartialResultHandler for LspPartialResultHandler {
     }
 }
 
+/// Receives DAP messages from the Starlark debugger, writes them to stdout.
+struct DapPartialResultHandler;
+
+#[async_trait]
+impl PartialResultHandler for DapPartialResultHandler {
+    type PartialResult = buck2_cli_proto::DapMessage;
+
+    fn new() -> Self {
+        Self
+    }
+
+    async fn handle_partial_result(
+        &mut self,
+        mut ctx: PartialResultCtx<'_>,
+        partial_res: Self::PartialResult,
+    ) -> anyhow::Result<()> {
+        let json = partial_res.dap_json;
+        let message = format!("Content-Length: {}\r\n\r\n{}", json.len(), json);
+        ctx.stdout(message.as_bytes()).await
+    }
+}
+
+/// Receives the results of `buck2 starlark repl` inputs, passing them on to the REPL, which
+/// prints them between prompts.
+pub struct StarlarkReplPartialResultHandler {
+    sender: tokio::sync::mpsc::UnboundedSender<StarlarkReplMessage>,
+    receiver: Option<tokio::sync::mpsc::UnboundedReceiver<StarlarkReplMessage>>,
+}
+
+impl StarlarkReplPartialResultHandler {
+    /// The results, in the order the inputs were sent. Can only be taken once.
+    pub fn take_results(
+        &mut self,
+    ) -> Option<tokio::sync::mpsc::UnboundedReceiver<StarlarkReplMessage>> {
+        self.receiver.take()
+    }
+}
+
+#[async_trait]
+impl PartialResultHandler for StarlarkReplPartialResultHandler {
+    type PartialResult = buck2_cli_proto::StarlarkReplMessage;
+
+    fn new() -> Self {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        Self {
+            sender,
+            receiver: Some(receiver),
+        }
+    }
+
+    async fn handle_partial_result(
+        &mut self,
+        _ctx: PartialResultCtx<'_>,
+        partial_res: Self::PartialResult,
+    ) -> anyhow::Result<()> {
+        // The REPL has gone if nobody is receiving, and the session is about to end.
+        let _ignored = self.sender.send(partial_res);
+        Ok(())
+    }
+}
+
 /// Outputs subscription messages.
 struct SubscriptionPartialResultHandler {
     /// We reuse our output buffer here.
@@ -645,6 +706,31 @@ impl<'a> FlushingBuckdClient<'a> {
     );
 
     bidirectional_stream_method!(lsp, LspRequest, LspResponse, LspPartialResultHandler);
+    bidirectional_stream_method!(bsp, BspRequest, BspResponse, LspPartialResultHandler);
+    bidirectional_stream_method!(dap, DapRequest, DapResponse, DapPartialResultHandler);
+
+    /// Like the other bidirectional methods, but the caller owns the handler, since it needs
+    /// the results.
+    pub async fn starlark_repl(
+        &mut self,
+        context: ClientContext,
+        requests: impl Stream<Item = StarlarkReplRequest> + Send + Sync + 'static,
+        handler: &mut StarlarkReplPartialResultHandler,
+    ) -> anyhow::Result<CommandOutcome<StarlarkReplResponse>> {
+        self.enter()?;
+        let req = create_client_stream(context, requests);
+        let res = self
+            .inner
+            .stream(
+                |d, r| Box::pin(DaemonApiClient::starlark_repl(d, r)),
+                req,
+                handler,
+                None,
+            )
+            .await;
+        self.exit().await?;
+        res
+    }
     bidirectional_stream_method!(
         subscription,
         SubscriptionRequestWrapper,
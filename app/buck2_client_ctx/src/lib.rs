@@ -18,6 +18,7 @@ pub mod client_ctx;
 pub mod command_outcome;
 pub mod common;
 pub mod console_interaction_stream;
+pub mod content_length_codec;
 pub mod daemon;
 pub mod daemon_constraints;
 pub mod events_ctx;
@@ -71,6 +71,17 @@ impl Component for TestCounterComponent {
             .to_span()?,
         );
         spans.push(". ".try_into()?);
+        if test_state.cached > 0 {
+            spans.push(
+                StylizedCount {
+                    label: "Cached",
+                    count: test_state.cached,
+                    color: Color::Green,
+                }
+                .to_span()?,
+            );
+            spans.push(". ".try_into()?);
+        }
         spans.push(
             StylizedCount {
                 label: "Fail",
@@ -620,6 +620,7 @@ mod tests {
                             identifier: "identifier".into(),
                         }),
                         kind: buck2_data::ActionKind::NotSet as i32,
+                        invalidation: None,
                     }
                     .into(),
                 ),
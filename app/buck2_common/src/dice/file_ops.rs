@@ -41,6 +41,7 @@ use crate::file_ops::RawDirEntry;
 use crate::file_ops::RawPathMetadata;
 use crate::file_ops::ReadDirOutput;
 use crate::file_ops::SimpleDirEntry;
+use crate::git_ignores::HasGitIgnores;
 use crate::ignores::AllCellIgnores;
 use crate::ignores::HasAllCellIgnores;
 use crate::ignores::MaybeIgnoredCellRelativePath;
@@ -287,6 +288,11 @@ impl FileChangeTracker {
         Ok(())
     }
 
+    /// Every path reported as changed, whether a file or a directory.
+    pub fn changed_paths(&self) -> impl Iterator<Item = &CellPath> {
+        self.paths_to_dirty.iter().map(|k| &k.0)
+    }
+
     fn file_contents_modify(&mut self, path: CellPath) {
         self.files_to_dirty
             .insert(ReadFileKey(Arc::new(path.clone())));
@@ -367,11 +373,28 @@ struct ReadDirKey(CellPath);
 impl Key for ReadDirKey {
     type Value = SharedResult<ReadDirOutput>;
     async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
-        get_default_file_ops(ctx)
+        let output = get_default_file_ops(ctx)
             .await?
             .read_dir(self.0.as_ref())
             .await
-            .shared_error()
+            .shared_error()?;
+
+        // Entries ignored by `.gitignore` files are filtered here rather than in
+        // `DiceFileOpsDelegate`, so that the listing depends on ignore files through DICE.
+        match ctx.get_git_ignores(self.0.as_ref()).await? {
+            None => Ok(output),
+            Some(git_ignores) => Ok(ReadDirOutput {
+                included: output
+                    .included
+                    .iter()
+                    .filter(|e| {
+                        !git_ignores
+                            .is_ignored(&self.0.path().join(&e.file_name), e.file_type.is_dir())
+                    })
+                    .cloned()
+                    .collect(),
+            }),
+        }
     }
 
     fn equality(x: &Self::Value, y: &Self::Value) -> bool {
@@ -0,0 +1,404 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Support for `.gitignore` and `.ignore` files.
+//!
+//! When `project.respect_gitignore = true` is set in the cell buckconfig,
+//! paths matched by `.gitignore` or `.ignore` files are hidden from directory listings
+//! (and so from package listing and `glob()`), and changes to them are dropped by file watchers,
+//! same as for paths matched by `project.ignore`.
+//! Changes to ignore files themselves are never dropped: they invalidate directory listings
+//! through DICE, and the ignores cached by the file watcher.
+//!
+//! Ignore files are interpreted hierarchically like git does: patterns in a file apply
+//! to the directory containing the file, patterns in deeper files take precedence,
+//! and nothing below an ignored directory is visible.
+//! Unlike git, ignore files are only looked up within the cell, so patterns in
+//! ignore files above the cell root do not apply to the cell.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use async_trait::async_trait;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::cell_path::CellPathRef;
+use buck2_core::cells::name::CellName;
+use buck2_core::cells::paths::CellRelativePath;
+use buck2_core::cells::paths::CellRelativePathBuf;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_core::fs::project::ProjectRoot;
+use derive_more::Display;
+use dice::DiceComputations;
+use dice::Key;
+use ignore::gitignore::Gitignore;
+use ignore::gitignore::GitignoreBuilder;
+use ignore::Match;
+use parking_lot::Mutex;
+
+use crate::dice::file_ops::HasFileOps;
+use crate::file_ops::FileOps;
+use crate::legacy_configs::dice::HasLegacyConfigs;
+use crate::legacy_configs::LegacyBuckConfig;
+use crate::result::SharedResult;
+use crate::result::ToUnsharedResultExt;
+
+/// Names of the ignore files, in order of increasing precedence.
+pub const IGNORE_FILE_NAMES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Whether the cell config has `project.respect_gitignore` enabled.
+pub fn respect_gitignore(config: &LegacyBuckConfig) -> anyhow::Result<bool> {
+    Ok(config
+        .parse("project", "respect_gitignore")?
+        .unwrap_or(false))
+}
+
+/// Is the path an ignore file, changes to which affect which paths are ignored.
+pub fn is_ignore_file(path: &CellRelativePath) -> bool {
+    path.file_name()
+        .map_or(false, |name| IGNORE_FILE_NAMES.contains(&name.as_str()))
+}
+
+/// Patterns from a single `.gitignore` or `.ignore` file.
+#[derive(Allocative)]
+struct IgnoreFile {
+    /// Directory containing the ignore file.
+    dir: CellRelativePathBuf,
+    /// Matcher is derived from contents, so we compare contents only.
+    contents: String,
+    #[allocative(skip)]
+    matcher: Gitignore,
+}
+
+impl PartialEq for IgnoreFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.dir == other.dir && self.contents == other.contents
+    }
+}
+
+impl IgnoreFile {
+    fn new(dir: &CellRelativePath, contents: String) -> anyhow::Result<IgnoreFile> {
+        let mut builder = GitignoreBuilder::new("");
+        for line in contents.lines() {
+            // Git silently skips invalid patterns, so do we.
+            let _ = builder.add_line(None, line);
+        }
+        Ok(IgnoreFile {
+            dir: dir.to_buf(),
+            matcher: builder.build()?,
+            contents,
+        })
+    }
+}
+
+/// Ignore files which apply to the entries of a directory.
+#[derive(Default, PartialEq, Allocative)]
+pub struct GitIgnores {
+    /// The directory itself is ignored, so all of its entries are.
+    all_ignored: bool,
+    /// Outermost first.
+    files: Vec<Arc<IgnoreFile>>,
+}
+
+impl GitIgnores {
+    /// Ignores for the entries of child directory `dir` of the directory `self` was built for.
+    fn for_child_dir(
+        &self,
+        dir: &CellRelativePath,
+        contents: impl IntoIterator<Item = Option<String>>,
+    ) -> anyhow::Result<GitIgnores> {
+        if self.all_ignored || self.is_ignored(dir, true) {
+            return Ok(GitIgnores {
+                all_ignored: true,
+                files: Vec::new(),
+            });
+        }
+        let mut files = self.files.clone();
+        for contents in contents.into_iter().flatten() {
+            files.push(Arc::new(IgnoreFile::new(dir, contents)?));
+        }
+        Ok(GitIgnores {
+            all_ignored: false,
+            files,
+        })
+    }
+
+    /// Is the entry `path` of the directory these ignores were built for ignored.
+    pub fn is_ignored(&self, path: &CellRelativePath, is_dir: bool) -> bool {
+        if self.all_ignored {
+            return true;
+        }
+        for file in self.files.iter().rev() {
+            let relative = match path.strip_prefix(&file.dir) {
+                Ok(relative) => relative,
+                Err(_) => continue,
+            };
+            match file.matcher.matched(Path::new(relative.as_str()), is_dir) {
+                Match::None => {}
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+            }
+        }
+        false
+    }
+}
+
+#[derive(Clone, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+struct GitIgnoresKey(CellPath);
+
+#[async_trait]
+impl Key for GitIgnoresKey {
+    type Value = SharedResult<Arc<GitIgnores>>;
+
+    async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
+        let parent = match self.0.parent() {
+            Some(parent) => ctx.compute(&GitIgnoresKey(parent.to_owned())).await??,
+            None => Arc::new(GitIgnores::default()),
+        };
+        if parent.all_ignored || parent.is_ignored(self.0.path(), true) {
+            // No need to read ignore files in ignored directories.
+            return Ok(Arc::new(parent.for_child_dir(self.0.path(), [])?));
+        }
+        let file_ops = ctx.file_ops();
+        let mut contents = Vec::new();
+        for name in IGNORE_FILE_NAMES {
+            // Reading through DICE so the key is invalidated when ignore files change.
+            let path = self.0.join(FileName::unchecked_new(name));
+            contents.push(file_ops.read_file_if_exists(path.as_ref()).await?);
+        }
+        Ok(Arc::new(parent.for_child_dir(self.0.path(), contents)?))
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+
+    fn validity(x: &Self::Value) -> bool {
+        x.is_ok()
+    }
+}
+
+/// Whether `project.respect_gitignore` is enabled for the cell.
+#[derive(Clone, Copy, Display, Debug, Eq, Hash, PartialEq, Allocative)]
+#[display(fmt = "{}//project.respect_gitignore", _0)]
+struct RespectGitIgnoreKey(CellName);
+
+#[async_trait]
+impl Key for RespectGitIgnoreKey {
+    type Value = SharedResult<bool>;
+
+    async fn compute(&self, ctx: &DiceComputations) -> Self::Value {
+        Ok(ctx
+            .parse_legacy_config_property::<bool>(self.0, "project", "respect_gitignore")
+            .await?
+            .unwrap_or(false))
+    }
+
+    fn equality(x: &Self::Value, y: &Self::Value) -> bool {
+        match (x, y) {
+            (Ok(x), Ok(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+#[async_trait]
+pub trait HasGitIgnores {
+    /// Ignores applicable to the entries of directory `dir`,
+    /// or `None` if `project.respect_gitignore` is not enabled for the cell.
+    async fn get_git_ignores(
+        &self,
+        dir: CellPathRef<'async_trait>,
+    ) -> anyhow::Result<Option<Arc<GitIgnores>>>;
+}
+
+#[async_trait]
+impl HasGitIgnores for DiceComputations {
+    async fn get_git_ignores(
+        &self,
+        dir: CellPathRef<'async_trait>,
+    ) -> anyhow::Result<Option<Arc<GitIgnores>>> {
+        if !self
+            .compute(&RespectGitIgnoreKey(dir.cell()))
+            .await?
+            .unshared_error()?
+        {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.compute(&GitIgnoresKey(dir.to_owned()))
+                .await?
+                .unshared_error()?,
+        ))
+    }
+}
+
+/// Ignore file matcher for file watchers.
+///
+/// File watchers process events outside of DICE, so this reads ignore files from disk
+/// and caches them per directory. Cache is dropped for a directory and its subdirectories
+/// when watcher reports a change to an ignore file in that directory.
+pub struct WatcherGitIgnores {
+    root: ProjectRoot,
+    cells: CellResolver,
+    enabled_cells: HashSet<CellName>,
+    cache: Mutex<HashMap<CellPath, Arc<GitIgnores>>>,
+}
+
+impl WatcherGitIgnores {
+    pub fn new(
+        root: ProjectRoot,
+        cells: CellResolver,
+        enabled_cells: HashSet<CellName>,
+    ) -> WatcherGitIgnores {
+        WatcherGitIgnores {
+            root,
+            cells,
+            enabled_cells,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Is the change to `path` ignored, because the path or any of its parent directories is.
+    ///
+    /// Changes to ignore files are never ignored, and they invalidate the cached ignores.
+    pub fn is_ignored(&self, path: CellPathRef, is_dir: bool) -> anyhow::Result<bool> {
+        if !self.enabled_cells.contains(&path.cell()) {
+            return Ok(false);
+        }
+        if is_ignore_file(path.path()) {
+            if let Some(dir) = path.parent() {
+                self.cache.lock().retain(|k, _| !k.starts_with(dir));
+            }
+            return Ok(false);
+        }
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            // Cell root is never ignored.
+            None => return Ok(false),
+        };
+        Ok(self
+            .ignores_for_dir(parent)?
+            .is_ignored(path.path(), is_dir))
+    }
+
+    fn ignores_for_dir(&self, dir: CellPathRef) -> anyhow::Result<Arc<GitIgnores>> {
+        if let Some(ignores) = self.cache.lock().get(&dir.to_owned()) {
+            return Ok(ignores.clone());
+        }
+        let ignores = match dir.parent() {
+            None => Arc::new(GitIgnores::default().for_child_dir(dir.path(), self.read(dir)?)?),
+            Some(parent) => {
+                let parent_ignores = self.ignores_for_dir(parent)?;
+                if parent_ignores.all_ignored || parent_ignores.is_ignored(dir.path(), true) {
+                    Arc::new(parent_ignores.for_child_dir(dir.path(), [])?)
+                } else {
+                    Arc::new(parent_ignores.for_child_dir(dir.path(), self.read(dir)?)?)
+                }
+            }
+        };
+        self.cache.lock().insert(dir.to_owned(), ignores.clone());
+        Ok(ignores)
+    }
+
+    fn read(&self, dir: CellPathRef) -> anyhow::Result<Vec<Option<String>>> {
+        let cell_root = self.cells.get(dir.cell())?.path().project_relative_path();
+        IGNORE_FILE_NAMES
+            .iter()
+            .map(|name| {
+                let path = cell_root
+                    .join(dir.path())
+                    .join(FileName::unchecked_new(name));
+                fs_util::read_to_string_opt(self.root.resolve(&path))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::cell_root_path::CellRootPathBuf;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::paths::CellRelativePath;
+    use buck2_core::cells::testing::CellResolverExt;
+    use buck2_core::cells::CellResolver;
+    use buck2_core::fs::fs_util;
+    use buck2_core::fs::project::ProjectRoot;
+    use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+
+    use crate::git_ignores::GitIgnores;
+    use crate::git_ignores::WatcherGitIgnores;
+
+    #[test]
+    fn test_hierarchical_ignores() -> anyhow::Result<()> {
+        let root = GitIgnores::default().for_child_dir(
+            CellRelativePath::unchecked_new(""),
+            [Some("node_modules/\n*.log\n".to_owned()), None],
+        )?;
+        assert!(root.is_ignored(CellRelativePath::unchecked_new("node_modules"), true));
+        assert!(!root.is_ignored(CellRelativePath::unchecked_new("node_modules"), false));
+        assert!(root.is_ignored(CellRelativePath::unchecked_new("a.log"), false));
+        assert!(!root.is_ignored(CellRelativePath::unchecked_new("src"), true));
+
+        let src = root.for_child_dir(
+            CellRelativePath::unchecked_new("src"),
+            [None, Some("!keep.log\ngen\n".to_owned())],
+        )?;
+        assert!(src.is_ignored(CellRelativePath::unchecked_new("src/a.log"), false));
+        assert!(!src.is_ignored(CellRelativePath::unchecked_new("src/keep.log"), false));
+        assert!(src.is_ignored(CellRelativePath::unchecked_new("src/gen"), true));
+
+        let gen = src.for_child_dir(CellRelativePath::unchecked_new("src/gen"), [])?;
+        assert!(gen.is_ignored(CellRelativePath::unchecked_new("src/gen/BUCK"), false));
+        Ok(())
+    }
+    #[test]
+    fn test_watcher_ignores_invalidated_by_ignore_file_change() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let root = ProjectRoot::new(fs_util::canonicalize(tempdir.path())?)?;
+        fs_util::write(tempdir.path().join(".gitignore"), "gen/\n")?;
+
+        let cell = CellName::testing_new("root");
+        let other = CellName::testing_new("other");
+        let cells = CellResolver::of_names_and_paths(
+            cell,
+            &[
+                (
+                    cell,
+                    CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("".to_owned())),
+                ),
+                (
+                    other,
+                    CellRootPathBuf::new(ProjectRelativePathBuf::unchecked_new("other".to_owned())),
+                ),
+            ],
+        );
+        let ignores = WatcherGitIgnores::new(root, cells, HashSet::from([cell]));
+
+        assert!(ignores.is_ignored(CellPath::testing_new("root", "gen/a.txt").as_ref(), false)?);
+        assert!(!ignores.is_ignored(CellPath::testing_new("root", "src/a.txt").as_ref(), false)?);
+        // Only cells with `respect_gitignore` enabled are affected.
+        assert!(!ignores.is_ignored(CellPath::testing_new("other", "gen/a.txt").as_ref(), false)?);
+
+        fs_util::write(tempdir.path().join(".gitignore"), "")?;
+        assert!(!ignores.is_ignored(CellPath::testing_new("root", ".gitignore").as_ref(), false)?);
+        assert!(!ignores.is_ignored(CellPath::testing_new("root", "gen/a.txt").as_ref(), false)?);
+        Ok(())
+    }
+}
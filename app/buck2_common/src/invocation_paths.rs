@@ -183,6 +183,18 @@ impl InvocationPaths {
             .join(self.materializer_state_dir_name())
     }
 
+    /// Subdirectory of `cache_dir` storing parsed Starlark files
+    pub fn starlark_parse_cache_path(&self) -> AbsNormPathBuf {
+        self.cache_dir_path()
+            .join(self.starlark_parse_cache_dir_name())
+    }
+
+    /// Subdirectory of `cache_dir` storing passing test results
+    pub fn test_result_cache_path(&self) -> AbsNormPathBuf {
+        self.cache_dir_path()
+            .join(self.test_result_cache_dir_name())
+    }
+
     /// This is used by the forkserver to write the miniperf wrapper binary (if used), as well as
     /// temporary files used by miniperf. We put this in buck-out because that directory gets
     /// allowlisted for execution (because we write lots of tools there).
@@ -195,8 +207,20 @@ impl InvocationPaths {
         FileName::unchecked_new("materializer_state")
     }
 
+    pub fn starlark_parse_cache_dir_name(&self) -> &FileName {
+        FileName::unchecked_new("starlark_parse")
+    }
+
+    pub fn test_result_cache_dir_name(&self) -> &FileName {
+        FileName::unchecked_new("test_results")
+    }
+
     pub fn valid_cache_dirs(&self) -> Vec<&FileName> {
-        vec![self.materializer_state_dir_name()]
+        vec![
+            self.materializer_state_dir_name(),
+            self.starlark_parse_cache_dir_name(),
+            self.test_result_cache_dir_name(),
+        ]
     }
 }
 
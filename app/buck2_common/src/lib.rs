@@ -37,6 +37,7 @@ pub mod executor_config;
 pub mod external_symlink;
 pub mod file_ops;
 pub mod find_buildfile;
+pub mod git_ignores;
 pub mod home_buck_tmp;
 pub mod ignores;
 pub mod invocation_paths;
@@ -52,3 +53,4 @@ pub mod result;
 pub mod sqlite;
 pub mod target_aliases;
 pub mod temp_path;
+pub mod watch_inputs;
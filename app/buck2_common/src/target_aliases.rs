@@ -77,9 +77,34 @@ impl BuckConfigTargetAliasResolver {
         Self { config }
     }
 
+    /// Aliases visited while resolving `name`, followed by the resolved target.
+    /// Returns `None` if `name` is not an alias.
+    pub fn resolve_alias_chain(&self, name: &str) -> anyhow::Result<Option<Vec<String>>> {
+        match self.resolve_alias_impl(name) {
+            Ok((mut chain, target)) => {
+                chain.push(target.to_owned());
+                Ok(Some(chain))
+            }
+            Err(AliasResolutionError::MissingAliasSection | AliasResolutionError::NotAnAlias) => {
+                Ok(None)
+            }
+            Err(e) => {
+                Err(anyhow::Error::from(e).context(format!("Error resolving alias `{}`", name)))
+            }
+        }
+    }
+
     /// Resolves an alias in the `[alias]` section. Aliases can refer to other aliases. Any
     /// string containing ":" is considered to be the end of the alias resolution.
     fn resolve_alias<'a>(&'a self, alias: &str) -> Result<&'a str, AliasResolutionError> {
+        Ok(self.resolve_alias_impl(alias)?.1)
+    }
+
+    /// Returns visited aliases along with the resolved target.
+    fn resolve_alias_impl<'a>(
+        &'a self,
+        alias: &str,
+    ) -> Result<(Vec<String>, &'a str), AliasResolutionError> {
         if alias.contains(':') {
             return Err(AliasResolutionError::NotAnAlias);
         }
@@ -119,7 +144,7 @@ impl BuckConfigTargetAliasResolver {
             };
 
             if new_alias.contains(':') {
-                return Ok(new_alias);
+                return Ok((stack.into_iter().map(|e| e.to_owned()).collect(), new_alias));
             }
             alias = new_alias;
         }
@@ -224,6 +249,17 @@ mod tests {
         assert_eq!("//:foo", target_alias_resolver.resolve_alias("bar2")?);
         assert_eq!("//:foo", target_alias_resolver.resolve_alias("baz")?);
 
+        assert_eq!(
+            Some(vec![
+                "bar2".to_owned(),
+                "bar".to_owned(),
+                "foo".to_owned(),
+                "//:foo".to_owned()
+            ]),
+            target_alias_resolver.resolve_alias_chain("bar2")?
+        );
+        assert_eq!(None, target_alias_resolver.resolve_alias_chain("missing")?);
+
         assert_matches!(
             target_alias_resolver.resolve_alias("missing"),
             Err(AliasResolutionError::NotAnAlias)
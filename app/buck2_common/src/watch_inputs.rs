@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Files a command depends on, used by `--watch` to decide whether a file change
+//! requires running the command again.
+
+use std::collections::HashSet;
+
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::cell_path::CellPathRef;
+
+/// Paths a command depends on. This is conservative: any change to a file
+/// under a watched directory (e.g. a package directory, where a new file
+/// can change the result of `glob()`) counts as affecting the command.
+#[derive(Default, Debug)]
+pub struct WatchInputs {
+    dirs: HashSet<CellPath>,
+    files: HashSet<CellPath>,
+}
+
+impl WatchInputs {
+    /// Changes to anything under `dir` affect the command.
+    pub fn add_dir(&mut self, dir: CellPath) {
+        self.dirs.insert(dir);
+    }
+
+    /// Changes to `file` affect the command.
+    pub fn add_file(&mut self, file: CellPath) {
+        self.files.insert(file);
+    }
+
+    /// Whether a change to `path` affects the command.
+    pub fn affected_by(&self, path: CellPathRef) -> bool {
+        if self.files.contains(&path.to_owned()) {
+            return true;
+        }
+        path.ancestors()
+            .any(|dir| self.dirs.contains(&dir.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::cells::cell_path::CellPath;
+    use buck2_core::cells::name::CellName;
+    use buck2_core::cells::paths::CellRelativePathBuf;
+
+    use crate::watch_inputs::WatchInputs;
+
+    fn cell_path(path: &str) -> CellPath {
+        CellPath::new(
+            CellName::testing_new("root"),
+            CellRelativePathBuf::unchecked_new(path.to_owned()),
+        )
+    }
+
+    #[test]
+    fn test_affected_by() {
+        let mut inputs = WatchInputs::default();
+        inputs.add_dir(cell_path("foo"));
+        inputs.add_file(cell_path("prelude/rules.bzl"));
+
+        assert!(inputs.affected_by(cell_path("foo").as_ref()));
+        assert!(inputs.affected_by(cell_path("foo/bar/baz.c").as_ref()));
+        assert!(inputs.affected_by(cell_path("prelude/rules.bzl").as_ref()));
+        assert!(!inputs.affected_by(cell_path("foobar/baz.c").as_ref()));
+        assert!(!inputs.affected_by(cell_path("prelude/other.bzl").as_ref()));
+        assert!(!inputs.affected_by(cell_path("").as_ref()));
+    }
+}
@@ -302,6 +302,7 @@ pub fn display_event(event: &BuckEvent, opts: TargetDisplayOptions) -> anyhow::R
             Data::ConnectToInstaller(buck2_data::ConnectToInstallerStart { tcp_port }) => {
                 Ok(format!("Connecting to installer on port {}", tcp_port))
             }
+            Data::WatchWaitForChanges(..) => Ok("Waiting for file changes".to_owned()),
             Data::Fake(fake) => Ok(format!("{} -- speak of the devil", fake.caramba)),
         };
 
@@ -445,7 +446,11 @@ pub fn format_test_result(test_result: &buck2_data::TestResult) -> anyhow::Resul
     // Pass results normally have no details, unless the --print-passing-details is set.
     // Do not display anything for passing tests unless details are present to avoid
     // cluttering the UI with unimportant test results.
-    if matches!(&status, TestStatus::PASS | TestStatus::LISTING_SUCCESS) && details.is_empty() {
+    if matches!(
+        &status,
+        TestStatus::PASS | TestStatus::CACHED | TestStatus::LISTING_SUCCESS
+    ) && details.is_empty()
+    {
         return Ok(None);
     }
 
@@ -456,6 +461,7 @@ pub fn format_test_result(test_result: &buck2_data::TestResult) -> anyhow::Resul
         TestStatus::FATAL => Span::new_styled("⚠ Fatal".to_owned().red()),
         TestStatus::TIMEOUT => Span::new_styled("✉ Timeout".to_owned().cyan()),
         TestStatus::PASS => Span::new_styled("✓ Pass".to_owned().green()),
+        TestStatus::CACHED => Span::new_styled("✓ Pass (cached)".to_owned().green()),
         TestStatus::LISTING_SUCCESS => Span::new_styled("✓ Listing success".to_owned().green()),
         TestStatus::UNKNOWN => Span::new_styled("? Unknown".to_owned().cyan()),
         TestStatus::RERUN => Span::new_styled("↻ Rerun".to_owned().cyan()),
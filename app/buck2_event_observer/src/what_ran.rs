@@ -8,6 +8,7 @@
  */
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
 
 use buck2_data::re_platform::Property;
@@ -65,6 +66,8 @@ pub struct WhatRanOutputCommand<'a> {
     identity: &'a str,
     repro: CommandReproducer<'a>,
     extra: Option<WhatRanOutputCommandExtra<'a>>,
+    execution_stats: Option<&'a buck2_data::CommandExecutionStats>,
+    invalidation: Option<&'a buck2_data::ActionInvalidation>,
 }
 
 impl WhatRanOutputCommand<'_> {
@@ -80,6 +83,15 @@ impl WhatRanOutputCommand<'_> {
     pub fn extra(&self) -> Option<WhatRanOutputCommandExtra<'_>> {
         self.extra
     }
+    /// Resources used by the command, if it ran locally and its action has finished.
+    pub fn execution_stats(&self) -> Option<&buck2_data::CommandExecutionStats> {
+        self.execution_stats
+    }
+    /// The file changes the command's action was attributed to, if it is a build action and
+    /// Buck2 could attribute it.
+    pub fn invalidation(&self) -> Option<&buck2_data::ActionInvalidation> {
+        self.invalidation
+    }
 }
 
 #[derive(Clone, Copy, Dupe)]
@@ -122,14 +134,20 @@ fn emit<T: fmt::Display + Copy>(
     state: &impl WhatRanState<T>,
     output: &mut impl WhatRanOutputWriter,
 ) -> anyhow::Result<()> {
-    emit_reproducer(state.get(parent_span_id), repro, output)
+    emit_reproducer(state.get(parent_span_id), repro, None, output)
 }
 
 pub fn emit_reproducer(
     action: Option<WhatRanRelevantAction<'_>>,
     repro: CommandReproducer<'_>,
+    execution_stats: Option<&buck2_data::CommandExecutionStats>,
     output: &mut impl WhatRanOutputWriter,
 ) -> anyhow::Result<()> {
+    let invalidation = match action {
+        Some(WhatRanRelevantAction::ActionExecution(action)) => action.invalidation.as_ref(),
+        _ => None,
+    };
+
     let (reason, identity, extra) = match action {
         Some(WhatRanRelevantAction::ActionExecution(action)) => (
             "build",
@@ -161,11 +179,53 @@ pub fn emit_reproducer(
         identity: &identity,
         repro,
         extra,
+        execution_stats,
+        invalidation,
     })?;
 
     Ok(())
 }
 
+/// The execution stats of the local commands an action ran, keyed by action digest.
+pub fn local_execution_stats(
+    action: &buck2_data::ActionExecutionEnd,
+) -> HashMap<&str, &buck2_data::CommandExecutionStats> {
+    action
+        .commands
+        .iter()
+        .filter_map(|command| command.details.as_ref())
+        .filter_map(|details| {
+            let action_digest = match details.command.as_ref()? {
+                buck2_data::command_execution_details::Command::LocalCommand(command) => {
+                    &command.action_digest
+                }
+                buck2_data::command_execution_details::Command::OmittedLocalCommand(command) => {
+                    &command.action_digest
+                }
+                buck2_data::command_execution_details::Command::RemoteCommand(..) => {
+                    return None;
+                }
+            };
+            Some((action_digest.as_str(), details.execution_stats.as_ref()?))
+        })
+        .collect()
+}
+
+/// The execution stats of `repro`, from the stats returned by `local_execution_stats`.
+pub fn reproducer_execution_stats<'a>(
+    repro: CommandReproducer<'_>,
+    stats: &HashMap<&str, &'a buck2_data::CommandExecutionStats>,
+) -> Option<&'a buck2_data::CommandExecutionStats> {
+    match repro {
+        CommandReproducer::LocalExecute(execute) => execute
+            .command
+            .as_ref()
+            .and_then(|command| stats.get(command.action_digest.as_str()))
+            .copied(),
+        _ => None,
+    }
+}
+
 /// The reproduction details for this command.
 #[derive(Clone, Copy, Dupe)]
 pub enum CommandReproducer<'a> {
@@ -9,6 +9,7 @@
 
 //! Implementations of `[crate::EventSink]` that are useful in different situations. Buck2 primarily uses the `channel`
 //! sink during normal operation.
+pub mod broadcast;
 pub(crate) mod channel;
 pub(crate) mod null;
 pub mod scribe;
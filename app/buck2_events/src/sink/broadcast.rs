@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::sync::Arc;
+
+use dupe::Dupe;
+use tokio::sync::broadcast;
+
+use crate::BuckEvent;
+use crate::ControlEvent;
+use crate::EventSink;
+use crate::EventSinkStats;
+
+/// An EventSink that forwards events to any number of subscribers, which may come and go while
+/// events are being sent. The daemon tees the events of every command into one of those (see
+/// [`BroadcastEventSink::tee`]), which is how subscriptions observe commands they did not start.
+///
+/// Sending is a no-op when there are no subscribers. Subscribers that fall more than `capacity`
+/// events behind miss events rather than slowing down the sender.
+#[derive(Clone, Dupe)]
+pub struct BroadcastEventSink {
+    sender: Arc<broadcast::Sender<Arc<BuckEvent>>>,
+}
+
+impl BroadcastEventSink {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _receiver) = broadcast::channel(capacity);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    /// Receive all events sent from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<BuckEvent>> {
+        self.sender.subscribe()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    /// Wrap `inner` so that events sent to it are also broadcast. Unlike a `TeeSink`, this only
+    /// clones an event when someone is subscribed, so commands pay nothing while nobody listens.
+    pub fn tee<S: EventSink>(&self, inner: S) -> BroadcastTeeSink<S> {
+        BroadcastTeeSink {
+            broadcast: self.dupe(),
+            inner,
+        }
+    }
+}
+
+impl EventSink for BroadcastEventSink {
+    fn send(&self, event: BuckEvent) {
+        if self.sender.receiver_count() > 0 {
+            // This only fails if the last subscriber went away since we checked.
+            let _ignored = self.sender.send(Arc::new(event));
+        }
+    }
+
+    fn send_control(&self, _: ControlEvent) {}
+
+    fn stats(&self) -> Option<EventSinkStats> {
+        None
+    }
+}
+
+/// Returned by [`BroadcastEventSink::tee`].
+pub struct BroadcastTeeSink<S> {
+    broadcast: BroadcastEventSink,
+    inner: S,
+}
+
+impl<S: EventSink> EventSink for BroadcastTeeSink<S> {
+    fn send(&self, event: BuckEvent) {
+        if self.broadcast.has_subscribers() {
+            self.broadcast.send(event.clone());
+        }
+        self.inner.send(event);
+    }
+
+    fn send_control(&self, control_event: ControlEvent) {
+        self.inner.send_control(control_event);
+    }
+
+    fn stats(&self) -> Option<EventSinkStats> {
+        self.inner.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::SystemTime;
+
+    use buck2_data::SpanStartEvent;
+
+    use super::*;
+    use crate::span::SpanId;
+    use crate::trace::TraceId;
+
+    fn test_event() -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(SpanId::new()),
+            None,
+            SpanStartEvent {
+                data: Some(buck2_data::CommandStart::default().into()),
+            }
+            .into(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_events_sent_after_subscribing() {
+        let sink = BroadcastEventSink::new(10);
+        sink.send(test_event());
+
+        let mut first = sink.subscribe();
+        sink.send(test_event());
+        let mut second = sink.subscribe();
+        sink.send(test_event());
+
+        assert!(first.recv().await.is_ok());
+        assert!(first.recv().await.is_ok());
+        assert!(first.try_recv().is_err());
+        assert!(second.recv().await.is_ok());
+        assert!(second.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tee_only_broadcasts_while_subscribed() {
+        struct CountingSink(Arc<AtomicUsize>);
+
+        impl EventSink for CountingSink {
+            fn send(&self, _: BuckEvent) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn send_control(&self, _: ControlEvent) {}
+
+            fn stats(&self) -> Option<EventSinkStats> {
+                None
+            }
+        }
+
+        let broadcast = BroadcastEventSink::new(10);
+        let count = Arc::new(AtomicUsize::new(0));
+        let sink = broadcast.tee(CountingSink(count.dupe()));
+
+        assert!(!broadcast.has_subscribers());
+        sink.send(test_event());
+
+        let mut receiver = broadcast.subscribe();
+        assert!(broadcast.has_subscribers());
+        sink.send(test_event());
+
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+        assert!(receiver.recv().await.is_ok());
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_lagging_subscriber() {
+        let sink = BroadcastEventSink::new(1);
+        let mut receiver = sink.subscribe();
+        sink.send(test_event());
+        sink.send(test_event());
+
+        assert!(matches!(
+            receiver.recv().await,
+            Err(broadcast::error::RecvError::Lagged(1))
+        ));
+        assert!(receiver.recv().await.is_ok());
+    }
+}
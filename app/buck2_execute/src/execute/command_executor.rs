@@ -93,6 +93,10 @@ impl CommandExecutor {
         ExecutorFs::new(&self.0.artifact_fs, self.0.options.path_separator)
     }
 
+    pub fn re_platform(&self) -> &RE::Platform {
+        &self.0.re_platform
+    }
+
     /// Execute a command.
     ///
     /// This intentionally does not return a Result since we want to capture information about the
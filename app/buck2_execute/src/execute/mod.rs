@@ -23,6 +23,7 @@ pub mod prepared;
 pub mod request;
 pub mod result;
 pub mod target;
+pub mod test_result_cache;
 pub mod testing_dry_run;
 
 use std::future::Future;
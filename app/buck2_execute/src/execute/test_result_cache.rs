@@ -0,0 +1,502 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Opt-in cache of passing test executions, enabled with `buck2.test_result_cache`.
+//!
+//! Entries are keyed on a digest of the prepared `CommandExecutionRequest` (its command line,
+//! environment, working directory and input tree) and of the executor and platform it runs on.
+//! Only executions that exited with 0 and declared no outputs are cached, since a cache hit
+//! cannot reproduce outputs. Entries are stored on disk in the daemon's cache directory; when
+//! it grows too large, the oldest entries are evicted.
+//!
+//! When the test executor has the remote cache enabled, entries missing locally are also looked
+//! up in the RE action cache under the same key, and passing results are written there if the
+//! executor allows cache uploads, so results are shared across daemons and machines.
+//! Cache errors are never fatal: the test just runs.
+
+use std::io::ErrorKind;
+use std::io::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_common::cas_digest::Digester;
+use buck2_common::executor_config::CacheUploadBehavior;
+use buck2_common::executor_config::CommandExecutorConfig;
+use buck2_common::executor_config::Executor;
+use buck2_common::executor_config::RemoteEnabledExecutor;
+use buck2_common::executor_config::RemoteExecutorUseCase;
+use buck2_core::directory::FingerprintedDirectory;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
+use dice::UserComputationData;
+use dupe::Dupe;
+use remote_execution as RE;
+
+use crate::digest::CasDigestToReExt;
+use crate::digest_config::DigestConfig;
+use crate::execute::action_digest::ActionDigest;
+use crate::execute::action_digest::ActionDigestKind;
+use crate::execute::output::CommandStdStreams;
+use crate::execute::request::CommandExecutionRequest;
+use crate::re::manager::ManagedRemoteExecutionClient;
+use crate::re::streams::RemoteCommandStdStreams;
+
+/// Bumped whenever the key or the entry format changes.
+const KEY_VERSION: &[u8] = b"buck2-test-result-v2";
+
+/// Evict entries when the cache grows larger than this.
+const MAX_CACHE_SIZE_BYTES: u64 = 1 << 30;
+/// Eviction removes the oldest entries until the cache is no larger than this.
+const EVICT_TO_SIZE_BYTES: u64 = MAX_CACHE_SIZE_BYTES / 2;
+
+/// The output of a cached passing test execution.
+pub struct CachedTestExecution {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Where a test runs. Part of the cache key, since the same command may behave
+/// differently locally and remotely.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum ExecutorKind {
+    Local,
+    Remote,
+    Hybrid,
+}
+
+impl ExecutorKind {
+    pub fn from_config(config: &CommandExecutorConfig) -> Self {
+        match &config.executor {
+            Executor::Local(..)
+            | Executor::RemoteEnabled {
+                executor: RemoteEnabledExecutor::Local(..),
+                ..
+            } => Self::Local,
+            Executor::RemoteEnabled {
+                executor: RemoteEnabledExecutor::Remote(..),
+                ..
+            } => Self::Remote,
+            Executor::RemoteEnabled {
+                executor: RemoteEnabledExecutor::Hybrid { .. },
+                ..
+            } => Self::Hybrid,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Local => "local",
+            Self::Remote => "remote",
+            Self::Hybrid => "hybrid",
+        }
+    }
+}
+
+/// How the executor a test runs on takes part in caching.
+#[derive(Debug, Clone, Copy, Dupe)]
+pub struct TestCacheExecutor {
+    pub kind: ExecutorKind,
+    /// Use case for the RE action cache, if the executor has the remote cache enabled.
+    pub re_use_case: Option<RemoteExecutorUseCase>,
+    /// Whether passing results may be written to the RE action cache.
+    pub re_upload: bool,
+}
+
+impl TestCacheExecutor {
+    pub fn from_config(config: &CommandExecutorConfig) -> Self {
+        let (re_use_case, re_upload) = match &config.executor {
+            Executor::Local(..) => (None, false),
+            Executor::RemoteEnabled {
+                re_use_case,
+                cache_upload_behavior,
+                remote_cache_enabled,
+                ..
+            } => (
+                Some(*re_use_case).filter(|_| *remote_cache_enabled),
+                matches!(cache_upload_behavior, CacheUploadBehavior::Enabled { .. }),
+            ),
+        };
+        TestCacheExecutor {
+            kind: ExecutorKind::from_config(config),
+            re_use_case,
+            re_upload,
+        }
+    }
+}
+
+#[derive(Allocative)]
+pub struct TestResultCache {
+    dir: AbsNormPathBuf,
+    /// Approximate size of the cache directory.
+    size: AtomicU64,
+}
+
+impl TestResultCache {
+    pub fn new(dir: AbsNormPathBuf) -> anyhow::Result<TestResultCache> {
+        fs_util::create_dir_all(&dir)?;
+        let mut size = 0;
+        for entry in fs_util::read_dir(&dir)? {
+            size += entry?.metadata()?.len();
+        }
+        Ok(TestResultCache {
+            dir,
+            size: AtomicU64::new(size),
+        })
+    }
+
+    pub fn lookup(&self, key: &ActionDigest) -> Option<CachedTestExecution> {
+        let path = self.entry_path(key);
+        match std::fs::read(&path) {
+            Ok(entry) => decode_entry(&entry),
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => {
+                tracing::debug!("Error reading test result cache entry `{}`: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn store(&self, key: &ActionDigest, stdout: &[u8], stderr: &[u8]) {
+        if let Err(e) = self.write(key, &encode_entry(stdout, stderr)) {
+            tracing::debug!("Error writing test result cache entry: {:#}", e);
+        }
+    }
+
+    /// Look up the entry in the RE action cache, storing it locally on hit.
+    pub async fn re_lookup(
+        &self,
+        client: &ManagedRemoteExecutionClient,
+        use_case: RemoteExecutorUseCase,
+        key: &ActionDigest,
+        digest_config: DigestConfig,
+    ) -> Option<CachedTestExecution> {
+        let response = client.action_cache(key.dupe(), use_case).await.ok()??;
+        if response.action_result.exit_code != 0 {
+            return None;
+        }
+        let streams = CommandStdStreams::Remote(RemoteCommandStdStreams::new(
+            &response.action_result,
+            client,
+            use_case,
+            digest_config,
+        ));
+        match streams.into_bytes().await {
+            Ok(streams) => {
+                self.store(key, &streams.stdout, &streams.stderr);
+                Some(CachedTestExecution {
+                    stdout: streams.stdout,
+                    stderr: streams.stderr,
+                })
+            }
+            Err(e) => {
+                tracing::debug!("Error downloading cached test output: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Write a passing result to the RE action cache.
+    pub async fn re_store(
+        &self,
+        client: &ManagedRemoteExecutionClient,
+        use_case: RemoteExecutorUseCase,
+        key: &ActionDigest,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    ) {
+        let res: anyhow::Result<()> = try {
+            let streams = CommandStdStreams::Local { stdout, stderr }
+                .into_re(client, use_case)
+                .await?;
+            let (stdout_raw, stdout_digest) = streams.stdout.into_raw_or_digest();
+            let (stderr_raw, stderr_digest) = streams.stderr.into_raw_or_digest();
+            let result = RE::TActionResult2 {
+                exit_code: 0,
+                stdout_raw,
+                stdout_digest,
+                stderr_raw,
+                stderr_digest,
+                ..Default::default()
+            };
+            client
+                .write_action_result(key.to_re(), result, use_case)
+                .await?;
+        };
+        if let Err(e) = res {
+            tracing::debug!("Error writing test result to the action cache: {:#}", e);
+        }
+    }
+
+    fn entry_path(&self, key: &ActionDigest) -> AbsNormPathBuf {
+        self.dir
+            .join(FileName::unchecked_new(&key.raw_digest().to_string()))
+    }
+
+    fn write(&self, key: &ActionDigest, entry: &[u8]) -> anyhow::Result<()> {
+        let size = self.size.fetch_add(entry.len() as u64, Ordering::Relaxed);
+        if size > MAX_CACHE_SIZE_BYTES {
+            self.evict(EVICT_TO_SIZE_BYTES)?;
+        }
+        // Write to a uniquely named temporary file and rename it so that concurrent
+        // writers do not clobber each other, and readers never observe partially written entries.
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.dir)?;
+        tmp.write_all(entry)?;
+        tmp.persist(self.entry_path(key))?;
+        Ok(())
+    }
+
+    /// Remove the oldest entries until the cache is no larger than `to_size`.
+    fn evict(&self, to_size: u64) -> anyhow::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs_util::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+        entries.sort();
+        let mut size: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in entries {
+            if size <= to_size {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => {}
+                // Removed by a concurrent eviction.
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            size -= len;
+        }
+        self.size.store(size, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Compute the cache key for a test execution, or `None` if it must not be cached.
+pub fn cache_key(
+    request: &CommandExecutionRequest,
+    executor: ExecutorKind,
+    platform: &RE::Platform,
+    digest_config: DigestConfig,
+) -> Option<ActionDigest> {
+    if !request.paths().output_paths().is_empty() {
+        return None;
+    }
+
+    let input_digest = request.paths().input_directory().fingerprint();
+
+    let mut digester = ActionDigest::digester(digest_config.cas_digest_config());
+    add_field(&mut digester, KEY_VERSION);
+    add_executor_fields(&mut digester, executor, platform);
+    add_field(
+        &mut digester,
+        request
+            .working_directory()
+            .map_or("", |p| p.as_str())
+            .as_bytes(),
+    );
+    add_field(&mut digester, &(request.args().len() as u64).to_le_bytes());
+    for arg in request.args() {
+        add_field(&mut digester, arg.as_bytes());
+    }
+    add_field(&mut digester, &(request.env().len() as u64).to_le_bytes());
+    for (k, v) in request.env() {
+        add_field(&mut digester, k.as_bytes());
+        add_field(&mut digester, v.as_bytes());
+    }
+    add_field(
+        &mut digester,
+        input_digest.raw_digest().to_string().as_bytes(),
+    );
+    add_field(&mut digester, &input_digest.size().to_le_bytes());
+    Some(digester.finalize())
+}
+
+fn add_executor_fields(
+    digester: &mut Digester<ActionDigestKind>,
+    executor: ExecutorKind,
+    platform: &RE::Platform,
+) {
+    add_field(digester, executor.as_str().as_bytes());
+    add_field(digester, &(platform.properties.len() as u64).to_le_bytes());
+    for property in &platform.properties {
+        add_field(digester, property.name.as_bytes());
+        add_field(digester, property.value.as_bytes());
+    }
+}
+
+/// Length-prefix each field so that e.g. the args `["ab", "c"]` and `["a", "bc"]` differ.
+fn add_field(digester: &mut Digester<ActionDigestKind>, bytes: &[u8]) {
+    digester.update(&(bytes.len() as u64).to_le_bytes());
+    digester.update(bytes);
+}
+
+fn encode_entry(stdout: &[u8], stderr: &[u8]) -> Vec<u8> {
+    let mut entry = Vec::with_capacity(8 + stdout.len() + stderr.len());
+    entry.extend_from_slice(&(stdout.len() as u64).to_le_bytes());
+    entry.extend_from_slice(stdout);
+    entry.extend_from_slice(stderr);
+    entry
+}
+
+fn decode_entry(entry: &[u8]) -> Option<CachedTestExecution> {
+    let (len, rest) = entry.split_at(entry.len().min(8));
+    let len = usize::try_from(u64::from_le_bytes(len.try_into().ok()?)).ok()?;
+    if len > rest.len() {
+        return None;
+    }
+    let (stdout, stderr) = rest.split_at(len);
+    Some(CachedTestExecution {
+        stdout: stdout.to_vec(),
+        stderr: stderr.to_vec(),
+    })
+}
+
+pub trait SetTestResultCache {
+    fn set_test_result_cache(&mut self, cache: Arc<TestResultCache>);
+}
+
+impl SetTestResultCache for UserComputationData {
+    fn set_test_result_cache(&mut self, cache: Arc<TestResultCache>) {
+        self.data.set(cache);
+    }
+}
+
+pub trait HasTestResultCache {
+    /// The cache, if enabled for the daemon.
+    fn get_test_result_cache(&self) -> Option<&TestResultCache>;
+}
+
+impl HasTestResultCache for UserComputationData {
+    fn get_test_result_cache(&self) -> Option<&TestResultCache> {
+        match self.data.get::<Arc<TestResultCache>>() {
+            Ok(cache) => Some(&**cache),
+            Err(..) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(fields: &[&[u8]]) -> ActionDigest {
+        let mut digester =
+            ActionDigest::digester(DigestConfig::testing_default().cas_digest_config());
+        for f in fields {
+            add_field(&mut digester, f);
+        }
+        digester.finalize()
+    }
+
+    #[test]
+    fn test_entry_roundtrip() {
+        let entry = encode_entry(b"out", b"err");
+        let decoded = decode_entry(&entry).unwrap();
+        assert_eq!(decoded.stdout, b"out");
+        assert_eq!(decoded.stderr, b"err");
+
+        let entry = encode_entry(b"", b"");
+        let decoded = decode_entry(&entry).unwrap();
+        assert!(decoded.stdout.is_empty());
+        assert!(decoded.stderr.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_entry() {
+        let entry = encode_entry(b"out", b"err");
+        assert!(decode_entry(&entry[..4]).is_none());
+        assert!(decode_entry(&entry[..9]).is_none());
+    }
+
+    #[test]
+    fn test_fields_are_delimited() {
+        assert_ne!(digest(&[b"ab", b"c"]), digest(&[b"a", b"bc"]));
+        assert_eq!(digest(&[b"ab", b"c"]), digest(&[b"ab", b"c"]));
+    }
+
+    #[test]
+    fn test_hit_and_miss() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let cache = TestResultCache::new(AbsNormPathBuf::try_from(tempdir.path().to_path_buf())?)?;
+        let key = digest(&[b"test"]);
+        assert!(cache.lookup(&key).is_none());
+
+        cache.store(&key, b"out", b"err");
+        let cached = cache.lookup(&key).unwrap();
+        assert_eq!(cached.stdout, b"out");
+        assert_eq!(cached.stderr, b"err");
+
+        // A different command, e.g. after its inputs changed, misses.
+        assert!(cache.lookup(&digest(&[b"other"])).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = AbsNormPathBuf::try_from(tempdir.path().to_path_buf())?;
+        let cache = TestResultCache::new(dir.clone())?;
+        let (a, b) = (digest(&[b"a"]), digest(&[b"b"]));
+        cache.store(&a, b"out", b"");
+        cache.store(&b, b"out", b"");
+
+        let entry_size = encode_entry(b"out", b"").len() as u64;
+        cache.evict(entry_size)?;
+        assert_eq!(1, std::fs::read_dir(&dir)?.count());
+
+        cache.evict(0)?;
+        assert!(cache.lookup(&a).is_none());
+        assert!(cache.lookup(&b).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cache_executor_without_remote_cache() {
+        let executor = TestCacheExecutor::from_config(&CommandExecutorConfig::testing_local());
+        assert_eq!(ExecutorKind::Local, executor.kind);
+        assert!(executor.re_use_case.is_none());
+        assert!(!executor.re_upload);
+    }
+
+    #[test]
+    fn test_key_includes_executor() {
+        let key = |executor, os: &str| {
+            let platform = RE::Platform {
+                properties: vec![RE::Property {
+                    name: "OSFamily".to_owned(),
+                    value: os.to_owned(),
+                }],
+            };
+            let mut digester =
+                ActionDigest::digester(DigestConfig::testing_default().cas_digest_config());
+            add_executor_fields(&mut digester, executor, &platform);
+            digester.finalize()
+        };
+        assert_eq!(
+            key(ExecutorKind::Local, "linux"),
+            key(ExecutorKind::Local, "linux")
+        );
+        assert_ne!(
+            key(ExecutorKind::Local, "linux"),
+            key(ExecutorKind::Remote, "linux")
+        );
+        assert_ne!(
+            key(ExecutorKind::Remote, "linux"),
+            key(ExecutorKind::Remote, "windows")
+        );
+        assert_eq!(
+            ExecutorKind::Local,
+            ExecutorKind::from_config(&CommandExecutorConfig::testing_local())
+        );
+    }
+}
@@ -111,27 +111,38 @@ impl StatusDecoder for MiniperfStatusDecoder {
                 {
                     use std::os::unix::process::ExitStatusExt;
                     let exit_code = default_decode_exit_code(ExitStatus::from_raw(v));
-                    let execution_stats =
-                        status
-                            .counters
-                            .map(|counters| buck2_data::CommandExecutionStats {
-                                cpu_instructions_user: Some(
-                                    counters.user_instructions.adjusted_count(),
-                                ),
-                                cpu_instructions_kernel: Some(
-                                    counters.kernel_instructions.adjusted_count(),
-                                ),
-                            });
-
-                    if let Err(e) = execution_stats.as_ref() {
-                        // TODO @torozco: report this in the event log? Might be verbose for little
-                        // value.
-                        tracing::debug!("Miniperf stats not availble: {}", e);
-                    }
+                    // TODO @torozco: report errors in the event log? Might be verbose for little
+                    // value.
+                    let counters = status
+                        .counters
+                        .map_err(|e| tracing::debug!("Miniperf counters not available: {}", e))
+                        .ok();
+                    let resources = status
+                        .resources
+                        .map_err(|e| tracing::debug!("Miniperf resources not available: {}", e))
+                        .ok();
+
+                    let execution_stats = if counters.is_some() || resources.is_some() {
+                        Some(buck2_data::CommandExecutionStats {
+                            cpu_instructions_user: counters
+                                .map(|c| c.user_instructions.adjusted_count()),
+                            cpu_instructions_kernel: counters
+                                .map(|c| c.kernel_instructions.adjusted_count()),
+                            peak_rss_bytes: resources.map(|r| r.peak_rss_bytes),
+                            user_cpu_time_us: resources.map(|r| r.user_cpu_time_us),
+                            system_cpu_time_us: resources.map(|r| r.system_cpu_time_us),
+                            syscall_read_bytes: resources.map(|r| r.syscall_read_bytes),
+                            syscall_write_bytes: resources.map(|r| r.syscall_write_bytes),
+                            block_read_bytes: resources.and_then(|r| r.block_read_bytes),
+                            block_write_bytes: resources.and_then(|r| r.block_write_bytes),
+                        })
+                    } else {
+                        None
+                    };
 
                     Ok(DecodedStatus::Status {
                         exit_code,
-                        execution_stats: execution_stats.ok(),
+                        execution_stats,
                     })
                 }
 
@@ -0,0 +1,282 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A generic installer for `buck2 install`: it copies the files of each install to a destination
+//! directory, and optionally runs a command once all of them have been copied.
+//!
+//! Use it as the `installer` of an `installer` rule, passing arguments via the `RunInfo` of the
+//! installer target, e.g. `--dst /tmp/my_app --post-install-cmd "/tmp/my_app/run.sh"`.
+
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_installer::run_installer;
+use buck2_installer::Installer;
+use buck2_installer::InstallerArgs;
+use buck2_installer::ReadyFile;
+use clap::Parser;
+
+#[derive(Debug, thiserror::Error)]
+enum CopyInstallerError {
+    #[error("File name `{0}` is not a relative path without `..`")]
+    InvalidName(String),
+    #[error("Post-install command `{cmd}` failed for `{install_id}` with {status}")]
+    PostInstallFailed {
+        cmd: String,
+        install_id: String,
+        status: std::process::ExitStatus,
+    },
+}
+
+#[derive(Debug, Parser)]
+#[clap(about = "Copy files built by `buck2 install` to a directory")]
+struct CopyInstallerArgs {
+    #[clap(flatten)]
+    installer: InstallerArgs,
+
+    /// The directory to copy files to. Each file is copied to `<dst>/<name>`, where `<name>` is
+    /// its key in the `files` of the install.
+    #[clap(long)]
+    dst: PathBuf,
+
+    /// A shell command to run once all the files of an install have been copied. It runs in
+    /// `dst`, with `BUCK2_INSTALL_ID` set to the label of the installed target.
+    #[clap(long)]
+    post_install_cmd: Option<String>,
+}
+
+struct CopyInstaller {
+    dst: PathBuf,
+    post_install_cmd: Option<String>,
+}
+
+#[async_trait]
+impl Installer for CopyInstaller {
+    async fn file_ready(&self, file: &ReadyFile) -> anyhow::Result<()> {
+        let dst = self.dst.join(checked_name(&file.name)?);
+        let src = file.path.clone();
+        tokio::task::spawn_blocking(move || copy_recursively(&src, &dst))
+            .await?
+            .with_context(|| format!("Error copying `{}`", file.path.display()))
+    }
+
+    async fn install_finished(&self, install_id: &str) -> anyhow::Result<()> {
+        let cmd = match &self.post_install_cmd {
+            Some(cmd) => cmd,
+            None => return Ok(()),
+        };
+
+        let mut command = if cfg!(windows) {
+            let mut command = tokio::process::Command::new("cmd");
+            command.arg("/C");
+            command
+        } else {
+            let mut command = tokio::process::Command::new("sh");
+            command.arg("-c");
+            command
+        };
+        let status = command
+            .arg(cmd)
+            .current_dir(&self.dst)
+            .env("BUCK2_INSTALL_ID", install_id)
+            .status()
+            .await
+            .with_context(|| format!("Error spawning post-install command `{}`", cmd))?;
+
+        if !status.success() {
+            return Err(CopyInstallerError::PostInstallFailed {
+                cmd: cmd.clone(),
+                install_id: install_id.to_owned(),
+                status,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Names come from the installer rule, but make sure they can't escape `dst` anyway.
+fn checked_name(name: &str) -> anyhow::Result<&Path> {
+    let path = Path::new(name);
+    if name.is_empty()
+        || !path
+            .components()
+            .all(|c| matches!(c, Component::Normal(..) | Component::CurDir))
+    {
+        return Err(CopyInstallerError::InvalidName(name.to_owned()).into());
+    }
+    Ok(path)
+}
+
+/// Copy a file or a directory, replacing whatever `dst` was before.
+fn copy_recursively(src: &Path, dst: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = dst.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    remove_existing(dst)?;
+
+    if src.is_dir() {
+        std::fs::create_dir(dst)?;
+        for entry in std::fs::read_dir(src)? {
+            let entry = entry?;
+            copy_recursively(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else {
+        // This follows symlinks, so that the destination is usable without buck-out.
+        std::fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+/// Remove whatever is at `path`, including files and directories left read-only by an earlier
+/// copy (`std::fs::copy` preserves permissions, and outputs in buck-out are often read-only).
+fn remove_existing(path: &Path) -> anyhow::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    if metadata.is_dir() {
+        // Entries can only be removed from a writable directory.
+        make_writable(path, &metadata)?;
+        for entry in std::fs::read_dir(path)? {
+            remove_existing(&entry?.path())?;
+        }
+        std::fs::remove_dir(path)?;
+    } else {
+        // Windows refuses to delete read-only files, Unix only cares about the parent directory.
+        if cfg!(windows) {
+            make_writable(path, &metadata)?;
+        }
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn make_writable(path: &Path, metadata: &std::fs::Metadata) -> anyhow::Result<()> {
+    let mut permissions = metadata.permissions();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if permissions.mode() & 0o200 != 0 {
+            return Ok(());
+        }
+        permissions.set_mode(permissions.mode() | 0o200);
+    }
+
+    #[cfg(not(unix))]
+    {
+        if !permissions.readonly() {
+            return Ok(());
+        }
+        #[allow(clippy::permissions_set_readonly_false)] // Only on Windows, where that is fine.
+        permissions.set_readonly(false);
+    }
+
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = CopyInstallerArgs::parse();
+
+    let installer = CopyInstaller {
+        dst: args.dst,
+        post_install_cmd: args.post_install_cmd,
+    };
+    run_installer(installer, args.installer).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_name() {
+        assert!(checked_name("app.apk").is_ok());
+        assert!(checked_name("lib/foo.so").is_ok());
+        assert!(checked_name("").is_err());
+        assert!(checked_name("../foo").is_err());
+        assert!(checked_name("lib/../../foo").is_err());
+        assert!(checked_name("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_copy_recursively() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let src = tempdir.path().join("src");
+        std::fs::create_dir_all(src.join("sub"))?;
+        std::fs::write(src.join("a"), "a")?;
+        std::fs::write(src.join("sub/b"), "b")?;
+
+        let dst = tempdir.path().join("dst/nested");
+        copy_recursively(&src, &dst)?;
+        assert_eq!(std::fs::read_to_string(dst.join("a"))?, "a");
+        assert_eq!(std::fs::read_to_string(dst.join("sub/b"))?, "b");
+
+        // Copying again replaces what was there.
+        std::fs::remove_file(src.join("a"))?;
+        copy_recursively(&src, &dst)?;
+        assert!(!dst.join("a").exists());
+        assert_eq!(std::fs::read_to_string(dst.join("sub/b"))?, "b");
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_recursively_over_read_only() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let src = tempdir.path().join("src");
+        std::fs::write(&src, "new")?;
+
+        let dst = tempdir.path().join("dst");
+        std::fs::create_dir_all(dst.join("sub"))?;
+        std::fs::write(dst.join("sub/file"), "old")?;
+        std::fs::write(dst.join("file"), "old")?;
+        for path in [dst.join("sub/file"), dst.join("sub"), dst.join("file")] {
+            let mut permissions = std::fs::metadata(&path)?.permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(&path, permissions)?;
+        }
+
+        // A read-only file.
+        copy_recursively(&src, &dst.join("file"))?;
+        assert_eq!(std::fs::read_to_string(dst.join("file"))?, "new");
+
+        // A read-only directory with read-only contents.
+        copy_recursively(&src, &dst.join("sub"))?;
+        assert_eq!(std::fs::read_to_string(dst.join("sub"))?, "new");
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_directory_over_file() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let src = tempdir.path().join("src");
+        std::fs::create_dir(&src)?;
+        std::fs::write(src.join("a"), "a")?;
+
+        let dst = tempdir.path().join("dst");
+        std::fs::write(&dst, "file")?;
+        copy_recursively(&src, &dst)?;
+        assert_eq!(std::fs::read_to_string(dst.join("a"))?, "a");
+
+        // And back.
+        copy_recursively(&src.join("a"), &dst)?;
+        assert_eq!(std::fs::read_to_string(&dst)?, "a");
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! A library to implement installers for `buck2 install`.
+//!
+//! `buck2 install` launches the installer of the target being installed with `--tcp-port` and
+//! `--log-path` (see [`InstallerArgs`]), then talks to it over the `Installer` gRPC service from
+//! `buck2_install_proto`: it announces each install and its files with `Install`, sends
+//! `FileReady` for each file as soon as it is built, and finally sends `ShutdownServer`.
+//!
+//! This crate handles that protocol. Installers implement [`Installer`], which only deals with
+//! the files, and pass it to [`run_installer`].
+
+mod service;
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use async_trait::async_trait;
+use buck2_install_proto::installer_server::InstallerServer;
+use tokio::sync::oneshot;
+
+use crate::service::InstallerLog;
+use crate::service::InstallerService;
+
+/// The arguments `buck2 install` passes to every installer, after the installer's own arguments.
+/// Installers should `#[clap(flatten)]` this into their own arguments.
+#[derive(Debug, clap::Parser)]
+pub struct InstallerArgs {
+    /// The port on localhost to listen on for buck2.
+    #[clap(long)]
+    pub tcp_port: u16,
+
+    /// Where to write the installer's log. buck2 points users at it when an install fails.
+    #[clap(long)]
+    pub log_path: Option<PathBuf>,
+}
+
+/// A file buck2 built for an install.
+#[derive(Debug, Clone)]
+pub struct ReadyFile {
+    /// Identifies the install this file is part of. This is the label of the target being
+    /// installed.
+    pub install_id: String,
+    /// The name of the file, as a key in the `files` of the install.
+    pub name: String,
+    /// The absolute path to the file, which may also be a directory.
+    pub path: PathBuf,
+    /// The digest of the file. For symlinks, this is `re-symlink:<target>` instead.
+    pub digest: String,
+    /// The algorithm of `digest`, e.g. `SHA1`. Empty for symlinks.
+    pub digest_algorithm: String,
+    pub size: u64,
+}
+
+/// The part of an installer specific to what is installed and where.
+///
+/// Methods may be called concurrently, including for different installs.
+#[async_trait]
+pub trait Installer: Send + Sync + 'static {
+    /// Called when buck2 announces an install and the files it consists of, by name. The files
+    /// have not necessarily been built yet. Returning an error fails the install.
+    async fn install(
+        &self,
+        install_id: &str,
+        files: &HashMap<String, PathBuf>,
+    ) -> anyhow::Result<()> {
+        let _unused = (install_id, files);
+        Ok(())
+    }
+
+    /// Called for every file of an install, as soon as it is built.
+    async fn file_ready(&self, file: &ReadyFile) -> anyhow::Result<()>;
+
+    /// Called once `file_ready` has succeeded for all the files announced in `install`. This is
+    /// where e.g. an app would be started on the device it was installed to.
+    async fn install_finished(&self, install_id: &str) -> anyhow::Result<()> {
+        let _unused = install_id;
+        Ok(())
+    }
+}
+
+/// Serve the installer protocol for `installer` until buck2 asks for it to shut down.
+pub async fn run_installer(installer: impl Installer, args: InstallerArgs) -> anyhow::Result<()> {
+    let log = match &args.log_path {
+        Some(path) => InstallerLog::file(path)
+            .with_context(|| format!("Error opening installer log `{}`", path.display()))?,
+        None => InstallerLog::stderr(),
+    };
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let service = InstallerService::new(installer, log, shutdown_tx);
+
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, args.tcp_port));
+    tonic::transport::Server::builder()
+        .add_service(InstallerServer::new(service))
+        .serve_with_shutdown(addr, async move {
+            // An error means the service was dropped, in which case we are done too.
+            let _ignored = shutdown_rx.await;
+        })
+        .await
+        .with_context(|| format!("Error serving installer on `{}`", addr))
+}
@@ -0,0 +1,326 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use buck2_install_proto::installer_server;
+use buck2_install_proto::ErrorDetail;
+use buck2_install_proto::FileReadyRequest;
+use buck2_install_proto::FileResponse;
+use buck2_install_proto::InstallInfoRequest;
+use buck2_install_proto::InstallResponse;
+use buck2_install_proto::ShutdownRequest;
+use buck2_install_proto::ShutdownResponse;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+
+use crate::Installer;
+use crate::ReadyFile;
+
+/// Where the installer logs what it is doing.
+pub(crate) enum InstallerLog {
+    File(Mutex<File>),
+    Stderr,
+}
+
+impl InstallerLog {
+    pub(crate) fn file(path: &Path) -> std::io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self::File(Mutex::new(file)))
+    }
+
+    pub(crate) fn stderr() -> Self {
+        Self::Stderr
+    }
+
+    fn log(&self, args: fmt::Arguments) {
+        // There is nowhere to report errors writing the log to, so ignore them.
+        match self {
+            Self::File(file) => {
+                let _ignored = writeln!(file.lock(), "{}", args);
+            }
+            Self::Stderr => {
+                let _ignored = writeln!(std::io::stderr().lock(), "{}", args);
+            }
+        }
+    }
+}
+
+pub(crate) struct InstallerService<I> {
+    installer: I,
+    log: InstallerLog,
+    /// For each install in progress, the names of the files that have not been installed yet.
+    pending: Mutex<HashMap<String, HashSet<String>>>,
+    shutdown: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl<I: Installer> InstallerService<I> {
+    pub(crate) fn new(installer: I, log: InstallerLog, shutdown: oneshot::Sender<()>) -> Self {
+        Self {
+            installer,
+            log,
+            pending: Mutex::new(HashMap::new()),
+            shutdown: Mutex::new(Some(shutdown)),
+        }
+    }
+
+    async fn handle_file_ready(&self, file: &ReadyFile) -> anyhow::Result<()> {
+        self.installer.file_ready(file).await?;
+
+        let finished = {
+            let mut pending = self.pending.lock();
+            match pending.get_mut(&file.install_id) {
+                Some(names) => {
+                    names.remove(&file.name);
+                    if names.is_empty() {
+                        pending.remove(&file.install_id);
+                        true
+                    } else {
+                        false
+                    }
+                }
+                // Either a file buck2 did not announce, or the install already finished.
+                None => false,
+            }
+        };
+
+        if finished {
+            self.log
+                .log(format_args!("Finishing install `{}`", file.install_id));
+            self.installer.install_finished(&file.install_id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl<I: Installer> installer_server::Installer for InstallerService<I> {
+    async fn install(
+        &self,
+        request: tonic::Request<InstallInfoRequest>,
+    ) -> Result<tonic::Response<InstallResponse>, tonic::Status> {
+        let InstallInfoRequest { install_id, files } = request.into_inner();
+        self.log.log(format_args!(
+            "Received install `{}` with {} files",
+            install_id,
+            files.len()
+        ));
+
+        let files: HashMap<String, PathBuf> = files
+            .into_iter()
+            .map(|(name, path)| (name, PathBuf::from(path)))
+            .collect();
+
+        if let Err(e) = self.installer.install(&install_id, &files).await {
+            self.log
+                .log(format_args!("Install `{}` failed: {:#}", install_id, e));
+            return Err(tonic::Status::internal(format!("{:#}", e)));
+        }
+
+        if files.is_empty() {
+            // No file_ready request is coming to finish this install, so finish it now.
+            self.log
+                .log(format_args!("Finishing install `{}`", install_id));
+            if let Err(e) = self.installer.install_finished(&install_id).await {
+                self.log
+                    .log(format_args!("Install `{}` failed: {:#}", install_id, e));
+                return Err(tonic::Status::internal(format!("{:#}", e)));
+            }
+        } else {
+            self.pending
+                .lock()
+                .insert(install_id.clone(), files.into_keys().collect());
+        }
+
+        Ok(tonic::Response::new(InstallResponse { install_id }))
+    }
+
+    async fn file_ready(
+        &self,
+        request: tonic::Request<FileReadyRequest>,
+    ) -> Result<tonic::Response<FileResponse>, tonic::Status> {
+        let FileReadyRequest {
+            install_id,
+            name,
+            digest,
+            path,
+            digest_algorithm,
+            size,
+        } = request.into_inner();
+        let file = ReadyFile {
+            install_id,
+            name,
+            path: PathBuf::from(path),
+            digest,
+            digest_algorithm,
+            size,
+        };
+
+        let error_detail = match self.handle_file_ready(&file).await {
+            Ok(()) => {
+                self.log.log(format_args!(
+                    "Installed `{}` of `{}` from `{}`",
+                    file.name,
+                    file.install_id,
+                    file.path.display()
+                ));
+                None
+            }
+            Err(e) => {
+                self.log.log(format_args!(
+                    "Failed to install `{}` of `{}` from `{}`: {:#}",
+                    file.name,
+                    file.install_id,
+                    file.path.display(),
+                    e
+                ));
+                Some(ErrorDetail {
+                    message: format!("{:#}", e),
+                })
+            }
+        };
+
+        Ok(tonic::Response::new(FileResponse {
+            install_id: file.install_id,
+            name: file.name,
+            path: file.path.to_string_lossy().into_owned(),
+            error_detail,
+        }))
+    }
+
+    async fn shutdown_server(
+        &self,
+        _request: tonic::Request<ShutdownRequest>,
+    ) -> Result<tonic::Response<ShutdownResponse>, tonic::Status> {
+        self.log.log(format_args!("Shutting down"));
+        if let Some(shutdown) = self.shutdown.lock().take() {
+            // The receiver only goes away if the server already stopped.
+            let _ignored = shutdown.send(());
+        }
+        Ok(tonic::Response::new(ShutdownResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use buck2_install_proto::installer_server::Installer as _;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingInstaller {
+        finished: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl Installer for RecordingInstaller {
+        async fn file_ready(&self, file: &ReadyFile) -> anyhow::Result<()> {
+            if file.name == "bad" {
+                return Err(anyhow::anyhow!("bad file"));
+            }
+            Ok(())
+        }
+
+        async fn install_finished(&self, install_id: &str) -> anyhow::Result<()> {
+            self.finished.lock().push(install_id.to_owned());
+            Ok(())
+        }
+    }
+
+    fn service() -> (InstallerService<RecordingInstaller>, oneshot::Receiver<()>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            InstallerService::new(RecordingInstaller::default(), InstallerLog::stderr(), tx),
+            rx,
+        )
+    }
+
+    async fn install(service: &InstallerService<RecordingInstaller>, id: &str, names: &[&str]) {
+        service
+            .install(tonic::Request::new(InstallInfoRequest {
+                install_id: id.to_owned(),
+                files: names
+                    .iter()
+                    .map(|n| ((*n).to_owned(), format!("/out/{}", n)))
+                    .collect(),
+            }))
+            .await
+            .unwrap();
+    }
+
+    async fn file_ready(
+        service: &InstallerService<RecordingInstaller>,
+        id: &str,
+        name: &str,
+    ) -> Option<ErrorDetail> {
+        service
+            .file_ready(tonic::Request::new(FileReadyRequest {
+                install_id: id.to_owned(),
+                name: name.to_owned(),
+                path: format!("/out/{}", name),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .error_detail
+    }
+
+    #[tokio::test]
+    async fn test_install_finished_after_all_files() {
+        let (service, _rx) = service();
+        install(&service, "a", &["x", "y"]).await;
+        install(&service, "b", &["x"]).await;
+
+        assert_eq!(file_ready(&service, "a", "x").await, None);
+        assert_eq!(file_ready(&service, "b", "x").await, None);
+        assert_eq!(*service.installer.finished.lock(), vec!["b".to_owned()]);
+
+        assert_eq!(file_ready(&service, "a", "y").await, None);
+        assert_eq!(
+            *service.installer.finished.lock(),
+            vec!["b".to_owned(), "a".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_install_without_files() {
+        let (service, _rx) = service();
+        install(&service, "a", &[]).await;
+        assert_eq!(*service.installer.finished.lock(), vec!["a".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_file() {
+        let (service, _rx) = service();
+        install(&service, "a", &["bad"]).await;
+
+        let error = file_ready(&service, "a", "bad").await.unwrap();
+        assert_eq!(error.message, "bad file");
+        assert!(service.installer.finished.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let (service, rx) = service();
+        service
+            .shutdown_server(tonic::Request::new(ShutdownRequest {}))
+            .await
+            .unwrap();
+        rx.await.unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Debugging of Starlark evaluation in the daemon, with `buck2 starlark debug-attach`.
+//!
+//! While a debugger is attached, every evaluator hooked with [`hook_starlark_debugger`]
+//! reports to it, and can be stopped at breakpoints. Each evaluation is a separate DAP thread.
+
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use starlark::debug::DapAdapterEvalHook;
+use starlark::eval::Evaluator;
+
+/// The debugger attached to the daemon, if any. Every evaluation reads this, and it is only
+/// written when attaching or detaching.
+static CONTROLLER: RwLock<Option<Arc<dyn StarlarkDebugController>>> = RwLock::new(None);
+
+#[derive(Debug, thiserror::Error)]
+enum StarlarkDebugError {
+    #[error("A Starlark debugger is already attached to this daemon")]
+    AlreadyAttached,
+}
+
+/// A debugger attached to the daemon.
+pub trait StarlarkDebugController: Send + Sync + 'static {
+    /// Starts debugging an evaluation, returning its id and the hook to add to its evaluator.
+    /// `description` says what is being evaluated, e.g. `analysis of root//foo:bar`.
+    fn start_eval(&self, description: String) -> (u64, Box<dyn DapAdapterEvalHook>);
+
+    /// The evaluation with the given id finished.
+    fn end_eval(&self, id: u64);
+}
+
+/// Detaches the debugger when dropped.
+#[must_use]
+pub struct StarlarkDebuggerAttachment(());
+
+impl Drop for StarlarkDebuggerAttachment {
+    fn drop(&mut self) {
+        *CONTROLLER.write().unwrap() = None;
+    }
+}
+
+/// Attach a debugger to the daemon. Evaluations which start after this report to it.
+///
+/// Only one debugger can be attached at a time.
+pub fn attach_starlark_debugger(
+    controller: Arc<dyn StarlarkDebugController>,
+) -> anyhow::Result<StarlarkDebuggerAttachment> {
+    let mut current = CONTROLLER.write().unwrap();
+    if current.is_some() {
+        return Err(StarlarkDebugError::AlreadyAttached.into());
+    }
+    *current = Some(controller);
+    Ok(StarlarkDebuggerAttachment(()))
+}
+
+/// Tells the debugger that an evaluation finished when dropped.
+#[must_use]
+pub struct StarlarkDebugEvalGuard(Option<(Arc<dyn StarlarkDebugController>, u64)>);
+
+impl Drop for StarlarkDebugEvalGuard {
+    fn drop(&mut self) {
+        if let Some((controller, id)) = self.0.take() {
+            controller.end_eval(id);
+        }
+    }
+}
+
+/// Let the attached debugger, if any, stop `eval`. Must be called before evaluation starts,
+/// and the returned guard kept until it finishes.
+///
+/// Functions are instrumented for the debugger when their module is evaluated, so
+/// breakpoints in functions from modules evaluated before the debugger attached are ignored.
+pub fn hook_starlark_debugger(
+    eval: &mut Evaluator,
+    description: impl FnOnce() -> String,
+) -> StarlarkDebugEvalGuard {
+    let controller = CONTROLLER.read().unwrap().clone();
+    StarlarkDebugEvalGuard(controller.map(|controller| {
+        let (id, hook) = controller.start_eval(description());
+        hook.add_dap_hooks(eval);
+        (controller, id)
+    }))
+}
@@ -57,6 +57,7 @@ use crate::interpreter::dice_calculation_delegate::keys::EvalImportKey;
 use crate::interpreter::global_interpreter_state::HasGlobalInterpreterState;
 use crate::interpreter::interpreter_for_cell::InterpreterForCell;
 use crate::interpreter::interpreter_for_cell::ParseResult;
+use crate::interpreter::parse_cache::HasStarlarkParseCache;
 use crate::load_signals::HasLoadSignals;
 use crate::super_package::data::SuperPackage;
 
@@ -200,7 +201,11 @@ impl<'c> DiceCalculationDelegate<'c> {
     async fn parse_file(&self, starlark_path: StarlarkPath<'_>) -> anyhow::Result<ParseResult> {
         let content =
             <dyn FileOps>::read_file(&self.fs, starlark_path.path().as_ref().as_ref()).await?;
-        self.configs.parse(starlark_path, content)
+        self.configs.parse_with_cache(
+            starlark_path,
+            content,
+            self.ctx.per_transaction_data().get_starlark_parse_cache(),
+        )
     }
 
     async fn eval_deps(
@@ -238,7 +243,11 @@ impl<'c> DiceCalculationDelegate<'c> {
         starlark_file: StarlarkPath<'_>,
         content: String,
     ) -> anyhow::Result<AstModule> {
-        let ParseResult(ast, _) = self.configs.parse(starlark_file, content)?;
+        let ParseResult(ast, _) = self.configs.parse_with_cache(
+            starlark_file,
+            content,
+            self.ctx.per_transaction_data().get_starlark_parse_cache(),
+        )?;
         Ok(ast)
     }
 
@@ -37,6 +37,7 @@ use buck2_interpreter::path::OwnedStarlarkPath;
 use buck2_interpreter::path::PackageFilePath;
 use buck2_interpreter::path::StarlarkModulePath;
 use buck2_interpreter::path::StarlarkPath;
+use buck2_interpreter::starlark_debug::hook_starlark_debugger;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerInstrumentation;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerOrInstrumentation;
 use buck2_node::nodes::eval_result::EvaluationResult;
@@ -58,6 +59,7 @@ use crate::interpreter::build_context::BuildContext;
 use crate::interpreter::build_context::PerFileTypeContext;
 use crate::interpreter::global_interpreter_state::GlobalInterpreterState;
 use crate::interpreter::module_internals::ModuleInternals;
+use crate::interpreter::parse_cache::StarlarkParseCache;
 use crate::super_package::data::SuperPackage;
 use crate::super_package::eval_ctx::PackageFileEvalCtx;
 use crate::super_package::package_value::PackageValues;
@@ -397,6 +399,16 @@ impl InterpreterForCell {
         self: &Arc<Self>,
         import: StarlarkPath,
         content: String,
+    ) -> anyhow::Result<ParseResult> {
+        self.parse_with_cache(import, content, None)
+    }
+
+    /// Parse the file, reusing the AST from the persistent cache if it is enabled.
+    pub(crate) fn parse_with_cache(
+        self: &Arc<Self>,
+        import: StarlarkPath,
+        content: String,
+        cache: Option<&StarlarkParseCache>,
     ) -> anyhow::Result<ParseResult> {
         // Indentation with tabs is prohibited by starlark spec and configured starlark dialect.
         // This check also prohibits tabs even where spaces are not significant,
@@ -412,11 +424,11 @@ impl InterpreterForCell {
             .resolve_path(import.path().as_ref().as_ref())?;
         let result: anyhow::Result<_> = try {
             let disable_starlark_types = self.global_state.disable_starlark_types;
-            let ast = AstModule::parse(
-                project_relative_path.as_str(),
-                content,
-                &import.file_type().dialect(disable_starlark_types),
-            )?;
+            let dialect = import.file_type().dialect(disable_starlark_types);
+            let ast = match cache {
+                Some(cache) => cache.parse(project_relative_path.as_str(), content, &dialect)?,
+                None => AstModule::parse(project_relative_path.as_str(), content, &dialect)?,
+            };
             let mut implicit_imports = Vec::new();
             if let Some(i) = self.prelude_import(import) {
                 implicit_imports.push(OwnedStarlarkModulePath::LoadFile(i.clone()));
@@ -474,6 +486,7 @@ impl InterpreterForCell {
             eval.set_print_handler(&print);
             eval.set_loader(&file_loader);
             eval.extra = Some(&extra);
+            let _debug = hook_starlark_debugger(&mut eval, || import.to_string());
             profiler.initialize(&mut eval)?;
             if self.verbose_gc {
                 eval.verbose_gc();
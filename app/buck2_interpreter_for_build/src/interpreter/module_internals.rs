@@ -172,6 +172,7 @@ impl ModuleInternals {
                                 buildfile_path: self.buildfile_path.dupe(),
                                 oncall,
                                 default_visibility_to_public: self.default_visibility_to_public,
+                                package_values: self.super_package.package_values_json().dupe(),
                             }),
                             recorder: TargetsRecorder::new(),
                         });
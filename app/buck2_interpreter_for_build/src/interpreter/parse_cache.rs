@@ -0,0 +1,242 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! On-disk cache of parsed Starlark files.
+//!
+//! Parsing large `.bzl` files is a noticeable part of the cold start of the daemon.
+//! When `buck2.starlark_parse_cache = true` is set in the root buckconfig,
+//! parsed ASTs are written to `buck-out/<isolation>/cache/starlark_parse`
+//! and reused by subsequent daemons.
+//!
+//! Entries are keyed by the hash of the buck2 binary version, the dialect,
+//! the file path and the file content, so there is no invalidation to do:
+//! stale entries are simply never read again. When the directory grows over
+//! the size limit, the oldest entries are evicted.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
+use dice::UserComputationData;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+/// Evict entries when the cache grows larger than this.
+const MAX_CACHE_SIZE_BYTES: u64 = 1 << 30;
+/// Eviction removes the oldest entries until the cache is no larger than this.
+const EVICT_TO_SIZE_BYTES: u64 = MAX_CACHE_SIZE_BYTES / 2;
+
+/// Persistent cache of parsed Starlark ASTs.
+#[derive(Allocative)]
+pub struct StarlarkParseCache {
+    dir: AbsNormPathBuf,
+    /// Identifies the buck2 binary which produced the cache entries.
+    buck2_version: String,
+    /// Approximate size of the cache directory.
+    size: AtomicU64,
+}
+
+impl StarlarkParseCache {
+    pub fn new(dir: AbsNormPathBuf, buck2_version: String) -> anyhow::Result<StarlarkParseCache> {
+        fs_util::create_dir_all(&dir)?;
+        let mut size = 0;
+        for entry in fs_util::read_dir(&dir)? {
+            size += entry?.metadata()?.len();
+        }
+        Ok(StarlarkParseCache {
+            dir,
+            buck2_version,
+            size: AtomicU64::new(size),
+        })
+    }
+
+    fn entry_path(&self, filename: &str, content: &str, dialect: &Dialect) -> AbsNormPathBuf {
+        let mut hasher = blake3::Hasher::new();
+        for part in [
+            self.buck2_version.as_str(),
+            &format!("{:?}", dialect),
+            filename,
+            content,
+        ] {
+            // Length prefix to make the hash input unambiguous.
+            hasher.update(&(part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        self.dir
+            .join(FileName::unchecked_new(hasher.finalize().to_hex().as_str()))
+    }
+
+    /// Parse the file or fetch the parsed file from the cache.
+    ///
+    /// Errors reading or writing the cache are ignored, and the file is parsed as usual.
+    pub fn parse(
+        &self,
+        filename: &str,
+        content: String,
+        dialect: &Dialect,
+    ) -> anyhow::Result<AstModule> {
+        let path = self.entry_path(filename, &content, dialect);
+        match fs::read(&path) {
+            Ok(bytes) => match bincode::deserialize::<AstModule>(&bytes) {
+                Ok(ast) => return Ok(ast),
+                Err(e) => tracing::debug!("Corrupted Starlark parse cache entry `{}`: {}", path, e),
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => tracing::debug!("Error reading Starlark parse cache entry `{}`: {}", path, e),
+        }
+
+        let ast = AstModule::parse(filename, content, dialect)?;
+        if let Err(e) = self.write(&path, &ast) {
+            tracing::debug!(
+                "Error writing Starlark parse cache entry `{}`: {:#}",
+                path,
+                e
+            );
+        }
+        Ok(ast)
+    }
+
+    fn write(&self, path: &AbsNormPathBuf, ast: &AstModule) -> anyhow::Result<()> {
+        let bytes = bincode::serialize(ast)?;
+        let size = self.size.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        if size > MAX_CACHE_SIZE_BYTES {
+            self.evict(EVICT_TO_SIZE_BYTES)?;
+        }
+        // Write to a uniquely named temporary file and rename it so that concurrent
+        // writers do not clobber each other, and readers never observe partially written entries.
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.dir)?;
+        tmp.write_all(&bytes)?;
+        tmp.persist(path)?;
+        Ok(())
+    }
+
+    /// Remove the oldest entries until the cache is no larger than `to_size`.
+    fn evict(&self, to_size: u64) -> anyhow::Result<()> {
+        let mut entries = Vec::new();
+        for entry in fs_util::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+        entries.sort();
+        let mut size: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        for (_, len, path) in entries {
+            if size <= to_size {
+                break;
+            }
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                // Removed by another daemon sharing the cache.
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            size -= len;
+        }
+        self.size.store(size, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+pub trait SetStarlarkParseCache {
+    fn set_starlark_parse_cache(&mut self, cache: Arc<StarlarkParseCache>);
+}
+
+impl SetStarlarkParseCache for UserComputationData {
+    fn set_starlark_parse_cache(&mut self, cache: Arc<StarlarkParseCache>) {
+        self.data.set(cache);
+    }
+}
+
+pub trait HasStarlarkParseCache {
+    /// The cache, if enabled for the daemon.
+    fn get_starlark_parse_cache(&self) -> Option<&StarlarkParseCache>;
+}
+
+impl HasStarlarkParseCache for UserComputationData {
+    fn get_starlark_parse_cache(&self) -> Option<&StarlarkParseCache> {
+        match self.data.get::<Arc<StarlarkParseCache>>() {
+            Ok(cache) => Some(&**cache),
+            Err(..) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+    use starlark::syntax::Dialect;
+
+    use crate::interpreter::parse_cache::StarlarkParseCache;
+
+    #[test]
+    fn test_parse_cache() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = AbsNormPathBuf::try_from(tempdir.path().to_path_buf())?;
+        let content = "def f(x):\n    return x + 1\n";
+
+        let cache = StarlarkParseCache::new(dir.clone(), "v1".to_owned())?;
+        let ast = cache.parse("a.bzl", content.to_owned(), &Dialect::Extended)?;
+        assert_eq!(1, count_entries(&dir)?);
+
+        // Fresh cache instance, like after daemon restart.
+        let cache = StarlarkParseCache::new(dir.clone(), "v1".to_owned())?;
+        let cached = cache.parse("a.bzl", content.to_owned(), &Dialect::Extended)?;
+        assert_eq!(format!("{:?}", ast), format!("{:?}", cached));
+        assert_eq!(1, count_entries(&dir)?);
+
+        // Different buck2 version does not reuse entries.
+        let cache = StarlarkParseCache::new(dir.clone(), "v2".to_owned())?;
+        cache.parse("a.bzl", content.to_owned(), &Dialect::Extended)?;
+        assert_eq!(2, count_entries(&dir)?);
+
+        // Parse errors are not cached.
+        assert!(
+            cache
+                .parse("b.bzl", "def (".to_owned(), &Dialect::Extended)
+                .is_err()
+        );
+        assert_eq!(2, count_entries(&dir)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cache_evict() -> anyhow::Result<()> {
+        let tempdir = tempfile::tempdir()?;
+        let dir = AbsNormPathBuf::try_from(tempdir.path().to_path_buf())?;
+
+        let cache = StarlarkParseCache::new(dir.clone(), "v1".to_owned())?;
+        cache.parse("a.bzl", "a = 1\n".to_owned(), &Dialect::Extended)?;
+        cache.parse("b.bzl", "b = 2\n".to_owned(), &Dialect::Extended)?;
+        assert_eq!(2, count_entries(&dir)?);
+
+        let max_entry_size = std::fs::read_dir(&dir)?
+            .map(|e| Ok(e?.metadata()?.len()))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .max()
+            .unwrap();
+        cache.evict(max_entry_size)?;
+        assert_eq!(1, count_entries(&dir)?);
+
+        cache.evict(0)?;
+        assert_eq!(0, count_entries(&dir)?);
+        Ok(())
+    }
+
+    fn count_entries(dir: &AbsNormPathBuf) -> anyhow::Result<usize> {
+        Ok(std::fs::read_dir(dir)?.count())
+    }
+}
@@ -10,6 +10,7 @@
 use std::sync::Arc;
 
 use allocative::Allocative;
+use buck2_node::package_values::PackageValues;
 use dupe::Dupe;
 use starlark::values::OwnedFrozenValue;
 use starlark_map::small_map::SmallMap;
@@ -17,6 +18,8 @@ use starlark_map::small_map::SmallMap;
 #[derive(Default, Debug, Allocative)]
 pub(crate) struct SuperPackageData {
     package_values: SmallMap<String, OwnedFrozenValue>,
+    /// Same values converted to JSON, to be attached to target nodes.
+    package_values_json: PackageValues,
 }
 
 /// Contents of a `PACKAGE` file merged with contents of containing `PACKAGE` files.
@@ -25,22 +28,34 @@ pub(crate) struct SuperPackageData {
 pub(crate) struct SuperPackage(Arc<SuperPackageData>);
 
 impl SuperPackage {
-    pub(crate) fn new(package_values: SmallMap<String, OwnedFrozenValue>) -> SuperPackage {
-        SuperPackage(Arc::new(SuperPackageData { package_values }))
+    pub(crate) fn new(
+        package_values: SmallMap<String, OwnedFrozenValue>,
+        package_values_json: PackageValues,
+    ) -> SuperPackage {
+        SuperPackage(Arc::new(SuperPackageData {
+            package_values,
+            package_values_json,
+        }))
     }
 
     pub(crate) fn package_values(&self) -> &SmallMap<String, OwnedFrozenValue> {
         &self.0.package_values
     }
+
+    pub(crate) fn package_values_json(&self) -> &PackageValues {
+        &self.0.package_values_json
+    }
 }
 
 impl PartialEq for SuperPackage {
     fn eq(&self, other: &Self) -> bool {
         let SuperPackageData {
             package_values: this_values,
+            package_values_json: _,
         } = &*self.0;
         let SuperPackageData {
             package_values: other_values,
+            package_values_json: _,
         } = &*other.0;
         // If either package values are not empty, we cannot compare them
         // because we cannot reliably compare arbitrary Starlark values.
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use buck2_node::package_values::PackageValues;
 use starlark::values::OwnedFrozenValue;
 use starlark_map::small_map::SmallMap;
 
@@ -24,8 +25,31 @@ impl PackageFileEvalCtx {
         self,
         package_values: SmallMap<String, OwnedFrozenValue>,
     ) -> SuperPackage {
+        let mut merged_package_values_json: SmallMap<String, serde_json::Value> = self
+            .parent
+            .package_values_json()
+            .iter()
+            .map(|(k, v)| (k.to_owned(), v.clone()))
+            .collect();
+        for (key, value) in &package_values {
+            // Values which cannot be serialized (e.g. functions) are only
+            // accessible with `read_package_value`.
+            match serde_json::to_value(value.value()) {
+                Ok(json) => {
+                    merged_package_values_json.insert(key.clone(), json);
+                }
+                Err(_) => {
+                    // Value may be overwritten, do not expose stale parent value.
+                    merged_package_values_json.remove(key);
+                }
+            }
+        }
+
         let mut merged_package_values = self.parent.package_values().clone();
         merged_package_values.extend(package_values);
-        SuperPackage::new(merged_package_values)
+        SuperPackage::new(
+            merged_package_values,
+            PackageValues::new(merged_package_values_json),
+        )
     }
 }
@@ -65,6 +65,12 @@ impl<'v> StarlarkValue<'v> for PackageValues<'v> {
 #[starlark_module]
 pub(crate) fn register_write_package_value(globals: &mut GlobalsBuilder) {
     /// Set the value to be accessible in the nested `PACKAGE` files.
+    ///
+    /// The value can be any Starlark value. Values which can be serialized to JSON
+    /// are also attached to the targets of the packages, and can be inspected with
+    /// `packagevaluefilter` query function, `package_values()` on BXL target nodes,
+    /// and `buck.package_values` attribute in `buck2 targets` JSON output.
+    /// Other values (e.g. functions) are only accessible with `read_package_value`.
     fn write_package_value<'v>(
         #[starlark(require = pos)] key: &str,
         #[starlark(require = pos)] value: Value<'v>,
@@ -204,3 +204,62 @@ async fn test_overwrite_package_value_with_flag() {
             .to_string()
     );
 }
+
+#[tokio::test]
+async fn test_package_values_attached_to_target_node() {
+    let fs = ProjectRootTemp::new().unwrap();
+
+    fs.write_file("rules.bzl", RULES);
+    fs.write_file(
+        "PACKAGE",
+        indoc!(
+            r#"
+                write_package_value("ownership.team", "buck2")
+                write_package_value("ownership.labels", ["a", "b"])
+                write_package_value("ownership.impl", lambda: None)
+            "#
+        ),
+    );
+    fs.write_file(
+        "mouse/PACKAGE",
+        "write_package_value('ownership.team', 'peripherals', overwrite = True)",
+    );
+    fs.write_file(
+        "mouse/BUCK",
+        indoc!(
+            r#"
+                load("//:rules.bzl", "rrr")
+                rrr(
+                    name = "mouse",
+                    value = "x",
+                )
+            "#
+        ),
+    );
+
+    let ctx = calculation(&fs).await;
+    let interpreter = ctx
+        .get_interpreter_calculator(root_cell(), BuildFileCell::new(root_cell()))
+        .await
+        .unwrap();
+
+    let result = interpreter
+        .eval_build_file(
+            PackageLabel::testing_parse("root//mouse"),
+            &mut StarlarkProfilerOrInstrumentation::disabled(),
+        )
+        .await
+        .unwrap();
+
+    let target_nodes: Vec<_> = result.targets().values().collect();
+    assert_eq!(1, target_nodes.len());
+    let target_node = &target_nodes[0];
+    // Functions cannot be converted to JSON, so they are not exposed.
+    assert_eq!(
+        serde_json::json!({
+            "ownership.team": "peripherals",
+            "ownership.labels": ["a", "b"],
+        }),
+        target_node.package_values().to_json()
+    );
+}
@@ -8,14 +8,22 @@
  */
 
 use std::env;
+use std::fs;
+use std::fs::File;
 use std::io;
+use std::io::Write;
+use std::mem::MaybeUninit;
+use std::os::unix::process::CommandExt;
 use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::path::PathBuf;
 use std::process::Command;
 
 use anyhow::Context as _;
 use buck2_miniperf_proto::MiniperfCounter;
 use buck2_miniperf_proto::MiniperfCounters;
 use buck2_miniperf_proto::MiniperfOutput;
+use buck2_miniperf_proto::MiniperfResources;
 use perf_event::events::Hardware;
 use perf_event::Builder;
 use smallvec::SmallVec;
@@ -95,6 +103,177 @@ impl Counters {
     }
 }
 
+#[derive(thiserror::Error, Debug)]
+#[error("Error at {}: {}", stage, error)]
+struct ResourceError {
+    stage: &'static str,
+    error: io::Error,
+}
+
+#[derive(Clone, Copy, Default)]
+struct IoBytes {
+    read: u64,
+    write: u64,
+}
+
+impl IoBytes {
+    /// Bytes read and written in syscalls by this process and its reaped children.
+    fn current() -> Result<Self, ResourceError> {
+        let io = fs::read_to_string("/proc/self/io").map_err(|error| ResourceError {
+            stage: "read /proc/self/io",
+            error,
+        })?;
+        Ok(Self {
+            read: find_value(&io, "rchar:").unwrap_or_default(),
+            write: find_value(&io, "wchar:").unwrap_or_default(),
+        })
+    }
+}
+
+/// Find the value following `key` in a `key value` formatted file.
+fn find_value(contents: &str, key: &str) -> Option<u64> {
+    contents.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next()? != key {
+            return None;
+        }
+        parts.next()?.parse().ok()
+    })
+}
+
+/// A cgroup v2 created for the command, which lets us account for the whole process tree (notably
+/// its peak memory usage, which `getrusage` only reports per process).
+struct ActionCgroup {
+    path: PathBuf,
+    procs: File,
+}
+
+impl ActionCgroup {
+    /// Create a cgroup under the one we are running in. This only works if cgroup v2 is mounted
+    /// and our cgroup was delegated to us, so failing is expected and not an error.
+    fn create() -> Option<Self> {
+        let cgroups = fs::read_to_string("/proc/self/cgroup").ok()?;
+        // With cgroup v2 there is a single hierarchy, listed as `0::/path`.
+        let current = cgroups.lines().find_map(|l| l.strip_prefix("0::"))?;
+        let path = Path::new("/sys/fs/cgroup")
+            .join(current.trim_start_matches('/'))
+            .join(format!("buck2-miniperf-{}", std::process::id()));
+        fs::create_dir(&path).ok()?;
+        match fs::OpenOptions::new()
+            .write(true)
+            .open(path.join("cgroup.procs"))
+        {
+            Ok(procs) => Some(Self { path, procs }),
+            Err(_) => {
+                let _ignored = fs::remove_dir(&path);
+                None
+            }
+        }
+    }
+
+    /// Make the command join this cgroup between fork and exec.
+    fn enter_on_spawn(&self, command: &mut Command) {
+        if let Ok(procs) = self.procs.try_clone() {
+            // SAFETY: this only does a write syscall, which is async-signal-safe.
+            unsafe {
+                command.pre_exec(move || {
+                    // Writing 0 moves the writing process. If this fails, the command runs
+                    // outside of the cgroup and `collect` notices it used no CPU there.
+                    let _ignored = (&procs).write_all(b"0");
+                    Ok(())
+                });
+            }
+        }
+    }
+
+    /// Override `resources` with what the cgroup recorded, then remove it.
+    fn collect(self, resources: &mut MiniperfResources) {
+        let read = |name: &str| fs::read_to_string(self.path.join(name)).ok();
+
+        let cpu = read("cpu.stat");
+        let cpu_usage = cpu
+            .as_deref()
+            .and_then(|cpu| find_value(cpu, "usage_usec"))
+            .unwrap_or_default();
+
+        if cpu_usage > 0 {
+            if let Some(cpu) = cpu.as_deref() {
+                if let (Some(user), Some(system)) =
+                    (find_value(cpu, "user_usec"), find_value(cpu, "system_usec"))
+                {
+                    resources.user_cpu_time_us = user;
+                    resources.system_cpu_time_us = system;
+                }
+            }
+
+            // `memory.peak` requires Linux 5.19 and the memory controller to be enabled for us.
+            if let Some(peak) = read("memory.peak").and_then(|p| p.trim().parse().ok()) {
+                resources.peak_rss_bytes = peak;
+            }
+
+            // `io.stat` has one line per device, e.g. `8:0 rbytes=123 wbytes=456 rios=7 ...`.
+            if let Some(io) = read("io.stat") {
+                let mut bytes = IoBytes::default();
+                for field in io.lines().flat_map(|l| l.split_whitespace().skip(1)) {
+                    if let Some((key, value)) = field.split_once('=') {
+                        let value = value.parse::<u64>().unwrap_or_default();
+                        match key {
+                            "rbytes" => bytes.read += value,
+                            "wbytes" => bytes.write += value,
+                            _ => {}
+                        }
+                    }
+                }
+                resources.block_read_bytes = Some(bytes.read);
+                resources.block_write_bytes = Some(bytes.write);
+            }
+        }
+
+        // This fails if the command left processes behind, in which case there is nothing we can
+        // do about it.
+        let _ignored = fs::remove_dir(&self.path);
+    }
+}
+
+/// Collect the resources used by our (waited for) children.
+fn collect_resources(
+    io_before: Result<IoBytes, ResourceError>,
+    cgroup: Option<ActionCgroup>,
+) -> Result<MiniperfResources, ResourceError> {
+    let mut usage = MaybeUninit::<libc::rusage>::zeroed();
+    // SAFETY: `usage` is a valid pointer to a `rusage`.
+    if unsafe { libc::getrusage(libc::RUSAGE_CHILDREN, usage.as_mut_ptr()) } != 0 {
+        return Err(ResourceError {
+            stage: "getrusage",
+            error: io::Error::last_os_error(),
+        });
+    }
+    // SAFETY: `getrusage` succeeded, so it initialized `usage`.
+    let usage = unsafe { usage.assume_init() };
+
+    let timeval_us = |t: libc::timeval| t.tv_sec as u64 * 1_000_000 + t.tv_usec as u64;
+
+    let io_before = io_before?;
+    let io_after = IoBytes::current()?;
+
+    let mut resources = MiniperfResources {
+        // `ru_maxrss` is in KiB.
+        peak_rss_bytes: usage.ru_maxrss as u64 * 1024,
+        user_cpu_time_us: timeval_us(usage.ru_utime),
+        system_cpu_time_us: timeval_us(usage.ru_stime),
+        syscall_read_bytes: io_after.read.saturating_sub(io_before.read),
+        syscall_write_bytes: io_after.write.saturating_sub(io_before.write),
+        block_read_bytes: None,
+        block_write_bytes: None,
+    };
+
+    if let Some(cgroup) = cgroup {
+        cgroup.collect(&mut resources);
+    }
+
+    Ok(resources)
+}
+
 /// First argument is an output path to write output data into. The rest is the command to execute.
 pub fn main() -> anyhow::Result<()> {
     let mut args = env::args_os();
@@ -110,19 +289,27 @@ pub fn main() -> anyhow::Result<()> {
     let out = args.next().context("No output path")?;
 
     let counters = Counters::open();
+    let cgroup = ActionCgroup::create();
+    let io_before = IoBytes::current();
 
     let status = args.next().context("No process to run").and_then(|bin| {
-        Command::new(bin)
-            .args(args)
-            .status()
-            .map_err(anyhow::Error::from)
+        let mut command = Command::new(bin);
+        command.args(args);
+        if let Some(cgroup) = &cgroup {
+            // NOTE: This makes `Command` fork instead of using posix_spawn, but we only spawn
+            // once.
+            cgroup.enter_on_spawn(&mut command);
+        }
+        command.status().map_err(anyhow::Error::from)
     });
 
     let counters = counters.and_then(|c| c.collect());
+    let resources = collect_resources(io_before, cgroup);
 
     let output = MiniperfOutput {
         raw_exit_code: status.map(|s| s.into_raw()).map_err(|e| e.to_string()),
         counters: counters.map_err(|e| e.to_string()),
+        resources: resources.map_err(|e| e.to_string()),
     };
 
     // Stack allocate in the happy path.
@@ -50,5 +50,9 @@ fn test_miniperf() -> anyhow::Result<()> {
             < 3150000000
     );
 
+    let resources = out.resources.as_ref().unwrap();
+    assert!(resources.user_cpu_time_us > 0);
+    assert!(resources.peak_rss_bytes > 0);
+
     Ok(())
 }
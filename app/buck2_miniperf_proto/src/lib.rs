@@ -15,6 +15,7 @@ use dupe::Dupe;
 pub struct MiniperfOutput {
     pub raw_exit_code: Result<i32, String>,
     pub counters: Result<MiniperfCounters, String>,
+    pub resources: Result<MiniperfResources, String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Copy, Clone, Dupe)]
@@ -26,7 +27,27 @@ pub struct MiniperfCounters {
 
 impl MiniperfOutput {
     // This is the size we expect this record to take if the command worked out fine.
-    pub const EXPECTED_SIZE: usize = 60;
+    pub const EXPECTED_SIZE: usize = 122;
+}
+
+/// Resources used by the command and all its descendants.
+#[derive(serde::Serialize, serde::Deserialize, Copy, Clone, Dupe)]
+pub struct MiniperfResources {
+    /// Peak resident set size, in bytes. Without a cgroup, this is the peak of the largest
+    /// single process rather than of the whole process tree.
+    pub peak_rss_bytes: u64,
+    /// CPU time spent in user mode, in microseconds.
+    pub user_cpu_time_us: u64,
+    /// CPU time spent in kernel mode, in microseconds.
+    pub system_cpu_time_us: u64,
+    /// Bytes read in syscalls, including from the page cache.
+    pub syscall_read_bytes: u64,
+    /// Bytes written in syscalls, including to the page cache.
+    pub syscall_write_bytes: u64,
+    /// Bytes read from block devices. Only known with a cgroup.
+    pub block_read_bytes: Option<u64>,
+    /// Bytes written to block devices. Only known with a cgroup.
+    pub block_write_bytes: Option<u64>,
 }
 
 /// The fields here come straight out of `perf_event_open`. The count is
@@ -92,6 +113,15 @@ mod test {
                 user_instructions: max_counter,
                 kernel_instructions: max_counter,
             }),
+            resources: Ok(MiniperfResources {
+                peak_rss_bytes: u64::MAX,
+                user_cpu_time_us: u64::MAX,
+                system_cpu_time_us: u64::MAX,
+                syscall_read_bytes: u64::MAX,
+                syscall_write_bytes: u64::MAX,
+                block_read_bytes: Some(u64::MAX),
+                block_write_bytes: Some(u64::MAX),
+            }),
         };
 
         assert_eq!(
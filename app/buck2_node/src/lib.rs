@@ -19,6 +19,7 @@ pub mod configuration;
 pub mod configured_universe;
 pub mod nodes;
 pub mod package;
+pub mod package_values;
 pub mod provider_id_set;
 pub mod query;
 pub mod rule;
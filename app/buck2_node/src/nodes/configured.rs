@@ -59,10 +59,12 @@ use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::EXECUTION_PLATFORM;
 use crate::nodes::attributes::ONCALL;
 use crate::nodes::attributes::PACKAGE;
+use crate::nodes::attributes::PACKAGE_VALUES;
 use crate::nodes::attributes::TARGET_CONFIGURATION;
 use crate::nodes::attributes::TYPE;
 use crate::nodes::unconfigured::RuleKind;
 use crate::nodes::unconfigured::TargetNode;
+use crate::package_values::PackageValues;
 use crate::provider_id_set::ProviderIdSet;
 use crate::rule_type::RuleType;
 use crate::rule_type::StarlarkRuleType;
@@ -138,6 +140,13 @@ impl TargetNodeOrForward {
         }
     }
 
+    fn package_values(&self) -> &PackageValues {
+        match self {
+            TargetNodeOrForward::TargetNode(node) => node.package_values(),
+            TargetNodeOrForward::Forward(_, forward) => forward.package_values(),
+        }
+    }
+
     fn attr_or_none<'a>(
         &'a self,
         name: &str,
@@ -499,12 +508,22 @@ impl ConfiguredTargetNode {
             ),
         ]
         .into_iter()
+        .chain(
+            // Like `buck2 targets`, only show package values when there are some.
+            (!self.package_values().is_empty())
+                .then(|| (PACKAGE_VALUES, self.package_values().to_configured_attr())),
+        )
     }
 
     pub fn oncall(&self) -> Option<&str> {
         self.0.target_node.oncall()
     }
 
+    /// Values set with `write_package_value` in `PACKAGE` files applicable to this target.
+    pub fn package_values(&self) -> &PackageValues {
+        self.0.target_node.package_values()
+    }
+
     fn attr_configuration_context(&self) -> AttrConfigurationContextImpl {
         AttrConfigurationContextImpl::new(
             &self.0.resolved_configuration,
@@ -143,6 +143,14 @@ impl QueryTarget for ConfiguredGraphNodeRef {
         )
     }
 
+    fn map_package_value<R, F: FnMut(Option<&Self::Attr>) -> R>(
+        &self,
+        key: &str,
+        mut func: F,
+    ) -> R {
+        func(self.0.package_values().get_configured(key).as_ref())
+    }
+
     fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
         &self,
         mut func: F,
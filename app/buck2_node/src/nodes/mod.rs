@@ -44,6 +44,9 @@ pub mod attributes {
     /// The resolved target configuration for this node.
     pub static TARGET_CONFIGURATION: &str = "buck.target_configuration";
 
+    /// Values set with `write_package_value` in `PACKAGE` files.
+    pub static PACKAGE_VALUES: &str = "buck.package_values";
+
     /// The input source files/directories that this node uses.
     pub static INPUTS: &str = "buck.inputs";
 }
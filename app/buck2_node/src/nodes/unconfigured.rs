@@ -38,8 +38,10 @@ use crate::nodes::attributes::CONFIGURATION_DEPS;
 use crate::nodes::attributes::DEPS;
 use crate::nodes::attributes::ONCALL;
 use crate::nodes::attributes::PACKAGE;
+use crate::nodes::attributes::PACKAGE_VALUES;
 use crate::nodes::attributes::TYPE;
 use crate::package::Package;
+use crate::package_values::PackageValues;
 use crate::rule::Rule;
 use crate::rule_type::RuleType;
 use crate::visibility::VisibilitySpecification;
@@ -219,12 +221,22 @@ impl TargetNode {
             ),
         ]
         .into_iter()
+        .chain(
+            // Like `buck2 targets`, only show package values when there are some.
+            (!self.package_values().is_empty())
+                .then(|| (PACKAGE_VALUES, self.package_values().to_coerced_attr())),
+        )
     }
 
     pub fn oncall(&self) -> Option<&str> {
         self.0.package.oncall.as_ref().map(|x| x.as_str())
     }
 
+    /// Values set with `write_package_value` in `PACKAGE` files applicable to this target.
+    pub fn package_values(&self) -> &PackageValues {
+        &self.0.package.package_values
+    }
+
     fn visibility(&self) -> anyhow::Result<&VisibilitySpecification> {
         let mut visibility = match self.0.attributes.get(AttributeSpec::visibility_attr_id()) {
             Some(CoercedAttr::Literal(AttrLiteral::Visibility(v))) => v,
@@ -365,6 +377,20 @@ impl TargetNode {
     }
 
     pub fn inputs(&self) -> impl Iterator<Item = CellPath> + '_ {
+        self.collect_inputs(self.attrs(AttrInspectOptions::All))
+            .into_iter()
+    }
+
+    /// Files referenced by the given attribute, none if the rule has no such attribute.
+    pub fn attr_inputs(&self, key: &str) -> impl Iterator<Item = CellPath> {
+        self.collect_inputs(self.attr_or_none(key, AttrInspectOptions::All))
+            .into_iter()
+    }
+
+    fn collect_inputs<'a>(
+        &'a self,
+        attrs: impl IntoIterator<Item = CoercedAttrFull<'a>>,
+    ) -> Vec<CellPath> {
         struct InputsCollector {
             inputs: Vec<CellPath>,
         }
@@ -412,12 +438,12 @@ impl TargetNode {
             }
         }
         let mut traversal = InputsCollector { inputs: Vec::new() };
-        for a in self.attrs(AttrInspectOptions::All) {
+        for a in attrs {
             a.traverse(self.label().pkg(), &mut traversal)
                 .expect("inputs collector shouldn't return errors");
         }
 
-        traversal.inputs.into_iter()
+        traversal.inputs
     }
 
     pub fn call_stack(&self) -> Option<String> {
@@ -511,6 +537,7 @@ pub mod testing {
                     buildfile_path,
                     oncall: None,
                     default_visibility_to_public: false,
+                    package_values: PackageValues::default(),
                 }),
                 label,
                 attributes,
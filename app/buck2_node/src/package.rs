@@ -12,10 +12,9 @@ use std::sync::Arc;
 use allocative::Allocative;
 use buck2_core::build_file_path::BuildFilePath;
 
+use crate::package_values::PackageValues;
+
 /// Package-specific data for `TargetNode`.
-///
-/// (Note this has nothing to do with `PACKAGE` files which are not implemented
-/// at the moment of writing.)
 #[derive(Debug, Hash, Allocative, Eq, PartialEq)]
 pub struct Package {
     /// The build file which defined this target, e.g. `fbcode//foo/bar/TARGETS`
@@ -24,4 +23,6 @@ pub struct Package {
     pub oncall: Option<Arc<String>>,
     /// Visibility is public by default.
     pub default_visibility_to_public: bool,
+    /// Values set with `write_package_value` in `PACKAGE` files.
+    pub package_values: PackageValues,
 }
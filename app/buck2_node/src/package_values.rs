@@ -0,0 +1,138 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use allocative::Allocative;
+use buck2_util::arc_str::ArcStr;
+use dupe::Dupe;
+use starlark_map::small_map::SmallMap;
+
+use crate::attrs::attr_type::attr_config::AttrConfig;
+use crate::attrs::attr_type::attr_literal::AttrLiteral;
+use crate::attrs::coerced_attr::CoercedAttr;
+use crate::attrs::configured_attr::ConfiguredAttr;
+
+/// Values set with `write_package_value` in `PACKAGE` files, merged with values
+/// set in parent `PACKAGE` files.
+///
+/// Values are converted to JSON when `PACKAGE` file is evaluated,
+/// so they can be inspected by queries and printed by `buck2 targets`
+/// without access to the Starlark heap which produced them.
+/// Values which cannot be converted to JSON (e.g. functions) are not stored here,
+/// they are only accessible from `PACKAGE` files with `read_package_value`.
+#[derive(Default, Debug, Clone, Dupe, Eq, PartialEq, Allocative)]
+pub struct PackageValues(#[allocative(skip)] Arc<SmallMap<String, serde_json::Value>>);
+
+impl Hash for PackageValues {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Only hash keys: hash of `serde_json::Value` is not defined,
+        // and hashing keys is enough to be consistent with `Eq`.
+        self.0.len().hash(state);
+        for key in self.0.keys() {
+            key.hash(state);
+        }
+    }
+}
+
+impl PackageValues {
+    pub fn new(values: SmallMap<String, serde_json::Value>) -> PackageValues {
+        PackageValues(Arc::new(values))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.0.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &serde_json::Value)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// All the values as JSON object.
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::Value::Object(self.0.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+
+    /// Package value converted to attribute, so it can be inspected
+    /// with the same machinery as target attributes.
+    pub fn get_coerced(&self, key: &str) -> Option<CoercedAttr> {
+        self.get(key)
+            .map(|v| json_to_attr(v, &CoercedAttr::new_literal))
+    }
+
+    /// Package value converted to configured attribute.
+    pub fn get_configured(&self, key: &str) -> Option<ConfiguredAttr> {
+        self.get(key).map(|v| json_to_attr(v, &ConfiguredAttr::new))
+    }
+
+    /// All the package values as a dict attribute.
+    pub fn to_coerced_attr(&self) -> CoercedAttr {
+        json_to_attr(&self.to_json(), &CoercedAttr::new_literal)
+    }
+
+    /// All the package values as a dict configured attribute.
+    pub fn to_configured_attr(&self) -> ConfiguredAttr {
+        json_to_attr(&self.to_json(), &ConfiguredAttr::new)
+    }
+}
+
+fn json_to_attr<C: AttrConfig>(value: &serde_json::Value, new: &impl Fn(AttrLiteral<C>) -> C) -> C {
+    new(match value {
+        serde_json::Value::Null => AttrLiteral::None,
+        serde_json::Value::Bool(b) => AttrLiteral::Bool(*b),
+        serde_json::Value::Number(n) => match n.as_i64().and_then(|n| i32::try_from(n).ok()) {
+            Some(n) => AttrLiteral::Int(n),
+            // Attributes only support 32-bit integers.
+            None => AttrLiteral::String(ArcStr::from(n.to_string().as_str())),
+        },
+        serde_json::Value::String(s) => AttrLiteral::String(ArcStr::from(s.as_str())),
+        serde_json::Value::Array(xs) => {
+            AttrLiteral::List(xs.iter().map(|x| json_to_attr(x, new)).collect())
+        }
+        serde_json::Value::Object(m) => AttrLiteral::Dict(
+            m.iter()
+                .map(|(k, v)| {
+                    (
+                        new(AttrLiteral::String(ArcStr::from(k.as_str()))),
+                        json_to_attr(v, new),
+                    )
+                })
+                .collect(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark_map::small_map::SmallMap;
+
+    use crate::package_values::PackageValues;
+
+    #[test]
+    fn test_get_coerced() {
+        let mut values = SmallMap::new();
+        values.insert(
+            "ownership.team".to_owned(),
+            serde_json::json!({"name": "buck2", "labels": ["a", "b"]}),
+        );
+        let values = PackageValues::new(values);
+
+        let attr = values.get_coerced("ownership.team").unwrap();
+        assert!(attr.any_matches(&|s| Ok(s == "buck2")).unwrap());
+        assert!(attr.any_matches(&|s| Ok(s == "b")).unwrap());
+        assert!(!attr.any_matches(&|s| Ok(s == "c")).unwrap());
+        assert!(values.get_coerced("ownership.oncall").is_none());
+    }
+}
@@ -96,6 +96,14 @@ impl QueryTarget for ConfiguredTargetNode {
         )
     }
 
+    fn map_package_value<R, F: FnMut(Option<&Self::Attr>) -> R>(
+        &self,
+        key: &str,
+        mut func: F,
+    ) -> R {
+        func(self.package_values().get_configured(key).as_ref())
+    }
+
     fn inputs_for_each<E, F: FnMut(CellPath) -> Result<(), E>>(
         &self,
         mut func: F,
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -14,6 +15,8 @@ use anyhow::Context;
 use buck2_cli_proto::profile_request::ProfileOpts;
 use buck2_cli_proto::profile_request::Profiler;
 use buck2_core::fs::fs_util;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_interpreter::dice::starlark_profiler::StarlarkProfilerConfiguration;
 use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
 use starlark::eval::ProfileMode;
@@ -34,6 +37,7 @@ pub fn starlark_profiler_configuration_from_request(
         Profiler::Bytecode => ProfileMode::Bytecode,
         Profiler::BytecodePairs => ProfileMode::BytecodePairs,
         Profiler::Typecheck => ProfileMode::Typecheck,
+        Profiler::Coverage => ProfileMode::Coverage,
     };
 
     match req.profile_opts.as_ref().expect("Missing profile opts") {
@@ -49,9 +53,13 @@ pub fn starlark_profiler_configuration_from_request(
                         "Recursive profiling is not supported for loading profiling"
                     ));
                 }
-                (buck2_cli_proto::target_profile::Action::Analysis, false) => {
+                (buck2_cli_proto::target_profile::Action::Analysis, false) if !opts.aggregate => {
                     StarlarkProfilerConfiguration::ProfileLastAnalysis(profile_mode)
                 }
+                // Aggregated analysis profiles all the analysis needed for the targets.
+                (buck2_cli_proto::target_profile::Action::Analysis, false) => {
+                    StarlarkProfilerConfiguration::ProfileAnalysisRecursively(profile_mode)
+                }
                 (buck2_cli_proto::target_profile::Action::Analysis, true) => {
                     StarlarkProfilerConfiguration::ProfileAnalysisRecursively(profile_mode)
                 }
@@ -61,9 +69,28 @@ pub fn starlark_profiler_configuration_from_request(
     }
 }
 
+/// Write the coverage in `profile_data` as an LCOV tracefile. Only files in the project are
+/// included, so files such as the prelude's internal modules or REPL input are left out.
+pub fn write_starlark_coverage(
+    profile_data: &StarlarkProfileDataAndStats,
+    project_root: &ProjectRoot,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let lcov = profile_data.profile_data.gen_lcov(|file| {
+        let path = ProjectRelativePath::new(file).ok()?;
+        if project_root.resolve(path).as_path().exists() {
+            Some(file.to_owned())
+        } else {
+            None
+        }
+    })?;
+    fs_util::write(output, lcov).context("Failed to write coverage")
+}
+
 pub fn get_profile_response(
     profile_data: Arc<StarlarkProfileDataAndStats>,
     req: &buck2_cli_proto::ProfileRequest,
+    project_root: &ProjectRoot,
     output: PathBuf,
 ) -> anyhow::Result<buck2_cli_proto::ProfileResponse> {
     let command_profile_mode = buck2_cli_proto::profile_request::Profiler::from_i32(req.profiler)
@@ -89,6 +116,19 @@ pub fn get_profile_response(
             fs_util::write(output.join("flame.src"), &profile)
                 .context("Failed to write profile")?;
             fs_util::write(output.join("flame.svg"), &svg).context("Failed to write profile")?;
+
+            if let Some(ProfileOpts::TargetProfile(opts)) = &req.profile_opts {
+                if opts.aggregate && opts.top_functions != 0 {
+                    let top = profile_data
+                        .profile_data
+                        .gen_top_functions_csv(opts.top_functions as usize)?;
+                    fs_util::write(output.join("top.csv"), top)
+                        .context("Failed to write profile")?;
+                }
+            }
+        }
+        Profiler::Coverage => {
+            write_starlark_coverage(&profile_data, project_root, &output)?;
         }
         _ => {
             let profile = profile_data.profile_data.gen()?;
@@ -99,5 +139,6 @@ pub fn get_profile_response(
     Ok(buck2_cli_proto::ProfileResponse {
         elapsed: Some(profile_data.elapsed().try_into()?),
         total_retained_bytes: profile_data.total_retained_bytes() as u64,
+        errors: Vec::new(),
     })
 }
@@ -131,6 +131,16 @@ pub trait QueryTarget: LabeledNode + Dupe + Send + Sync + 'static {
 
     fn map_attr<R, F: FnMut(Option<&Self::Attr>) -> R>(&self, key: &str, func: F) -> R;
 
+    /// Like `map_attr`, but for values set with `write_package_value` in `PACKAGE` files.
+    fn map_package_value<R, F: FnMut(Option<&Self::Attr>) -> R>(
+        &self,
+        key: &str,
+        mut func: F,
+    ) -> R {
+        let _ = key;
+        func(None)
+    }
+
     fn call_stack(&self) -> Option<String>;
 }
 
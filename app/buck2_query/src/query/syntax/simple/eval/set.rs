@@ -210,6 +210,19 @@ pub trait TargetSetExt {
         self.attrfilter(attribute, &filter)
     }
 
+    fn package_value_filter(
+        &self,
+        key: &str,
+        filter: &dyn Fn(&str) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<TargetSet<Self::T>> {
+        self.filter(move |node| {
+            node.map_package_value(key, |val| match val {
+                None => Ok(false),
+                Some(v) => Self::T::attr_any_matches(v, &filter),
+            })
+        })
+    }
+
     fn filter_name(&self, regex: &str) -> anyhow::Result<TargetSet<Self::T>> {
         let re = Regex::new(regex)?;
         self.filter(|node| Ok(re.is_match(&node.node_ref().to_string())?))
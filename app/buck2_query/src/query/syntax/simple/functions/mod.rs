@@ -263,6 +263,27 @@ impl<Env: QueryEnvironment> DefaultQueryFunctionsModule<Env> {
             .into())
     }
 
+    /// Filter targets by values set with `write_package_value` in `PACKAGE` files.
+    ///
+    /// `packagevaluefilter(key, value, targets)` returns the targets for which
+    /// package value `key` is set and either equal to `value`, or is a list or a dict
+    /// containing `value`.
+    ///
+    /// For example, if `PACKAGE` file contains `write_package_value("ownership.team", "buck2")`,
+    /// `buck2 uquery "packagevaluefilter(ownership.team, buck2, //...)"` returns
+    /// all the targets owned by that team.
+    async fn packagevaluefilter(
+        &self,
+        key: String,
+        value: String,
+        targets: TargetSet<Env::Target>,
+    ) -> QueryFuncResult<Env> {
+        Ok(self
+            .implementation
+            .packagevaluefilter(&key, &value, &targets)?
+            .into())
+    }
+
     async fn buildfile(&self, targets: TargetSet<Env::Target>) -> QueryFuncResult<Env> {
         Ok(self.implementation.buildfile(&targets).into())
     }
@@ -462,6 +483,15 @@ impl<Env: QueryEnvironment> DefaultQueryFunctions<Env> {
         targets.attrregexfilter(attr, value)
     }
 
+    pub fn packagevaluefilter(
+        &self,
+        key: &str,
+        value: &str,
+        targets: &TargetSet<Env::Target>,
+    ) -> anyhow::Result<TargetSet<Env::Target>> {
+        targets.package_value_filter(key, &|v| Ok(v == value))
+    }
+
     pub fn buildfile(&self, targets: &TargetSet<Env::Target>) -> FileSet {
         targets.buildfile()
     }
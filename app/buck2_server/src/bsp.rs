@@ -0,0 +1,902 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Build Server Protocol (<https://build-server-protocol.github.io/>) server.
+//!
+//! BSP is JSON-RPC with the same framing as LSP, so messages are passed around as
+//! [`lsp_server::Message`]. Buck targets matched by the workspace target patterns
+//! are exposed as BSP build targets, identified by `buck2:<target label>` URIs.
+//!
+//! Requests are processed one at a time, each in its own DICE transaction, so file
+//! changes are picked up between requests.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use buck2_build_api::build;
+use buck2_build_api::build::ConvertMaterializationContext;
+use buck2_build_api::build::MaterializationContext;
+use buck2_build_api::build::ProvidersToBuild;
+use buck2_build_api::calculation::Calculation;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_cli_proto::build_request::Materializations;
+use buck2_cli_proto::BspRequest;
+use buck2_cli_proto::BspResponse;
+use buck2_cli_proto::ClientContext;
+use buck2_cli_proto::LspMessage;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::pattern::resolve::resolve_target_patterns;
+use buck2_core::cells::cell_path::CellPathRef;
+use buck2_core::cells::CellResolver;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::PackageSpec;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::provider::label::ProvidersLabel;
+use buck2_core::target::label::TargetLabel;
+use buck2_events::dispatch::span_async;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
+use buck2_node::nodes::unconfigured::TargetNode;
+use buck2_server_ctx::command_end::command_end;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use itertools::Itertools;
+use lsp_server::ErrorCode;
+use lsp_server::Message;
+use lsp_server::Notification;
+use lsp_server::Request;
+use lsp_server::Response;
+use lsp_types::Diagnostic;
+use lsp_types::DiagnosticSeverity;
+use lsp_types::Position;
+use lsp_types::Range;
+use lsp_types::Url;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::streaming_request_handler::StreamingRequestHandler;
+
+/// Version of the protocol implemented here.
+const BSP_VERSION: &str = "2.1.0";
+
+/// Prefix of the URIs identifying build targets.
+const TARGET_URI_PREFIX: &str = "buck2:";
+
+/// Attributes listing the source files of a target, by prelude convention.
+const SOURCES_ATTRS: &[&str] = &["srcs", "headers", "exported_headers"];
+
+/// Attributes listing the resources of a target, by prelude convention.
+const RESOURCES_ATTRS: &[&str] = &["resources"];
+
+/// BSP language ids, by prefix of the prelude rule name.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("android_", "java"),
+    ("cxx_", "cpp"),
+    ("go_", "go"),
+    ("haskell_", "haskell"),
+    ("java_", "java"),
+    ("kotlin_", "kotlin"),
+    ("ocaml_", "ocaml"),
+    ("python_", "python"),
+    ("rust_", "rust"),
+    ("scala_", "scala"),
+    ("swift_", "swift"),
+];
+
+#[derive(Debug, thiserror::Error)]
+enum BspError {
+    #[error("Unknown BSP method `{0}`")]
+    UnknownMethod(String),
+    #[error("`{0}` is not a buck2 build target identifier")]
+    InvalidTargetUri(String),
+    #[error("Cannot convert path `{0}` to a file URI")]
+    InvalidPath(String),
+}
+
+pub(crate) async fn run_bsp_server_command(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    partial_result_dispatcher: PartialResultDispatcher<LspMessage>,
+    client_ctx: ClientContext,
+    req: StreamingRequestHandler<BspRequest>,
+) -> anyhow::Result<BspResponse> {
+    let metadata = ctx.request_metadata().await?;
+    let start_event = buck2_data::CommandStart {
+        metadata: metadata.clone(),
+        data: Some(buck2_data::BspCommandStart {}.into()),
+    };
+    span_async(start_event, async move {
+        let result = run_bsp_server(ctx, partial_result_dispatcher, client_ctx, req).await;
+        let end_event = command_end(metadata, &result, buck2_data::BspCommandEnd {});
+        (result, end_event)
+    })
+    .await
+}
+
+/// Run a BSP server for a given client until it exits or disconnects.
+async fn run_bsp_server(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    mut partial_result_dispatcher: PartialResultDispatcher<LspMessage>,
+    client_ctx: ClientContext,
+    mut req: StreamingRequestHandler<BspRequest>,
+) -> anyhow::Result<BspResponse> {
+    let init = req.message().await?;
+    let server = BspServer {
+        ctx,
+        client_ctx,
+        target_patterns: init
+            .target_patterns
+            .into_map(|value| buck2_data::TargetPattern { value }),
+        buck2_version: init.buck2_version,
+        published_diagnostics: Mutex::new(HashMap::new()),
+    };
+
+    loop {
+        let message = match req.message().await {
+            Ok(m) => m,
+            // The client disconnected.
+            Err(_) => break,
+        };
+        let message: Message = match serde_json::from_str(&message.bsp_json) {
+            Ok(message) => message,
+            Err(e) => {
+                // The id of a malformed message is unknown, so JSON-RPC requires a null id.
+                emit(
+                    &mut partial_result_dispatcher,
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": null,
+                        "error": {
+                            "code": ErrorCode::ParseError as i32,
+                            "message": format!("Invalid BSP message: {}", e),
+                        },
+                    }),
+                )?;
+                continue;
+            }
+        };
+        match message {
+            Message::Request(request) => {
+                let mut notifications = Vec::new();
+                let response = server.handle_request(request, &mut notifications).await;
+                for notification in notifications {
+                    emit(
+                        &mut partial_result_dispatcher,
+                        Message::Notification(notification),
+                    )?;
+                }
+                emit(&mut partial_result_dispatcher, Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if notification.method == "build/exit" {
+                    break;
+                }
+            }
+            // We never send requests to the client.
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(BspResponse {})
+}
+
+fn emit(
+    partial_result_dispatcher: &mut PartialResultDispatcher<LspMessage>,
+    message: impl Serialize,
+) -> anyhow::Result<()> {
+    let lsp_json = serde_json::to_string(&message)?;
+    partial_result_dispatcher.emit(LspMessage { lsp_json });
+    Ok(())
+}
+
+struct BspServer {
+    ctx: Box<dyn ServerCommandContextTrait>,
+    client_ctx: ClientContext,
+    target_patterns: Vec<buck2_data::TargetPattern>,
+    buck2_version: String,
+    /// Files with diagnostics published by the last compilation of each target,
+    /// which must be cleared when the next compilation does not report them again.
+    published_diagnostics: Mutex<HashMap<BuildTargetIdentifier, BTreeSet<String>>>,
+}
+
+impl BspServer {
+    async fn handle_request(
+        &self,
+        request: Request,
+        notifications: &mut Vec<Notification>,
+    ) -> Response {
+        let id = request.id.clone();
+        match self.dispatch(request, notifications).await {
+            Ok(result) => Response::new_ok(id, result),
+            Err(e) => {
+                let code = match e.downcast_ref::<BspError>() {
+                    Some(BspError::UnknownMethod(..)) => ErrorCode::MethodNotFound,
+                    Some(BspError::InvalidTargetUri(..)) => ErrorCode::InvalidParams,
+                    None if e.is::<serde_json::Error>() => ErrorCode::InvalidParams,
+                    _ => ErrorCode::InternalError,
+                };
+                Response::new_err(id, code as i32, format!("{:#}", e))
+            }
+        }
+    }
+
+    async fn dispatch(
+        &self,
+        request: Request,
+        notifications: &mut Vec<Notification>,
+    ) -> anyhow::Result<serde_json::Value> {
+        Ok(match request.method.as_str() {
+            "build/initialize" => serde_json::to_value(self.initialize())?,
+            // Every request uses a fresh DICE transaction, so there is no state to reload or release.
+            "build/shutdown" | "workspace/reload" => serde_json::Value::Null,
+            "workspace/buildTargets" => serde_json::to_value(self.build_targets().await?)?,
+            "buildTarget/sources" => serde_json::to_value(
+                self.sources(serde_json::from_value(request.params)?)
+                    .await?,
+            )?,
+            "buildTarget/resources" => serde_json::to_value(
+                self.resources(serde_json::from_value(request.params)?)
+                    .await?,
+            )?,
+            "buildTarget/compile" => serde_json::to_value(
+                self.compile(serde_json::from_value(request.params)?, notifications)
+                    .await?,
+            )?,
+            method => return Err(BspError::UnknownMethod(method.to_owned()).into()),
+        })
+    }
+
+    fn initialize(&self) -> InitializeBuildResult {
+        InitializeBuildResult {
+            display_name: "buck2".to_owned(),
+            version: self.buck2_version.clone(),
+            bsp_version: BSP_VERSION.to_owned(),
+            capabilities: BuildServerCapabilities {
+                compile_provider: CompileProvider {
+                    language_ids: LANGUAGES
+                        .iter()
+                        .map(|(_, language)| (*language).to_owned())
+                        .unique()
+                        .collect(),
+                },
+                resources_provider: true,
+                can_reload: true,
+            },
+        }
+    }
+
+    async fn build_targets(&self) -> anyhow::Result<WorkspaceBuildTargetsResult> {
+        self.ctx
+            .with_dice_ctx(|server_ctx, dice| async move {
+                let cell_resolver = dice.get_cell_resolver().await?;
+                let nodes =
+                    workspace_nodes(&dice, server_ctx.working_dir(), &self.target_patterns).await?;
+                let targets = nodes.try_map(|node| {
+                    build_target(node, server_ctx.project_root(), &cell_resolver)
+                })?;
+                Ok(WorkspaceBuildTargetsResult { targets })
+            })
+            .await
+    }
+
+    async fn sources(&self, params: TargetsParams) -> anyhow::Result<SourcesResult> {
+        let items = self
+            .attr_files(params.targets, SOURCES_ATTRS)
+            .await?
+            .into_map(|(target, files)| SourcesItem {
+                target,
+                sources: files.into_map(|uri| SourceItem {
+                    uri,
+                    kind: SourceItemKind::File,
+                    generated: false,
+                }),
+            });
+        Ok(SourcesResult { items })
+    }
+
+    async fn resources(&self, params: TargetsParams) -> anyhow::Result<ResourcesResult> {
+        let items = self
+            .attr_files(params.targets, RESOURCES_ATTRS)
+            .await?
+            .into_map(|(target, resources)| ResourcesItem { target, resources });
+        Ok(ResourcesResult { items })
+    }
+
+    /// File URIs referenced by the given attributes of each target.
+    async fn attr_files(
+        &self,
+        targets: Vec<BuildTargetIdentifier>,
+        attrs: &[&str],
+    ) -> anyhow::Result<Vec<(BuildTargetIdentifier, Vec<String>)>> {
+        self.ctx
+            .with_dice_ctx(|server_ctx, dice| async move {
+                let cell_resolver = dice.get_cell_resolver().await?;
+                let mut items = Vec::with_capacity(targets.len());
+                for id in targets {
+                    let label = target_label(&dice, server_ctx.working_dir(), &id).await?;
+                    let node = dice.get_target_node(&label).await?;
+                    let files = attrs
+                        .iter()
+                        .flat_map(|attr| node.attr_inputs(attr))
+                        .map(|path| {
+                            file_uri(server_ctx.project_root(), &cell_resolver, path.as_ref())
+                        })
+                        .collect::<anyhow::Result<_>>()?;
+                    items.push((id, files));
+                }
+                Ok(items)
+            })
+            .await
+    }
+
+    /// Builds the default outputs of the targets, publishing the failures as diagnostics
+    /// on the source files they point to, or on the build files which define the targets.
+    async fn compile(
+        &self,
+        params: CompileParams,
+        notifications: &mut Vec<Notification>,
+    ) -> anyhow::Result<CompileResult> {
+        let targets = &params.targets;
+        let results = self
+            .ctx
+            .with_dice_ctx(|server_ctx, dice| async move {
+                let cell_resolver = dice.get_cell_resolver().await?;
+                let global_target_platform =
+                    target_platform_from_client_context(&self.client_ctx, server_ctx, &dice)
+                        .await?;
+                let materialization_context =
+                    ConvertMaterializationContext::from(Materializations::Default);
+
+                futures::future::try_join_all(targets.iter().map(|id| {
+                    let dice = &dice;
+                    let cell_resolver = &cell_resolver;
+                    let global_target_platform = global_target_platform.as_ref();
+                    let materialization_context = &materialization_context;
+                    async move {
+                        let label = target_label(dice, server_ctx.working_dir(), id).await?;
+                        let node = dice.get_target_node(&label).await?;
+                        let build_file = file_uri(
+                            server_ctx.project_root(),
+                            cell_resolver,
+                            node.buildfile_path().path().as_ref(),
+                        )?;
+                        let errors = match compile_target(
+                            dice,
+                            label,
+                            global_target_platform,
+                            materialization_context,
+                        )
+                        .await
+                        {
+                            Ok(errors) => errors,
+                            Err(e) => vec![format!("{:#}", e)],
+                        };
+                        anyhow::Ok((id.clone(), build_file, errors))
+                    }
+                }))
+                .await
+            })
+            .await?;
+
+        let project_root = self.ctx.project_root();
+        let mut published_diagnostics = self.published_diagnostics.lock().unwrap();
+        let mut status_code = StatusCode::Ok;
+        for (id, build_file, errors) in results {
+            if !errors.is_empty() {
+                status_code = StatusCode::Error;
+            }
+            let mut diagnostics: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+            // Always publish on the build file, so that diagnostics from previous compilations are cleared.
+            diagnostics.insert(build_file.clone(), Vec::new());
+            for error in &errors {
+                for (uri, diagnostic) in
+                    error_diagnostics(project_root.root().as_path(), &build_file, error)
+                {
+                    diagnostics.entry(uri).or_default().push(diagnostic);
+                }
+            }
+            let uris: BTreeSet<String> = diagnostics.keys().cloned().collect();
+            if let Some(previous) = published_diagnostics.insert(id.clone(), uris) {
+                for uri in previous {
+                    diagnostics.entry(uri).or_default();
+                }
+            }
+            for (uri, diagnostics) in diagnostics {
+                notifications.push(Notification::new(
+                    "build/publishDiagnostics".to_owned(),
+                    PublishDiagnosticsParams {
+                        text_document: TextDocumentIdentifier { uri },
+                        build_target: id.clone(),
+                        origin_id: params.origin_id.clone(),
+                        diagnostics,
+                        reset: true,
+                    },
+                ));
+            }
+        }
+
+        Ok(CompileResult {
+            origin_id: params.origin_id,
+            status_code,
+        })
+    }
+}
+
+/// Builds the default outputs of a target, returning the build errors.
+async fn compile_target(
+    dice: &DiceComputations,
+    label: TargetLabel,
+    global_target_platform: Option<&TargetLabel>,
+    materialization_context: &MaterializationContext,
+) -> anyhow::Result<Vec<String>> {
+    let providers_label = dice
+        .get_configured_target(&ProvidersLabel::default_for(label), global_target_platform)
+        .await?;
+    let result = build::build_configured_label(
+        dice,
+        materialization_context,
+        &providers_label,
+        &ProvidersToBuild {
+            default: true,
+            default_other: true,
+            run: false,
+            tests: false,
+        },
+        false,
+    )
+    .await?;
+    Ok(result
+        .into_iter()
+        .flat_map(|result| result.outputs)
+        .filter_map(|output| output.err())
+        .map(|e| format!("{:#}", e))
+        .unique()
+        .collect())
+}
+
+/// Target nodes matched by the workspace target patterns.
+async fn workspace_nodes(
+    dice: &DiceComputations,
+    cwd: &ProjectRelativePath,
+    target_patterns: &[buck2_data::TargetPattern],
+) -> anyhow::Result<Vec<TargetNode>> {
+    let patterns =
+        parse_patterns_from_cli_args::<TargetPatternExtra>(dice, target_patterns, cwd).await?;
+    let resolved = resolve_target_patterns(
+        &dice.get_cell_resolver().await?,
+        &patterns,
+        &dice.file_ops(),
+    )
+    .await?;
+
+    let packages = futures::future::try_join_all(resolved.specs.into_iter().map(
+        |(package, spec)| async move {
+            let res = dice.get_interpreter_results(package).await?;
+            anyhow::Ok(match spec {
+                PackageSpec::All => res.targets().values().cloned().collect(),
+                PackageSpec::Targets(targets) => {
+                    targets.try_map(|(name, TargetPatternExtra)| {
+                        res.resolve_target(name).map(|node| node.dupe())
+                    })?
+                }
+            })
+        },
+    ))
+    .await?;
+    Ok(packages.into_iter().flatten().collect())
+}
+
+fn build_target(
+    node: &TargetNode,
+    project_root: &ProjectRoot,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<BuildTarget> {
+    let rule = node.rule_type().name();
+    Ok(BuildTarget {
+        id: BuildTargetIdentifier::new(node.label()),
+        display_name: node.label().to_string(),
+        base_directory: file_uri(
+            project_root,
+            cell_resolver,
+            node.label().pkg().as_cell_path(),
+        )?,
+        tags: tags(rule),
+        language_ids: language_ids(rule),
+        dependencies: node.target_deps().map(BuildTargetIdentifier::new).collect(),
+        // Only `buildTarget/compile` is implemented.
+        capabilities: BuildTargetCapabilities {
+            can_compile: true,
+            can_test: false,
+            can_run: false,
+            can_debug: false,
+        },
+    })
+}
+
+async fn target_label(
+    dice: &DiceComputations,
+    cwd: &ProjectRelativePath,
+    id: &BuildTargetIdentifier,
+) -> anyhow::Result<TargetLabel> {
+    let invalid = || BspError::InvalidTargetUri(id.uri.clone());
+    let label = id.uri.strip_prefix(TARGET_URI_PREFIX).ok_or_else(invalid)?;
+    let pattern = buck2_data::TargetPattern {
+        value: label.to_owned(),
+    };
+    match parse_patterns_from_cli_args::<TargetPatternExtra>(dice, &[pattern], cwd)
+        .await?
+        .pop()
+    {
+        Some(ParsedPattern::Target(package, name, TargetPatternExtra)) => {
+            Ok(TargetLabel::new(package, &name))
+        }
+        _ => Err(invalid().into()),
+    }
+}
+
+fn file_uri(
+    project_root: &ProjectRoot,
+    cell_resolver: &CellResolver,
+    path: CellPathRef,
+) -> anyhow::Result<String> {
+    let path = project_root.resolve(&cell_resolver.resolve_path(path)?);
+    Ok(Url::from_file_path(&path)
+        .map_err(|()| BspError::InvalidPath(path.to_string()))?
+        .to_string())
+}
+
+/// Diagnostics for a build error, keyed by file URI. Source locations in the error
+/// (`path:line[:column][: message]`, as printed by most compilers, relative to the
+/// project root) are reported on the source files, the rest of the error on `build_file`.
+fn error_diagnostics(
+    project_root: &Path,
+    build_file: &str,
+    error: &str,
+) -> Vec<(String, Diagnostic)> {
+    let mut diagnostics = Vec::new();
+    // Compilers like rustc print the message on the line before the location.
+    let mut last_message = None;
+    for line in error.lines() {
+        let line = line.trim_start();
+        let location = line.strip_prefix("--> ").unwrap_or(line);
+        let uri = source_location(location).and_then(|location| {
+            let path = project_root.join(location.path);
+            let uri = Url::from_file_path(&path).ok().filter(|_| path.is_file())?;
+            Some((uri, location))
+        });
+        match uri {
+            Some((uri, location)) => {
+                let message = match location.message {
+                    "" => last_message.unwrap_or(line),
+                    message => message,
+                };
+                let position = Position::new(
+                    location.line.saturating_sub(1),
+                    location.column.unwrap_or(1).saturating_sub(1),
+                );
+                diagnostics.push((
+                    uri.to_string(),
+                    diagnostic(message, Range::new(position, position)),
+                ));
+            }
+            None => {
+                if line.starts_with("error") || line.starts_with("warning") {
+                    last_message = Some(line);
+                }
+            }
+        }
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push((build_file.to_owned(), diagnostic(error, Range::default())));
+    }
+    diagnostics
+}
+
+fn diagnostic(message: &str, range: Range) -> Diagnostic {
+    let severity = if message.starts_with("warning") {
+        DiagnosticSeverity::WARNING
+    } else {
+        DiagnosticSeverity::ERROR
+    };
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        source: Some("buck2".to_owned()),
+        message: message.to_owned(),
+        ..Default::default()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct SourceLocation<'a> {
+    path: &'a str,
+    /// 1-based.
+    line: u32,
+    /// 1-based.
+    column: Option<u32>,
+    message: &'a str,
+}
+
+/// Parse `path:line[:column][: message]`.
+fn source_location(s: &str) -> Option<SourceLocation<'_>> {
+    let (path, rest) = s.split_once(':')?;
+    if path.is_empty() || path.contains(char::is_whitespace) {
+        return None;
+    }
+    let (line, rest) = rest.split_once(':').unwrap_or((rest, ""));
+    let line = line.parse().ok()?;
+    let (column, message) = match rest.split_once(':') {
+        Some((column, message)) => match column.parse() {
+            Ok(column) => (Some(column), message),
+            Err(_) => (None, rest),
+        },
+        None => match rest.parse() {
+            Ok(column) => (Some(column), ""),
+            Err(_) => (None, rest),
+        },
+    };
+    Some(SourceLocation {
+        path,
+        line,
+        column,
+        message: message.trim(),
+    })
+}
+
+fn tags(rule: &str) -> Vec<String> {
+    let tag = if rule.ends_with("_test") {
+        "test"
+    } else if rule.ends_with("_binary") {
+        "application"
+    } else if rule.ends_with("_library") {
+        "library"
+    } else {
+        return Vec::new();
+    };
+    vec![tag.to_owned()]
+}
+
+fn language_ids(rule: &str) -> Vec<String> {
+    LANGUAGES
+        .iter()
+        .filter(|(prefix, _)| rule.starts_with(prefix))
+        .map(|(_, language)| (*language).to_owned())
+        .collect()
+}
+
+// Subset of the BSP data structures used above.
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+struct BuildTargetIdentifier {
+    uri: String,
+}
+
+impl BuildTargetIdentifier {
+    fn new(label: &TargetLabel) -> Self {
+        Self {
+            uri: format!("{}{}", TARGET_URI_PREFIX, label),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeBuildResult {
+    display_name: String,
+    version: String,
+    bsp_version: String,
+    capabilities: BuildServerCapabilities,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildServerCapabilities {
+    compile_provider: CompileProvider,
+    resources_provider: bool,
+    can_reload: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileProvider {
+    language_ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct WorkspaceBuildTargetsResult {
+    targets: Vec<BuildTarget>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildTarget {
+    id: BuildTargetIdentifier,
+    display_name: String,
+    base_directory: String,
+    tags: Vec<String>,
+    language_ids: Vec<String>,
+    dependencies: Vec<BuildTargetIdentifier>,
+    capabilities: BuildTargetCapabilities,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildTargetCapabilities {
+    can_compile: bool,
+    can_test: bool,
+    can_run: bool,
+    can_debug: bool,
+}
+
+/// Parameters of `buildTarget/sources` and `buildTarget/resources`.
+#[derive(Deserialize)]
+struct TargetsParams {
+    targets: Vec<BuildTargetIdentifier>,
+}
+
+#[derive(Serialize)]
+struct SourcesResult {
+    items: Vec<SourcesItem>,
+}
+
+#[derive(Serialize)]
+struct SourcesItem {
+    target: BuildTargetIdentifier,
+    sources: Vec<SourceItem>,
+}
+
+#[derive(Serialize)]
+struct SourceItem {
+    uri: String,
+    kind: SourceItemKind,
+    generated: bool,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(into = "u8")]
+enum SourceItemKind {
+    File = 1,
+}
+
+impl From<SourceItemKind> for u8 {
+    fn from(kind: SourceItemKind) -> u8 {
+        kind as u8
+    }
+}
+
+#[derive(Serialize)]
+struct ResourcesResult {
+    items: Vec<ResourcesItem>,
+}
+
+#[derive(Serialize)]
+struct ResourcesItem {
+    target: BuildTargetIdentifier,
+    resources: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileParams {
+    targets: Vec<BuildTargetIdentifier>,
+    origin_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompileResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin_id: Option<String>,
+    status_code: StatusCode,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(into = "u8")]
+enum StatusCode {
+    Ok = 1,
+    Error = 2,
+}
+
+impl From<StatusCode> for u8 {
+    fn from(code: StatusCode) -> u8 {
+        code as u8
+    }
+}
+
+#[derive(Serialize)]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PublishDiagnosticsParams {
+    text_document: TextDocumentIdentifier,
+    build_target: BuildTargetIdentifier,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    origin_id: Option<String>,
+    diagnostics: Vec<Diagnostic>,
+    reset: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bsp::language_ids;
+    use crate::bsp::source_location;
+    use crate::bsp::tags;
+    use crate::bsp::CompileResult;
+    use crate::bsp::SourceLocation;
+    use crate::bsp::StatusCode;
+
+    #[test]
+    fn test_rule_classification() {
+        assert_eq!(vec!["rust".to_owned()], language_ids("rust_library"));
+        assert_eq!(vec!["library".to_owned()], tags("rust_library"));
+        assert_eq!(vec!["test".to_owned()], tags("python_test"));
+        assert_eq!(vec!["application".to_owned()], tags("cxx_binary"));
+        assert!(language_ids("genrule").is_empty());
+        assert!(tags("genrule").is_empty());
+    }
+
+    #[test]
+    fn test_source_location() {
+        assert_eq!(
+            Some(SourceLocation {
+                path: "foo/bar.c",
+                line: 3,
+                column: Some(7),
+                message: "error: use of undeclared identifier 'x'",
+            }),
+            source_location("foo/bar.c:3:7: error: use of undeclared identifier 'x'")
+        );
+        assert_eq!(
+            Some(SourceLocation {
+                path: "src/lib.rs",
+                line: 12,
+                column: Some(5),
+                message: "",
+            }),
+            source_location("src/lib.rs:12:5")
+        );
+        assert_eq!(
+            Some(SourceLocation {
+                path: "Foo.java",
+                line: 10,
+                column: None,
+                message: "error: cannot find symbol",
+            }),
+            source_location("Foo.java:10: error: cannot find symbol")
+        );
+        assert_eq!(None, source_location("Action failed: root//:foo"));
+        assert_eq!(None, source_location("Stderr: nothing"));
+    }
+
+    #[test]
+    fn test_compile_result_serialization() -> anyhow::Result<()> {
+        let result = CompileResult {
+            origin_id: Some("x".to_owned()),
+            status_code: StatusCode::Error,
+        };
+        assert_eq!(
+            r#"{"originId":"x","statusCode":2}"#,
+            serde_json::to_string(&result)?
+        );
+        Ok(())
+    }
+}
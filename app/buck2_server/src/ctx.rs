@@ -22,6 +22,9 @@ use buck2_build_api::actions::build_listener::BuildSignalSender;
 use buck2_build_api::actions::build_listener::SetBuildSignals;
 use buck2_build_api::actions::impls::run_action_knobs::HasRunActionKnobs;
 use buck2_build_api::actions::impls::run_action_knobs::RunActionKnobs;
+use buck2_build_api::actions::invalidation::ActionInvalidationTracker;
+use buck2_build_api::actions::invalidation::FileChanges;
+use buck2_build_api::actions::invalidation::SetActionInvalidationTracker;
 use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
 use buck2_build_api::calculation::ConfiguredGraphCycleDescriptor;
 use buck2_build_api::context::SetBuildContextData;
@@ -50,6 +53,7 @@ use buck2_common::result::SharedError;
 use buck2_common::result::SharedResult;
 use buck2_common::result::ToSharedResultExt;
 use buck2_core::async_once_cell::AsyncOnceCell;
+use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::cells::CellResolver;
 use buck2_core::facebook_only;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
@@ -64,11 +68,14 @@ use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_core::truncate::truncate_container;
 use buck2_events::dispatch::EventDispatcher;
 use buck2_events::metadata;
+use buck2_events::sink::broadcast::BroadcastEventSink;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::SetBlockingExecutor;
 use buck2_execute::execute::dice_data::set_fallback_executor_config;
 use buck2_execute::execute::dice_data::SetCommandExecutor;
 use buck2_execute::execute::dice_data::SetReClient;
+use buck2_execute::execute::test_result_cache::SetTestResultCache;
+use buck2_execute::execute::test_result_cache::TestResultCache;
 use buck2_execute::knobs::ExecutorGlobalKnobs;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::materialize::materializer::SetMaterializer;
@@ -85,10 +92,14 @@ use buck2_interpreter_for_build::interpreter::configuror::BuildInterpreterConfig
 use buck2_interpreter_for_build::interpreter::configuror::CONFIGURE_BXL_FILE_GLOBALS;
 use buck2_interpreter_for_build::interpreter::cycles::LoadCycleDescriptor;
 use buck2_interpreter_for_build::interpreter::interpreter_setup::setup_interpreter;
+use buck2_interpreter_for_build::interpreter::parse_cache::SetStarlarkParseCache;
+use buck2_interpreter_for_build::interpreter::parse_cache::StarlarkParseCache;
 use buck2_server_ctx::concurrency::ConcurrencyHandler;
 use buck2_server_ctx::concurrency::DiceDataProvider;
 use buck2_server_ctx::concurrency::DiceUpdater;
 use buck2_server_ctx::ctx::DiceAccessor;
+use buck2_server_ctx::ctx::FileChangesEvent;
+use buck2_server_ctx::ctx::FileChangesSubscription;
 use buck2_server_ctx::ctx::PrivateStruct;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::stderr_output_guard::StderrOutputGuard;
@@ -102,6 +113,8 @@ use dupe::Dupe;
 use gazebo::prelude::SliceExt;
 use host_sharing::HostSharingBroker;
 use host_sharing::HostSharingStrategy;
+use tokio::sync::broadcast;
+use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tracing::warn;
 
@@ -149,12 +162,22 @@ pub struct BaseServerCommandContext {
     pub _drop_guard: ActiveCommandDropGuard,
     /// The file watcher that keeps buck2 up to date with disk changes.
     pub file_watcher: Arc<dyn FileWatcher>,
+    /// Notified of the changes picked up by every file watcher sync.
+    pub file_changes: broadcast::Sender<Arc<FileChanges>>,
+    /// Attributes the actions this daemon runs to the file changes that invalidated them.
+    pub action_invalidation: Arc<ActionInvalidationTracker>,
     /// Whether or not to hash all commands
     pub hash_all_commands: bool,
     /// Start time to track daemon uptime
     pub daemon_start_time: Instant,
     /// Mutex for creating symlinks
     pub create_unhashed_outputs_lock: Arc<Mutex<()>>,
+    /// Persistent cache of parsed Starlark files, if enabled.
+    pub starlark_parse_cache: Option<Arc<StarlarkParseCache>>,
+    /// Persistent cache of passing test results, if enabled.
+    pub test_result_cache: Option<Arc<TestResultCache>>,
+    /// Receives the events of every command running on this daemon.
+    pub event_broadcast: BroadcastEventSink,
 }
 
 /// ServerCommandContext provides access to the global daemon state and information about the calling client for
@@ -351,6 +374,7 @@ impl ServerCommandContext {
         let materializer = self.base_context.materializer.dupe();
         let re_connection = Arc::new(self.get_re_connection());
         let build_signals = self.build_signals.dupe();
+        let action_invalidation = self.base_context.action_invalidation.dupe();
 
         let forkserver = self.base_context.forkserver.dupe();
 
@@ -361,6 +385,10 @@ impl ServerCommandContext {
 
         let create_unhashed_symlink_lock = self.base_context.create_unhashed_outputs_lock.dupe();
 
+        let starlark_parse_cache = self.base_context.starlark_parse_cache.dupe();
+
+        let test_result_cache = self.base_context.test_result_cache.dupe();
+
         DiceCommandDataProvider {
             cell_configs_loader: self.cell_configs_loader.dupe(),
             events: self.events().dupe(),
@@ -372,10 +400,13 @@ impl ServerCommandContext {
             materializer,
             re_connection,
             build_signals,
+            action_invalidation,
             forkserver,
             upload_all_actions,
             no_remote_cache,
             create_unhashed_symlink_lock,
+            starlark_parse_cache,
+            test_result_cache,
         }
     }
 
@@ -385,6 +416,8 @@ impl ServerCommandContext {
 
         Ok(DiceCommandUpdater {
             file_watcher: self.base_context.file_watcher.dupe(),
+            file_changes: self.base_context.file_changes.clone(),
+            action_invalidation: self.base_context.action_invalidation.dupe(),
             cell_config_loader: self.cell_configs_loader.dupe(),
             buck_out_dir: self.buck_out_dir.clone(),
             interpreter_platform,
@@ -460,11 +493,14 @@ struct DiceCommandDataProvider {
     materializer: Arc<dyn Materializer>,
     re_connection: Arc<ReConnectionHandle>,
     build_signals: BuildSignalSender,
+    action_invalidation: Arc<ActionInvalidationTracker>,
     forkserver: Option<ForkserverClient>,
     upload_all_actions: bool,
     run_action_knobs: RunActionKnobs,
     no_remote_cache: bool,
     create_unhashed_symlink_lock: Arc<Mutex<()>>,
+    starlark_parse_cache: Option<Arc<StarlarkParseCache>>,
+    test_result_cache: Option<Arc<TestResultCache>>,
 }
 
 #[async_trait]
@@ -550,8 +586,15 @@ impl DiceDataProvider for DiceCommandDataProvider {
         data.set_blocking_executor(self.blocking_executor.dupe());
         data.set_materializer(self.materializer.dupe());
         data.set_build_signals(self.build_signals.dupe());
+        data.set_action_invalidation_tracker(self.action_invalidation.dupe());
         data.set_run_action_knobs(self.run_action_knobs.dupe());
         data.set_create_unhashed_symlink_lock(self.create_unhashed_symlink_lock.dupe());
+        if let Some(starlark_parse_cache) = &self.starlark_parse_cache {
+            data.set_starlark_parse_cache(starlark_parse_cache.dupe());
+        }
+        if let Some(test_result_cache) = &self.test_result_cache {
+            data.set_test_result_cache(test_result_cache.dupe());
+        }
         data.spawner = Arc::new(BuckSpawner::default());
 
         let tags = vec![
@@ -575,6 +618,8 @@ fn create_cycle_detector() -> Arc<dyn UserCycleDetector> {
 
 struct DiceCommandUpdater {
     file_watcher: Arc<dyn FileWatcher>,
+    file_changes: broadcast::Sender<Arc<FileChanges>>,
+    action_invalidation: Arc<ActionInvalidationTracker>,
     cell_config_loader: Arc<CellConfigLoader>,
     buck_out_dir: ProjectRelativePathBuf,
     interpreter_platform: InterpreterHostPlatform,
@@ -608,7 +653,10 @@ impl DiceUpdater for DiceCommandUpdater {
             None,
         );
 
-        let mut ctx = self.file_watcher.sync(ctx).await?;
+        let (mut ctx, changes) = self.file_watcher.sync(ctx).await?;
+        self.action_invalidation.record_file_changes(&changes);
+        // No subscribers is fine, nobody is waiting for changes.
+        let _ignored = self.file_changes.send(Arc::new(changes));
 
         ctx.set_buck_out_path(Some(self.buck_out_dir.clone()))?;
 
@@ -625,6 +673,49 @@ impl DiceUpdater for DiceCommandUpdater {
     }
 }
 
+struct FileChangesSubscriptionImpl {
+    /// Changes picked up by the file watcher, which are not synced yet.
+    watcher_changes: watch::Receiver<()>,
+    /// Changes synced by any command.
+    synced_changes: broadcast::Receiver<Arc<FileChanges>>,
+}
+
+#[async_trait]
+impl FileChangesSubscription for FileChangesSubscriptionImpl {
+    async fn wait(
+        &mut self,
+        affected: &(dyn Fn(CellPathRef) -> bool + Send + Sync),
+    ) -> anyhow::Result<FileChangesEvent> {
+        loop {
+            tokio::select! {
+                changes = self.synced_changes.recv() => {
+                    let changes = match changes {
+                        Ok(changes) => changes,
+                        // We missed some changes, so we can't tell what changed.
+                        Err(broadcast::error::RecvError::Lagged(_)) => {
+                            return Ok(FileChangesEvent::Affected);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            return Err(anyhow::anyhow!("File changes channel closed"));
+                        }
+                    };
+                    let is_affected = match &*changes {
+                        FileChanges::FreshInstance => true,
+                        FileChanges::Paths(paths) => paths.iter().any(|p| affected(p.as_ref())),
+                    };
+                    if is_affected {
+                        return Ok(FileChangesEvent::Affected);
+                    }
+                }
+                changed = self.watcher_changes.changed() => {
+                    changed.context("File watcher was dropped")?;
+                    return Ok(FileChangesEvent::Unsynced);
+                }
+            }
+        }
+    }
+}
+
 impl Drop for ServerCommandContext {
     fn drop(&mut self) {
         // Ensure we cancel the heartbeat guard first.
@@ -671,6 +762,13 @@ impl ServerCommandContextTrait for ServerCommandContext {
         &self.base_context.events
     }
 
+    fn subscribe_file_changes(&self) -> Box<dyn FileChangesSubscription> {
+        Box::new(FileChangesSubscriptionImpl {
+            watcher_changes: self.base_context.file_watcher.subscribe(),
+            synced_changes: self.base_context.file_changes.subscribe(),
+        })
+    }
+
     fn stderr(&self) -> anyhow::Result<StderrOutputGuard<'_>> {
         Ok(StderrOutputGuard {
             _phantom: PhantomData,
@@ -65,6 +65,7 @@ use futures::StreamExt;
 use futures::TryFutureExt;
 use more_futures::drop::DropTogether;
 use more_futures::spawn::spawn_dropcancel;
+use starlark::eval::ProfileMode;
 use tokio::sync::oneshot;
 use tonic::service::interceptor;
 use tonic::service::Interceptor;
@@ -76,6 +77,7 @@ use tonic::Status;
 use tracing::debug_span;
 
 use crate::active_commands::ActiveCommand;
+use crate::bsp::run_bsp_server_command;
 use crate::clean_stale::clean_stale_command;
 use crate::ctx::ServerCommandContext;
 use crate::daemon::multi_event_stream::MultiEventStream;
@@ -85,6 +87,8 @@ use crate::file_status::file_status_command;
 use crate::lsp::run_lsp_server_command;
 use crate::materialize::materialize_command;
 use crate::snapshot;
+use crate::starlark_debug::run_dap_server_command;
+use crate::starlark_repl::run_starlark_repl_server_command;
 use crate::streaming_request_handler::StreamingRequestHandler;
 use crate::subscription::run_subscription_server_command;
 use crate::trace_io::trace_io_command;
@@ -319,7 +323,9 @@ impl BuckdServer {
         let (shutdown_channel, shutdown_receiver): (UnboundedSender<()>, _) = mpsc::unbounded();
         let (command_channel, command_receiver): (UnboundedSender<()>, _) = mpsc::unbounded();
 
-        let daemon_state = Arc::new(DaemonState::new(fb, paths, init_ctx).await);
+        let daemon_state = Arc::new(
+            DaemonState::new(fb, paths, init_ctx, daemon_constraints.version.clone()).await,
+        );
 
         let auth_token = process_info.auth_token.clone();
         let api_server = BuckdServer(Arc::new(BuckdServerData {
@@ -880,7 +886,7 @@ impl DaemonApi for BuckdServer {
         let callbacks = self.0.callbacks;
         self.run_streaming(
             req,
-            DefaultCommandOptions,
+            BuildCommandOptions,
             |ctx, partial_result_dispatcher, req| {
                 callbacks.build(Box::new(ctx), partial_result_dispatcher, req)
             },
@@ -906,7 +912,7 @@ impl DaemonApi for BuckdServer {
         let callbacks = self.0.callbacks;
         self.run_streaming(
             req,
-            DefaultCommandOptions,
+            BuildCommandOptions,
             |ctx, partial_result_dispatcher, req| {
                 callbacks.test(Box::new(ctx), partial_result_dispatcher, req)
             },
@@ -1276,6 +1282,70 @@ impl DaemonApi for BuckdServer {
         .await
     }
 
+    type BspStream = ResponseStream;
+    async fn bsp(
+        &self,
+        req: Request<tonic::Streaming<StreamingRequest>>,
+    ) -> Result<Response<Self::BspStream>, Status> {
+        self.run_bidirectional(
+            req,
+            DefaultCommandOptions,
+            |ctx,
+             partial_result_dispatcher,
+             client_ctx,
+             req: StreamingRequestHandler<BspRequest>| {
+                run_bsp_server_command(
+                    Box::new(ctx),
+                    partial_result_dispatcher,
+                    client_ctx.clone(),
+                    req,
+                )
+            },
+        )
+        .await
+    }
+
+    type DapStream = ResponseStream;
+    async fn dap(
+        &self,
+        req: Request<tonic::Streaming<StreamingRequest>>,
+    ) -> Result<Response<Self::DapStream>, Status> {
+        self.run_bidirectional(
+            req,
+            DefaultCommandOptions,
+            |ctx,
+             partial_result_dispatcher,
+             _client_ctx,
+             req: StreamingRequestHandler<DapRequest>| {
+                run_dap_server_command(Box::new(ctx), partial_result_dispatcher, req)
+            },
+        )
+        .await
+    }
+
+    type StarlarkReplStream = ResponseStream;
+    async fn starlark_repl(
+        &self,
+        req: Request<tonic::Streaming<StreamingRequest>>,
+    ) -> Result<Response<Self::StarlarkReplStream>, Status> {
+        self.run_bidirectional(
+            req,
+            DefaultCommandOptions,
+            |ctx,
+             partial_result_dispatcher,
+             client_ctx,
+             req: StreamingRequestHandler<StarlarkReplRequest>| {
+                run_starlark_repl_server_command(
+                    Box::new(ctx),
+                    partial_result_dispatcher,
+                    client_ctx.clone(),
+                    req,
+                )
+            },
+        )
+        .await
+    }
+
     type SubscriptionStream = ResponseStream;
     async fn subscription(
         &self,
@@ -1288,7 +1358,13 @@ impl DaemonApi for BuckdServer {
              partial_result_dispatcher,
              _client_ctx,
              req: StreamingRequestHandler<SubscriptionRequestWrapper>| {
-                run_subscription_server_command(Box::new(ctx), partial_result_dispatcher, req)
+                let event_broadcast = ctx.base_context.event_broadcast.dupe();
+                run_subscription_server_command(
+                    Box::new(ctx),
+                    event_broadcast,
+                    partial_result_dispatcher,
+                    req,
+                )
             },
         )
         .await
@@ -1400,3 +1476,23 @@ struct DefaultCommandOptions;
 
 impl OneshotCommandOptions for DefaultCommandOptions {}
 impl<Req> StreamingCommandOptions<Req> for DefaultCommandOptions {}
+
+/// Options for commands that build targets, which profile analysis when asked for Starlark
+/// coverage.
+struct BuildCommandOptions;
+
+impl OneshotCommandOptions for BuildCommandOptions {}
+
+impl<Req: HasBuildOptions> StreamingCommandOptions<Req> for BuildCommandOptions {
+    fn starlark_profiler_instrumentation_override(
+        &self,
+        req: &Req,
+    ) -> anyhow::Result<StarlarkProfilerConfiguration> {
+        Ok(match req.build_options() {
+            Some(opts) if !opts.starlark_coverage_filename.is_empty() => {
+                StarlarkProfilerConfiguration::ProfileAnalysisRecursively(ProfileMode::Coverage)
+            }
+            _ => StarlarkProfilerConfiguration::None,
+        })
+    }
+}
@@ -16,8 +16,12 @@ use std::time::Instant;
 use allocative::Allocative;
 use anyhow::Context;
 use buck2_build_api::actions::build_listener::CriticalPathBackendName;
+use buck2_build_api::actions::invalidation::ActionInvalidationTracker;
+use buck2_build_api::actions::invalidation::FileChanges;
 use buck2_cli_proto::unstable_dice_dump_request::DiceDumpFormat;
 use buck2_common::cas_digest::DigestAlgorithm;
+use buck2_common::git_ignores::respect_gitignore;
+use buck2_common::git_ignores::WatcherGitIgnores;
 use buck2_common::ignores::IgnoreSet;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_common::io::IoProvider;
@@ -30,6 +34,7 @@ use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_events::dispatch::EventDispatcher;
+use buck2_events::sink::broadcast::BroadcastEventSink;
 use buck2_events::sink::scribe;
 use buck2_events::sink::tee::TeeSink;
 use buck2_events::trace::TraceId;
@@ -38,6 +43,7 @@ use buck2_events::EventSource;
 use buck2_execute::digest_config::DigestConfig;
 use buck2_execute::execute::blocking::BlockingExecutor;
 use buck2_execute::execute::blocking::BuckBlockingExecutor;
+use buck2_execute::execute::test_result_cache::TestResultCache;
 use buck2_execute::materialize::materializer::MaterializationMethod;
 use buck2_execute::materialize::materializer::Materializer;
 use buck2_execute::re::manager::ReConnectionManager;
@@ -48,6 +54,7 @@ use buck2_execute_impl::materializers::immediate::ImmediateMaterializer;
 use buck2_execute_impl::materializers::sqlite::MaterializerState;
 use buck2_execute_impl::materializers::sqlite::MaterializerStateSqliteDb;
 use buck2_forkserver::client::ForkserverClient;
+use buck2_interpreter_for_build::interpreter::parse_cache::StarlarkParseCache;
 use buck2_re_configuration::RemoteExecutionStaticMetadata;
 use buck2_re_configuration::RemoteExecutionStaticMetadataImpl;
 use buck2_server_ctx::concurrency::ConcurrencyHandler;
@@ -57,6 +64,7 @@ use buck2_server_ctx::concurrency::ParallelInvocation;
 use dupe::Dupe;
 use fbinit::FacebookInit;
 use gazebo::variants::VariantName;
+use tokio::sync::broadcast;
 use tokio::sync::Mutex;
 
 use crate::active_commands::ActiveCommandDropGuard;
@@ -95,6 +103,14 @@ pub struct DaemonStateData {
     /// Synced every time we run a command.
     file_watcher: Arc<dyn FileWatcher>,
 
+    /// Receives the changes of every file watcher sync, for commands waiting for changes.
+    #[allocative(skip)]
+    file_changes: broadcast::Sender<Arc<FileChanges>>,
+
+    /// Attributes executed actions to the file changes that invalidated them, across commands.
+    #[allocative(skip)]
+    action_invalidation: Arc<ActionInvalidationTracker>,
+
     /// Settled every time we run a command.
     io: Arc<dyn IoProvider>,
 
@@ -118,6 +134,10 @@ pub struct DaemonStateData {
     #[allocative(skip)]
     pub scribe_sink: Option<Arc<dyn EventSink>>,
 
+    /// Receives the events of every command, for subscriptions that want to observe them.
+    #[allocative(skip)]
+    pub event_broadcast: BroadcastEventSink,
+
     /// Whether or not to hash all commands
     pub hash_all_commands: bool,
 
@@ -130,6 +150,12 @@ pub struct DaemonStateData {
     pub create_unhashed_outputs_lock: Arc<Mutex<()>>,
 
     pub critical_path_backend: CriticalPathBackendName,
+
+    /// Persistent cache of parsed Starlark files, if enabled.
+    pub starlark_parse_cache: Option<Arc<StarlarkParseCache>>,
+
+    /// Persistent cache of passing test results, if enabled.
+    pub test_result_cache: Option<Arc<TestResultCache>>,
 }
 
 impl DaemonStateData {
@@ -155,8 +181,9 @@ impl DaemonState {
         fb: fbinit::FacebookInit,
         paths: InvocationPaths,
         init_ctx: BuckdServerInitPreferences,
+        buck2_version: String,
     ) -> Self {
-        let data = Self::init_data(fb, &paths, init_ctx)
+        let data = Self::init_data(fb, &paths, init_ctx, buck2_version)
             .await
             .context("Error initializing DaemonStateData");
         if let Ok(data) = &data {
@@ -176,6 +203,7 @@ impl DaemonState {
         fb: fbinit::FacebookInit,
         paths: &InvocationPaths,
         init_ctx: BuckdServerInitPreferences,
+        buck2_version: String,
     ) -> anyhow::Result<Arc<DaemonStateData>> {
         let fs = paths.project_root().clone();
 
@@ -218,6 +246,19 @@ impl DaemonState {
             })
             .collect::<anyhow::Result<_>>()?;
 
+        let git_ignores = WatcherGitIgnores::new(
+            paths.project_root().dupe(),
+            cells.dupe(),
+            legacy_configs
+                .iter()
+                .filter_map(|(cell, config)| match respect_gitignore(config) {
+                    Ok(true) => Some(Ok(cell)),
+                    Ok(false) => None,
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        );
+
         let materialization_method =
             MaterializationMethod::try_new_from_config(legacy_configs.get(cells.root_cell()).ok())?;
         let disk_state_options = DiskStateOptions::new(root_config, materialization_method.dupe())?;
@@ -326,6 +367,7 @@ impl DaemonState {
             root_config,
             cells.dupe(),
             ignore_specs,
+            git_ignores,
         )
         .context("Error creating a FileWatcher")?;
 
@@ -348,6 +390,30 @@ impl DaemonState {
 
         let create_unhashed_outputs_lock = Arc::new(Mutex::new(()));
 
+        let starlark_parse_cache = if root_config
+            .parse::<bool>("buck2", "starlark_parse_cache")?
+            .unwrap_or(false)
+        {
+            Some(Arc::new(
+                StarlarkParseCache::new(paths.starlark_parse_cache_path(), buck2_version)
+                    .context("Error initializing Starlark parse cache")?,
+            ))
+        } else {
+            None
+        };
+
+        let test_result_cache = if root_config
+            .parse::<bool>("buck2", "test_result_cache")?
+            .unwrap_or(false)
+        {
+            Some(Arc::new(
+                TestResultCache::new(paths.test_result_cache_path())
+                    .context("Error initializing test result cache")?,
+            ))
+        } else {
+            None
+        };
+
         let buffer_size = root_config
             .parse("buck2", "event_log_buffer_size")?
             .unwrap_or(10000);
@@ -369,6 +435,12 @@ impl DaemonState {
         )
         .context("failed to init scribe sink")?;
 
+        let event_broadcast = BroadcastEventSink::new(
+            root_config
+                .parse("buck2", "subscription_event_buffer_size")?
+                .unwrap_or(10000),
+        );
+
         let critical_path_backend = root_config
             .parse("buck2", "critical_path_backend2")?
             .unwrap_or(CriticalPathBackendName::Default);
@@ -386,17 +458,22 @@ impl DaemonState {
                 cleanup_config,
             ),
             file_watcher,
+            file_changes: broadcast::channel(16).0,
+            action_invalidation: Arc::new(ActionInvalidationTracker::default()),
             io,
             re_client_manager,
             blocking_executor,
             materializer,
             forkserver,
             scribe_sink,
+            event_broadcast,
             hash_all_commands,
             disk_state_options,
             start_time: std::time::Instant::now(),
             create_unhashed_outputs_lock,
             critical_path_backend,
+            starlark_parse_cache,
+            test_result_cache,
         }))
     }
 
@@ -494,7 +571,8 @@ impl DaemonState {
     }
 
     /// Prepares an event stream for a request by bootstrapping an event source and EventDispatcher pair. The given
-    /// EventDispatcher will log to the returned EventSource and (optionally) to Scribe if enabled via buckconfig.
+    /// EventDispatcher will log to the returned EventSource, to subscriptions observing all commands and (optionally)
+    /// to Scribe if enabled via buckconfig.
     pub async fn prepare_events(
         &self,
         trace_id: TraceId,
@@ -503,6 +581,7 @@ impl DaemonState {
         facebook_only();
         let (events, sink) = buck2_events::create_source_sink_pair();
         let data = self.data()?;
+        let sink = data.event_broadcast.tee(sink);
         let dispatcher = if let Some(scribe_sink) = data.scribe_sink.dupe() {
             EventDispatcher::new(trace_id, TeeSink::new(scribe_sink, sink))
         } else {
@@ -556,12 +635,17 @@ impl DaemonState {
             blocking_executor: data.blocking_executor.dupe(),
             materializer: data.materializer.dupe(),
             file_watcher: data.file_watcher.dupe(),
+            file_changes: data.file_changes.clone(),
+            action_invalidation: data.action_invalidation.dupe(),
             events: dispatcher,
             forkserver: data.forkserver.dupe(),
             hash_all_commands: data.hash_all_commands,
             _drop_guard: drop_guard,
             daemon_start_time: data.start_time,
             create_unhashed_outputs_lock: data.create_unhashed_outputs_lock.dupe(),
+            starlark_parse_cache: data.starlark_parse_cache.dupe(),
+            test_result_cache: data.test_result_cache.dupe(),
+            event_broadcast: data.event_broadcast.dupe(),
         })
     }
 
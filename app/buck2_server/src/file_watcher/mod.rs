@@ -12,6 +12,8 @@ use std::sync::Arc;
 
 use allocative::Allocative;
 use async_trait::async_trait;
+use buck2_build_api::actions::invalidation::FileChanges;
+use buck2_common::git_ignores::WatcherGitIgnores;
 use buck2_common::ignores::IgnoreSet;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_core::cells::name::CellName;
@@ -19,6 +21,7 @@ use buck2_core::cells::CellResolver;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::is_open_source;
 use dice::DiceTransactionUpdater;
+use tokio::sync::watch;
 
 use crate::file_watcher::notify::NotifyFileWatcher;
 use crate::file_watcher::watchman::interface::WatchmanFileWatcher;
@@ -29,7 +32,15 @@ mod watchman;
 
 #[async_trait]
 pub trait FileWatcher: Allocative + Send + Sync + 'static {
-    async fn sync(&self, dice: DiceTransactionUpdater) -> anyhow::Result<DiceTransactionUpdater>;
+    /// Write the changes seen since the last sync to `dice`, and return them.
+    async fn sync(
+        &self,
+        dice: DiceTransactionUpdater,
+    ) -> anyhow::Result<(DiceTransactionUpdater, FileChanges)>;
+
+    /// Returns a receiver which is notified when the file watcher observes changes
+    /// which were not synced yet.
+    fn subscribe(&self) -> watch::Receiver<()>;
 }
 
 impl dyn FileWatcher {
@@ -40,6 +51,7 @@ impl dyn FileWatcher {
         root_config: &LegacyBuckConfig,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
+        git_ignores: WatcherGitIgnores,
     ) -> anyhow::Result<Arc<dyn FileWatcher>> {
         let default = if is_open_source() {
             "notify"
@@ -53,11 +65,13 @@ impl dyn FileWatcher {
                 root_config,
                 cells,
                 ignore_specs,
+                git_ignores,
             )?)),
             "notify" => Ok(Arc::new(NotifyFileWatcher::new(
                 project_root,
                 cells,
                 ignore_specs,
+                git_ignores,
             )?)),
             other => Err(anyhow::anyhow!("Invalid buck2.file_watcher: {}", other)),
         }
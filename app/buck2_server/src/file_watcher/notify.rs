@@ -14,7 +14,9 @@ use std::sync::Mutex;
 
 use allocative::Allocative;
 use async_trait::async_trait;
+use buck2_build_api::actions::invalidation::FileChanges;
 use buck2_common::dice::file_ops::FileChangeTracker;
+use buck2_common::git_ignores::WatcherGitIgnores;
 use buck2_common::ignores::IgnoreSet;
 use buck2_common::invocation_paths::InvocationPaths;
 use buck2_core::cells::cell_path::CellPath;
@@ -33,6 +35,7 @@ use notify::event::RemoveKind;
 use notify::EventKind;
 use notify::RecommendedWatcher;
 use notify::Watcher;
+use tokio::sync::watch;
 use tracing::info;
 
 use crate::file_watcher::stats::FileWatcherStats;
@@ -98,6 +101,7 @@ impl NotifyFileData {
         root: &ProjectRoot,
         cells: &CellResolver,
         ignore_specs: &HashMap<CellName, IgnoreSet>,
+        git_ignores: &WatcherGitIgnores,
     ) -> anyhow::Result<()> {
         let event = event?;
         let change_type = ChangeType::new(event.kind);
@@ -120,7 +124,9 @@ impl NotifyFileData {
             let ignore = ignore_specs
                 .get(&cell_path.cell())
                 .expect("unexpected cell name mismatch")
-                .is_match(cell_path.path());
+                .is_match(cell_path.path())
+                || git_ignores
+                    .is_ignored(cell_path.as_ref(), change_type == ChangeType::DirExistence)?;
 
             info!(
                 "FileWatcher: {:?} {:?} (ignore = {})",
@@ -180,6 +186,8 @@ pub struct NotifyFileWatcher {
     #[allocative(skip)]
     watcher: RecommendedWatcher,
     data: Arc<Mutex<anyhow::Result<NotifyFileData>>>,
+    #[allocative(skip)]
+    changes: Arc<watch::Sender<()>>,
 }
 
 impl NotifyFileWatcher {
@@ -187,44 +195,69 @@ impl NotifyFileWatcher {
         root: &ProjectRoot,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
+        git_ignores: WatcherGitIgnores,
     ) -> anyhow::Result<Self> {
         let data = Arc::new(Mutex::new(Ok(NotifyFileData::new())));
         let data2 = data.dupe();
         let root2 = root.dupe();
+        let (changes, _) = watch::channel(());
+        let changes = Arc::new(changes);
+        let changes2 = changes.dupe();
         let mut watcher = notify::recommended_watcher(move |event| {
             let mut guard = data2.lock().unwrap();
             if let Ok(state) = &mut *guard {
-                if let Err(e) = state.process(event, &root2, &cells, &ignore_specs) {
+                if let Err(e) = state.process(event, &root2, &cells, &ignore_specs, &git_ignores) {
                     *guard = Err(e);
                 }
             }
+            let pending = match &*guard {
+                Ok(state) => !state.events.is_empty(),
+                // Sync reports the error.
+                Err(_) => true,
+            };
+            if pending {
+                // Fails when nobody is subscribed, which is fine.
+                let _ignored = changes2.send(());
+            }
         })?;
         watcher.watch(root.root().as_path(), notify::RecursiveMode::Recursive)?;
-        Ok(Self { watcher, data })
+        Ok(Self {
+            watcher,
+            data,
+            changes,
+        })
     }
 
     fn sync2(
         &self,
         mut dice: DiceTransactionUpdater,
-    ) -> anyhow::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
+    ) -> anyhow::Result<(
+        buck2_data::FileWatcherStats,
+        DiceTransactionUpdater,
+        FileChanges,
+    )> {
         let mut guard = self.data.lock().unwrap();
         let old = mem::replace(&mut *guard, Ok(NotifyFileData::new()));
         let (stats, changes) = old?.sync();
+        let changed_paths = changes.changed_paths().cloned().collect();
         changes.write_to_dice(&mut dice)?;
-        Ok((stats, dice))
+        Ok((stats, dice, FileChanges::Paths(changed_paths)))
     }
 }
 
 #[async_trait]
 impl FileWatcher for NotifyFileWatcher {
-    async fn sync(&self, dice: DiceTransactionUpdater) -> anyhow::Result<DiceTransactionUpdater> {
+    async fn sync(
+        &self,
+        dice: DiceTransactionUpdater,
+    ) -> anyhow::Result<(DiceTransactionUpdater, FileChanges)> {
         span_async(
             buck2_data::FileWatcherStart {
                 provider: buck2_data::FileWatcherProvider::RustNotify as i32,
             },
             async {
                 let (stats, res) = match self.sync2(dice) {
-                    Ok((stats, dice)) => ((Some(stats)), Ok(dice)),
+                    Ok((stats, dice, changes)) => ((Some(stats)), Ok((dice, changes))),
                     Err(e) => (None, Err(e)),
                 };
                 (res, buck2_data::FileWatcherEnd { stats })
@@ -232,4 +265,8 @@ impl FileWatcher for NotifyFileWatcher {
         )
         .await
     }
+
+    fn subscribe(&self) -> watch::Receiver<()> {
+        self.changes.subscribe()
+    }
 }
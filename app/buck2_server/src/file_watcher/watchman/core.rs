@@ -23,6 +23,7 @@ use serde::Deserialize;
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
+use tokio::sync::watch;
 use watchman_client::prelude::*;
 
 // We use the "new" field. This is marked as deprecated, but buck1 uses it and
@@ -153,6 +154,14 @@ impl WatchmanClient {
         }?)
     }
 
+    async fn subscribe(
+        &self,
+        request: SubscribeRequest,
+    ) -> anyhow::Result<Subscription<BuckQueryResult>> {
+        let (subscription, _) = self.client().subscribe(self.root(), request).await?;
+        Ok(subscription)
+    }
+
     fn root(&self) -> &ResolvedRoot {
         &self.0.1
     }
@@ -404,6 +413,72 @@ where
     }
 }
 
+/// Notifies `changes` whenever watchman reports changes matching `expr` under `path`.
+///
+/// This only wakes up whoever waits for changes, the changes themselves are still
+/// picked up by `SyncableQuery::sync`.
+pub fn spawn_change_notifier(
+    connector: Connector,
+    path: impl AsRef<Path>,
+    expr: Expr,
+    changes: Arc<watch::Sender<()>>,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let path = CanonicalPath::canonicalize(path)
+        .with_context(|| format!("Error canonicalizing: `{}`", path.display()))?;
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = notify_changes(&connector, &path, &expr, &changes).await {
+                tracing::warn!("Watchman subscription failed (will re-attempt): {:#}", e);
+            }
+            // Changes might have been missed while not subscribed.
+            let _ignored = changes.send(());
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+    Ok(())
+}
+
+async fn notify_changes(
+    connector: &Connector,
+    path: &CanonicalPath,
+    expr: &Expr,
+    changes: &watch::Sender<()>,
+) -> anyhow::Result<()> {
+    let client = WatchmanClient::connect(connector, path.clone()).await?;
+    let mut subscription = client
+        .subscribe(SubscribeRequest {
+            expression: Some(expr.clone()),
+            empty_on_fresh_instance: true,
+            ..SubscribeRequest::default()
+        })
+        .await?;
+    // The first result is the fresh instance of the new subscription.
+    let mut initial = true;
+    loop {
+        match subscription.next().await? {
+            SubscriptionData::FilesChanged(QueryResult {
+                is_fresh_instance,
+                files,
+                ..
+            }) => {
+                let changed =
+                    (is_fresh_instance && !initial) || files.map_or(false, |f| !f.is_empty());
+                initial = false;
+                if changed {
+                    // Fails when nobody is subscribed, which is fine.
+                    let _ignored = changes.send(());
+                }
+            }
+            SubscriptionData::Canceled => {
+                return Err(anyhow::anyhow!("Watchman subscription was canceled"));
+            }
+            SubscriptionData::StateEntered { .. } | SubscriptionData::StateLeft { .. } => {}
+        }
+    }
+}
+
 /// Unpacks the clock returned for an scm-aware query into a tuple of the mergebase and the clockspec.
 fn unpack_clock(clock: Clock) -> (Option<String>, ClockSpec) {
     match clock {
@@ -9,20 +9,27 @@
 
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::Once;
 
 use allocative::Allocative;
 use anyhow::Context as _;
 use async_trait::async_trait;
+use buck2_build_api::actions::invalidation::FileChanges;
 use buck2_common::dice::file_ops::FileChangeTracker;
+use buck2_common::git_ignores::WatcherGitIgnores;
 use buck2_common::ignores::IgnoreSet;
 use buck2_common::legacy_configs::LegacyBuckConfig;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::rollout_percentage::RolloutPercentage;
 use buck2_events::dispatch::span_async;
 use dice::DiceTransactionUpdater;
+use dupe::Dupe;
+use tokio::sync::watch;
 use tracing::info;
 use tracing::warn;
 use watchman_client::expr::Expr;
@@ -30,6 +37,7 @@ use watchman_client::prelude::Connector;
 use watchman_client::prelude::FileType;
 
 use crate::file_watcher::stats::FileWatcherStats;
+use crate::file_watcher::watchman::core::spawn_change_notifier;
 use crate::file_watcher::watchman::core::SyncableQuery;
 use crate::file_watcher::watchman::core::SyncableQueryProcessor;
 use crate::file_watcher::watchman::core::WatchmanEvent;
@@ -40,6 +48,7 @@ use crate::file_watcher::FileWatcher;
 struct WatchmanQueryProcessor {
     cells: CellResolver,
     ignore_specs: HashMap<CellName, IgnoreSet>,
+    git_ignores: WatcherGitIgnores,
     retain_dep_files_on_watchman_fresh_instance: bool,
 }
 
@@ -57,7 +66,10 @@ impl WatchmanQueryProcessor {
         events: Vec<WatchmanEvent>,
         mergebase: &Option<String>,
         watchman_version: Option<String>,
-    ) -> anyhow::Result<(buck2_data::FileWatcherStats, DiceTransactionUpdater)> {
+    ) -> anyhow::Result<(
+        (buck2_data::FileWatcherStats, FileChanges),
+        DiceTransactionUpdater,
+    )> {
         let mut handler = FileChangeTracker::new();
         let mut stats = FileWatcherStats::new(events.len(), mergebase.as_deref(), watchman_version);
 
@@ -85,9 +97,10 @@ impl WatchmanQueryProcessor {
         }
 
         let stats = stats.finish();
+        let changes = FileChanges::Paths(handler.changed_paths().cloned().collect());
         handler.write_to_dice(&mut ctx)?;
 
-        Ok((stats, ctx))
+        Ok(((stats, changes), ctx))
     }
 
     fn process_one_change(
@@ -103,7 +116,11 @@ impl WatchmanQueryProcessor {
             .ignore_specs
             .get(&cell_path.cell())
             .expect("unexpected cell name mismatch")
-            .is_match(cell_path.path());
+            .is_match(cell_path.path())
+            || self.git_ignores.is_ignored(
+                cell_path.as_ref(),
+                matches!(ev, ChangeEvent::Watchman(ev) if matches!(ev.kind, WatchmanKind::Directory)),
+            )?;
 
         info!("Watchman: {:?} (ignore = {})", ev, ignore);
 
@@ -203,7 +220,7 @@ fn find_first_valid_parent(mut path: &Path) -> Option<&ProjectRelativePath> {
 
 #[async_trait]
 impl SyncableQueryProcessor for WatchmanQueryProcessor {
-    type Output = buck2_data::FileWatcherStats;
+    type Output = (buck2_data::FileWatcherStats, FileChanges);
     type Payload = DiceTransactionUpdater;
 
     async fn process_events(
@@ -236,13 +253,16 @@ impl SyncableQueryProcessor for WatchmanQueryProcessor {
         let ctx = ctx.unstable_take();
 
         Ok((
-            buck2_data::FileWatcherStats {
-                fresh_instance: true,
-                branched_from_revision: mergebase.clone(),
-                incomplete_events_reason: Some("Fresh instance".to_owned()),
-                watchman_version,
-                ..Default::default()
-            },
+            (
+                buck2_data::FileWatcherStats {
+                    fresh_instance: true,
+                    branched_from_revision: mergebase.clone(),
+                    incomplete_events_reason: Some("Fresh instance".to_owned()),
+                    watchman_version,
+                    ..Default::default()
+                },
+                FileChanges::FreshInstance,
+            ),
             ctx,
         ))
     }
@@ -251,7 +271,22 @@ impl SyncableQueryProcessor for WatchmanQueryProcessor {
 #[derive(Allocative)]
 pub(crate) struct WatchmanFileWatcher {
     #[allocative(skip)]
-    query: SyncableQuery<buck2_data::FileWatcherStats, DiceTransactionUpdater>,
+    query: SyncableQuery<(buck2_data::FileWatcherStats, FileChanges), DiceTransactionUpdater>,
+    project_root: AbsNormPathBuf,
+    /// Change notifications are only needed with `--watch`, so the watchman
+    /// subscription is only started when someone first subscribes.
+    #[allocative(skip)]
+    notifier: Once,
+    #[allocative(skip)]
+    changes: Arc<watch::Sender<()>>,
+}
+
+fn query_expr() -> Expr {
+    Expr::Any(vec![
+        Expr::FileType(FileType::Regular),
+        Expr::FileType(FileType::Directory),
+        Expr::FileType(FileType::Symlink),
+    ])
 }
 
 /// The watchman query is constructed once on daemon startup. It is an unfiltered watchman query
@@ -264,6 +299,7 @@ impl WatchmanFileWatcher {
         root_config: &LegacyBuckConfig,
         cells: CellResolver,
         ignore_specs: HashMap<CellName, IgnoreSet>,
+        git_ignores: WatcherGitIgnores,
     ) -> anyhow::Result<Self> {
         let watchman_merge_base = root_config
             .get("project", "watchman_merge_base")
@@ -277,33 +313,38 @@ impl WatchmanFileWatcher {
         let query = SyncableQuery::new(
             Connector::new(),
             project_root,
-            Expr::Any(vec![
-                Expr::FileType(FileType::Regular),
-                Expr::FileType(FileType::Directory),
-                Expr::FileType(FileType::Symlink),
-            ]),
+            query_expr(),
             Box::new(WatchmanQueryProcessor {
                 cells,
                 ignore_specs,
+                git_ignores,
                 retain_dep_files_on_watchman_fresh_instance,
             }),
             watchman_merge_base,
         )?;
 
-        Ok(Self { query })
+        Ok(Self {
+            query,
+            project_root: project_root.to_owned(),
+            notifier: Once::new(),
+            changes: Arc::new(watch::channel(()).0),
+        })
     }
 }
 
 #[async_trait]
 impl FileWatcher for WatchmanFileWatcher {
-    async fn sync(&self, dice: DiceTransactionUpdater) -> anyhow::Result<DiceTransactionUpdater> {
+    async fn sync(
+        &self,
+        dice: DiceTransactionUpdater,
+    ) -> anyhow::Result<(DiceTransactionUpdater, FileChanges)> {
         span_async(
             buck2_data::FileWatcherStart {
                 provider: buck2_data::FileWatcherProvider::Watchman as i32,
             },
             async {
                 let (stats, res) = match self.query.sync(dice).await {
-                    Ok((stats, dice)) => ((Some(stats)), Ok(dice)),
+                    Ok(((stats, changes), dice)) => ((Some(stats)), Ok((dice, changes))),
                     Err(e) => (None, Err(e)),
                 };
                 (res, buck2_data::FileWatcherEnd { stats })
@@ -311,4 +352,18 @@ impl FileWatcher for WatchmanFileWatcher {
         )
         .await
     }
+
+    fn subscribe(&self) -> watch::Receiver<()> {
+        self.notifier.call_once(|| {
+            if let Err(e) = spawn_change_notifier(
+                Connector::new(),
+                &self.project_root,
+                query_expr(),
+                self.changes.dupe(),
+            ) {
+                warn!("Error subscribing to Watchman changes: {:#}", e);
+            }
+        });
+        self.changes.subscribe()
+    }
 }
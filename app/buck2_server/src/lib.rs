@@ -11,6 +11,7 @@
 #![feature(try_blocks)]
 
 pub mod active_commands;
+mod bsp;
 pub mod builtin_docs;
 mod clean_stale;
 mod configs;
@@ -27,6 +28,8 @@ mod materialize;
 mod net_io;
 pub mod profile;
 mod snapshot;
+mod starlark_debug;
+mod starlark_repl;
 mod streaming_request_handler;
 mod subscription;
 mod trace_io;
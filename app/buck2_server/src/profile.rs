@@ -15,6 +15,8 @@ use anyhow::Context as _;
 use async_trait::async_trait;
 use buck2_build_api::analysis::calculation::profile_analysis;
 use buck2_build_api::analysis::calculation::profile_analysis_recursively;
+use buck2_build_api::analysis::calculation::profile_analysis_recursively_aggregated;
+use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::calculation::Calculation;
 use buck2_cli_proto::profile_request::ProfileOpts;
 use buck2_cli_proto::target_profile::Action;
@@ -26,11 +28,13 @@ use buck2_core::cells::build_file_cell::BuildFileCell;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::TargetPatternExtra;
 use buck2_core::pattern::PackageSpec;
+use buck2_core::target::label::ConfiguredTargetLabel;
 use buck2_core::target::label::TargetLabel;
 use buck2_interpreter::dice::starlark_profiler::StarlarkProfilerConfiguration;
 use buck2_interpreter::starlark_profiler::StarlarkProfileDataAndStats;
 use buck2_interpreter::starlark_profiler::StarlarkProfiler;
 use buck2_interpreter::starlark_profiler::StarlarkProfilerOrInstrumentation;
+use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
 use buck2_profile::get_profile_response;
 use buck2_profile::starlark_profiler_configuration_from_request;
@@ -41,8 +45,10 @@ use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
 use buck2_server_ctx::pattern::target_platform_from_client_context;
 use buck2_server_ctx::template::run_server_command;
 use buck2_server_ctx::template::ServerCommandTemplate;
+use dice::DiceComputations;
 use dice::DiceTransaction;
 use dupe::Dupe;
+use gazebo::prelude::*;
 
 async fn generate_profile_analysis(
     ctx: DiceTransaction,
@@ -92,6 +98,16 @@ async fn generate_profile_loading(
         PackageSpec::All => {}
     }
 
+    profile_loading(&ctx, package, profile_mode)
+        .await
+        .map(Arc::new)
+}
+
+async fn profile_loading(
+    ctx: &DiceComputations,
+    package: PackageLabel,
+    profile_mode: &StarlarkProfilerConfiguration,
+) -> anyhow::Result<StarlarkProfileDataAndStats> {
     let calculation = ctx
         .get_interpreter_calculator(package.cell_name(), BuildFileCell::new(package.cell_name()))
         .await?;
@@ -105,7 +121,124 @@ async fn generate_profile_loading(
         )
         .await?;
 
-    profiler.finish().map(Arc::new)
+    profiler.finish()
+}
+
+/// Split results into the successes and the errors, so a failure in one package doesn't
+/// prevent profiling all the others.
+fn partition_errors<T>(results: Vec<anyhow::Result<T>>) -> (Vec<T>, Vec<anyhow::Error>) {
+    let mut oks = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(x) => oks.push(x),
+            Err(e) => errors.push(e),
+        }
+    }
+    (oks, errors)
+}
+
+/// Merge the profiles that succeeded, returning the errors of those which failed.
+/// If everything failed, there is no profile to return, so that is an error.
+fn merge_aggregated(
+    profiles: &[StarlarkProfileDataAndStats],
+    errors: Vec<anyhow::Error>,
+) -> anyhow::Result<(Arc<StarlarkProfileDataAndStats>, Vec<String>)> {
+    if profiles.is_empty() {
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error);
+        }
+    }
+    let profile = StarlarkProfileDataAndStats::merge(profiles.iter())?;
+    Ok((
+        Arc::new(profile),
+        errors.iter().map(|e| format!("{:#}", e)).collect(),
+    ))
+}
+
+/// Profile loading of every package matched by the pattern, and merge the profiles.
+/// Packages which fail to load are left out, and their errors returned.
+async fn generate_profile_loading_aggregated(
+    ctx: DiceTransaction,
+    packages: Vec<PackageLabel>,
+    profile_mode: &StarlarkProfilerConfiguration,
+) -> anyhow::Result<(Arc<StarlarkProfileDataAndStats>, Vec<String>)> {
+    let ctx = &ctx;
+    let results = futures::future::join_all(packages.into_iter().map(|package| async move {
+        profile_loading(ctx, package.dupe(), profile_mode)
+            .await
+            .with_context(|| format!("Error loading package `{}`", package))
+    }))
+    .await;
+
+    let (profiles, errors) = partition_errors(results);
+    merge_aggregated(&profiles, errors)
+}
+
+/// Configure and analyse a target, returning the configured label.
+async fn analyse(
+    ctx: &DiceComputations,
+    label: &TargetLabel,
+    global_target_platform: Option<&TargetLabel>,
+) -> anyhow::Result<ConfiguredTargetLabel> {
+    let configured = ctx
+        .get_configured_target(label, global_target_platform)
+        .await?;
+    ctx.get_analysis_result(&configured).await?;
+    Ok(configured)
+}
+
+/// Profile analysis of every target matched by the pattern (and their dependencies),
+/// and merge the profiles. Targets incompatible with the target platform are skipped.
+/// Packages and targets which fail to load, configure or analyse are left out, and their
+/// errors returned.
+async fn generate_profile_analysis_aggregated(
+    ctx: DiceTransaction,
+    specs: Vec<(PackageLabel, PackageSpec<TargetPatternExtra>)>,
+    global_target_platform: Option<TargetLabel>,
+) -> anyhow::Result<(Arc<StarlarkProfileDataAndStats>, Vec<String>)> {
+    let ctx = &ctx;
+    let labels = futures::future::join_all(specs.into_iter().map(|(package, spec)| async move {
+        let res = ctx
+            .get_interpreter_results(package.dupe())
+            .await
+            .with_context(|| format!("Error loading package `{}`", package))?;
+        anyhow::Ok(match spec {
+            PackageSpec::All => res
+                .targets()
+                .values()
+                .map(|node| node.label().dupe())
+                .collect(),
+            PackageSpec::Targets(targets) => targets.try_map(|(name, TargetPatternExtra)| {
+                res.resolve_target(name).map(|node| node.label().dupe())
+            })?,
+        })
+    }))
+    .await;
+    let (labels, mut errors) = partition_errors(labels);
+
+    // Analysis of a target only succeeds if analysis of all its dependencies did, so leaving
+    // out the targets which fail leaves nothing in the merged profile which failed.
+    let global_target_platform = global_target_platform.as_ref();
+    let configured = futures::future::join_all(labels.iter().flatten().map(|label| async move {
+        analyse(ctx, label, global_target_platform)
+            .await
+            .with_context(|| format!("Error analysing target `{}`", label))
+    }))
+    .await;
+    let (configured, analysis_errors) = partition_errors(configured);
+    errors.extend(analysis_errors);
+
+    let profile = if configured.is_empty() {
+        Vec::new()
+    } else {
+        vec![
+            profile_analysis_recursively_aggregated(ctx, &configured)
+                .await
+                .context("Analysis failed")?,
+        ]
+    };
+    merge_aggregated(&profile, errors)
 }
 
 pub async fn profile_command(
@@ -157,17 +290,25 @@ impl ServerCommandTemplate for ProfileServerCommand {
                     .as_ref()
                     .context("Missing client context")?;
 
-                let profile_data = generate_profile(
+                let (profile_data, errors) = generate_profile(
                     server_ctx,
                     ctx,
                     context,
                     target_pattern,
                     action,
+                    opts.aggregate,
                     &profile_mode,
                 )
                 .await?;
 
-                get_profile_response(profile_data, &self.req, output)
+                let mut response = get_profile_response(
+                    profile_data,
+                    &self.req,
+                    server_ctx.project_root(),
+                    output,
+                )?;
+                response.errors = errors;
+                Ok(response)
             }
             _ => {
                 return Err(anyhow::anyhow!(
@@ -189,8 +330,9 @@ async fn generate_profile(
     client_ctx: &ClientContext,
     pattern: &buck2_data::TargetPattern,
     action: Action,
+    aggregate: bool,
     profile_mode: &StarlarkProfilerConfiguration,
-) -> anyhow::Result<Arc<StarlarkProfileDataAndStats>> {
+) -> anyhow::Result<(Arc<StarlarkProfileDataAndStats>, Vec<String>)> {
     let cells = ctx.get_cell_resolver().await?;
 
     let global_target_platform =
@@ -206,16 +348,38 @@ async fn generate_profile(
     let resolved_pattern =
         resolve_target_patterns(&cells, &parsed_patterns, &ctx.file_ops()).await?;
 
+    if aggregate {
+        return match action {
+            Action::Analysis => {
+                generate_profile_analysis_aggregated(
+                    ctx,
+                    resolved_pattern.specs,
+                    global_target_platform,
+                )
+                .await
+            }
+            Action::Loading => {
+                let packages = resolved_pattern
+                    .specs
+                    .into_iter()
+                    .map(|(package, _)| package)
+                    .collect();
+                generate_profile_loading_aggregated(ctx, packages, profile_mode).await
+            }
+        };
+    }
+
     let (package, spec) =
         one(resolved_pattern.specs).context("Did not find exactly one pattern")?;
 
-    match action {
+    let profile_data = match action {
         Action::Analysis => {
             generate_profile_analysis(ctx, package, spec, global_target_platform, profile_mode)
-                .await
+                .await?
         }
-        Action::Loading => generate_profile_loading(ctx, package, spec, profile_mode).await,
-    }
+        Action::Loading => generate_profile_loading(ctx, package, spec, profile_mode).await?,
+    };
+    Ok((profile_data, Vec::new()))
 }
 
 fn one<T>(it: impl IntoIterator<Item = T>) -> anyhow::Result<T> {
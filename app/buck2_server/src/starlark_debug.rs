@@ -0,0 +1,613 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Starlark debugger attached to the daemon with `buck2 starlark debug-attach`, speaking the
+//! Debug Adapter Protocol (<https://microsoft.github.io/debug-adapter-protocol/>).
+//!
+//! Every Starlark evaluation which starts while the debugger is attached (loading a `BUCK`,
+//! `PACKAGE` or `.bzl` file, analysing a target, running BXL, ...) is a DAP thread with its
+//! own [`DapAdapter`], and breakpoints apply to all of them. Threads are only listed while
+//! they are stopped, since a build runs far too many evaluations to show them all.
+//!
+//! Breakpoints are resolved against their file once, when the client sets them, and shared
+//! by the adapters of all evaluations, so starting an evaluation is cheap.
+//!
+//! DAP clients use absolute paths, while Starlark files are named by their project relative
+//! path, so paths are converted at the boundary.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::Context as _;
+use buck2_cli_proto::DapMessage;
+use buck2_cli_proto::DapRequest;
+use buck2_cli_proto::DapResponse;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::project::ProjectRoot;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_events::dispatch::span_async;
+use buck2_interpreter::starlark_debug::attach_starlark_debugger;
+use buck2_interpreter::starlark_debug::StarlarkDebugController;
+use buck2_interpreter::starlark_debug::StarlarkDebuggerAttachment;
+use buck2_server_ctx::command_end::command_end;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use debugserver_types::*;
+use dupe::Dupe;
+use futures::channel::mpsc::UnboundedSender;
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use starlark::debug::dap_capabilities;
+use starlark::debug::prepare_dap_adapter_with_breakpoints;
+use starlark::debug::DapAdapter;
+use starlark::debug::DapAdapterClient;
+use starlark::debug::DapAdapterEvalHook;
+use starlark::debug::DapBreakpoints;
+use starlark::debug::StopReason;
+use starlark::debug::VariablePath;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+
+use crate::streaming_request_handler::StreamingRequestHandler;
+
+/// Stack frame ids are `thread * FRAME_ID_STRIDE + frame`, so `scopes` knows which thread
+/// the frame belongs to.
+const FRAME_ID_STRIDE: i64 = 100_000;
+
+#[derive(Debug, thiserror::Error)]
+enum StarlarkDebugError {
+    #[error("Unknown DAP command `{0}`")]
+    UnknownCommand(String),
+    #[error("Thread {0} is not stopped")]
+    NotStopped(i64),
+    #[error("No thread is stopped")]
+    NoneStopped,
+    #[error("Unknown variables reference {0}")]
+    UnknownVariablesReference(i64),
+    #[error("DAP request has no source path")]
+    NoSourcePath,
+}
+
+pub(crate) async fn run_dap_server_command(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    partial_result_dispatcher: PartialResultDispatcher<DapMessage>,
+    req: StreamingRequestHandler<DapRequest>,
+) -> anyhow::Result<DapResponse> {
+    let metadata = ctx.request_metadata().await?;
+    let start_event = buck2_data::CommandStart {
+        metadata: metadata.clone(),
+        data: Some(buck2_data::StarlarkDebugAttachCommandStart {}.into()),
+    };
+    span_async(start_event, async move {
+        let result = run_dap_server(ctx, partial_result_dispatcher, req).await;
+        let end_event = command_end(
+            metadata,
+            &result,
+            buck2_data::StarlarkDebugAttachCommandEnd {},
+        );
+        (result, end_event)
+    })
+    .await
+}
+
+/// Run the debugger until the client disconnects, then let any stopped evaluations finish.
+async fn run_dap_server(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    mut partial_result_dispatcher: PartialResultDispatcher<DapMessage>,
+    mut req: StreamingRequestHandler<DapRequest>,
+) -> anyhow::Result<DapResponse> {
+    let (events, mut events_to_client) = futures::channel::mpsc::unbounded();
+    let debugger = BuckStarlarkDebugger(Arc::new(DebuggerState {
+        project_root: ctx.project_root().dupe(),
+        events,
+        next_seq: AtomicI64::new(1),
+        next_eval_id: AtomicU64::new(1),
+        breakpoints: DapBreakpoints::default(),
+        evals: Default::default(),
+    }));
+    let _attachment = DebuggerAttachment {
+        _attachment: attach_starlark_debugger(Arc::new(debugger.dupe()))?,
+        debugger: debugger.dupe(),
+    };
+
+    loop {
+        tokio::select! {
+            message = req.message() => {
+                let message = match message {
+                    Ok(m) => m,
+                    // The client disconnected.
+                    Err(_) => break,
+                };
+                let request: Request =
+                    serde_json::from_str(&message.dap_json).context("Invalid DAP message")?;
+                let command = request.command.clone();
+                // Requests wait for the stopped evaluation to answer, so don't block the runtime.
+                let response = {
+                    let debugger = debugger.dupe();
+                    tokio::task::spawn_blocking(move || debugger.respond(request)).await?
+                };
+                partial_result_dispatcher.emit(DapMessage {
+                    dap_json: serde_json::to_string(&response)?,
+                });
+                match command.as_str() {
+                    "initialize" => debugger.event("initialized", serde_json::Value::Null),
+                    "disconnect" => break,
+                    _ => {}
+                }
+            }
+            Some(dap_json) = events_to_client.next() => {
+                partial_result_dispatcher.emit(DapMessage { dap_json });
+            }
+        }
+    }
+
+    Ok(DapResponse {})
+}
+
+/// Detaches the debugger when dropped, which includes the command being cancelled, so
+/// evaluations are never left stopped.
+struct DebuggerAttachment {
+    debugger: BuckStarlarkDebugger,
+    _attachment: StarlarkDebuggerAttachment,
+}
+
+impl Drop for DebuggerAttachment {
+    fn drop(&mut self) {
+        self.debugger.detach();
+    }
+}
+
+#[derive(Debug)]
+struct Eval {
+    /// What is being evaluated, used as the thread name.
+    description: String,
+    adapter: Arc<Mutex<Box<dyn DapAdapter>>>,
+    stopped: bool,
+}
+
+#[derive(Debug, Default)]
+struct Evals {
+    evals: HashMap<u64, Eval>,
+    /// The breakpoints set by the client, by project relative path, so they can be cleared
+    /// when detaching.
+    breakpoints: HashMap<String, SetBreakpointsArguments>,
+    /// The values behind the `variablesReference`s handed out to the client. Each refers to
+    /// the locals of a stopped evaluation, or to a value inside one of them.
+    variables: HashMap<i64, (u64, Option<VariablePath>)>,
+    next_variables_reference: i64,
+    /// The evaluation which stopped most recently, used to evaluate hovers.
+    last_stopped: Option<u64>,
+    detached: bool,
+}
+
+impl Evals {
+    fn variables_reference(&mut self, eval: u64, path: Option<VariablePath>) -> i64 {
+        self.next_variables_reference += 1;
+        self.variables
+            .insert(self.next_variables_reference, (eval, path));
+        self.next_variables_reference
+    }
+}
+
+#[derive(Debug)]
+struct DebuggerState {
+    project_root: ProjectRoot,
+    /// DAP events for the client, as json.
+    events: UnboundedSender<String>,
+    /// The `seq` of the next message sent to the client.
+    next_seq: AtomicI64,
+    /// Evaluation ids start at 1, 0 is used by the adapters which only set breakpoints.
+    next_eval_id: AtomicU64,
+    /// Shared by the adapters of all evaluations.
+    breakpoints: DapBreakpoints,
+    evals: Mutex<Evals>,
+}
+
+#[derive(Debug, Clone, Dupe)]
+struct BuckStarlarkDebugger(Arc<DebuggerState>);
+
+/// Receives events from the [`DapAdapter`] of one evaluation.
+#[derive(Debug)]
+struct EvalClient {
+    id: u64,
+    debugger: BuckStarlarkDebugger,
+}
+
+impl DapAdapterClient for EvalClient {
+    fn event_stopped(&self, reason: StopReason) {
+        let mut evals = self.debugger.0.evals.lock().unwrap();
+        let detached = evals.detached;
+        let eval = match evals.evals.get_mut(&self.id) {
+            Some(eval) => eval,
+            None => return,
+        };
+        if detached {
+            // Nobody is going to resume this evaluation, so do it ourselves. That has to be
+            // on another thread, since this thread is the one that processes the request.
+            let adapter = eval.adapter.dupe();
+            let thread_id = self.id as i64;
+            std::thread::spawn(move || {
+                adapter
+                    .lock()
+                    .unwrap()
+                    .continue_(ContinueArguments { thread_id })
+            });
+            return;
+        }
+        eval.stopped = true;
+        let description = eval.description.clone();
+        evals.last_stopped = Some(self.id);
+        self.debugger.event(
+            "stopped",
+            StoppedEventBody {
+                reason: reason.to_dap(),
+                thread_id: Some(self.id as i64),
+                description: Some(description),
+                all_threads_stopped: Some(false),
+                preserve_focus_hint: None,
+                text: None,
+            },
+        );
+    }
+
+    fn event_output(&self, output: String) {
+        self.debugger.event(
+            "output",
+            OutputEventBody {
+                output,
+                category: Some("console".to_owned()),
+                column: None,
+                data: None,
+                line: None,
+                source: None,
+                variables_reference: None,
+            },
+        );
+    }
+
+    fn get_ast(&self, source: &str) -> anyhow::Result<AstModule> {
+        let path = self
+            .debugger
+            .0
+            .project_root
+            .resolve(ProjectRelativePath::new(source)?);
+        let content = fs_util::read_to_string(&path)?;
+        AstModule::parse(source, content, &Dialect::Extended)
+    }
+
+    fn while_stopped(&self, wait: &mut dyn FnMut()) {
+        // Evaluations run on the tokio runtime. Let it hand this thread's other work to
+        // another worker, rather than have it wait until the client resumes us.
+        tokio::task::block_in_place(wait)
+    }
+}
+
+impl StarlarkDebugController for BuckStarlarkDebugger {
+    fn start_eval(&self, description: String) -> (u64, Box<dyn DapAdapterEvalHook>) {
+        let id = self.0.next_eval_id.fetch_add(1, Ordering::Relaxed);
+        let (adapter, hook) = self.adapter(id);
+        self.0.evals.lock().unwrap().evals.insert(
+            id,
+            Eval {
+                description,
+                adapter: Arc::new(Mutex::new(Box::new(adapter))),
+                stopped: false,
+            },
+        );
+        (id, Box::new(hook))
+    }
+
+    fn end_eval(&self, id: u64) {
+        let mut evals = self.0.evals.lock().unwrap();
+        evals.evals.remove(&id);
+        evals.variables.retain(|_, (eval, _)| *eval != id);
+        if evals.last_stopped == Some(id) {
+            evals.last_stopped = None;
+        }
+    }
+}
+
+impl BuckStarlarkDebugger {
+    /// An adapter for the evaluation with the given id, which shares our breakpoints.
+    fn adapter(&self, id: u64) -> (impl DapAdapter, impl DapAdapterEvalHook) {
+        prepare_dap_adapter_with_breakpoints(
+            Box::new(EvalClient {
+                id,
+                debugger: self.dupe(),
+            }),
+            self.0.breakpoints.dupe(),
+        )
+    }
+
+    fn next_seq(&self) -> i64 {
+        self.0.next_seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn event(&self, event: &str, body: impl Serialize) {
+        let event = serde_json::json!({
+            "type": "event",
+            "seq": self.next_seq(),
+            "event": event,
+            "body": body,
+        });
+        // Fails if the client is gone, in which case nobody cares about the event.
+        let _ignored = self.0.events.unbounded_send(event.to_string());
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        let result = self.dispatch(&request);
+        Response {
+            type_: "response".to_owned(),
+            command: request.command,
+            request_seq: request.seq,
+            seq: self.next_seq(),
+            success: result.is_ok(),
+            message: result.as_ref().err().map(|e| format!("{:#}", e)),
+            body: result.unwrap_or(None),
+        }
+    }
+
+    fn dispatch(&self, request: &Request) -> anyhow::Result<Option<serde_json::Value>> {
+        fn arg<T: DeserializeOwned>(request: &Request) -> anyhow::Result<T> {
+            let arguments = request
+                .arguments
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({}));
+            serde_json::from_value(arguments)
+                .with_context(|| format!("Invalid arguments to `{}`", request.command))
+        }
+
+        fn body(x: impl Serialize) -> anyhow::Result<Option<serde_json::Value>> {
+            Ok(Some(serde_json::to_value(x)?))
+        }
+
+        match request.command.as_str() {
+            "initialize" => body(dap_capabilities()),
+            // Evaluations are started by other buck2 commands, so there is nothing to do.
+            "attach" | "launch" | "configurationDone" | "disconnect" => Ok(None),
+            // We always stop on errors, when the evaluation fails.
+            "setExceptionBreakpoints" => Ok(None),
+            "setBreakpoints" => body(self.set_breakpoints(arg(request)?)?),
+            "threads" => body(self.threads()),
+            "stackTrace" => body(self.stack_trace(arg(request)?)?),
+            "scopes" => body(self.scopes(arg(request)?)?),
+            "variables" => body(self.variables(arg(request)?)?),
+            "evaluate" => body(self.evaluate(arg(request)?)?),
+            "continue" => {
+                let args: ContinueArguments = arg(request)?;
+                body(
+                    self.resume(args.thread_id)?
+                        .lock()
+                        .unwrap()
+                        .continue_(args)?,
+                )
+            }
+            "next" => {
+                let args: NextArguments = arg(request)?;
+                self.resume(args.thread_id)?
+                    .lock()
+                    .unwrap()
+                    .step_over(args)?;
+                Ok(None)
+            }
+            "stepIn" => {
+                let args: StepInArguments = arg(request)?;
+                self.resume(args.thread_id)?.lock().unwrap().step_in(args)?;
+                Ok(None)
+            }
+            "stepOut" => {
+                let args: StepOutArguments = arg(request)?;
+                self.resume(args.thread_id)?
+                    .lock()
+                    .unwrap()
+                    .step_out(args)?;
+                Ok(None)
+            }
+            command => Err(StarlarkDebugError::UnknownCommand(command.to_owned()).into()),
+        }
+    }
+
+    fn relative_path(&self, path: &str) -> anyhow::Result<String> {
+        let path = AbsNormPath::new(path)?;
+        Ok(self.0.project_root.relativize(path)?.as_str().to_owned())
+    }
+
+    fn absolute_path(&self, path: String) -> String {
+        match ProjectRelativePath::new(&path) {
+            Ok(relative) => self.0.project_root.resolve(relative).to_string(),
+            // Not a file in the project, e.g. an expression being evaluated.
+            Err(_) => path,
+        }
+    }
+
+    /// The adapter of a stopped evaluation.
+    fn stopped(&self, thread_id: i64) -> anyhow::Result<Arc<Mutex<Box<dyn DapAdapter>>>> {
+        let evals = self.0.evals.lock().unwrap();
+        u64::try_from(thread_id)
+            .ok()
+            .and_then(|id| evals.evals.get(&id))
+            .filter(|eval| eval.stopped)
+            .map(|eval| eval.adapter.dupe())
+            .ok_or_else(|| StarlarkDebugError::NotStopped(thread_id).into())
+    }
+
+    /// The adapter of a stopped evaluation, which the caller is about to resume.
+    fn resume(&self, thread_id: i64) -> anyhow::Result<Arc<Mutex<Box<dyn DapAdapter>>>> {
+        let adapter = self.stopped(thread_id)?;
+        let mut evals = self.0.evals.lock().unwrap();
+        let id = thread_id as u64;
+        if let Some(eval) = evals.evals.get_mut(&id) {
+            eval.stopped = false;
+        }
+        // The values behind these references may change once the evaluation resumes.
+        evals.variables.retain(|_, (eval, _)| *eval != id);
+        Ok(adapter)
+    }
+
+    fn set_breakpoints(
+        &self,
+        mut args: SetBreakpointsArguments,
+    ) -> anyhow::Result<SetBreakpointsResponseBody> {
+        let path = args
+            .source
+            .path
+            .as_deref()
+            .ok_or(StarlarkDebugError::NoSourcePath)?;
+        let path = self.relative_path(path)?;
+        args.source.path = Some(path.clone());
+
+        // The breakpoints are shared, so setting them with an adapter of our own applies
+        // them to every evaluation, and works when there are none running.
+        let res = self.adapter(0).0.set_breakpoints(args.clone())?;
+
+        let mut evals = self.0.evals.lock().unwrap();
+        if args.breakpoints.as_ref().map_or(true, |x| x.is_empty()) {
+            evals.breakpoints.remove(&path);
+        } else {
+            evals.breakpoints.insert(path, args);
+        }
+        Ok(res)
+    }
+
+    fn threads(&self) -> ThreadsResponseBody {
+        let evals = self.0.evals.lock().unwrap();
+        let mut threads: Vec<_> = evals
+            .evals
+            .iter()
+            .filter(|(_, eval)| eval.stopped)
+            .map(|(id, eval)| Thread {
+                id: *id as i64,
+                name: eval.description.clone(),
+            })
+            .collect();
+        threads.sort_by_key(|thread| thread.id);
+        ThreadsResponseBody { threads }
+    }
+
+    fn stack_trace(&self, args: StackTraceArguments) -> anyhow::Result<StackTraceResponseBody> {
+        let thread_id = args.thread_id;
+        let mut res = self.stopped(thread_id)?.lock().unwrap().stack_trace(args)?;
+        for frame in &mut res.stack_frames {
+            frame.id += thread_id * FRAME_ID_STRIDE;
+            if let Some(source) = &mut frame.source {
+                source.path = source.path.take().map(|path| self.absolute_path(path));
+            }
+        }
+        Ok(res)
+    }
+
+    fn scopes(&self, args: ScopesArguments) -> anyhow::Result<ScopesResponseBody> {
+        let thread_id = args.frame_id / FRAME_ID_STRIDE;
+        let scopes_info = self.stopped(thread_id)?.lock().unwrap().scopes()?;
+        let variables_reference = self
+            .0
+            .evals
+            .lock()
+            .unwrap()
+            .variables_reference(thread_id as u64, None);
+        Ok(ScopesResponseBody {
+            scopes: vec![Scope {
+                name: "Locals".to_owned(),
+                named_variables: Some(scopes_info.num_locals as i64),
+                variables_reference,
+                expensive: false,
+                column: None,
+                end_column: None,
+                end_line: None,
+                indexed_variables: None,
+                line: None,
+                source: None,
+            }],
+        })
+    }
+
+    fn variables(&self, args: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
+        let (eval, path) = self
+            .0
+            .evals
+            .lock()
+            .unwrap()
+            .variables
+            .get(&args.variables_reference)
+            .cloned()
+            .ok_or(StarlarkDebugError::UnknownVariablesReference(
+                args.variables_reference,
+            ))?;
+        let adapter = self.stopped(eval as i64)?;
+        let vars = match path {
+            None => adapter.lock().unwrap().variables()?.locals,
+            Some(path) => adapter.lock().unwrap().inspect_variable(path)?.sub_values,
+        };
+        let mut evals = self.0.evals.lock().unwrap();
+        Ok(VariablesResponseBody {
+            variables: vars
+                .into_iter()
+                .map(|var| {
+                    let reference = match &var.children {
+                        None => 0,
+                        Some(path) => evals.variables_reference(eval, Some(path.clone())),
+                    };
+                    var.to_dap(reference)
+                })
+                .collect(),
+        })
+    }
+
+    fn evaluate(&self, args: EvaluateArguments) -> anyhow::Result<EvaluateResponseBody> {
+        let thread_id = match args.frame_id {
+            Some(frame_id) => frame_id / FRAME_ID_STRIDE,
+            None => self
+                .0
+                .evals
+                .lock()
+                .unwrap()
+                .last_stopped
+                .ok_or(StarlarkDebugError::NoneStopped)? as i64,
+        };
+        self.stopped(thread_id)?.lock().unwrap().evaluate(args)
+    }
+
+    /// Resume every stopped evaluation and make sure none stop again.
+    fn detach(&self) {
+        let (breakpoints, adapters) = {
+            let mut evals = self.0.evals.lock().unwrap();
+            evals.detached = true;
+            evals.variables.clear();
+            let adapters = evals
+                .evals
+                .iter_mut()
+                .map(|(id, eval)| {
+                    let stopped = std::mem::take(&mut eval.stopped);
+                    (*id, eval.adapter.dupe(), stopped)
+                })
+                .collect::<Vec<_>>();
+            (std::mem::take(&mut evals.breakpoints), adapters)
+        };
+        let (adapter, _hook) = self.adapter(0);
+        for args in breakpoints.into_values() {
+            let _ignored = adapter.set_breakpoints(SetBreakpointsArguments {
+                breakpoints: Some(Vec::new()),
+                ..args
+            });
+        }
+        for (id, adapter, stopped) in adapters {
+            if stopped {
+                let _ignored = adapter.lock().unwrap().continue_(ContinueArguments {
+                    thread_id: id as i64,
+                });
+            }
+        }
+    }
+}
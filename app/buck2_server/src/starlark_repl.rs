@@ -0,0 +1,610 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Interactive Starlark evaluation in the daemon, backing `buck2 starlark repl`.
+//!
+//! Inputs are evaluated in a single long-lived module, with the `.bzl` globals and the public
+//! symbols of the prelude in scope, so definitions persist between inputs. `load()` statements
+//! are resolved relative to the client's working directory, and the `ctx` global gives access
+//! to target nodes and queries.
+//!
+//! A `Module` can't be sent between threads, so the module lives on a dedicated thread, which
+//! blocks on DICE whenever `ctx` needs to compute something.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use allocative::Allocative;
+use buck2_build_api::interpreter::context::prelude_path;
+use buck2_build_api::interpreter::rule_defs::artifact::json_convert;
+use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_build_api::query::cquery::environment::CqueryOwnerBehavior;
+use buck2_build_api::query::cquery::evaluator::get_cquery_evaluator;
+use buck2_build_api::query::uquery::evaluator::get_uquery_evaluator;
+use buck2_cli_proto::ClientContext;
+use buck2_cli_proto::StarlarkReplMessage;
+use buck2_cli_proto::StarlarkReplRequest;
+use buck2_cli_proto::StarlarkReplResponse;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::events::HasEvents;
+use buck2_core::bzl::ImportPath;
+use buck2_core::cells::build_file_cell::BuildFileCell;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
+use buck2_core::pattern::pattern_type::TargetPatternExtra;
+use buck2_core::pattern::ParsedPattern;
+use buck2_core::target::label::TargetLabel;
+use buck2_events::dispatch::span_async;
+use buck2_events::dispatch::with_dispatcher_async;
+use buck2_events::dispatch::EventDispatcher;
+use buck2_interpreter::file_type::StarlarkFileType;
+use buck2_interpreter::path::StarlarkModulePath;
+use buck2_interpreter::path::StarlarkPath;
+use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_interpreter_for_build::interpreter::global_interpreter_state::HasGlobalInterpreterState;
+use buck2_query::query::environment::LabeledNode;
+use buck2_query::query::environment::QueryTarget;
+use buck2_query::query::environment::QueryTargets;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationResult;
+use buck2_query::query::syntax::simple::eval::values::QueryEvaluationValue;
+use buck2_server_ctx::command_end::command_end;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use buck2_server_ctx::pattern::parse_patterns_from_cli_args;
+use buck2_server_ctx::pattern::target_platform_from_client_context;
+use dice::DiceComputations;
+use dice::DiceTransaction;
+use dupe::Dupe;
+use gazebo::prelude::*;
+use starlark::any::ProvidesStaticType;
+use starlark::collections::SmallMap;
+use starlark::environment::FrozenModule;
+use starlark::environment::Globals;
+use starlark::environment::Methods;
+use starlark::environment::MethodsBuilder;
+use starlark::environment::MethodsStatic;
+use starlark::environment::Module;
+use starlark::eval::Evaluator;
+use starlark::eval::ReturnFileLoader;
+use starlark::starlark_module;
+use starlark::starlark_simple_value;
+use starlark::starlark_type;
+use starlark::syntax::AstModule;
+use starlark::syntax::Dialect;
+use starlark::values::dict::Dict;
+use starlark::values::list::AllocList;
+use starlark::values::Heap;
+use starlark::values::NoSerialize;
+use starlark::values::StarlarkValue;
+use starlark::values::Value;
+use starlark::PrintHandler;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+
+use crate::streaming_request_handler::StreamingRequestHandler;
+
+/// The name of the virtual file inputs are evaluated as. It lives in the client's working
+/// directory, so that relative `load()`s resolve the way they would for a file there.
+const REPL_FILE: &str = "repl.bzl";
+
+#[derive(Debug, thiserror::Error)]
+enum StarlarkReplError {
+    #[error("`ctx` can only be used while an input is being evaluated")]
+    NotEvaluating,
+    #[error("The Starlark evaluator exited unexpectedly")]
+    EvaluatorExited,
+    #[error("`{0}` is not a target label")]
+    NotATarget(String),
+}
+
+pub(crate) async fn run_starlark_repl_server_command(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    partial_result_dispatcher: PartialResultDispatcher<StarlarkReplMessage>,
+    client_ctx: ClientContext,
+    req: StreamingRequestHandler<StarlarkReplRequest>,
+) -> anyhow::Result<StarlarkReplResponse> {
+    let metadata = ctx.request_metadata().await?;
+    let start_event = buck2_data::CommandStart {
+        metadata: metadata.clone(),
+        data: Some(buck2_data::StarlarkReplCommandStart {}.into()),
+    };
+    span_async(start_event, async move {
+        let result = run_starlark_repl(ctx, partial_result_dispatcher, client_ctx, req).await;
+        let end_event = command_end(metadata, &result, buck2_data::StarlarkReplCommandEnd {});
+        (result, end_event)
+    })
+    .await
+}
+
+/// Evaluate inputs until the client disconnects. The first message sent to the client has the
+/// names in scope before anything is evaluated.
+async fn run_starlark_repl(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    mut partial_result_dispatcher: PartialResultDispatcher<StarlarkReplMessage>,
+    client_ctx: ClientContext,
+    mut req: StreamingRequestHandler<StarlarkReplRequest>,
+) -> anyhow::Result<StarlarkReplResponse> {
+    let (globals, prelude, repl_path) = ctx
+        .with_dice_ctx(|server_ctx, dice| async move {
+            let cell_resolver = dice.get_cell_resolver().await?;
+            let globals = dice
+                .get_global_interpreter_state()
+                .await?
+                .globals_for_file_type(StarlarkFileType::Bzl)
+                .dupe();
+            // Repositories without a prelude still get a REPL, just with fewer symbols.
+            let prelude =
+                match prelude_path(cell_resolver.root_cell_instance().cell_alias_resolver()) {
+                    Ok(path) => Some(
+                        dice.get_interpreter_calculator(path.cell(), path.build_file_cell())
+                            .await?
+                            .eval_module(StarlarkModulePath::LoadFile(&path))
+                            .await?
+                            .env()
+                            .dupe(),
+                    ),
+                    Err(_) => None,
+                };
+            let cell_path = cell_resolver.get_cell_path(
+                &server_ctx
+                    .working_dir()
+                    .join(ForwardRelativePath::new(REPL_FILE)?),
+            )?;
+            let build_file_cell = BuildFileCell::new(cell_path.cell());
+            anyhow::Ok((
+                globals,
+                prelude,
+                ImportPath::new(cell_path, build_file_cell)?,
+            ))
+        })
+        .await?;
+
+    let (jobs, jobs_rx) = mpsc::unbounded_channel();
+    std::thread::Builder::new()
+        .name("starlark-repl".to_owned())
+        .spawn(move || run_evaluator(globals, prelude, jobs_rx))?;
+
+    let initial = evaluate(
+        &jobs,
+        AstModule::parse(REPL_FILE, String::new(), &repl_dialect())?,
+        HashMap::new(),
+        None,
+    )
+    .await?;
+    let mut names = initial.names.clone();
+    partial_result_dispatcher.emit(initial);
+
+    loop {
+        let message = match req.message().await {
+            Ok(m) => m,
+            // The client disconnected.
+            Err(_) => break,
+        };
+        let jobs = &jobs;
+        let names_ref = &names;
+        let client_ctx = &client_ctx;
+        let repl_path = &repl_path;
+        let reply = ctx
+            .with_dice_ctx(|server_ctx, dice| async move {
+                match prepare_input(&dice, server_ctx, client_ctx, repl_path, message.input).await {
+                    Ok((ast, loads, repl_dice)) => {
+                        evaluate(jobs, ast, loads, Some(repl_dice)).await
+                    }
+                    // The module is unchanged, so the names in scope are too.
+                    Err(e) => Ok(StarlarkReplMessage {
+                        output: String::new(),
+                        error: format!("{:#}", e),
+                        names: names_ref.clone(),
+                    }),
+                }
+            })
+            .await?;
+        names = reply.names.clone();
+        partial_result_dispatcher.emit(reply);
+    }
+
+    Ok(StarlarkReplResponse {})
+}
+
+fn repl_dialect() -> Dialect {
+    Dialect {
+        enable_top_level_stmt: true,
+        ..StarlarkFileType::Bzl.dialect(false)
+    }
+}
+
+/// Parse an input and evaluate everything it loads.
+async fn prepare_input(
+    dice: &DiceTransaction,
+    server_ctx: &dyn ServerCommandContextTrait,
+    client_ctx: &ClientContext,
+    repl_path: &ImportPath,
+    input: String,
+) -> anyhow::Result<(AstModule, HashMap<String, FrozenModule>, ReplDice)> {
+    let ast = AstModule::parse(REPL_FILE, input, &repl_dialect())?;
+    let calc = dice
+        .get_interpreter_calculator(repl_path.cell(), repl_path.build_file_cell())
+        .await?;
+    let mut loads = HashMap::new();
+    for load in ast.loads() {
+        let path = calc
+            .resolve_load(StarlarkPath::LoadFile(repl_path), load.module_id)
+            .await?;
+        let module = calc.eval_module(path.borrow()).await?;
+        loads.insert(load.module_id.to_owned(), module.env().dupe());
+    }
+    let global_target_platform =
+        target_platform_from_client_context(client_ctx, server_ctx, dice).await?;
+    let repl_dice = ReplDice {
+        dice: dice.dupe(),
+        handle: Handle::current(),
+        dispatcher: dice.per_transaction_data().get_dispatcher().dupe(),
+        working_dir: server_ctx.working_dir().to_buf(),
+        global_target_platform,
+    };
+    Ok((ast, loads, repl_dice))
+}
+
+struct ReplJob {
+    ast: AstModule,
+    loads: HashMap<String, FrozenModule>,
+    dice: Option<ReplDice>,
+    reply: oneshot::Sender<StarlarkReplMessage>,
+}
+
+async fn evaluate(
+    jobs: &mpsc::UnboundedSender<ReplJob>,
+    ast: AstModule,
+    loads: HashMap<String, FrozenModule>,
+    dice: Option<ReplDice>,
+) -> anyhow::Result<StarlarkReplMessage> {
+    let (reply, response) = oneshot::channel();
+    jobs.send(ReplJob {
+        ast,
+        loads,
+        dice,
+        reply,
+    })
+    .map_err(|_| StarlarkReplError::EvaluatorExited)?;
+    Ok(response
+        .await
+        .map_err(|_| StarlarkReplError::EvaluatorExited)?)
+}
+
+/// Owns the REPL module, evaluating inputs until the session ends.
+fn run_evaluator(
+    globals: Globals,
+    prelude: Option<FrozenModule>,
+    mut jobs: mpsc::UnboundedReceiver<ReplJob>,
+) {
+    let module = Module::new();
+    if let Some(prelude) = &prelude {
+        module.import_public_symbols(prelude);
+    }
+    let current = Arc::new(Mutex::new(None));
+    module.set(
+        "ctx",
+        module.heap().alloc(ReplContext {
+            current: current.dupe(),
+        }),
+    );
+
+    while let Some(job) = jobs.blocking_recv() {
+        *current.lock().unwrap() = job.dice;
+        let message = evaluate_input(&module, &globals, job.ast, &job.loads);
+        // Don't keep the DICE transaction alive between inputs.
+        *current.lock().unwrap() = None;
+        // The session may have ended while we were evaluating.
+        let _ignored = job.reply.send(message);
+    }
+}
+
+#[derive(Default)]
+struct CapturePrintHandler(RefCell<String>);
+
+impl PrintHandler for CapturePrintHandler {
+    fn println(&self, text: &str) -> anyhow::Result<()> {
+        let mut output = self.0.borrow_mut();
+        output.push_str(text);
+        output.push('\n');
+        Ok(())
+    }
+}
+
+fn evaluate_input(
+    module: &Module,
+    globals: &Globals,
+    ast: AstModule,
+    loads: &HashMap<String, FrozenModule>,
+) -> StarlarkReplMessage {
+    let print_handler = CapturePrintHandler::default();
+    let result = eval_module(module, globals, ast, loads, &print_handler);
+    let mut output = print_handler.0.into_inner();
+    let error = match result {
+        Ok(Some(repr)) => {
+            output.push_str(&repr);
+            output.push('\n');
+            String::new()
+        }
+        Ok(None) => String::new(),
+        Err(e) => format!("{:#}", e),
+    };
+    let mut names: Vec<String> = module
+        .names()
+        .chain(globals.names())
+        .map(|name| name.as_str().to_owned())
+        .collect();
+    names.sort();
+    names.dedup();
+    StarlarkReplMessage {
+        output,
+        error,
+        names,
+    }
+}
+
+/// Evaluate an input, returning the repr of its value unless it's `None`, like Python does.
+fn eval_module(
+    module: &Module,
+    globals: &Globals,
+    ast: AstModule,
+    loads: &HashMap<String, FrozenModule>,
+    print_handler: &CapturePrintHandler,
+) -> anyhow::Result<Option<String>> {
+    let modules = loads.iter().map(|(k, v)| (k.as_str(), v)).collect();
+    let loader = ReturnFileLoader { modules: &modules };
+    let mut eval = Evaluator::new(module);
+    eval.set_loader(&loader);
+    eval.set_print_handler(print_handler);
+    let value = eval.eval_module(ast, globals)?;
+    Ok(if value.is_none() {
+        None
+    } else {
+        Some(value.to_repr())
+    })
+}
+
+/// What `ctx` needs to compute things for the input currently being evaluated.
+struct ReplDice {
+    dice: DiceTransaction,
+    handle: Handle,
+    dispatcher: EventDispatcher,
+    working_dir: ProjectRelativePathBuf,
+    global_target_platform: Option<TargetLabel>,
+}
+
+impl ReplDice {
+    /// Evaluation is synchronous, so block the evaluator thread on the computation.
+    fn block_on<'a, F, T>(&'a self, f: impl FnOnce(&'a DiceComputations) -> F) -> anyhow::Result<T>
+    where
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        self.handle
+            .block_on(with_dispatcher_async(self.dispatcher.dupe(), f(&self.dice)))
+    }
+}
+
+/// The `ctx` global of the REPL.
+#[derive(ProvidesStaticType, NoSerialize, Allocative)]
+struct ReplContext {
+    #[allocative(skip)]
+    current: Arc<Mutex<Option<ReplDice>>>,
+}
+
+impl ReplContext {
+    fn with_dice<R>(&self, f: impl FnOnce(&ReplDice) -> anyhow::Result<R>) -> anyhow::Result<R> {
+        match &*self.current.lock().unwrap() {
+            Some(dice) => f(dice),
+            None => Err(StarlarkReplError::NotEvaluating.into()),
+        }
+    }
+}
+
+impl Debug for ReplContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplContext").finish_non_exhaustive()
+    }
+}
+
+impl Display for ReplContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "repl_ctx")
+    }
+}
+
+starlark_simple_value!(ReplContext);
+
+impl<'v> StarlarkValue<'v> for ReplContext {
+    starlark_type!("repl_ctx");
+
+    fn get_methods() -> Option<&'static Methods> {
+        static RES: MethodsStatic = MethodsStatic::new();
+        RES.methods(repl_ctx_methods)
+    }
+}
+
+async fn target_label(
+    dice: &DiceComputations,
+    cwd: &ProjectRelativePath,
+    label: &str,
+) -> anyhow::Result<TargetLabel> {
+    let pattern = buck2_data::TargetPattern {
+        value: label.to_owned(),
+    };
+    match parse_patterns_from_cli_args::<TargetPatternExtra>(dice, &[pattern], cwd)
+        .await?
+        .pop()
+    {
+        Some(ParsedPattern::Target(package, name, TargetPatternExtra)) => {
+            Ok(TargetLabel::new(package, &name))
+        }
+        _ => Err(StarlarkReplError::NotATarget(label.to_owned()).into()),
+    }
+}
+
+/// Query results are returned as lists of labels or paths, or a dict of those for queries
+/// with `%s`.
+fn query_result<'v, T: QueryTarget>(
+    result: QueryEvaluationResult<T>,
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
+    fn value<'v, T: QueryTarget>(value: QueryEvaluationValue<T>, heap: &'v Heap) -> Value<'v> {
+        match value {
+            QueryEvaluationValue::TargetSet(targets) => heap.alloc(AllocList(
+                targets.iter().map(|target| target.node_ref().to_string()),
+            )),
+            QueryEvaluationValue::FileSet(files) => {
+                heap.alloc(AllocList(files.iter().map(|file| file.to_string())))
+            }
+        }
+    }
+
+    Ok(match result {
+        QueryEvaluationResult::Single(result) => value(result, heap),
+        QueryEvaluationResult::Multiple(results) => {
+            let mut res = SmallMap::with_capacity(results.0.len());
+            for (query, result) in results.0 {
+                res.insert_hashed(heap.alloc(query).get_hashed()?, value(result?, heap));
+            }
+            heap.alloc(Dict::new(res))
+        }
+    })
+}
+
+#[starlark_module]
+fn repl_ctx_methods(builder: &mut MethodsBuilder) {
+    /// Evaluate a `uquery`, returning the labels of the matching targets. Any extra arguments
+    /// are substituted for `%s` in the query.
+    fn uquery<'v>(
+        this: &ReplContext,
+        query: &str,
+        #[starlark(args)] args: Vec<&str>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let args = args.into_map(|arg| arg.to_owned());
+        let result = this.with_dice(|d| {
+            d.block_on(|dice| async move {
+                get_uquery_evaluator(dice, &d.working_dir, d.global_target_platform.clone())
+                    .await?
+                    .eval_query(query, &args)
+                    .await
+            })
+        })?;
+        query_result(result, heap)
+    }
+
+    /// Evaluate a `cquery`, returning the labels of the matching configured targets. Any extra
+    /// arguments are substituted for `%s` in the query.
+    fn cquery<'v>(
+        this: &ReplContext,
+        query: &str,
+        #[starlark(args)] args: Vec<&str>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let result = this.with_dice(|d| {
+            d.block_on(|dice| async move {
+                get_cquery_evaluator(
+                    dice,
+                    &d.working_dir,
+                    d.global_target_platform.clone(),
+                    CqueryOwnerBehavior::Correct,
+                )
+                .await?
+                .eval_query(query, &args, None::<&[&str]>)
+                .await
+            })
+        })?;
+        query_result(result, heap)
+    }
+
+    /// The attributes of an unconfigured target node, as a dict.
+    fn target_node<'v>(
+        this: &ReplContext,
+        label: &str,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let node = this.with_dice(|d| {
+            d.block_on(|dice| async move {
+                let label = target_label(dice, &d.working_dir, label).await?;
+                dice.get_target_node(&label).await
+            })
+        })?;
+        let mut attrs = serde_json::Map::new();
+        QueryTargets::for_all_attrs::<anyhow::Error, _, _>(&node, |name, attr| {
+            attrs.insert(
+                name.to_owned(),
+                node.attr_serialize(attr, serde_json::value::Serializer)?,
+            );
+            Ok(())
+        })?;
+        json_convert(serde_json::Value::Object(attrs), heap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn input(jobs: &mpsc::UnboundedSender<ReplJob>, input: &str) -> StarlarkReplMessage {
+        let ast = AstModule::parse(REPL_FILE, input.to_owned(), &repl_dialect()).unwrap();
+        evaluate(jobs, ast, HashMap::new(), None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_evaluator_keeps_globals() {
+        let (jobs, jobs_rx) = mpsc::unbounded_channel();
+        let evaluator =
+            std::thread::spawn(move || run_evaluator(Globals::standard(), None, jobs_rx));
+
+        let initial = input(&jobs, "").await;
+        assert_eq!("", initial.output);
+        assert_eq!("", initial.error);
+        assert!(initial.names.contains(&"ctx".to_owned()));
+        assert!(initial.names.contains(&"len".to_owned()));
+        assert!(!initial.names.contains(&"x".to_owned()));
+
+        let assign = input(&jobs, "x = 1\n").await;
+        assert_eq!("", assign.output);
+        assert!(assign.names.contains(&"x".to_owned()));
+
+        let define = input(&jobs, "def f(y):\n    print('f')\n    return x + y\n").await;
+        assert_eq!("", define.error);
+        assert!(define.names.contains(&"f".to_owned()));
+
+        let call = input(&jobs, "f(2)\n").await;
+        assert_eq!("f\n3\n", call.output);
+        assert_eq!("", call.error);
+
+        let error = input(&jobs, "x + z\n").await;
+        assert_eq!("", error.output);
+        assert!(error.error.contains("`z`"), "{}", error.error);
+        assert!(error.names.contains(&"f".to_owned()));
+
+        // `ctx` needs DICE, which is only provided while evaluating client inputs.
+        let ctx = input(&jobs, "ctx.uquery('//...')\n").await;
+        assert!(
+            ctx.error
+                .contains("can only be used while an input is being evaluated"),
+            "{}",
+            ctx.error
+        );
+
+        drop(jobs);
+        evaluator.join().unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Translation of the daemon's events into the notifications clients subscribe to with
+//! `SubscribeToEvents`.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use buck2_data::action_execution_end;
+use buck2_data::buck_event;
+use buck2_data::command_end;
+use buck2_data::command_start;
+use buck2_data::instant_event;
+use buck2_data::span_end_event;
+use buck2_data::span_start_event;
+use buck2_event_observer::display::display_action_error;
+use buck2_event_observer::display::display_action_key;
+use buck2_event_observer::display::display_configured_target_label;
+use buck2_event_observer::display::TargetDisplayOptions;
+use buck2_events::BuckEvent;
+use buck2_subscription_proto::subscription_response::Response;
+use buck2_subscription_proto::EventClass;
+
+#[derive(Debug, thiserror::Error)]
+enum EventSubscriptionError {
+    #[error("Invalid event class: `{0}`")]
+    InvalidEventClass(i32),
+}
+
+/// The classes of events a subscription asked for.
+#[derive(Default)]
+pub(crate) struct EventClasses(HashSet<EventClass>);
+
+impl EventClasses {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn subscribe(&mut self, classes: &[i32]) -> anyhow::Result<()> {
+        for class in classes {
+            self.0.insert(parse_event_class(*class)?);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn unsubscribe(&mut self, classes: &[i32]) -> anyhow::Result<()> {
+        for class in classes {
+            self.0.remove(&parse_event_class(*class)?);
+        }
+        Ok(())
+    }
+
+    fn contains(&self, class: EventClass) -> bool {
+        self.0.contains(&class)
+    }
+
+    /// Produce the notifications this event translates to, given the classes subscribed to.
+    pub(crate) fn notifications(&self, event: &BuckEvent) -> anyhow::Result<Vec<Response>> {
+        let trace_id = &event.event().trace_id;
+        let mut notifications = Vec::new();
+
+        match event.data() {
+            buck_event::Data::SpanStart(start) => match &start.data {
+                Some(span_start_event::Data::Command(command))
+                    if self.contains(EventClass::Commands) =>
+                {
+                    notifications.push(
+                        buck2_subscription_proto::CommandStarted {
+                            trace_id: trace_id.clone(),
+                            command: command
+                                .data
+                                .as_ref()
+                                .map_or("", command_start_name)
+                                .to_owned(),
+                        }
+                        .into(),
+                    );
+                }
+                Some(span_start_event::Data::ActionExecution(action))
+                    if self.contains(EventClass::Actions) =>
+                {
+                    let (category, identifier) = action_name(action.name.as_ref());
+                    notifications.push(
+                        buck2_subscription_proto::ActionStarted {
+                            trace_id: trace_id.clone(),
+                            owner: action_owner(action.key.as_ref())?,
+                            category,
+                            identifier,
+                        }
+                        .into(),
+                    );
+                }
+                _ => {}
+            },
+            buck_event::Data::SpanEnd(end) => match &end.data {
+                Some(span_end_event::Data::Command(command))
+                    if self.contains(EventClass::Commands) =>
+                {
+                    notifications.push(
+                        buck2_subscription_proto::CommandFinished {
+                            trace_id: trace_id.clone(),
+                            command: command
+                                .data
+                                .as_ref()
+                                .map_or("", command_end_name)
+                                .to_owned(),
+                            success: command.is_success,
+                            error_messages: command.error_messages.clone(),
+                        }
+                        .into(),
+                    );
+                }
+                Some(span_end_event::Data::ActionExecution(action)) => {
+                    if self.contains(EventClass::Actions) {
+                        let (category, identifier) = action_name(action.name.as_ref());
+                        notifications.push(
+                            buck2_subscription_proto::ActionFinished {
+                                trace_id: trace_id.clone(),
+                                owner: action_owner(action.key.as_ref())?,
+                                category,
+                                identifier,
+                                failed: action.failed,
+                                execution_kind: execution_kind(action.execution_kind),
+                            }
+                            .into(),
+                        );
+                    }
+                    if self.contains(EventClass::Failures) {
+                        if let Some(error) = &action.error {
+                            notifications.push(action_failed(trace_id, action, error)?.into());
+                        }
+                    }
+                }
+                _ => {}
+            },
+            buck_event::Data::Instant(instant) => match &instant.data {
+                Some(instant_event::Data::TestResult(result))
+                    if self.contains(EventClass::Tests) =>
+                {
+                    notifications.push(test_result(trace_id, result)?.into());
+                }
+                _ => {}
+            },
+            buck_event::Data::Record(..) => {}
+        }
+
+        Ok(notifications)
+    }
+}
+
+fn parse_event_class(class: i32) -> anyhow::Result<EventClass> {
+    match EventClass::from_i32(class) {
+        Some(EventClass::Unspecified) | None => {
+            Err(EventSubscriptionError::InvalidEventClass(class).into())
+        }
+        Some(class) => Ok(class),
+    }
+}
+
+fn action_owner(key: Option<&buck2_data::ActionKey>) -> anyhow::Result<String> {
+    match key {
+        Some(key) => display_action_key(key, TargetDisplayOptions::for_log()),
+        None => Ok(String::new()),
+    }
+}
+
+fn action_name(name: Option<&buck2_data::ActionName>) -> (String, String) {
+    match name {
+        Some(name) => (name.category.clone(), name.identifier.clone()),
+        None => (String::new(), String::new()),
+    }
+}
+
+fn execution_kind(kind: i32) -> String {
+    match buck2_data::ActionExecutionKind::from_i32(kind) {
+        Some(buck2_data::ActionExecutionKind::NotSet) | None => String::new(),
+        Some(buck2_data::ActionExecutionKind::Local) => "local".to_owned(),
+        Some(buck2_data::ActionExecutionKind::Remote) => "remote".to_owned(),
+        Some(buck2_data::ActionExecutionKind::ActionCache) => "action_cache".to_owned(),
+        Some(buck2_data::ActionExecutionKind::Simple) => "simple".to_owned(),
+        Some(buck2_data::ActionExecutionKind::Skipped) => "skipped".to_owned(),
+        Some(buck2_data::ActionExecutionKind::Deferred) => "deferred".to_owned(),
+    }
+}
+
+fn action_failed(
+    trace_id: &str,
+    action: &buck2_data::ActionExecutionEnd,
+    error: &action_execution_end::Error,
+) -> anyhow::Result<buck2_subscription_proto::ActionFailed> {
+    let (category, identifier) = action_name(action.name.as_ref());
+    let display = display_action_error(action, error, TargetDisplayOptions::for_log())?;
+    let (stdout, stderr) = match &display.command {
+        Some(command) => (command.stdout.clone(), command.stderr.clone()),
+        None => (String::new(), String::new()),
+    };
+    Ok(buck2_subscription_proto::ActionFailed {
+        trace_id: trace_id.to_owned(),
+        owner: action_owner(action.key.as_ref())?,
+        category,
+        identifier,
+        reason: display.reason,
+        stdout,
+        stderr,
+    })
+}
+
+fn test_result(
+    trace_id: &str,
+    result: &buck2_data::TestResult,
+) -> anyhow::Result<buck2_subscription_proto::TestResult> {
+    let target = match &result.target_label {
+        Some(label) => display_configured_target_label(label, TargetDisplayOptions::for_log())?,
+        None => String::new(),
+    };
+    let status =
+        buck2_data::TestStatus::from_i32(result.status).map_or("", |status| status.as_str_name());
+    let duration_ms = result
+        .duration
+        .clone()
+        .and_then(|duration| Duration::try_from(duration).ok())
+        .map(|duration| duration.as_millis() as u64);
+    Ok(buck2_subscription_proto::TestResult {
+        trace_id: trace_id.to_owned(),
+        target,
+        name: result.name.clone(),
+        status: status.to_owned(),
+        duration_ms,
+        details: result.details.clone(),
+    })
+}
+
+fn command_start_name(data: &command_start::Data) -> &'static str {
+    match data {
+        command_start::Data::Build(..) => "build",
+        command_start::Data::Targets(..) => "targets",
+        command_start::Data::Ctargets(..) => "ctargets",
+        command_start::Data::Query(..) => "query",
+        command_start::Data::Cquery(..) => "cquery",
+        command_start::Data::Test(..) => "test",
+        command_start::Data::Audit(..) => "audit",
+        command_start::Data::Docs(..) => "docs",
+        command_start::Data::Clean(..) => "clean",
+        command_start::Data::Aquery(..) => "aquery",
+        command_start::Data::Install(..) => "install",
+        command_start::Data::Materialize(..) => "materialize",
+        command_start::Data::Profile(..) => "profile",
+        command_start::Data::Bxl(..) => "bxl",
+        command_start::Data::Lsp(..) => "lsp",
+        command_start::Data::FileStatus(..) => "file-status",
+        command_start::Data::Starlark(..) => "starlark",
+        command_start::Data::Subscribe(..) => "subscribe",
+        command_start::Data::Trace(..) => "trace-io",
+        command_start::Data::Bsp(..) => "bsp",
+        command_start::Data::StarlarkDebugAttach(..) => "starlark-debug-attach",
+        command_start::Data::StarlarkRepl(..) => "starlark-repl",
+    }
+}
+
+fn command_end_name(data: &command_end::Data) -> &'static str {
+    match data {
+        command_end::Data::Build(..) => "build",
+        command_end::Data::Targets(..) => "targets",
+        command_end::Data::Ctargets(..) => "ctargets",
+        command_end::Data::Query(..) => "query",
+        command_end::Data::Cquery(..) => "cquery",
+        command_end::Data::Test(..) => "test",
+        command_end::Data::Audit(..) => "audit",
+        command_end::Data::Docs(..) => "docs",
+        command_end::Data::Clean(..) => "clean",
+        command_end::Data::Aquery(..) => "aquery",
+        command_end::Data::Install(..) => "install",
+        command_end::Data::Materialize(..) => "materialize",
+        command_end::Data::Profile(..) => "profile",
+        command_end::Data::Bxl(..) => "bxl",
+        command_end::Data::Lsp(..) => "lsp",
+        command_end::Data::FileStatus(..) => "file-status",
+        command_end::Data::Starlark(..) => "starlark",
+        command_end::Data::Subscribe(..) => "subscribe",
+        command_end::Data::Trace(..) => "trace-io",
+        command_end::Data::Bsp(..) => "bsp",
+        command_end::Data::StarlarkDebugAttach(..) => "starlark-debug-attach",
+        command_end::Data::StarlarkRepl(..) => "starlark-repl",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use buck2_events::span::SpanId;
+    use buck2_events::trace::TraceId;
+
+    use super::*;
+
+    fn span_start(data: impl Into<span_start_event::Data>) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(SpanId::new()),
+            None,
+            buck2_data::SpanStartEvent {
+                data: Some(data.into()),
+            }
+            .into(),
+        )
+    }
+
+    fn span_end(data: impl Into<span_end_event::Data>) -> BuckEvent {
+        BuckEvent::new(
+            SystemTime::now(),
+            TraceId::new(),
+            Some(SpanId::new()),
+            None,
+            buck2_data::SpanEndEvent {
+                data: Some(data.into()),
+                ..Default::default()
+            }
+            .into(),
+        )
+    }
+
+    fn classes(classes: &[EventClass]) -> EventClasses {
+        let mut res = EventClasses::default();
+        res.subscribe(&classes.iter().map(|c| *c as i32).collect::<Vec<_>>())
+            .unwrap();
+        res
+    }
+
+    #[test]
+    fn test_invalid_event_class() {
+        let mut classes = EventClasses::default();
+        assert!(
+            classes
+                .subscribe(&[EventClass::Unspecified as i32])
+                .is_err()
+        );
+        assert!(classes.subscribe(&[1000]).is_err());
+        assert!(classes.is_empty());
+    }
+
+    #[test]
+    fn test_command_notifications() {
+        let event = span_start(buck2_data::CommandStart {
+            data: Some(buck2_data::BuildCommandStart {}.into()),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            classes(&[EventClass::Actions])
+                .notifications(&event)
+                .unwrap(),
+            Vec::new()
+        );
+
+        match classes(&[EventClass::Commands])
+            .notifications(&event)
+            .unwrap()
+            .as_slice()
+        {
+            [Response::CommandStarted(started)] => {
+                assert_eq!(started.trace_id, event.event().trace_id);
+                assert_eq!(started.command, "build");
+            }
+            r => panic!("Unexpected notifications: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_failed_action_notifications() {
+        let event = span_end(buck2_data::ActionExecutionEnd {
+            key: Some(buck2_data::ActionKey {
+                owner: Some(buck2_data::action_key::Owner::TargetLabel(
+                    buck2_data::ConfiguredTargetLabel {
+                        label: Some(buck2_data::TargetLabel {
+                            package: "root//foo".to_owned(),
+                            name: "bar".to_owned(),
+                        }),
+                        configuration: Some(buck2_data::Configuration {
+                            full_name: "cfg".to_owned(),
+                        }),
+                        ..Default::default()
+                    },
+                )),
+                ..Default::default()
+            }),
+            name: Some(buck2_data::ActionName {
+                category: "cxx_compile".to_owned(),
+                identifier: "foo.cpp".to_owned(),
+            }),
+            failed: true,
+            error: Some(action_execution_end::Error::Unknown("oops".to_owned())),
+            ..Default::default()
+        });
+
+        match classes(&[EventClass::Actions])
+            .notifications(&event)
+            .unwrap()
+            .as_slice()
+        {
+            [Response::ActionFinished(finished)] => {
+                assert_eq!(finished.owner, "root//foo:bar (cfg)");
+                assert_eq!(finished.category, "cxx_compile");
+                assert_eq!(finished.identifier, "foo.cpp");
+                assert!(finished.failed);
+            }
+            r => panic!("Unexpected notifications: {:?}", r),
+        }
+
+        match classes(&[EventClass::Actions, EventClass::Failures])
+            .notifications(&event)
+            .unwrap()
+            .as_slice()
+        {
+            [Response::ActionFinished(..), Response::ActionFailed(failed)] => {
+                assert_eq!(failed.owner, "root//foo:bar (cfg)");
+                assert_eq!(failed.reason, "Internal error: oops");
+                assert_eq!(failed.stderr, "");
+            }
+            r => panic!("Unexpected notifications: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+mod events;
+
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use buck2_events::dispatch::span_async;
+use buck2_events::sink::broadcast::BroadcastEventSink;
+use buck2_events::BuckEvent;
+use buck2_execute::materialize::materializer::DeferredMaterializerSubscription;
+use buck2_server_ctx::command_end::command_end;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use futures::future::FutureExt;
+use gazebo::prelude::*;
+use tokio::sync::broadcast;
+
+use crate::streaming_request_handler::StreamingRequestHandler;
+use crate::subscription::events::EventClasses;
+
+pub(crate) async fn run_subscription_server_command(
+    ctx: Box<dyn ServerCommandContextTrait>,
+    event_broadcast: BroadcastEventSink,
+    mut partial_result_dispatcher: PartialResultDispatcher<
+        buck2_cli_proto::SubscriptionResponseWrapper,
+    >,
+    mut req: StreamingRequestHandler<buck2_cli_proto::SubscriptionRequestWrapper>,
+) -> anyhow::Result<buck2_cli_proto::SubscriptionCommandResponse> {
+    let metadata = ctx.request_metadata().await?;
+    let start_event = buck2_data::CommandStart {
+        metadata: metadata.clone(),
+        data: Some(buck2_data::SubscriptionCommandStart {}.into()),
+    };
+    span_async(start_event, async move {
+        let result: anyhow::Result<buck2_cli_proto::SubscriptionCommandResponse> = try {
+            let own_trace_id = ctx.events().trace_id().to_string();
+
+            // Both of those are only set up once the client asks for them, so that e.g. clients
+            // that only want events don't need the deferred materializer.
+            let mut materializer_subscription: Option<Box<dyn DeferredMaterializerSubscription>> =
+                None;
+            let mut event_classes = EventClasses::default();
+            let mut events: Option<broadcast::Receiver<Arc<BuckEvent>>> = None;
+
+            loop {
+                futures::select! {
+                    message = req.message().fuse() => {
+                        use buck2_subscription_proto::subscription_request::Request;
+
+                        match message?.request.context("Empty message")?.request.context("Empty request")? {
+                            Request::Disconnect(buck2_subscription_proto::Disconnect {}) => {
+                                break;
+                            }
+                            Request::SubscribeToPaths(buck2_subscription_proto::SubscribeToPaths { paths }) => {
+                                let paths = paths.into_try_map(|path| path.try_into())?;
+                                let subscription = match materializer_subscription.take() {
+                                    Some(subscription) => subscription,
+                                    None => create_materializer_subscription(&*ctx).await?,
+                                };
+                                materializer_subscription.insert(subscription).subscribe_to_paths(paths);
+                            }
+                            Request::UnsubscribeFromPaths(buck2_subscription_proto::UnsubscribeFromPaths { paths }) => {
+                                let paths = paths.into_try_map(|path| path.try_into())?;
+                                if let Some(materializer_subscription) = &mut materializer_subscription {
+                                    materializer_subscription.unsubscribe_from_paths(paths);
+                                }
+                            }
+                            Request::SubscribeToEvents(buck2_subscription_proto::SubscribeToEvents { classes }) => {
+                                event_classes.subscribe(&classes)?;
+                                if events.is_none() && !event_classes.is_empty() {
+                                    events = Some(event_broadcast.subscribe());
+                                }
+                            }
+                            Request::UnsubscribeFromEvents(buck2_subscription_proto::UnsubscribeFromEvents { classes }) => {
+                                event_classes.unsubscribe(&classes)?;
+                                if event_classes.is_empty() {
+                                    // Stop receiving (and buffering) events nobody wants.
+                                    events = None;
+                                }
+                            }
+                        }
+                    }
+                    path = next_materialization(&mut materializer_subscription).fuse() => {
+                        let path = path.context("Materializer hung up")?;
+                        emit(&mut partial_result_dispatcher, buck2_subscription_proto::Materialized { path: path.to_string() }.into());
+                    }
+                    event = next_event(&mut events).fuse() => {
+                        let event = match event {
+                            Err(broadcast::error::RecvError::Lagged(count)) => {
+                                emit(&mut partial_result_dispatcher, buck2_subscription_proto::EventsDropped { count }.into());
+                                continue;
+                            }
+                            event => event.context("Event broadcast hung up")?,
+                        };
+                        // Don't report on ourselves.
+                        if event.event().trace_id == own_trace_id {
+                            continue;
+                        }
+                        for notification in event_classes.notifications(&event)? {
+                            emit(&mut partial_result_dispatcher, notification);
+                        }
+                    }
+                }
+            }
+
+            buck2_cli_proto::SubscriptionCommandResponse {}
+        };
+
+        let end_event = command_end(metadata, &result, buck2_data::SubscriptionCommandEnd {});
+        (result, end_event)
+    })
+    .await
+}
+
+async fn create_materializer_subscription(
+    ctx: &dyn ServerCommandContextTrait,
+) -> anyhow::Result<Box<dyn DeferredMaterializerSubscription>> {
+    ctx.materializer()
+        .as_deferred_materializer_extension()
+        .context("Subscribing to paths only works with the deferred materializer")?
+        .create_subscription()
+        .await
+        .context("Error creating a materializer subscription")
+}
+
+/// Wait for the next materialization, or forever if the client never subscribed to paths.
+async fn next_materialization(
+    subscription: &mut Option<Box<dyn DeferredMaterializerSubscription>>,
+) -> Option<buck2_core::fs::project_rel_path::ProjectRelativePathBuf> {
+    match subscription {
+        Some(subscription) => subscription.next_materialization().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Wait for the next event, or forever if the client isn't subscribed to any.
+async fn next_event(
+    events: &mut Option<broadcast::Receiver<Arc<BuckEvent>>>,
+) -> Result<Arc<BuckEvent>, broadcast::error::RecvError> {
+    match events {
+        Some(events) => events.recv().await,
+        None => futures::future::pending().await,
+    }
+}
+
+fn emit(
+    partial_result_dispatcher: &mut PartialResultDispatcher<
+        buck2_cli_proto::SubscriptionResponseWrapper,
+    >,
+    response: buck2_subscription_proto::subscription_response::Response,
+) {
+    partial_result_dispatcher.emit(buck2_cli_proto::SubscriptionResponseWrapper {
+        response: Some(buck2_subscription_proto::SubscriptionResponse {
+            response: Some(response),
+        }),
+    });
+}
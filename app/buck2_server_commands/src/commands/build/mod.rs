@@ -10,10 +10,12 @@
 use std::collections::BTreeMap;
 use std::future;
 use std::io::BufWriter;
+use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
+use buck2_build_api::analysis::calculation::profile_analysis_recursively_aggregated;
 use buck2_build_api::build;
 use buck2_build_api::build::BuildTargetResult;
 use buck2_build_api::build::ConvertMaterializationContext;
@@ -21,6 +23,7 @@ use buck2_build_api::build::HasCreateUnhashedSymlinkLock;
 use buck2_build_api::build::MaterializationContext;
 use buck2_build_api::build::ProvidersToBuild;
 use buck2_build_api::calculation::Calculation;
+use buck2_build_api::nodes::watch_inputs::collect_watch_inputs;
 use buck2_build_api::query::cquery::evaluator::universe_from_literals;
 use buck2_build_api::query::dice::get_dice_query_delegate;
 use buck2_cli_proto::build_request::build_providers::Action as BuildProviderAction;
@@ -33,6 +36,7 @@ use buck2_common::dice::file_ops::HasFileOps;
 use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::pattern::resolve::resolve_target_patterns;
 use buck2_common::pattern::resolve::ResolvedPattern;
+use buck2_common::watch_inputs::WatchInputs;
 use buck2_core::fs::fs_util;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::ConfiguredProvidersPatternExtra;
@@ -47,6 +51,7 @@ use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_node::configured_universe::CqueryUniverse;
 use buck2_node::nodes::eval_result::EvaluationResult;
+use buck2_profile::write_starlark_coverage;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
@@ -110,6 +115,29 @@ impl ServerCommandTemplate for BuildServerCommand {
     fn is_success(&self, response: &Self::Response) -> bool {
         response.error_messages.is_empty()
     }
+
+    fn watch_token(&self) -> Option<&str> {
+        self.req.watch_token.as_deref()
+    }
+
+    async fn watch_inputs(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        ctx: DiceTransaction,
+    ) -> anyhow::Result<Option<WatchInputs>> {
+        let parsed_patterns: Vec<ParsedPattern<ConfiguredProvidersPatternExtra>> =
+            parse_patterns_from_cli_args(&ctx, &self.req.target_patterns, server_ctx.working_dir())
+                .await?;
+        let resolved_pattern = resolve_target_patterns(
+            &ctx.get_cell_resolver().await?,
+            &parsed_patterns,
+            &ctx.file_ops(),
+        )
+        .await?;
+        Ok(Some(
+            collect_watch_inputs(&ctx, &parsed_patterns, &resolved_pattern).await?,
+        ))
+    }
 }
 
 enum TargetResolutionConfig {
@@ -125,6 +153,7 @@ async fn build(
     request: &BuildRequest,
 ) -> anyhow::Result<buck2_cli_proto::BuildResponse> {
     // TODO(nmj): Move build report printing logic out of here.
+    let watch_token = ctx.equality_token().to_string();
     let fs = server_ctx.project_root();
     let cwd = server_ctx.working_dir();
 
@@ -226,6 +255,7 @@ async fn build(
         ConvertMaterializationContext::from(final_artifact_materializations);
 
     let mut provider_artifacts = Vec::new();
+    let mut built_targets = Vec::new();
     for (k, v) in build_targets(
         &ctx,
         resolved_pattern,
@@ -236,6 +266,7 @@ async fn build(
     .await?
     {
         result_collectors.collect_result(&BuildOwner::Target(&k), &v);
+        built_targets.push(k.target().dupe());
         let mut outputs = v.outputs.into_iter().filter_map(|output| match output {
             Ok(output) => Some(output),
             _ => None,
@@ -294,6 +325,17 @@ async fn build(
         }
     };
 
+    // Analysis of every target must have succeeded to merge its profile.
+    if !build_opts.starlark_coverage_filename.is_empty() && error_messages.is_empty() {
+        let targets = built_targets.into_iter().unique().collect::<Vec<_>>();
+        let profile_data = profile_analysis_recursively_aggregated(&ctx, &targets).await?;
+        write_starlark_coverage(
+            &profile_data,
+            fs,
+            Path::new(&build_opts.starlark_coverage_filename),
+        )?;
+    }
+
     let project_root = server_ctx.project_root().to_string();
 
     Ok(buck2_cli_proto::BuildResponse {
@@ -301,6 +343,7 @@ async fn build(
         project_root,
         serialized_build_report: serialized_build_report.unwrap_or_default(),
         error_messages,
+        watch_token,
     })
 }
 
@@ -23,6 +23,7 @@ use buck2_node::attrs::inspect_options::AttrInspectOptions;
 use buck2_node::nodes::attributes::DEPS;
 use buck2_node::nodes::attributes::INPUTS;
 use buck2_node::nodes::attributes::PACKAGE;
+use buck2_node::nodes::attributes::PACKAGE_VALUES;
 use buck2_node::nodes::attributes::TARGET_CALL_STACK;
 use buck2_node::nodes::attributes::TARGET_HASH;
 use buck2_node::nodes::attributes::TYPE;
@@ -197,6 +198,13 @@ impl TargetFormatter for JsonFormat {
             format!("\"{}\"", target_info.node.label().pkg())
         });
 
+        let package_values = target_info.node.package_values();
+        if !package_values.is_empty() {
+            print_attr(self, buffer, &mut first, PACKAGE_VALUES, || {
+                package_values.to_json().to_string()
+            });
+        }
+
         for a in target_info.node.attrs(self.attr_inspect_opts) {
             print_attr(self, buffer, &mut first, a.name, || {
                 value_to_json(a.value, target_info.node.label().pkg())
@@ -13,6 +13,7 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use buck2_common::result::SharedResult;
+use buck2_core::cells::cell_path::CellPathRef;
 use buck2_core::fs::project::ProjectRoot;
 use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::working_dir::WorkingDir;
@@ -61,6 +62,27 @@ pub trait ServerCommandContextTrait: Send + Sync + 'static {
         &self,
         providers_patterns: &[ParsedPattern<ConfiguredProvidersPatternExtra>],
     );
+
+    /// Subscribe to the file changes the file watcher picks up from now on.
+    fn subscribe_file_changes(&self) -> Box<dyn FileChangesSubscription>;
+}
+
+/// What a `FileChangesSubscription` observed.
+pub enum FileChangesEvent {
+    /// The file watcher observed changes, which are picked up by the next DICE transaction.
+    Unsynced,
+    /// Changes which affect the caller were picked up, or changes were lost track of.
+    Affected,
+}
+
+#[async_trait]
+pub trait FileChangesSubscription: Send {
+    /// Wait until the file watcher observes changes, or changes to paths `affected`
+    /// returns true for are picked up by a DICE transaction of any command.
+    async fn wait(
+        &mut self,
+        affected: &(dyn Fn(CellPathRef) -> bool + Send + Sync),
+    ) -> anyhow::Result<FileChangesEvent>;
 }
 
 pub struct PrivateStruct(());
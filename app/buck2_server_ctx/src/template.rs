@@ -8,10 +8,13 @@
  */
 
 use async_trait::async_trait;
+use buck2_common::watch_inputs::WatchInputs;
+use buck2_core::cells::cell_path::CellPathRef;
 use buck2_events::dispatch::span_async;
 use dice::DiceTransaction;
 
 use crate::command_end::command_end_ext;
+use crate::ctx::FileChangesEvent;
 use crate::ctx::ServerCommandContextTrait;
 use crate::ctx::ServerCommandDiceContext;
 use crate::logging::TracingLogFile;
@@ -50,6 +53,23 @@ pub trait ServerCommandTemplate: Send + Sync {
         None
     }
 
+    /// If not `None`, command will wait until the file watcher picks up changes to
+    /// `watch_inputs` since the state identified by this token (as returned by
+    /// `DiceTransaction::equality_token`), or since now if the token is empty.
+    fn watch_token(&self) -> Option<&str> {
+        None
+    }
+
+    /// Files the command depends on, see `watch_token`.
+    /// `None` means that any file change reruns the command.
+    async fn watch_inputs(
+        &self,
+        _server_ctx: &dyn ServerCommandContextTrait,
+        _ctx: DiceTransaction,
+    ) -> anyhow::Result<Option<WatchInputs>> {
+        Ok(None)
+    }
+
     /// Command implementation.
     async fn command(
         &self,
@@ -71,6 +91,54 @@ pub async fn run_server_command<T: ServerCommandTemplate>(
         data: Some(command.start_event().into()),
     };
 
+    if let Some(watch_token) = command.watch_token() {
+        span_async(buck2_data::WatchWaitForChangesStart {}, async {
+            let result = async {
+                // Subscribe first, so that no changes are missed after we check the token.
+                let mut changes = server_ctx.subscribe_file_changes();
+                let command = &command;
+                let inputs = server_ctx
+                    .with_dice_ctx(|server_ctx, ctx| async move {
+                        if !watch_token.is_empty()
+                            && ctx.equality_token().to_string() != watch_token
+                        {
+                            // Files changed since the previous run started, but we do not know which.
+                            return Ok(None);
+                        }
+                        match command.watch_inputs(server_ctx, ctx).await {
+                            Ok(inputs) => Ok(Some(inputs)),
+                            Err(e) => {
+                                // Commands which fail to load might not know their inputs,
+                                // so rerun them on any change.
+                                tracing::debug!("Error collecting inputs to watch: {:#}", e);
+                                Ok(Some(None))
+                            }
+                        }
+                    })
+                    .await?;
+                let inputs = match inputs {
+                    Some(inputs) => inputs,
+                    None => return anyhow::Ok(()),
+                };
+                let affected =
+                    |path: CellPathRef| inputs.as_ref().map_or(true, |i| i.affected_by(path));
+                loop {
+                    match changes.wait(&affected).await? {
+                        FileChangesEvent::Affected => return Ok(()),
+                        FileChangesEvent::Unsynced => {
+                            // Every DICE transaction syncs the file watcher, and reports
+                            // the changes to all subscriptions.
+                            server_ctx.with_dice_ctx(|_, _| async { Ok(()) }).await?;
+                        }
+                    }
+                }
+            }
+            .await;
+            (result, buck2_data::WatchWaitForChangesEnd {})
+        })
+        .await?;
+    }
+
     // refresh our tracing log per command
     TracingLogFile::refresh()?;
 
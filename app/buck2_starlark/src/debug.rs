@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 starlark debug-attach`, which forwards DAP messages between stdin/stdout and the
+//! debugger in the daemon.
+
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_cli_proto::DapRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::content_length_codec::ContentLengthDecoder;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_client_ctx::stream_util::reborrow_stream_for_static;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use futures::stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+use crate::StarlarkCommandCommonOptions;
+use crate::StarlarkSubcommand;
+
+#[derive(Debug, thiserror::Error)]
+enum StarlarkDebugAttachError {
+    #[error("`debug-attach` is not run as a generic `starlark` request")]
+    NotGenericRequest,
+}
+
+/// Sent on behalf of clients which close stdin without disconnecting.
+const DISCONNECT: &str = r#"{"seq":0,"type":"request","command":"disconnect"}"#;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "starlark-debug-attach",
+    about = "Attach a Starlark debugger to the daemon, speaking the Debug Adapter Protocol over stdin and stdout.",
+    long_about = "Attach a Starlark debugger to the daemon, speaking the Debug Adapter Protocol over stdin and stdout.\n\nBreakpoints apply to Starlark evaluated by other buck2 commands while the debugger is attached: BUCK, PACKAGE and .bzl files, rule implementations and BXL. Functions are instrumented when their file is evaluated, so breakpoints in functions from files which were already loaded are only hit once those files are evaluated again."
+)]
+pub struct StarlarkDebugAttachCommand {
+    #[clap(flatten)]
+    common_opts: StarlarkCommandCommonOptions,
+}
+
+#[async_trait]
+impl StarlarkSubcommand for StarlarkDebugAttachCommand {
+    async fn server_execute(
+        &self,
+        _server_ctx: Box<dyn ServerCommandContextTrait>,
+        _stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_server_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        // The client sends this command with the `Dap` method instead, see `attach`.
+        Err(StarlarkDebugAttachError::NotGenericRequest.into())
+    }
+
+    fn common_opts(&self) -> &StarlarkCommandCommonOptions {
+        &self.common_opts
+    }
+}
+
+/// Forward DAP messages until the client or the daemon disconnects.
+pub(crate) async fn attach(
+    buckd: &mut BuckdClientConnector,
+    context: ClientContext,
+    mut ctx: ClientCommandContext,
+) -> ExitResult {
+    let messages = FramedRead::new(ctx.stdin(), ContentLengthDecoder).filter_map(|m| {
+        let m = m.and_then(|m| {
+            Ok(DapRequest {
+                dap_json: String::from_utf8(m.to_vec())?,
+            })
+        });
+
+        futures::future::ready(match m {
+            Ok(m) => Some(m),
+            Err(e) => {
+                let _ignored =
+                    buck2_client_ctx::eprintln!("Could not read message from stdin: `{}`", e);
+                None
+            }
+        })
+    });
+
+    reborrow_stream_for_static(
+        messages,
+        |stream| async move { buckd.with_flushing().dap(context, stream).await },
+        || {
+            Some(DapRequest {
+                dap_json: DISCONNECT.to_owned(),
+            })
+        },
+    )
+    .await??;
+
+    ExitResult::success()
+}
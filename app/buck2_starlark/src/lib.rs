@@ -17,23 +17,34 @@ use buck2_client_ctx::client_ctx::ClientCommandContext;
 use buck2_client_ctx::common::CommonBuildConfigurationOptions;
 use buck2_client_ctx::common::CommonConsoleOptions;
 use buck2_client_ctx::common::CommonDaemonCommandOptions;
+use buck2_client_ctx::common::ConsoleType;
 use buck2_client_ctx::daemon::client::BuckdClientConnector;
 use buck2_client_ctx::daemon::client::StdoutPartialResultHandler;
 use buck2_client_ctx::exit_result::ExitResult;
 use buck2_client_ctx::streaming::StreamingCommand;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use once_cell::sync::Lazy;
 
+use crate::debug::StarlarkDebugAttachCommand;
 use crate::lint::StarlarkLintCommand;
+use crate::repl::StarlarkReplCommand;
+use crate::typecheck::StarlarkTypecheckCommand;
 
+mod debug;
 mod lint;
+mod repl;
 pub mod server;
+mod typecheck;
 mod util;
 
 #[derive(Debug, clap::Subcommand, serde::Serialize, serde::Deserialize)]
 #[clap(name = "starlark", about = "Run Starlark operations")]
 pub enum StarlarkCommand {
     Lint(StarlarkLintCommand),
+    Typecheck(StarlarkTypecheckCommand),
+    DebugAttach(StarlarkDebugAttachCommand),
+    Repl(StarlarkReplCommand),
 }
 
 #[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize, Default)]
@@ -75,6 +86,9 @@ impl StarlarkCommand {
     fn as_subcommand(&self) -> &dyn StarlarkSubcommand {
         match self {
             StarlarkCommand::Lint(cmd) => cmd,
+            StarlarkCommand::Typecheck(cmd) => cmd,
+            StarlarkCommand::DebugAttach(cmd) => cmd,
+            StarlarkCommand::Repl(cmd) => cmd,
         }
     }
 }
@@ -101,6 +115,15 @@ impl StreamingCommand for StarlarkCommand {
 
         let context = ctx.client_context(config_opts, submatches, self.sanitized_argv())?;
 
+        if let StarlarkCommand::DebugAttach(..) = self {
+            // DAP is a bidirectional protocol, so it has a method of its own.
+            return debug::attach(buckd, context, ctx).await;
+        }
+        if let StarlarkCommand::Repl(..) = self {
+            // The REPL sends inputs as it reads them, so it has a method of its own.
+            return repl::repl(buckd, context, ctx).await;
+        }
+
         buckd
             .with_flushing()
             .starlark(
@@ -116,7 +139,20 @@ impl StreamingCommand for StarlarkCommand {
     }
 
     fn console_opts(&self) -> &CommonConsoleOptions {
-        &self.as_subcommand().common_opts().console_opts
+        match self {
+            // Stdout is the protocol channel, or the REPL's prompt is on the terminal, so only
+            // use the simple console.
+            StarlarkCommand::DebugAttach(..) | StarlarkCommand::Repl(..) => {
+                static SIMPLE_CONSOLE: Lazy<CommonConsoleOptions> =
+                    Lazy::new(|| CommonConsoleOptions {
+                        console_type: ConsoleType::Simple,
+                        ui: vec![],
+                        no_interactive_console: true,
+                    });
+                &SIMPLE_CONSOLE
+            }
+            _ => &self.as_subcommand().common_opts().console_opts,
+        }
     }
 
     fn event_log_opts(&self) -> &CommonDaemonCommandOptions {
@@ -126,4 +162,11 @@ impl StreamingCommand for StarlarkCommand {
     fn common_opts(&self) -> &CommonBuildConfigurationOptions {
         &self.as_subcommand().common_opts().config_opts
     }
+
+    fn should_show_waiting_message(&self) -> bool {
+        !matches!(
+            self,
+            StarlarkCommand::DebugAttach(..) | StarlarkCommand::Repl(..)
+        )
+    }
 }
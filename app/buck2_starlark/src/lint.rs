@@ -64,13 +64,13 @@ async fn lint_file(
                 Err(err) => (None, err),
                 Ok(diag) => (diag.span, diag.message),
             };
-            Ok(vec![Lint {
-                location: span.unwrap_or_else(|| FileSpan::new(path_str, content)),
-                short_name: "parse_error".to_owned(),
-                serious: true,
-                problem: format!("{:#}", message),
-                original: "".to_owned(),
-            }])
+            Ok(vec![Lint::new(
+                span.unwrap_or_else(|| FileSpan::new(path_str, content)),
+                "parse_error".to_owned(),
+                true,
+                format!("{:#}", message),
+                "".to_owned(),
+            )])
         }
     }
 }
@@ -0,0 +1,346 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! `buck2 starlark repl`, an interactive prompt which evaluates Starlark in the daemon.
+
+use std::io;
+use std::mem;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_cli_proto::StarlarkReplMessage;
+use buck2_cli_proto::StarlarkReplRequest;
+use buck2_client_ctx::client_ctx::ClientCommandContext;
+use buck2_client_ctx::daemon::client::BuckdClientConnector;
+use buck2_client_ctx::daemon::client::StarlarkReplPartialResultHandler;
+use buck2_client_ctx::events_ctx::PartialResultHandler;
+use buck2_client_ctx::exit_result::ExitResult;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::file_name::FileName;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use dupe::Dupe;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::Context;
+use rustyline::Editor;
+use rustyline::Helper;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::StarlarkCommandCommonOptions;
+use crate::StarlarkSubcommand;
+
+#[derive(Debug, thiserror::Error)]
+enum StarlarkReplError {
+    #[error("`repl` is not run as a generic `starlark` request")]
+    NotGenericRequest,
+}
+
+const HISTORY_FILE: &str = "starlark_repl_history";
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "starlark-repl",
+    about = "Evaluate Starlark interactively in the daemon.",
+    long_about = "Evaluate Starlark interactively in the daemon.\n\nInputs are evaluated like a .bzl file in the current directory, with the prelude loaded, so any .bzl file in the repository can be loaded. The `ctx` global has `uquery(query, *args)` and `cquery(query, *args)`, which return target labels, and `target_node(label)`, which returns the attributes of a target.\n\nA line ending in `:` starts a block, which ends with an empty line. Tab completes names in scope, Ctrl-C discards the current input and Ctrl-D exits."
+)]
+pub struct StarlarkReplCommand {
+    #[clap(flatten)]
+    common_opts: StarlarkCommandCommonOptions,
+}
+
+#[async_trait]
+impl StarlarkSubcommand for StarlarkReplCommand {
+    async fn server_execute(
+        &self,
+        _server_ctx: Box<dyn ServerCommandContextTrait>,
+        _stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_server_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        // The client sends this command with the `StarlarkRepl` method instead, see `repl`.
+        Err(StarlarkReplError::NotGenericRequest.into())
+    }
+
+    fn common_opts(&self) -> &StarlarkCommandCommonOptions {
+        &self.common_opts
+    }
+}
+
+/// Run the REPL until the user exits or the daemon disconnects.
+pub(crate) async fn repl(
+    buckd: &mut BuckdClientConnector,
+    context: ClientContext,
+    ctx: ClientCommandContext,
+) -> ExitResult {
+    let history = ctx
+        .paths()?
+        .daemon_dir()?
+        .path
+        .join(FileName::new(HISTORY_FILE)?);
+    let (inputs, inputs_rx) = mpsc::unbounded_channel();
+    let mut handler = StarlarkReplPartialResultHandler::new();
+    let results = handler
+        .take_results()
+        .expect("results of a new handler have not been taken");
+
+    // Reading lines blocks, so the prompt gets a thread of its own. It isn't joined: if the
+    // daemon goes away while it is waiting for a line, we exit without it.
+    std::thread::Builder::new()
+        .name("starlark-repl".to_owned())
+        .spawn(move || {
+            if let Err(e) = prompt(history, inputs, results) {
+                let _ignored = buck2_client_ctx::eprintln!("{:#}", e);
+            }
+        })?;
+
+    buckd
+        .with_flushing()
+        .starlark_repl(
+            context,
+            UnboundedReceiverStream::new(inputs_rx),
+            &mut handler,
+        )
+        .await??;
+
+    ExitResult::success()
+}
+
+/// Read inputs and print their results. Returning drops `inputs`, which ends the session.
+fn prompt(
+    history: AbsNormPathBuf,
+    inputs: mpsc::UnboundedSender<StarlarkReplRequest>,
+    mut results: mpsc::UnboundedReceiver<StarlarkReplMessage>,
+) -> anyhow::Result<()> {
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let mut editor = Editor::new();
+    editor.set_helper(Some(ReplHelper {
+        names: names.dupe(),
+    }));
+    if let Err(e) = editor.load_history(&history) {
+        match e {
+            ReadlineError::Io(e) if e.kind() == io::ErrorKind::NotFound => {}
+            e => buck2_client_ctx::eprintln!("Failed to load history from `{}`: {}", history, e)?,
+        }
+    }
+
+    // The daemon tells us the names in scope once it's ready.
+    let mut result = match results.blocking_recv() {
+        Some(result) => result,
+        None => return Ok(()),
+    };
+    loop {
+        buck2_client_ctx::print!("{}", result.output)?;
+        if !result.error.is_empty() {
+            buck2_client_ctx::eprintln!("{}", result.error)?;
+        }
+        *names.lock().unwrap() = result.names;
+
+        let input = match read_input(&mut editor)? {
+            Some(input) => input,
+            None => break,
+        };
+        if let Err(e) = editor.save_history(&history) {
+            buck2_client_ctx::eprintln!("Failed to save history to `{}`: {}", history, e)?;
+        }
+        if inputs.send(StarlarkReplRequest { input }).is_err() {
+            break;
+        }
+        result = match results.blocking_recv() {
+            Some(result) => result,
+            None => break,
+        };
+    }
+    Ok(())
+}
+
+/// Read a line, or a block of lines if the first one ends in `:`. Returns `None` on EOF.
+fn read_input(editor: &mut Editor<ReplHelper>) -> anyhow::Result<Option<String>> {
+    let mut input = InputBuffer::default();
+    loop {
+        let prompt = if input.is_empty() { ">>> " } else { "... " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    editor.add_history_entry(line.as_str());
+                }
+                if let Some(input) = input.push_line(&line) {
+                    return Ok(Some(input));
+                }
+            }
+            // Ctrl-C discards the current input.
+            Err(ReadlineError::Interrupted) => input.clear(),
+            Err(ReadlineError::Eof) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Collects lines until they make up a complete input.
+#[derive(Default)]
+struct InputBuffer {
+    input: String,
+}
+
+impl InputBuffer {
+    fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.input.clear();
+    }
+
+    /// Add a line, returning the input if it is now complete. Blank inputs are dropped.
+    fn push_line(&mut self, line: &str) -> Option<String> {
+        let continues = if self.input.is_empty() {
+            line.trim_end().ends_with(':')
+        } else {
+            !line.trim().is_empty()
+        };
+        self.input.push_str(line);
+        self.input.push('\n');
+        if continues {
+            return None;
+        }
+        let input = mem::take(&mut self.input);
+        if input.trim().is_empty() {
+            None
+        } else {
+            Some(input)
+        }
+    }
+}
+
+/// Completes names in scope in the daemon.
+struct ReplHelper {
+    names: Arc<Mutex<Vec<String>>>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(pos, |(i, _)| i);
+        let word = &before[start..];
+        // We don't know the attributes of values, so don't complete after a `.`.
+        if word.is_empty() || before[..start].ends_with('.') {
+            return Ok((pos, Vec::new()));
+        }
+        let candidates = self
+            .names
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use rustyline::history::History;
+
+    use super::*;
+
+    #[test]
+    fn test_input_single_line() {
+        let mut input = InputBuffer::default();
+        assert_eq!(Some("x = 1\n".to_owned()), input.push_line("x = 1"));
+        assert!(input.is_empty());
+        // Blank lines are not sent to the daemon.
+        assert_eq!(None, input.push_line(""));
+        assert_eq!(None, input.push_line("   "));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_input_block() {
+        let mut input = InputBuffer::default();
+        assert_eq!(None, input.push_line("def f():"));
+        assert!(!input.is_empty());
+        assert_eq!(None, input.push_line("    if True:"));
+        assert_eq!(None, input.push_line("        return 1"));
+        assert_eq!(
+            Some("def f():\n    if True:\n        return 1\n\n".to_owned()),
+            input.push_line("")
+        );
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_input_clear() {
+        let mut input = InputBuffer::default();
+        assert_eq!(None, input.push_line("for x in []:"));
+        input.clear();
+        assert!(input.is_empty());
+        assert_eq!(Some("y = 2\n".to_owned()), input.push_line("y = 2"));
+    }
+
+    fn complete(line: &str, pos: usize) -> (usize, Vec<String>) {
+        let helper = ReplHelper {
+            names: Arc::new(Mutex::new(vec![
+                "glob".to_owned(),
+                "getattr".to_owned(),
+                "len".to_owned(),
+                "my_var".to_owned(),
+            ])),
+        };
+        let history = History::new();
+        helper.complete(line, pos, &Context::new(&history)).unwrap()
+    }
+
+    #[test]
+    fn test_complete() {
+        assert_eq!(
+            (0, vec!["glob".to_owned(), "getattr".to_owned()]),
+            complete("g", 1)
+        );
+        assert_eq!((4, vec!["len".to_owned()]), complete("x = le", 6));
+        assert_eq!((4, vec!["my_var".to_owned()]), complete("foo(my_", 7));
+        // Only the text before the cursor is completed.
+        assert_eq!((0, vec!["len".to_owned()]), complete("len(my_var)", 2));
+        assert_eq!((2, Vec::<String>::new()), complete("x ", 2));
+        assert_eq!((0, Vec::<String>::new()), complete("zzz", 3));
+    }
+
+    #[test]
+    fn test_complete_attribute() {
+        // We don't know the attributes of values.
+        assert_eq!((8, Vec::<String>::new()), complete("my_var.g", 8));
+    }
+}
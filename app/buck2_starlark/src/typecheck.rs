@@ -0,0 +1,198 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use buck2_cli_proto::ClientContext;
+use buck2_common::dice::cells::HasCellResolver;
+use buck2_common::dice::data::HasIoProvider;
+use buck2_common::dice::file_ops::HasFileOps;
+use buck2_common::io::IoProvider;
+use buck2_core::cells::CellResolver;
+use buck2_interpreter::file_type::StarlarkFileType;
+use buck2_interpreter::path::OwnedStarlarkPath;
+use buck2_interpreter::path::StarlarkPath;
+use buck2_interpreter_for_build::interpreter::dice_calculation_delegate::HasCalculationDelegate;
+use buck2_interpreter_for_build::interpreter::global_interpreter_state::HasGlobalInterpreterState;
+use buck2_server_ctx::ctx::ServerCommandContextTrait;
+use buck2_server_ctx::ctx::ServerCommandDiceContext;
+use buck2_server_ctx::partial_result_dispatcher::PartialResultDispatcher;
+use starlark::environment::LibraryExtension;
+use starlark::syntax::AstModule;
+use starlark::typing::typecheck_modules;
+use starlark::typing::OracleDocs;
+use starlark::typing::OracleStandard;
+use starlark::typing::TypingOracle;
+
+use crate::util::paths::starlark_files_from_patterns;
+use crate::StarlarkCommandCommonOptions;
+use crate::StarlarkSubcommand;
+
+#[derive(Debug, clap::Parser, serde::Serialize, serde::Deserialize)]
+#[clap(
+    name = "starlark-typecheck",
+    about = "Run the Starlark typechecker.",
+    long_about = "Run the Starlark typechecker.\n\n\
+        The given files (or all the Starlark files in the given directories) are typechecked \
+        together with everything they `load()`, so the types of functions, structs and other \
+        values exported by one module are checked where another module uses them.\n\n\
+        Files and directories can be given as paths, as cell paths like `cell//foo/bar.bzl`, \
+        or as recursive patterns like `//foo/...`."
+)]
+pub struct StarlarkTypecheckCommand {
+    #[clap(flatten)]
+    common_opts: StarlarkCommandCommonOptions,
+
+    #[clap(value_name = "PATTERN", required = true)]
+    patterns: Vec<String>,
+}
+
+/// The name of the file in the typechecker, and its error messages.
+fn module_name(path: &StarlarkPath<'_>, cell_resolver: &CellResolver) -> anyhow::Result<String> {
+    Ok(cell_resolver
+        .resolve_path(path.path().as_ref().as_ref())?
+        .to_string())
+}
+
+async fn parse_file(
+    path: &StarlarkPath<'_>,
+    cell_resolver: &CellResolver,
+    io: &dyn IoProvider,
+) -> anyhow::Result<AstModule> {
+    let dialect = path.file_type().dialect(false);
+    let proj_path = cell_resolver.resolve_path(path.path().as_ref().as_ref())?;
+    let path_str = proj_path.to_string();
+    let content = io
+        .read_file_if_exists(proj_path)
+        .await?
+        .with_context(|| format!("File not found: `{}`", path_str))?;
+    AstModule::parse(&path_str, content, &dialect)
+}
+
+#[async_trait]
+impl StarlarkSubcommand for StarlarkTypecheckCommand {
+    async fn server_execute(
+        &self,
+        server_ctx: Box<dyn ServerCommandContextTrait>,
+        mut stdout: PartialResultDispatcher<buck2_cli_proto::StdoutBytes>,
+        _client_ctx: ClientContext,
+    ) -> anyhow::Result<()> {
+        server_ctx
+            .with_dice_ctx(async move |server_ctx, ctx| {
+                let cell_resolver = ctx.get_cell_resolver().await?;
+                let fs = ctx.file_ops();
+                let io = ctx.global_data().get_io_provider();
+                let global_state = ctx.get_global_interpreter_state().await?;
+
+                let mut stdout = stdout.as_writer();
+                let mut error_count = 0;
+                let files = starlark_files_from_patterns(
+                    &self.patterns,
+                    server_ctx,
+                    &cell_resolver,
+                    &fs,
+                    &*io,
+                )
+                .await?;
+
+                let mut requested = HashSet::new();
+                for file in &files {
+                    requested.insert(module_name(&file.borrow(), &cell_resolver)?);
+                }
+
+                // Parse the requested files, and everything they load, recording where each load goes.
+                // Modules are identified by their project path, since the same file can be loaded
+                // from different cells, or through a cell alias other than its own.
+                let mut modules = Vec::new();
+                let mut resolved_loads: HashMap<(String, String), String> = HashMap::new();
+                let mut seen = requested.clone();
+                let mut todo = files.clone();
+                while let Some(path) = todo.pop() {
+                    let name = module_name(&path.borrow(), &cell_resolver)?;
+                    let ast = match parse_file(&path.borrow(), &cell_resolver, &*io).await {
+                        Ok(ast) => ast,
+                        Err(e) => {
+                            // Errors in files we only load are reported when those files are evaluated.
+                            if requested.contains(&name) {
+                                writeln!(stdout, "{:#}", e)?;
+                                error_count += 1;
+                            }
+                            continue;
+                        }
+                    };
+                    // Resolve the loads with the cell aliases of the cell the file is in, which
+                    // isn't the cell it was loaded as when that was through another cell's alias.
+                    let proj_path =
+                        cell_resolver.resolve_path(path.borrow().path().as_ref().as_ref())?;
+                    let cell = cell_resolver.get_cell_path(&proj_path)?.cell();
+                    let calc = ctx
+                        .get_interpreter_calculator(cell, path.borrow().build_file_cell())
+                        .await?;
+                    for load in ast.loads() {
+                        let dep = match calc.resolve_load(path.borrow(), load.module_id).await {
+                            Ok(dep) => OwnedStarlarkPath::new(dep.borrow().into()),
+                            // Reported when the file is evaluated, and the loaded symbols are just untyped.
+                            Err(_) => continue,
+                        };
+                        let dep_name = module_name(&dep.borrow(), &cell_resolver)?;
+                        resolved_loads
+                            .insert((name.clone(), load.module_id.to_owned()), dep_name.clone());
+                        if seen.insert(dep_name) {
+                            todo.push(dep);
+                        }
+                    }
+                    modules.push(ast);
+                }
+
+                // The buck2 globals differ slightly between file types, but anything we don't know
+                // about is treated as untyped, so the `.bzl` globals are a good enough approximation.
+                let oracle: Vec<Box<dyn TypingOracle>> = vec![
+                    Box::new(OracleStandard::new(LibraryExtension::all())),
+                    Box::new(OracleDocs::new_object(
+                        &global_state
+                            .globals_for_file_type(StarlarkFileType::Bzl)
+                            .documentation(),
+                    )),
+                ];
+                let results = typecheck_modules(&oracle, modules, |name, load| {
+                    resolved_loads
+                        .get(&(name.to_owned(), load.to_owned()))
+                        .cloned()
+                });
+                for (name, res) in &results {
+                    if requested.contains(name) {
+                        error_count += res.errors.len();
+                        for e in &res.errors {
+                            writeln!(stdout, "{:#}", e)?;
+                        }
+                    }
+                }
+
+                if error_count > 0 {
+                    Err(anyhow::anyhow!("Found {} type errors", error_count))
+                } else {
+                    writeln!(
+                        server_ctx.stderr()?,
+                        "Found no type errors in {} files",
+                        files.len()
+                    )?;
+                    Ok(())
+                }
+            })
+            .await
+    }
+
+    fn common_opts(&self) -> &StarlarkCommandCommonOptions {
+        &self.common_opts
+    }
+}
@@ -8,6 +8,7 @@
  */
 
 use std::ops::Deref;
+use std::path::Path;
 
 use async_recursion::async_recursion;
 use buck2_client_ctx::path_arg::PathArg;
@@ -18,10 +19,13 @@ use buck2_common::io::IoProvider;
 use buck2_core::build_file_path::BuildFilePath;
 use buck2_core::bzl::ImportPath;
 use buck2_core::cells::build_file_cell::BuildFileCell;
+use buck2_core::cells::cell_path::CellPath;
+use buck2_core::cells::paths::CellRelativePath;
 use buck2_core::cells::CellResolver;
 use buck2_core::fs::paths::file_name::FileName;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::package::PackageLabel;
+use buck2_core::pattern::maybe_split_cell_alias_and_relative_path;
 use buck2_interpreter::path::BxlFilePath;
 use buck2_interpreter::path::OwnedStarlarkPath;
 use buck2_interpreter::path::PackageFilePath;
@@ -143,3 +147,44 @@ pub(crate) async fn starlark_files(
     }
     Ok(files)
 }
+
+/// Find the paths to apply Starlark to, given as paths, cell paths (e.g. `cell//foo/bar.bzl`)
+/// or recursive patterns (e.g. `//foo/...`). Directories are always searched recursively.
+pub(crate) async fn starlark_files_from_patterns(
+    patterns: &[String],
+    context: &dyn ServerCommandContextTrait,
+    cell_resolver: &CellResolver,
+    fs: &dyn FileOps,
+    io: &dyn IoProvider,
+) -> anyhow::Result<Vec<OwnedStarlarkPath>> {
+    // Cell aliases are resolved relative to the cell we are in, like target patterns.
+    let cwd = cell_resolver.get_cell_path(context.working_dir())?;
+    let cell_alias_resolver = cell_resolver.get(cwd.cell())?.cell_alias_resolver();
+
+    let mut files = Vec::new();
+    for pattern in patterns {
+        let path = match pattern.strip_suffix("...") {
+            Some(dir) if !dir.ends_with("//") => dir.strip_suffix('/').unwrap_or(dir),
+            Some(dir) => dir,
+            None => pattern,
+        };
+        let proj_path = match maybe_split_cell_alias_and_relative_path(path)? {
+            Some((alias, path)) => {
+                let cell_path = CellPath::new(
+                    cell_alias_resolver.resolve(&alias)?,
+                    CellRelativePath::new(path).to_buf(),
+                );
+                cell_resolver.resolve_path(cell_path.as_ref())?
+            }
+            None if path.is_empty() => context.working_dir().to_buf(),
+            None => {
+                let path = context.working_dir_abs().resolve(Path::new(path));
+                let cell_path =
+                    cell_resolver.get_cell_path_from_abs_path(&path, context.project_root())?;
+                cell_resolver.resolve_path(cell_path.as_ref())?
+            }
+        };
+        starlark_file(proj_path, None, cell_resolver, fs, io, &mut files).await?;
+    }
+    Ok(files)
+}
@@ -9,11 +9,13 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::Context;
 use async_trait::async_trait;
+use buck2_build_api::analysis::calculation::profile_analysis_recursively_aggregated;
 use buck2_build_api::analysis::calculation::RuleAnalysisCalculation;
 use buck2_build_api::artifact_groups::calculation::ArtifactGroupCalculation;
 use buck2_build_api::artifact_groups::ArtifactGroup;
@@ -22,6 +24,9 @@ use buck2_build_api::interpreter::rule_defs::cmd_args::SimpleCommandLineArtifact
 use buck2_build_api::interpreter::rule_defs::provider::collection::FrozenProviderCollection;
 use buck2_build_api::interpreter::rule_defs::provider::test_provider::TestProvider;
 use buck2_build_api::nodes::calculation::NodeCalculation;
+use buck2_build_api::nodes::watch_inputs::collect_watch_inputs;
+use buck2_cli_proto::coverage_report_options::Format as CoverageReportFormat;
+use buck2_cli_proto::CoverageReportOptions;
 use buck2_cli_proto::HasClientContext;
 use buck2_cli_proto::TestRequest;
 use buck2_cli_proto::TestResponse;
@@ -32,16 +37,20 @@ use buck2_common::legacy_configs::dice::HasLegacyConfigs;
 use buck2_common::liveliness_observer::LivelinessGuard;
 use buck2_common::pattern::resolve::resolve_target_patterns;
 use buck2_common::pattern::resolve::ResolvedPattern;
+use buck2_common::watch_inputs::WatchInputs;
 use buck2_core::cells::name::CellName;
 use buck2_core::cells::CellResolver;
 use buck2_core::env_helper::EnvHelper;
 use buck2_core::fs::fs_util;
 use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePath;
+use buck2_core::fs::project_rel_path::ProjectRelativePath;
 use buck2_core::fs::project_rel_path::ProjectRelativePathBuf;
 use buck2_core::package::PackageLabel;
 use buck2_core::pattern::pattern_type::ConfiguredProvidersPatternExtra;
 use buck2_core::pattern::pattern_type::ProvidersPatternExtra;
 use buck2_core::pattern::PackageSpec;
+use buck2_core::pattern::ParsedPattern;
 use buck2_core::provider::label::ConfiguredProvidersLabel;
 use buck2_core::provider::label::ProvidersLabel;
 use buck2_core::provider::label::ProvidersName;
@@ -51,6 +60,7 @@ use buck2_core::target::name::TargetName;
 use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_interpreter_for_build::interpreter::calculation::InterpreterCalculation;
 use buck2_node::nodes::eval_result::EvaluationResult;
+use buck2_profile::write_starlark_coverage;
 use buck2_query::query::compatibility::MaybeCompatible;
 use buck2_server_ctx::ctx::ServerCommandContextTrait;
 use buck2_server_ctx::partial_result_dispatcher::NoPartialResult;
@@ -79,6 +89,8 @@ use indexmap::IndexSet;
 use more_futures::cancellable_future::critical_section;
 use serde::Serialize;
 
+use crate::coverage;
+use crate::coverage::CoverageTools;
 use crate::downward_api::BuckTestDownwardApi;
 use crate::executor_launcher::ExecutorLaunch;
 use crate::executor_launcher::ExecutorLauncher;
@@ -98,6 +110,8 @@ pub(crate) struct TestReport {
 struct TestOutcome {
     error_messages: Vec<String>,
     executor_report: ExecutorReport,
+    /// The test targets that were configured, whether or not they were then filtered out.
+    labels: HashSet<ConfiguredProvidersLabel>,
 }
 
 impl TestOutcome {
@@ -178,7 +192,7 @@ struct TestStatuses {
 impl TestStatuses {
     fn ingest(&mut self, result: &TestResult) {
         match result.status {
-            TestStatus::PASS => self.passed.add(&result.name),
+            TestStatus::PASS | TestStatus::CACHED => self.passed.add(&result.name),
             TestStatus::FAIL => self.failed.add(&result.name),
             TestStatus::SKIP => self.skipped.add(&result.name),
             TestStatus::OMITTED => self.skipped.add(&result.name),
@@ -221,6 +235,29 @@ impl ServerCommandTemplate for TestServerCommand {
         }
     }
 
+    fn watch_token(&self) -> Option<&str> {
+        self.req.watch_token.as_deref()
+    }
+
+    async fn watch_inputs(
+        &self,
+        server_ctx: &dyn ServerCommandContextTrait,
+        ctx: DiceTransaction,
+    ) -> anyhow::Result<Option<WatchInputs>> {
+        let parsed_patterns: Vec<ParsedPattern<ConfiguredProvidersPatternExtra>> =
+            parse_patterns_from_cli_args(&ctx, &self.req.target_patterns, server_ctx.working_dir())
+                .await?;
+        let resolved_pattern = resolve_target_patterns(
+            &ctx.get_cell_resolver().await?,
+            &parsed_patterns,
+            &ctx.file_ops(),
+        )
+        .await?;
+        Ok(Some(
+            collect_watch_inputs(&ctx, &parsed_patterns, &resolved_pattern).await?,
+        ))
+    }
+
     async fn command(
         &self,
         server_ctx: &dyn ServerCommandContextTrait,
@@ -236,6 +273,7 @@ async fn test(
     ctx: DiceTransaction,
     request: &TestRequest,
 ) -> anyhow::Result<TestResponse> {
+    let watch_token = ctx.equality_token().to_string();
     let cwd = server_ctx.working_dir();
     let cell_resolver = ctx.get_cell_resolver().await?;
     let working_dir_cell = cell_resolver.find(cwd)?;
@@ -287,11 +325,15 @@ async fn test(
         .as_ref()
         .context("Missing `options`")?;
 
-    let session = TestSession::new(TestSessionOptions {
+    let session = Arc::new(TestSession::new(TestSessionOptions {
         allow_re: options.allow_re,
         force_use_project_relative_paths: options.force_use_project_relative_paths,
         force_run_from_project_root: options.force_run_from_project_root,
-    });
+        // The cache itself is only enabled if `buck2.test_result_cache` was set when the daemon started.
+        // Cached results carry no coverage data, so every test has to run to collect it.
+        use_test_result_cache: !options.disable_test_result_cache && !options.collect_coverage,
+        collect_coverage: options.collect_coverage,
+    }));
 
     let test_outcome = test_targets(
         &ctx,
@@ -305,12 +347,47 @@ async fn test(
             request.build_filtered_targets,
         )),
         &*launcher,
-        session,
-        cell_resolver,
+        session.dupe(),
+        cell_resolver.dupe(),
         working_dir_cell,
     )
     .await?;
 
+    let coverage_report = if options.collect_coverage {
+        Some(
+            write_coverage_report(
+                &ctx,
+                &session,
+                request.coverage_report.as_ref(),
+                &cell_resolver,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+
+    let build_opts = request
+        .build_opts
+        .as_ref()
+        .context("Missing `build_opts`")?;
+    // Analysis of every target must have succeeded to merge its profile.
+    if !build_opts.starlark_coverage_filename.is_empty() && test_outcome.error_messages.is_empty() {
+        let targets = test_outcome
+            .labels
+            .iter()
+            .map(|label| label.target().dupe())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        let profile_data = profile_analysis_recursively_aggregated(&ctx, &targets).await?;
+        write_starlark_coverage(
+            &profile_data,
+            server_ctx.project_root(),
+            Path::new(&build_opts.starlark_coverage_filename),
+        )?;
+    }
+
     // TODO(bobyf) remap exit code for buck reserved exit code
     let exit_code = test_outcome.exit_code().context("No exit code available")?;
 
@@ -363,9 +440,59 @@ async fn test(
         exit_code,
         error_messages: test_outcome.error_messages,
         test_statuses: Some(test_statuses),
+        watch_token,
+        coverage_report,
     })
 }
 
+/// Write the coverage collected during `session` and return the path of the report.
+async fn write_coverage_report(
+    ctx: &DiceComputations,
+    session: &TestSession,
+    options: Option<&CoverageReportOptions>,
+    cell_resolver: &CellResolver,
+) -> anyhow::Result<String> {
+    let format = options.map_or(CoverageReportFormat::Lcov, |o| o.format());
+    let fs = ctx.get_artifact_fs().await?;
+
+    let output = match options.map(|o| o.output.as_str()).filter(|o| !o.is_empty()) {
+        Some(output) => AbsNormPathBuf::from(output.to_owned())?,
+        None => match ctx
+            .get_legacy_config_property(cell_resolver.root_cell(), "test", "coverage_output")
+            .await?
+            .filter(|s| !s.is_empty())
+        {
+            Some(configured) => fs.fs().resolve(
+                ProjectRelativePath::new(&*configured)
+                    .with_context(|| format!("Invalid `test.coverage_output`: {}", configured))?,
+            ),
+            None => {
+                let name = match format {
+                    CoverageReportFormat::Lcov => "coverage/lcov.info",
+                    CoverageReportFormat::Cobertura => "coverage/cobertura.xml",
+                };
+                fs.fs().resolve(
+                    fs.buck_out_path_resolver()
+                        .root()
+                        .join(ForwardRelativePath::unchecked_new(name)),
+                )
+            }
+        },
+    };
+
+    let tools = CoverageTools::from_config(ctx, cell_resolver.root_cell()).await?;
+    coverage::write_report(
+        session.coverage().take(),
+        &tools,
+        format,
+        fs.fs().root(),
+        &output,
+    )
+    .await?;
+
+    Ok(output.to_string())
+}
+
 async fn test_targets(
     ctx: &DiceComputations,
     pattern: ResolvedPattern<ConfiguredProvidersPatternExtra>,
@@ -373,11 +500,10 @@ async fn test_targets(
     external_runner_args: Vec<String>,
     label_filtering: Arc<TestLabelFiltering>,
     launcher: &dyn ExecutorLauncher,
-    session: TestSession,
+    session: Arc<TestSession>,
     cell_resolver: CellResolver,
     working_dir_cell: CellName,
 ) -> anyhow::Result<TestOutcome> {
-    let session = Arc::new(session);
     let (liveliness_observer, _guard) = LivelinessGuard::create();
 
     let tpx_args = {
@@ -481,7 +607,7 @@ async fn test_targets(
 
                     // And finally return our results;
 
-                    anyhow::Ok((driver.build_errors, test_statuses))
+                    anyhow::Ok((driver.build_errors, driver.labels_seen, test_statuses))
                 })
             }
         });
@@ -504,13 +630,14 @@ async fn test_targets(
         "Executor exited without reporting end-of-tests",
     )));
 
-    let (build_errors, executor_report) = test_run
+    let (build_errors, labels, executor_report) = test_run
         .await
         .context("Failed to collect executor report")?;
 
     Ok(TestOutcome {
         error_messages: build_errors,
         executor_report,
+        labels,
     })
 }
 
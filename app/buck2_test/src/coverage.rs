@@ -0,0 +1,719 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Coverage collection for `buck2 test --coverage`.
+//!
+//! Tests that declare a `coverage_format` in their `ExternalRunnerTestInfo` get a coverage
+//! directory allocated as a declared output. Once the test run is over, whatever the tests wrote
+//! there is converted to LCOV, merged across all targets, and written out as a single report.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Context as _;
+use buck2_build_api::interpreter::rule_defs::provider::builtin::external_runner_test_info::TestCoverageFormat;
+use buck2_cli_proto::coverage_report_options::Format as ReportFormat;
+use buck2_common::legacy_configs::dice::HasLegacyConfigs;
+use buck2_core::cells::name::CellName;
+use buck2_core::fs::fs_util;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPath;
+use buck2_core::fs::paths::abs_norm_path::AbsNormPathBuf;
+use buck2_core::fs::paths::forward_rel_path::ForwardRelativePathBuf;
+use buck2_test_api::data::DeclaredOutput;
+use buck2_util::process::async_background_command;
+use dice::DiceComputations;
+
+/// Name of the declared output tests write their coverage data into.
+pub(crate) const COVERAGE_OUTPUT_NAME: &str = "buck2_coverage";
+
+#[derive(Debug, thiserror::Error)]
+enum CoverageError {
+    #[error("Malformed LCOV record on line {0}: `{1}`")]
+    MalformedLcov(usize, String),
+    #[error("LCOV record on line {0} appears outside of a `SF:` section")]
+    RecordOutsideOfFile(usize),
+    #[error("`{0}` exited with {1}: {2}")]
+    ToolFailed(String, std::process::ExitStatus, String),
+    #[error("Cannot locate the binary to export LLVM coverage for")]
+    MissingLlvmBinary,
+}
+
+/// The declared output a test's coverage directory is allocated as.
+pub(crate) fn coverage_output() -> DeclaredOutput {
+    DeclaredOutput {
+        name: ForwardRelativePathBuf::unchecked_new(COVERAGE_OUTPUT_NAME.to_owned()),
+    }
+}
+
+/// The environment variables to set on a test producing coverage in `format`. `{}` in the values
+/// is substituted with the path to the coverage directory.
+pub(crate) fn coverage_env(format: TestCoverageFormat) -> Vec<(&'static str, &'static str)> {
+    let mut env = vec![("BUCK2_COVERAGE_DIR", "{}")];
+    match format {
+        TestCoverageFormat::Lcov => {}
+        TestCoverageFormat::Llvm => env.push(("LLVM_PROFILE_FILE", "{}/%p-%m.profraw")),
+        TestCoverageFormat::Gcov => env.push(("GCOV_PREFIX", "{}")),
+        TestCoverageFormat::Python => env.push(("COVERAGE_FILE", "{}/.coverage")),
+    }
+    env
+}
+
+/// Coverage data produced by a single test execution.
+#[derive(Debug)]
+pub(crate) struct CollectedCoverage {
+    pub(crate) target: String,
+    pub(crate) format: TestCoverageFormat,
+    pub(crate) dir: AbsNormPathBuf,
+    /// The test binary, which `llvm-cov` needs to map profiles back to sources.
+    pub(crate) binary: Option<PathBuf>,
+}
+
+/// Accumulates coverage directories over the course of a test session.
+#[derive(Default)]
+pub(crate) struct CoverageCollector {
+    collected: Mutex<Vec<CollectedCoverage>>,
+}
+
+impl CoverageCollector {
+    pub(crate) fn record(&self, coverage: CollectedCoverage) {
+        self.collected.lock().unwrap().push(coverage);
+    }
+
+    pub(crate) fn take(&self) -> Vec<CollectedCoverage> {
+        std::mem::take(&mut *self.collected.lock().unwrap())
+    }
+}
+
+/// External tools used to convert native coverage formats to LCOV. Configurable in the `[test]`
+/// section of the root cell's buckconfig.
+pub(crate) struct CoverageTools {
+    llvm_profdata: String,
+    llvm_cov: String,
+    lcov: String,
+    python: String,
+}
+
+impl CoverageTools {
+    pub(crate) async fn from_config(
+        ctx: &DiceComputations,
+        cell: CellName,
+    ) -> anyhow::Result<Self> {
+        async fn get(
+            ctx: &DiceComputations,
+            cell: CellName,
+            key: &str,
+            default: &str,
+        ) -> anyhow::Result<String> {
+            Ok(ctx
+                .get_legacy_config_property(cell, "test", key)
+                .await?
+                .filter(|s| !s.is_empty())
+                .map_or_else(|| default.to_owned(), |s| s.to_string()))
+        }
+
+        Ok(Self {
+            llvm_profdata: get(ctx, cell, "coverage_llvm_profdata", "llvm-profdata").await?,
+            llvm_cov: get(ctx, cell, "coverage_llvm_cov", "llvm-cov").await?,
+            lcov: get(ctx, cell, "coverage_lcov", "lcov").await?,
+            python: get(ctx, cell, "coverage_python", "python3").await?,
+        })
+    }
+}
+
+/// Convert everything collected into a single report and write it to `output`.
+///
+/// A target whose coverage cannot be converted produces a warning rather than failing the whole
+/// report, since the remaining targets' data is still useful.
+pub(crate) async fn write_report(
+    collected: Vec<CollectedCoverage>,
+    tools: &CoverageTools,
+    format: ReportFormat,
+    project_root: &AbsNormPath,
+    output: &AbsNormPath,
+) -> anyhow::Result<()> {
+    let mut data = CoverageData::default();
+
+    for coverage in collected {
+        let res = match to_lcov(&coverage, tools).await {
+            Ok(lcov) => data.merge_lcov(&lcov),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = res {
+            buck2_events::dispatch::console_message(format!(
+                "Warning: ignoring coverage for `{}`: {:#}",
+                coverage.target, e
+            ));
+        }
+    }
+
+    let report = match format {
+        ReportFormat::Lcov => data.to_lcov(),
+        ReportFormat::Cobertura => {
+            data.to_cobertura(project_root.as_path(), chrono::Utc::now().timestamp())
+        }
+    };
+
+    if let Some(parent) = output.parent() {
+        fs_util::create_dir_all(parent)?;
+    }
+    fs_util::write(output, report)
+        .with_context(|| format!("Error writing coverage report to `{}`", output))
+}
+
+async fn to_lcov(coverage: &CollectedCoverage, tools: &CoverageTools) -> anyhow::Result<String> {
+    let dir = coverage.dir.as_path();
+    let mut lcov = String::new();
+
+    match coverage.format {
+        TestCoverageFormat::Lcov => {
+            for file in files_with_extension(dir, &["info", "lcov"])? {
+                lcov.push_str(&fs_util::read_to_string(&file)?);
+                lcov.push('\n');
+            }
+        }
+        TestCoverageFormat::Llvm => {
+            let profraws = files_with_extension(dir, &["profraw"])?;
+            if profraws.is_empty() {
+                return Ok(lcov);
+            }
+            let binary = coverage
+                .binary
+                .as_ref()
+                .ok_or(CoverageError::MissingLlvmBinary)?;
+            let merged = dir.join("buck2-merged.profdata");
+
+            let mut merge = async_background_command(&tools.llvm_profdata);
+            merge.arg("merge").arg("-sparse").arg("-o").arg(&merged);
+            merge.args(&profraws);
+            run_tool(&tools.llvm_profdata, merge).await?;
+
+            let mut export = async_background_command(&tools.llvm_cov);
+            export
+                .arg("export")
+                .arg("-format=lcov")
+                .arg("-instr-profile")
+                .arg(&merged)
+                .arg(binary);
+            lcov = run_tool(&tools.llvm_cov, export).await?;
+        }
+        TestCoverageFormat::Gcov => {
+            let out = dir.join("buck2-gcov.info");
+            let mut capture = async_background_command(&tools.lcov);
+            capture
+                .arg("--capture")
+                .arg("--directory")
+                .arg(dir)
+                .arg("--output-file")
+                .arg(&out);
+            run_tool(&tools.lcov, capture).await?;
+            lcov = fs_util::read_to_string(&out)?;
+        }
+        TestCoverageFormat::Python => {
+            for (i, data_file) in python_data_files(dir)?.into_iter().enumerate() {
+                let out = dir.join(format!("buck2-python-{}.info", i));
+                let mut export = async_background_command(&tools.python);
+                export
+                    .arg("-m")
+                    .arg("coverage")
+                    .arg("lcov")
+                    .arg("--data-file")
+                    .arg(&data_file)
+                    .arg("-o")
+                    .arg(&out);
+                run_tool(&tools.python, export).await?;
+                lcov.push_str(&fs_util::read_to_string(&out)?);
+                lcov.push('\n');
+            }
+        }
+    }
+
+    Ok(lcov)
+}
+
+async fn run_tool(name: &str, mut command: tokio::process::Command) -> anyhow::Result<String> {
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("Error spawning `{}`", name))?;
+    if !output.status.success() {
+        return Err(CoverageError::ToolFailed(
+            name.to_owned(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn sorted_files(dir: &Path, keep: impl Fn(&str) -> bool) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => {
+            return Err(
+                anyhow::Error::from(e).context(format!("Error listing `{}`", dir.display()))
+            );
+        }
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && entry.file_name().to_str().map_or(false, &keep) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn files_with_extension(dir: &Path, extensions: &[&str]) -> anyhow::Result<Vec<PathBuf>> {
+    sorted_files(dir, |name| {
+        Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map_or(false, |e| extensions.contains(&e))
+    })
+}
+
+/// `coverage.py` writes `.coverage`, or `.coverage.<suffix>` when running in parallel mode.
+fn python_data_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    sorted_files(dir, |name| {
+        name == ".coverage" || name.starts_with(".coverage.")
+    })
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct FunctionCoverage {
+    line: u64,
+    hits: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FileCoverage {
+    functions: BTreeMap<String, FunctionCoverage>,
+    /// Hit counts by line number.
+    lines: BTreeMap<u64, u64>,
+    /// Keyed by (line, block, branch). `None` means the branch was never evaluated.
+    branches: BTreeMap<(u64, u64, u64), Option<u64>>,
+}
+
+/// Coverage merged across any number of LCOV tracefiles, keyed by source file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct CoverageData {
+    files: BTreeMap<String, FileCoverage>,
+}
+
+impl CoverageData {
+    /// Merge an LCOV tracefile into this data. Summary records (`LF`, `LH`, etc.) are ignored and
+    /// recomputed on output.
+    pub(crate) fn merge_lcov(&mut self, lcov: &str) -> anyhow::Result<()> {
+        let mut current: Option<&mut FileCoverage> = None;
+
+        for (i, line) in lcov.lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line == "end_of_record" {
+                if line == "end_of_record" {
+                    current = None;
+                }
+                continue;
+            }
+
+            let malformed = || CoverageError::MalformedLcov(line_no, line.to_owned());
+            let (tag, value) = line.split_once(':').ok_or_else(malformed)?;
+
+            if tag == "SF" {
+                current = Some(self.files.entry(value.to_owned()).or_default());
+                continue;
+            }
+
+            let file = match tag {
+                "FN" | "FNDA" | "DA" | "BRDA" => current
+                    .as_deref_mut()
+                    .ok_or(CoverageError::RecordOutsideOfFile(line_no))?,
+                // `TN` and the summary records carry nothing we need.
+                _ => continue,
+            };
+
+            let number = |s: &str| s.trim().parse::<u64>().map_err(|_| malformed());
+
+            match tag {
+                "FN" => {
+                    let (fn_line, name) = value.split_once(',').ok_or_else(malformed)?;
+                    file.functions.entry(name.to_owned()).or_default().line = number(fn_line)?;
+                }
+                "FNDA" => {
+                    let (hits, name) = value.split_once(',').ok_or_else(malformed)?;
+                    file.functions.entry(name.to_owned()).or_default().hits += number(hits)?;
+                }
+                "DA" => {
+                    // `DA` may carry a trailing checksum, which we drop.
+                    let mut parts = value.split(',');
+                    let da_line = number(parts.next().ok_or_else(malformed)?)?;
+                    let hits = number(parts.next().ok_or_else(malformed)?)?;
+                    *file.lines.entry(da_line).or_default() += hits;
+                }
+                "BRDA" => {
+                    let parts: Vec<&str> = value.split(',').collect();
+                    if parts.len() != 4 {
+                        return Err(malformed().into());
+                    }
+                    let key = (number(parts[0])?, number(parts[1])?, number(parts[2])?);
+                    let taken = match parts[3] {
+                        "-" => None,
+                        taken => Some(number(taken)?),
+                    };
+                    let entry = file.branches.entry(key).or_default();
+                    *entry = match (*entry, taken) {
+                        (Some(a), Some(b)) => Some(a + b),
+                        (a, b) => a.or(b),
+                    };
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for (path, file) in &self.files {
+            writeln!(out, "TN:").unwrap();
+            writeln!(out, "SF:{}", path).unwrap();
+            for (name, f) in &file.functions {
+                writeln!(out, "FN:{},{}", f.line, name).unwrap();
+            }
+            for (name, f) in &file.functions {
+                writeln!(out, "FNDA:{},{}", f.hits, name).unwrap();
+            }
+            writeln!(out, "FNF:{}", file.functions.len()).unwrap();
+            writeln!(
+                out,
+                "FNH:{}",
+                file.functions.values().filter(|f| f.hits > 0).count()
+            )
+            .unwrap();
+            for ((line, block, branch), taken) in &file.branches {
+                match taken {
+                    Some(taken) => writeln!(out, "BRDA:{},{},{},{}", line, block, branch, taken),
+                    None => writeln!(out, "BRDA:{},{},{},-", line, block, branch),
+                }
+                .unwrap();
+            }
+            let (branches_covered, branches_valid) = file.branch_totals();
+            writeln!(out, "BRF:{}", branches_valid).unwrap();
+            writeln!(out, "BRH:{}", branches_covered).unwrap();
+            for (line, hits) in &file.lines {
+                writeln!(out, "DA:{},{}", line, hits).unwrap();
+            }
+            let (lines_covered, lines_valid) = file.line_totals();
+            writeln!(out, "LF:{}", lines_valid).unwrap();
+            writeln!(out, "LH:{}", lines_covered).unwrap();
+            writeln!(out, "end_of_record").unwrap();
+        }
+        out
+    }
+
+    /// Render a Cobertura XML report. Files under `source_root` are reported relative to it, and
+    /// grouped into one package per directory.
+    pub(crate) fn to_cobertura(&self, source_root: &Path, timestamp: i64) -> String {
+        let mut packages = BTreeMap::<String, Vec<(String, &FileCoverage)>>::new();
+        for (path, file) in &self.files {
+            let path = Path::new(path)
+                .strip_prefix(source_root)
+                .map_or_else(|_| path.clone(), |p| p.to_string_lossy().into_owned());
+            let package = match path.rfind('/') {
+                Some(i) => path[..i].replace('/', "."),
+                None => String::new(),
+            };
+            packages.entry(package).or_default().push((path, file));
+        }
+
+        let mut out = String::new();
+        let (lc, lv, bc, bv) = totals(self.files.values());
+        writeln!(out, r#"<?xml version="1.0" ?>"#).unwrap();
+        writeln!(
+            out,
+            r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"<coverage line-rate="{}" branch-rate="{}" lines-covered="{}" lines-valid="{}" branches-covered="{}" branches-valid="{}" complexity="0" version="buck2" timestamp="{}">"#,
+            rate(lc, lv),
+            rate(bc, bv),
+            lc,
+            lv,
+            bc,
+            bv,
+            timestamp
+        )
+        .unwrap();
+        writeln!(out, "  <sources>").unwrap();
+        writeln!(
+            out,
+            "    <source>{}</source>",
+            xml_escape(&source_root.to_string_lossy())
+        )
+        .unwrap();
+        writeln!(out, "  </sources>").unwrap();
+        writeln!(out, "  <packages>").unwrap();
+        for (package, files) in &packages {
+            let (lc, lv, bc, bv) = totals(files.iter().map(|(_, f)| *f));
+            writeln!(
+                out,
+                r#"    <package name="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+                xml_escape(package),
+                rate(lc, lv),
+                rate(bc, bv)
+            )
+            .unwrap();
+            writeln!(out, "      <classes>").unwrap();
+            for (path, file) in files {
+                file.write_cobertura_class(&mut out, path);
+            }
+            writeln!(out, "      </classes>").unwrap();
+            writeln!(out, "    </package>").unwrap();
+        }
+        writeln!(out, "  </packages>").unwrap();
+        writeln!(out, "</coverage>").unwrap();
+        out
+    }
+}
+
+impl FileCoverage {
+    fn line_totals(&self) -> (usize, usize) {
+        (
+            self.lines.values().filter(|h| **h > 0).count(),
+            self.lines.len(),
+        )
+    }
+
+    fn branch_totals(&self) -> (usize, usize) {
+        (
+            self.branches
+                .values()
+                .filter(|t| matches!(t, Some(t) if *t > 0))
+                .count(),
+            self.branches.len(),
+        )
+    }
+
+    fn write_cobertura_class(&self, out: &mut String, path: &str) {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let (lc, lv) = self.line_totals();
+        let (bc, bv) = self.branch_totals();
+        writeln!(
+            out,
+            r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+            xml_escape(name),
+            xml_escape(path),
+            rate(lc, lv),
+            rate(bc, bv)
+        )
+        .unwrap();
+
+        writeln!(out, "          <methods>").unwrap();
+        for (fn_name, f) in &self.functions {
+            writeln!(
+                out,
+                r#"            <method name="{}" signature="" line-rate="{}" branch-rate="0">"#,
+                xml_escape(fn_name),
+                if f.hits > 0 { "1" } else { "0" }
+            )
+            .unwrap();
+            writeln!(
+                out,
+                r#"              <lines><line number="{}" hits="{}"/></lines>"#,
+                f.line, f.hits
+            )
+            .unwrap();
+            writeln!(out, "            </method>").unwrap();
+        }
+        writeln!(out, "          </methods>").unwrap();
+
+        writeln!(out, "          <lines>").unwrap();
+        for (line, hits) in &self.lines {
+            let branches: Vec<_> = self
+                .branches
+                .range((*line, 0, 0)..=(*line, u64::MAX, u64::MAX))
+                .map(|(_, t)| *t)
+                .collect();
+            if branches.is_empty() {
+                writeln!(
+                    out,
+                    r#"            <line number="{}" hits="{}"/>"#,
+                    line, hits
+                )
+                .unwrap();
+            } else {
+                let taken = branches
+                    .iter()
+                    .filter(|t| matches!(t, Some(t) if *t > 0))
+                    .count();
+                writeln!(
+                    out,
+                    r#"            <line number="{}" hits="{}" branch="true" condition-coverage="{}% ({}/{})"/>"#,
+                    line,
+                    hits,
+                    taken * 100 / branches.len(),
+                    taken,
+                    branches.len()
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out, "          </lines>").unwrap();
+        writeln!(out, "        </class>").unwrap();
+    }
+}
+
+/// Sum (lines covered, lines valid, branches covered, branches valid) over `files`.
+fn totals<'a>(files: impl Iterator<Item = &'a FileCoverage>) -> (usize, usize, usize, usize) {
+    files.fold((0, 0, 0, 0), |acc, f| {
+        let (lc, lv) = f.line_totals();
+        let (bc, bv) = f.branch_totals();
+        (acc.0 + lc, acc.1 + lv, acc.2 + bc, acc.3 + bv)
+    })
+}
+
+fn rate(covered: usize, valid: usize) -> String {
+    if valid == 0 {
+        "1".to_owned()
+    } else {
+        format!("{:.4}", covered as f64 / valid as f64)
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = "TN:
+SF:/repo/src/lib.rs
+FN:3,foo
+FNDA:1,foo
+FN:10,bar
+FNDA:0,bar
+BRDA:4,0,0,1
+BRDA:4,0,1,-
+DA:3,1
+DA:4,1
+DA:10,0
+LF:3
+LH:2
+end_of_record
+";
+
+    const SECOND: &str = "SF:/repo/src/lib.rs
+FNDA:2,bar
+BRDA:4,0,1,3
+DA:10,2
+DA:11,1,abcdef
+end_of_record
+SF:/repo/main.rs
+DA:1,1
+end_of_record
+";
+
+    #[test]
+    fn test_merge_lcov() -> anyhow::Result<()> {
+        let mut data = CoverageData::default();
+        data.merge_lcov(FIRST)?;
+        data.merge_lcov(SECOND)?;
+
+        let lib = &data.files["/repo/src/lib.rs"];
+        assert_eq!(lib.functions["foo"], FunctionCoverage { line: 3, hits: 1 });
+        assert_eq!(lib.functions["bar"], FunctionCoverage { line: 10, hits: 2 });
+        assert_eq!(lib.lines.get(&10), Some(&2));
+        assert_eq!(lib.lines.get(&11), Some(&1));
+        assert_eq!(lib.branches.get(&(4, 0, 0)), Some(&Some(1)));
+        assert_eq!(lib.branches.get(&(4, 0, 1)), Some(&Some(3)));
+        assert_eq!(data.files["/repo/main.rs"].lines.get(&1), Some(&1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_lcov_roundtrip() -> anyhow::Result<()> {
+        let mut data = CoverageData::default();
+        data.merge_lcov(FIRST)?;
+
+        let written = data.to_lcov();
+        assert!(written.contains("LF:3\nLH:2\n"));
+        assert!(written.contains("BRDA:4,0,1,-\nBRF:2\nBRH:1\n"));
+
+        let mut reparsed = CoverageData::default();
+        reparsed.merge_lcov(&written)?;
+        assert_eq!(data, reparsed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_malformed_lcov() {
+        let mut data = CoverageData::default();
+        let err = data.merge_lcov("SF:a.rs\nDA:x,1\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+
+        let err = data.merge_lcov("DA:1,1\n").unwrap_err();
+        assert!(err.to_string().contains("outside"), "{}", err);
+    }
+
+    #[test]
+    fn test_cobertura() -> anyhow::Result<()> {
+        let mut data = CoverageData::default();
+        data.merge_lcov(FIRST)?;
+        data.merge_lcov("SF:/repo/a&b.rs\nDA:1,0\nend_of_record\n")?;
+
+        let xml = data.to_cobertura(Path::new("/repo"), 1234);
+        assert!(xml.contains(
+            r#"lines-covered="2" lines-valid="4" branches-covered="1" branches-valid="2" complexity="0" version="buck2" timestamp="1234""#
+        ));
+        assert!(xml.contains(r#"<package name="src" line-rate="0.6667""#));
+        assert!(xml.contains(r#"<class name="a&amp;b.rs" filename="a&amp;b.rs""#));
+        assert!(xml.contains(
+            r#"<line number="4" hits="1" branch="true" condition-coverage="50% (1/2)"/>"#
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_coverage_env() {
+        assert_eq!(
+            coverage_env(TestCoverageFormat::Llvm),
+            vec![
+                ("BUCK2_COVERAGE_DIR", "{}"),
+                ("LLVM_PROFILE_FILE", "{}/%p-%m.profraw")
+            ]
+        );
+        assert_eq!(
+            coverage_env(TestCoverageFormat::Lcov),
+            vec![("BUCK2_COVERAGE_DIR", "{}")]
+        );
+    }
+}
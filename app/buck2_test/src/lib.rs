@@ -12,6 +12,7 @@
 #![feature(async_closure)]
 
 pub mod command;
+pub(crate) mod coverage;
 pub mod downward_api;
 pub mod executor_launcher;
 pub mod orchestrator;
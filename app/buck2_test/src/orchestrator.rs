@@ -13,6 +13,7 @@ use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::sync::Arc;
 use std::time::Duration;
+use std::time::SystemTime;
 
 use anyhow::Context as _;
 use async_trait::async_trait;
@@ -60,6 +61,7 @@ use buck2_execute::execute::blocking::HasBlockingExecutor;
 use buck2_execute::execute::claim::MutexClaimManager;
 use buck2_execute::execute::command_executor::CommandExecutor;
 use buck2_execute::execute::dice_data::CommandExecutorResponse;
+use buck2_execute::execute::dice_data::GetReClient;
 use buck2_execute::execute::dice_data::HasCommandExecutor;
 use buck2_execute::execute::environment_inheritance::EnvironmentInheritance;
 use buck2_execute::execute::manager::CommandExecutionManager;
@@ -74,6 +76,9 @@ use buck2_execute::execute::result::CommandExecutionReport;
 use buck2_execute::execute::result::CommandExecutionResult;
 use buck2_execute::execute::result::CommandExecutionStatus;
 use buck2_execute::execute::target::CommandExecutionTarget;
+use buck2_execute::execute::test_result_cache;
+use buck2_execute::execute::test_result_cache::HasTestResultCache;
+use buck2_execute::execute::test_result_cache::TestCacheExecutor;
 use buck2_execute::materialize::materializer::HasMaterializer;
 use buck2_execute_impl::executors::local::apply_local_execution_environment;
 use buck2_execute_impl::executors::local::create_output_dirs;
@@ -105,6 +110,8 @@ use sorted_vector_map::SortedVectorMap;
 use starlark::values::FrozenRef;
 use uuid::Uuid;
 
+use crate::coverage;
+use crate::coverage::CollectedCoverage;
 use crate::session::TestSession;
 use crate::translations;
 
@@ -168,10 +175,10 @@ impl TestOrchestrator for BuckTestOrchestrator {
         metadata: DisplayMetadata,
         test_target: ConfiguredTargetHandle,
         cmd: Vec<ArgValue>,
-        env: SortedVectorMap<String, ArgValue>,
+        mut env: SortedVectorMap<String, ArgValue>,
         timeout: Duration,
         host_sharing_requirements: HostSharingRequirements,
-        pre_create_dirs: Vec<DeclaredOutput>,
+        mut pre_create_dirs: Vec<DeclaredOutput>,
         executor_override: Option<ExecutorConfigOverride>,
     ) -> anyhow::Result<ExecutionResult2> {
         self.liveliness_observer.require_alive().await?;
@@ -181,7 +188,26 @@ impl TestOrchestrator for BuckTestOrchestrator {
         let fs = self.dice.get_artifact_fs().await?;
 
         let test_info = self.get_test_info(&test_target).await?;
-        let executor = self
+
+        let coverage_format = if self.session.options().collect_coverage {
+            test_info.coverage_format()
+        } else {
+            None
+        };
+        if let Some(format) = coverage_format {
+            for (var, value) in coverage::coverage_env(format) {
+                env.insert(
+                    var.to_owned(),
+                    ArgValue {
+                        content: ArgValueContent::DeclaredOutput(coverage::coverage_output()),
+                        format: Some(value.to_owned()),
+                    },
+                );
+            }
+            pre_create_dirs.push(coverage::coverage_output());
+        }
+
+        let (executor, cache_executor) = self
             .get_test_executor(&test_target, &test_info, executor_override, &fs)
             .await?;
         let test_executable_expanded = self
@@ -203,6 +229,10 @@ impl TestOrchestrator for BuckTestOrchestrator {
             supports_re,
             declared_outputs,
         } = test_executable_expanded;
+        // `llvm-cov` needs the instrumented binary, which is the program being run.
+        let coverage_binary = expanded_cmd
+            .first()
+            .map(|program| fs.fs().resolve(&cwd).as_path().join(program));
         let execution_request = self
             .create_command_execution_request(
                 cwd,
@@ -214,6 +244,43 @@ impl TestOrchestrator for BuckTestOrchestrator {
             )
             .await?;
 
+        let result_cache = match self.dice.per_transaction_data().get_test_result_cache() {
+            Some(cache) if self.session.options().use_test_result_cache => {
+                test_result_cache::cache_key(
+                    &execution_request,
+                    cache_executor.kind,
+                    executor.re_platform(),
+                    self.digest_config,
+                )
+                .map(|key| (cache, key))
+            }
+            _ => None,
+        };
+
+        let re_client = self.dice.per_transaction_data().get_re_client();
+        if let Some((cache, key)) = &result_cache {
+            let cached = match (cache.lookup(key), cache_executor.re_use_case) {
+                (Some(cached), _) => Some(cached),
+                (None, Some(use_case)) => {
+                    cache
+                        .re_lookup(&re_client, use_case, key, self.digest_config)
+                        .await
+                }
+                (None, None) => None,
+            };
+            if let Some(cached) = cached {
+                return Ok(ExecutionResult2 {
+                    status: ExecutionStatus::Finished { exitcode: 0 },
+                    stdout: ExecutionStream::Inline(cached.stdout),
+                    stderr: ExecutionStream::Inline(cached.stderr),
+                    outputs: HashMap::new(),
+                    start_time: SystemTime::now(),
+                    execution_time: Duration::ZERO,
+                    cached: true,
+                });
+            }
+        }
+
         let (stdout, stderr, status, timing, outputs) = self
             .execute_shared(
                 &test_target,
@@ -228,7 +295,22 @@ impl TestOrchestrator for BuckTestOrchestrator {
 
         self.liveliness_observer.require_alive().await?;
 
-        let (outputs, paths_to_materialize) = outputs
+        if let (
+            Some((cache, key)),
+            ExecutionStatus::Finished { exitcode: 0 },
+            ExecutionStream::Inline(stdout),
+            ExecutionStream::Inline(stderr),
+        ) = (&result_cache, &status, &stdout, &stderr)
+        {
+            cache.store(key, stdout, stderr);
+            if let (Some(use_case), true) = (cache_executor.re_use_case, cache_executor.re_upload) {
+                cache
+                    .re_store(&re_client, use_case, key, stdout.clone(), stderr.clone())
+                    .await;
+            }
+        }
+
+        let (outputs, paths_to_materialize): (HashMap<_, _>, Vec<_>) = outputs
             .into_iter()
             .map(|test_path| {
                 let project_path = fs.buck_out_path_resolver().resolve_test(&test_path);
@@ -249,6 +331,17 @@ impl TestOrchestrator for BuckTestOrchestrator {
             .await
             .context("Error materializing test outputs")?;
 
+        if let Some(format) = coverage_format {
+            if let Some(Output::LocalPath(dir)) = outputs.get(&coverage::coverage_output()) {
+                self.session.coverage().record(CollectedCoverage {
+                    target: test_target.to_string(),
+                    format,
+                    dir: dir.clone(),
+                    binary: coverage_binary,
+                });
+            }
+        }
+
         Ok(ExecutionResult2 {
             status,
             stdout,
@@ -256,6 +349,7 @@ impl TestOrchestrator for BuckTestOrchestrator {
             outputs,
             start_time: timing.start_time,
             execution_time: timing.execution_time,
+            cached: false,
         })
     }
 
@@ -322,7 +416,7 @@ impl TestOrchestrator for BuckTestOrchestrator {
 
         let test_info = self.get_test_info(&test_target).await?;
         // Tests are not run, so there is no executor override.
-        let executor = self
+        let (executor, _cache_executor) = self
             .get_test_executor(&test_target, &test_info, None, &fs)
             .await?;
         let test_executable_expanded = self
@@ -523,7 +617,7 @@ impl BuckTestOrchestrator {
         fs: &ArtifactFs,
         test_target_node: &ConfiguredTargetNode,
         executor_override: Option<&CommandExecutorConfig>,
-    ) -> anyhow::Result<CommandExecutor> {
+    ) -> anyhow::Result<(CommandExecutor, TestCacheExecutor)> {
         let executor_config = match executor_override {
             Some(o) => o,
             None => test_target_node
@@ -536,7 +630,7 @@ impl BuckTestOrchestrator {
             self.dice.get_command_executor(fs, executor_config)?;
         let executor =
             CommandExecutor::new(executor, fs.clone(), executor_config.options, platform);
-        Ok(executor)
+        Ok((executor, TestCacheExecutor::from_config(executor_config)))
     }
 
     async fn get_test_info(
@@ -561,7 +655,7 @@ impl BuckTestOrchestrator {
         test_info: &FrozenExternalRunnerTestInfo,
         executor_override: Option<ExecutorConfigOverride>,
         fs: &ArtifactFs,
-    ) -> anyhow::Result<CommandExecutor> {
+    ) -> anyhow::Result<(CommandExecutor, TestCacheExecutor)> {
         // NOTE: get_providers() implicitly calls this already but it's not the end of the world
         // since this will get cached in DICE.
         let node = self
@@ -19,12 +19,18 @@ use chrono::Local;
 use dashmap::DashMap;
 use dupe::Dupe;
 
+use crate::coverage::CoverageCollector;
+
 #[derive(Debug, Clone, Copy, Dupe, Default)]
 pub struct TestSessionOptions {
     /// Whether this session should allow things to run on RE.
     pub allow_re: bool,
     pub force_use_project_relative_paths: bool,
     pub force_run_from_project_root: bool,
+    /// Whether passing results may be served from, and stored in, the test result cache.
+    pub use_test_result_cache: bool,
+    /// Whether tests that declare a `coverage_format` should have their coverage collected.
+    pub collect_coverage: bool,
 }
 
 /// The state of a buck2 test command.
@@ -41,6 +47,8 @@ pub struct TestSession {
     /// Options overriding the behavior of tests executed in this session. This is primarily
     /// intended for unstable or debugging features.
     options: TestSessionOptions,
+    /// Coverage produced by tests in this session, if `collect_coverage` is set.
+    coverage: CoverageCollector,
 }
 
 impl TestSession {
@@ -57,6 +65,7 @@ impl TestSession {
             labels: DashMap::new(),
             prefix,
             options,
+            coverage: CoverageCollector::default(),
         }
     }
 
@@ -64,6 +73,10 @@ impl TestSession {
         self.options
     }
 
+    pub(crate) fn coverage(&self) -> &CoverageCollector {
+        &self.coverage
+    }
+
     pub fn prefix(&self) -> &ForwardRelativePath {
         self.prefix.as_ref()
     }
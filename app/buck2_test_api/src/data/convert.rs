@@ -211,6 +211,7 @@ impl TryFrom<i32> for TestStatus {
             buck2_test_proto::TestStatus::Rerun => TestStatus::RERUN,
             buck2_test_proto::TestStatus::ListingSuccess => TestStatus::LISTING_SUCCESS,
             buck2_test_proto::TestStatus::ListingFailed => TestStatus::LISTING_FAILED,
+            buck2_test_proto::TestStatus::Cached => TestStatus::CACHED,
         })
     }
 }
@@ -230,6 +231,7 @@ impl TryInto<i32> for TestStatus {
             TestStatus::RERUN => buck2_test_proto::TestStatus::Rerun,
             TestStatus::LISTING_SUCCESS => buck2_test_proto::TestStatus::ListingSuccess,
             TestStatus::LISTING_FAILED => buck2_test_proto::TestStatus::ListingFailed,
+            TestStatus::CACHED => buck2_test_proto::TestStatus::Cached,
         } as i32)
     }
 }
@@ -594,6 +596,7 @@ impl TryInto<buck2_test_proto::ExecutionResult2> for ExecutionResult2 {
                     .try_into()?,
             ),
             execution_time: Some(self.execution_time.try_into()?),
+            cached: self.cached,
         })
     }
 }
@@ -609,6 +612,7 @@ impl TryFrom<buck2_test_proto::ExecutionResult2> for ExecutionResult2 {
             outputs,
             start_time,
             execution_time,
+            cached,
         } = s;
         let status = status
             .context("Missing `status`")?
@@ -657,6 +661,7 @@ impl TryFrom<buck2_test_proto::ExecutionResult2> for ExecutionResult2 {
             outputs,
             start_time,
             execution_time,
+            cached,
         })
     }
 }
@@ -913,6 +918,7 @@ mod tests {
             .collect(),
             start_time: SystemTime::UNIX_EPOCH + Duration::from_secs(123),
             execution_time: Duration::from_secs(456),
+            cached: true,
         };
         assert_roundtrips::<buck2_test_proto::ExecutionResult2, ExecutionResult2>(&result);
     }
@@ -111,6 +111,8 @@ pub enum TestStatus {
     RERUN,
     LISTING_SUCCESS,
     LISTING_FAILED,
+    /// The test passed in an earlier run with identical inputs and was not re-run.
+    CACHED,
 }
 
 /// The set of information about a test rule that is passed to the test executor
@@ -206,6 +208,8 @@ pub struct ExecutionResult2 {
     pub outputs: HashMap<DeclaredOutput, Output>,
     pub start_time: SystemTime,
     pub execution_time: Duration,
+    /// Whether this result was served from the test result cache.
+    pub cached: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
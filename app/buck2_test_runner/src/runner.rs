@@ -96,7 +96,7 @@ impl Buck2TestRunner {
             .fold(
                 RunVerdict::Pass,
                 async move |mut run_verdict, test_status| {
-                    if test_status != TestStatus::PASS {
+                    if !matches!(test_status, TestStatus::PASS | TestStatus::CACHED) {
                         run_verdict = RunVerdict::Fail;
                     }
                     run_verdict
@@ -187,6 +187,7 @@ fn get_test_result(
 ) -> TestResult {
     let status = match execution_result.status {
         ExecutionStatus::Finished { exitcode } => match exitcode {
+            0 if execution_result.cached => TestStatus::CACHED,
             0 => TestStatus::PASS,
             _ => TestStatus::FAIL,
         },
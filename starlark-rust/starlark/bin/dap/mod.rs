@@ -32,6 +32,8 @@ use starlark::debug::prepare_dap_adapter;
 use starlark::debug::DapAdapter;
 use starlark::debug::DapAdapterClient;
 use starlark::debug::DapAdapterEvalHook;
+use starlark::debug::StopReason;
+use starlark::debug::VariablePath;
 use starlark::environment::Module;
 use starlark::eval::Evaluator;
 use starlark::syntax::AstModule;
@@ -41,18 +43,24 @@ use crate::eval::globals;
 
 mod library;
 
+/// The `variablesReference` of the locals scope. Children of variables are numbered after it.
+const LOCALS_REFERENCE: i64 = 2000;
+
 #[derive(Debug)]
 struct Backend {
     adapter: Arc<dyn DapAdapter>,
     eval_wrapper: Mutex<Option<Box<dyn DapAdapterEvalHook>>>,
     client: Client,
     file: Mutex<Option<String>>,
+    // Variables we have handed out a reference to since we last stopped,
+    // where `LOCALS_REFERENCE + 1 + i` refers to the `i`th one.
+    variables: Mutex<Vec<VariablePath>>,
 }
 
 impl DapAdapterClient for Client {
-    fn event_stopped(&self) {
+    fn event_stopped(&self, reason: StopReason) {
         self.event_stopped(StoppedEventBody {
-            reason: "breakpoint".to_owned(),
+            reason: reason.to_dap(),
             thread_id: Some(0),
             description: Some("Hello".to_owned()),
             all_threads_stopped: Some(true),
@@ -61,12 +69,42 @@ impl DapAdapterClient for Client {
         });
     }
 
+    fn event_output(&self, output: String) {
+        self.event_output(OutputEventBody {
+            output,
+            category: Some("console".to_owned()),
+            column: None,
+            data: None,
+            line: None,
+            source: None,
+            variables_reference: None,
+        });
+    }
+
     fn get_ast(&self, source: &str) -> anyhow::Result<AstModule> {
         AstModule::parse_file(Path::new(source), &dialect())
     }
 }
 
 impl Backend {
+    fn variables_reference(&self, var: &starlark::debug::Variable) -> i64 {
+        match &var.children {
+            None => 0,
+            Some(path) => {
+                let mut variables = self.variables.lock().unwrap();
+                variables.push(path.clone());
+                LOCALS_REFERENCE + variables.len() as i64
+            }
+        }
+    }
+
+    fn to_dap(&self, vars: Vec<starlark::debug::Variable>) -> Vec<Variable> {
+        vars.into_map(|var| {
+            let reference = self.variables_reference(&var);
+            var.to_dap(reference)
+        })
+    }
+
     fn execute(&self, path: &str) {
         let client = self.client.dupe();
         let client2 = self.client.dupe();
@@ -170,7 +208,7 @@ impl DebugServer for Backend {
             scopes: vec![Scope {
                 name: "Locals".to_owned(),
                 named_variables: Some(scopes_info.num_locals as i64),
-                variables_reference: 2000,
+                variables_reference: LOCALS_REFERENCE,
                 expensive: false,
                 column: None,
                 end_column: None,
@@ -182,10 +220,20 @@ impl DebugServer for Backend {
         })
     }
 
-    fn variables(&self, _: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
-        let vars_info = self.adapter.variables()?;
+    fn variables(&self, x: VariablesArguments) -> anyhow::Result<VariablesResponseBody> {
+        let vars = if x.variables_reference == LOCALS_REFERENCE {
+            self.adapter.variables()?.locals
+        } else {
+            let path = usize::try_from(x.variables_reference - LOCALS_REFERENCE - 1)
+                .ok()
+                .and_then(|i| self.variables.lock().unwrap().get(i).cloned())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Unknown variables reference {}", x.variables_reference)
+                })?;
+            self.adapter.inspect_variable(path)?.sub_values
+        };
         Ok(VariablesResponseBody {
-            variables: vars_info.locals.into_map(|var| var.to_dap()),
+            variables: self.to_dap(vars),
         })
     }
 
@@ -194,8 +242,24 @@ impl DebugServer for Backend {
     }
 
     fn continue_(&self, x: ContinueArguments) -> anyhow::Result<ContinueResponseBody> {
+        self.variables.lock().unwrap().clear();
         self.adapter.continue_(x)
     }
+
+    fn next(&self, x: NextArguments) -> anyhow::Result<()> {
+        self.variables.lock().unwrap().clear();
+        self.adapter.step_over(x)
+    }
+
+    fn step_in(&self, x: StepInArguments) -> anyhow::Result<()> {
+        self.variables.lock().unwrap().clear();
+        self.adapter.step_in(x)
+    }
+
+    fn step_out(&self, x: StepOutArguments) -> anyhow::Result<()> {
+        self.variables.lock().unwrap().clear();
+        self.adapter.step_out(x)
+    }
 }
 
 pub(crate) fn server() {
@@ -206,6 +270,7 @@ pub(crate) fn server() {
             eval_wrapper: Mutex::new(Some(Box::new(wrapper))),
             client,
             file: Default::default(),
+            variables: Default::default(),
         }
     })
 }
@@ -33,8 +33,10 @@ use starlark::docs::Doc;
 use starlark::docs::DocItem;
 use starlark::environment::FrozenModule;
 use starlark::environment::Globals;
+use starlark::environment::LibraryExtension;
 use starlark::environment::Module;
 use starlark::errors::EvalMessage;
+use starlark::errors::LintFix;
 use starlark::eval::Evaluator;
 use starlark::lsp::server::LspContext;
 use starlark::lsp::server::LspEvalResult;
@@ -42,6 +44,8 @@ use starlark::lsp::server::LspUrl;
 use starlark::lsp::server::StringLiteralResult;
 use starlark::syntax::AstModule;
 use starlark::syntax::Dialect;
+use starlark::typing::typecheck_modules;
+use starlark::typing::OracleStandard;
 
 #[derive(Debug)]
 pub(crate) enum ContextMode {
@@ -62,6 +66,8 @@ enum ContextError {
 #[derive(Debug)]
 pub(crate) struct Context {
     pub(crate) mode: ContextMode,
+    /// In [`ContextMode::Check`], also typecheck. This consumes the AST, so none is returned.
+    pub(crate) typecheck: bool,
     pub(crate) print_non_none: bool,
     pub(crate) prelude: Vec<FrozenModule>,
     pub(crate) module: Option<Module>,
@@ -93,6 +99,7 @@ enum ResolveLoadError {
 impl Context {
     pub(crate) fn new(
         mode: ContextMode,
+        typecheck: bool,
         print_non_none: bool,
         prelude: &[PathBuf],
         module: bool,
@@ -127,6 +134,7 @@ impl Context {
 
         Ok(Self {
             mode,
+            typecheck,
             print_non_none,
             prelude,
             module,
@@ -161,8 +169,14 @@ impl Context {
         let mut errors = Either::Left(iter::empty());
         let final_ast = match self.mode {
             ContextMode::Check => {
-                warnings = Either::Right(self.check(&ast));
-                Some(ast)
+                let lints = self.check(&ast);
+                let (type_errors, ast) = if self.typecheck {
+                    (self.typecheck(file, ast), None)
+                } else {
+                    (Vec::new(), Some(ast))
+                };
+                warnings = Either::Right(lints.chain(type_errors));
+                ast
             }
             ContextMode::Run => {
                 errors = Either::Right(self.run(file, ast).messages);
@@ -250,8 +264,33 @@ impl Context {
         )
     }
 
-    fn check(&self, module: &AstModule) -> impl Iterator<Item = EvalMessage> {
-        let globals = if self.prelude.is_empty() {
+    /// Apply the fixes for the lints of `file` in place, until there are none left.
+    pub(crate) fn fix(&self, file: &Path) -> anyhow::Result<()> {
+        let filename = file.to_string_lossy();
+        let original = fs::read_to_string(file)?;
+        let globals = self.globals();
+        let mut content = original.clone();
+        loop {
+            let ast = match AstModule::parse(&filename, content.clone(), &dialect()) {
+                Ok(ast) => ast,
+                // Reported when the file is checked.
+                Err(_) => break,
+            };
+            let lints = ast.lint(globals.as_ref());
+            let fixed = LintFix::apply_all(&content, lints.iter().filter_map(|x| x.fix()));
+            if fixed == content {
+                break;
+            }
+            content = fixed;
+        }
+        if content != original {
+            fs::write(file, content)?;
+        }
+        Ok(())
+    }
+
+    fn globals(&self) -> Option<HashSet<String>> {
+        if self.prelude.is_empty() {
             None
         } else {
             let mut globals = HashSet::new();
@@ -266,13 +305,58 @@ impl Context {
             }
 
             Some(globals)
-        };
+        }
+    }
 
+    fn check(&self, module: &AstModule) -> impl Iterator<Item = EvalMessage> {
         module
-            .lint(globals.as_ref())
+            .lint(self.globals().as_ref())
             .into_iter()
             .map(EvalMessage::from)
     }
+
+    fn typecheck(&self, file: &str, ast: AstModule) -> Vec<EvalMessage> {
+        // Parse everything this file loads (transitively), so the types they export are known.
+        let mut modules = Vec::new();
+        let mut seen = HashSet::from([file.to_owned()]);
+        let mut todo = vec![(file.to_owned(), ast)];
+        while let Some((name, ast)) = todo.pop() {
+            for load in ast.loads() {
+                let path = load_path(&name, load.module_id);
+                let path = path.to_string_lossy();
+                if !seen.insert(path.to_string()) {
+                    continue;
+                }
+                // A missing or unparseable load is reported when that file is checked.
+                if let Ok(ast) = fs::read_to_string(&*path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|content| AstModule::parse(&path, content, &dialect()))
+                {
+                    todo.push((path.into_owned(), ast));
+                }
+            }
+            modules.push(ast);
+        }
+        let oracle = OracleStandard::new(LibraryExtension::all());
+        let mut res = typecheck_modules(&oracle, modules, |name, load| {
+            Some(load_path(name, load).to_string_lossy().into_owned())
+        });
+        match res.remove(file) {
+            Some(res) => res
+                .errors
+                .map(|e| EvalMessage::from_anyhow(Path::new(file), e)),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// The path a `load()` of `load` from the file `current_file` refers to.
+fn load_path(current_file: &str, load: &str) -> PathBuf {
+    let load = Path::new(load);
+    match Path::new(current_file).parent() {
+        Some(dir) if !load.is_absolute() => dir.join(load),
+        _ => load.to_owned(),
+    }
 }
 
 impl LspContext for Context {
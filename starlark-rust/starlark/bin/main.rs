@@ -26,6 +26,7 @@ use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt;
 use std::fmt::Display;
+use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -46,13 +47,16 @@ use starlark::errors::EvalMessage;
 use starlark::errors::EvalSeverity;
 use starlark::lsp;
 use starlark::read_line::ReadLine;
+use starlark::syntax::AstModule;
 use walkdir::WalkDir;
 
 use crate::eval::ContextMode;
+use crate::sarif::SarifLog;
 use crate::types::LintMessage;
 
 mod dap;
 mod eval;
+mod sarif;
 mod types;
 
 #[derive(Debug, Parser)]
@@ -64,8 +68,13 @@ struct Args {
         conflicts_with_all = &[
             "dap",
             "check",
+            "typecheck",
+            "fix",
             "json",
+            "sarif",
             "docs",
+            "format",
+            "check_format",
             "evaluate",
             "files",
         ],
@@ -79,8 +88,13 @@ struct Args {
         conflicts_with_all = &[
             "lsp",
             "check",
+            "typecheck",
+            "fix",
             "json",
+            "sarif",
             "docs",
+            "format",
+            "check_format",
             "extension",
             "prelude",
             "evaluate",
@@ -96,6 +110,20 @@ struct Args {
     )]
     check: bool,
 
+    #[arg(
+        long = "typecheck",
+        help = "Also typecheck, including the types of symbols loaded from other files.",
+        requires = "check"
+    )]
+    typecheck: bool,
+
+    #[arg(
+        long = "fix",
+        help = "Apply automatic fixes for lints to files in place.",
+        requires = "check"
+    )]
+    fix: bool,
+
     #[arg(
         long = "json",
         help = "Show output as JSON lines.",
@@ -103,6 +131,13 @@ struct Args {
     )]
     json: bool,
 
+    #[arg(
+        long = "sarif",
+        help = "Show output as a SARIF log.",
+        conflicts_with_all = &["lsp", "dap", "json"],
+    )]
+    sarif: bool,
+
     #[arg(
         long = "docs",
         help = "Generate documentation output.",
@@ -110,6 +145,20 @@ struct Args {
     )]
     docs: Option<ArgsDoc>,
 
+    #[arg(
+        long = "format",
+        help = "Format files in place.",
+        conflicts_with_all = &["lsp", "dap", "check", "docs", "evaluate", "check_format"],
+    )]
+    format: bool,
+
+    #[arg(
+        long = "check-format",
+        help = "Check that files are formatted, printing those which are not.",
+        conflicts_with_all = &["lsp", "dap", "check", "docs", "evaluate"],
+    )]
+    check_format: bool,
+
     #[arg(
         long = "extension",
         help = "File extension when searching directories."
@@ -200,10 +249,18 @@ impl Stats {
     }
 }
 
-fn drain(xs: impl Iterator<Item = EvalMessage>, json: bool, stats: &mut Stats) {
+/// Print the messages, or if `sarif` is set, save them to print as a single log at the end.
+fn drain(
+    xs: impl Iterator<Item = EvalMessage>,
+    json: bool,
+    sarif: &mut Option<Vec<EvalMessage>>,
+    stats: &mut Stats,
+) {
     for x in xs {
         stats.increment(x.severity);
-        if json {
+        if let Some(sarif) = sarif {
+            sarif.push(x);
+        } else if json {
             println!("{}", serde_json::to_string(&LintMessage::new(x)).unwrap());
         } else if let Some(error) = x.full_error_with_span {
             let mut error = error.to_owned();
@@ -223,7 +280,7 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
         match rl.read_line("$> ")? {
             Some(line) => {
                 let mut stats = Stats::default();
-                drain(ctx.expression(line).messages, false, &mut stats);
+                drain(ctx.expression(line).messages, false, &mut None, &mut stats);
             }
             // User pressed EOF - disconnected terminal, or similar
             None => return Ok(()),
@@ -231,6 +288,28 @@ fn interactive(ctx: &Context) -> anyhow::Result<()> {
     }
 }
 
+/// Format files in place, or if `check` is set, print the files which are not formatted.
+fn format(files: impl Iterator<Item = PathBuf>, check: bool) -> anyhow::Result<()> {
+    let mut unformatted = 0;
+    for file in files {
+        let content = fs::read_to_string(&file)?;
+        let ast = AstModule::parse(&file.to_string_lossy(), content.clone(), &eval::dialect())?;
+        let formatted = ast.format();
+        if formatted != content {
+            if check {
+                println!("{}", file.display());
+                unformatted += 1;
+            } else {
+                fs::write(&file, formatted)?;
+            }
+        }
+    }
+    if unformatted > 0 {
+        return Err(anyhow::anyhow!("{} files are not formatted", unformatted));
+    }
+    Ok(())
+}
+
 /// starlark-rust does not support panic.
 /// Terminate on panic even if compiled without `-Cpanic=abort`.
 fn terminate_on_panic() {
@@ -255,12 +334,17 @@ fn main() -> anyhow::Result<()> {
             .extension
             .as_ref()
             .map_or("bzl", |x| x.strip_prefix('.').unwrap_or(x.as_str()));
+        if args.format || args.check_format {
+            return format(expand_dirs(ext, args.files), args.check_format);
+        }
+
         let mut ctx = Context::new(
             if args.check {
                 ContextMode::Check
             } else {
                 ContextMode::Run
             },
+            args.typecheck,
             !args.evaluate.is_empty() || is_interactive,
             &expand_dirs(ext, args.prelude).collect::<Vec<_>>(),
             is_interactive,
@@ -298,21 +382,34 @@ fn main() -> anyhow::Result<()> {
             interactive(&ctx)?;
         } else {
             let mut stats = Stats::default();
+            let mut sarif = args.sarif.then(Vec::new);
             for e in args.evaluate.clone() {
                 stats.increment_file();
-                drain(ctx.expression(e).messages, args.json, &mut stats);
+                drain(
+                    ctx.expression(e).messages,
+                    args.json,
+                    &mut sarif,
+                    &mut stats,
+                );
             }
 
             for file in expand_dirs(ext, args.files.clone()) {
                 stats.increment_file();
-                drain(ctx.file(&file).messages, args.json, &mut stats);
+                if args.fix {
+                    ctx.fix(&file)?;
+                }
+                drain(ctx.file(&file).messages, args.json, &mut sarif, &mut stats);
             }
 
-            if !args.json {
+            let sarif_output = sarif.is_some();
+            if let Some(sarif) = sarif {
+                println!("{}", serde_json::to_string_pretty(&SarifLog::new(sarif))?);
+            } else if !args.json {
                 println!("{}", stats);
-                if stats.error > 0 {
-                    return Err(anyhow::anyhow!("Failed with {} errors", stats.error));
-                }
+            }
+            // The SARIF log is complete on stdout, so this only goes to stderr and the exit code.
+            if (sarif_output || !args.json) && stats.error > 0 {
+                return Err(anyhow::anyhow!("Failed with {} errors", stats.error));
             }
         }
     }
@@ -0,0 +1,248 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Output in the [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! format, which code review tools consume. Only the parts of the format we use are modelled.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+use starlark::errors::EvalMessage;
+use starlark::errors::EvalSeverity;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Serialize)]
+pub(crate) struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+    /// Our columns count characters, rather than the UTF-16 code units SARIF assumes.
+    column_kind: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+struct Rule {
+    id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+/// Lines and columns are 1-based, and the end column is the one after the region.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+fn level(x: EvalSeverity) -> &'static str {
+    match x {
+        EvalSeverity::Error => "error",
+        EvalSeverity::Warning => "warning",
+        EvalSeverity::Advice => "note",
+        EvalSeverity::Disabled => "none",
+    }
+}
+
+impl SarifLog {
+    pub(crate) fn new(messages: Vec<EvalMessage>) -> Self {
+        let rules: BTreeSet<&str> = messages.iter().map(|x| x.name.as_str()).collect();
+        let rules = rules
+            .into_iter()
+            .map(|x| Rule { id: x.to_owned() })
+            .collect();
+        let results = messages
+            .into_iter()
+            .map(|x| SarifResult {
+                rule_id: x.name,
+                level: level(x.severity),
+                message: Message {
+                    text: x.description,
+                },
+                locations: vec![Location {
+                    physical_location: PhysicalLocation {
+                        artifact_location: ArtifactLocation {
+                            uri: x.path.replace('\\', "/"),
+                        },
+                        region: x.span.map(|x| Region {
+                            start_line: x.begin_line + 1,
+                            start_column: x.begin_column + 1,
+                            end_line: x.end_line + 1,
+                            end_column: x.end_column + 1,
+                        }),
+                    },
+                }],
+            })
+            .collect();
+        Self {
+            schema: SCHEMA,
+            version: "2.1.0",
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "starlark",
+                        information_uri: "https://github.com/facebookexperimental/starlark-rust",
+                        rules,
+                    },
+                },
+                results,
+                column_kind: "unicodeCodePoints",
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use starlark::codemap::ResolvedSpan;
+
+    use super::*;
+
+    fn message(name: &str, severity: EvalSeverity, span: Option<ResolvedSpan>) -> EvalMessage {
+        EvalMessage {
+            path: "dir\\foo.bzl".to_owned(),
+            span,
+            severity,
+            name: name.to_owned(),
+            description: format!("{} happened", name),
+            full_error_with_span: None,
+            original: None,
+        }
+    }
+
+    #[test]
+    fn test_sarif_log() {
+        let log = SarifLog::new(vec![
+            message(
+                "unused-load",
+                EvalSeverity::Warning,
+                Some(ResolvedSpan {
+                    begin_line: 0,
+                    begin_column: 0,
+                    end_line: 0,
+                    end_column: 12,
+                }),
+            ),
+            message("error", EvalSeverity::Error, None),
+            message("unused-load", EvalSeverity::Advice, None),
+        ]);
+        let expected = serde_json::json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "starlark",
+                        "informationUri": "https://github.com/facebookexperimental/starlark-rust",
+                        "rules": [{"id": "error"}, {"id": "unused-load"}],
+                    },
+                },
+                "results": [
+                    {
+                        "ruleId": "unused-load",
+                        "level": "warning",
+                        "message": {"text": "unused-load happened"},
+                        "locations": [{
+                            "physicalLocation": {
+                                "artifactLocation": {"uri": "dir/foo.bzl"},
+                                "region": {
+                                    "startLine": 1,
+                                    "startColumn": 1,
+                                    "endLine": 1,
+                                    "endColumn": 13,
+                                },
+                            },
+                        }],
+                    },
+                    {
+                        "ruleId": "error",
+                        "level": "error",
+                        "message": {"text": "error happened"},
+                        "locations": [{
+                            "physicalLocation": {"artifactLocation": {"uri": "dir/foo.bzl"}},
+                        }],
+                    },
+                    {
+                        "ruleId": "unused-load",
+                        "level": "note",
+                        "message": {"text": "unused-load happened"},
+                        "locations": [{
+                            "physicalLocation": {"artifactLocation": {"uri": "dir/foo.bzl"}},
+                        }],
+                    },
+                ],
+                "columnKind": "unicodeCodePoints",
+            }],
+        });
+        assert_eq!(serde_json::to_value(&log).unwrap(), expected);
+    }
+}
@@ -20,6 +20,8 @@ use std::collections::HashMap;
 use num_bigint::BigInt;
 use thiserror::Error;
 
+use crate::analysis::fix::remove_item;
+use crate::analysis::fix::LintFix;
 use crate::analysis::types::LintT;
 use crate::analysis::types::LintWarning;
 use crate::codemap::CodeMap;
@@ -89,18 +91,36 @@ fn duplicate_dictionary_key(module: &AstModule, res: &mut Vec<LintT<Dubious>>) {
         }
     }
 
+    // Removing an entry is only safe if evaluating it can't have side effects.
+    fn has_call(x: &AstExpr) -> bool {
+        let mut res = matches!(&**x, Expr::Call(..));
+        x.visit_expr(|x| res = res || has_call(x));
+        res
+    }
+
     fn expr<'a>(x: &'a AstExpr, codemap: &CodeMap, res: &mut Vec<LintT<Dubious>>) {
         match &**x {
             Expr::Dict(args) => {
+                let entries: Vec<Span> = args.iter().map(|(k, v)| k.span.merge(v.span)).collect();
                 let mut seen = HashMap::new();
-                for (key, _) in args {
+                for (i, (key, _)) in args.iter().enumerate() {
                     if let Some((key_id, pos)) = to_key(key) {
-                        if let Some(old) = seen.insert(key_id, pos) {
-                            res.push(LintT::new(
+                        if let Some((old, j)) = seen.insert(key_id, (pos, i)) {
+                            let mut lint = LintT::new(
                                 codemap,
                                 old,
                                 Dubious::DuplicateKey(key.to_string(), codemap.file_span(pos)),
-                            ))
+                            );
+                            // The later entry wins, so the earlier one can be removed.
+                            if !has_call(&args[j].1) {
+                                lint.fix = Some(LintFix::new(
+                                    codemap,
+                                    remove_item(codemap, &entries, j),
+                                    format!("Remove the overridden entry for `{}`", key),
+                                    String::new(),
+                                ));
+                            }
+                            res.push(lint)
                         }
                     }
                 }
@@ -0,0 +1,227 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Machine-applicable fixes for lints, and the helpers lints use to compute them.
+
+use crate::codemap::CodeMap;
+use crate::codemap::FileSpan;
+use crate::codemap::Pos;
+use crate::codemap::Span;
+
+/// An edit which fixes the problem described by a [`Lint`](crate::analysis::Lint).
+#[derive(Debug, Clone)]
+pub struct LintFix {
+    /// A short description of the edit, e.g. ``Remove unused `load` of `x` ``.
+    pub description: String,
+    /// The code to replace, which may be larger than the location of the lint.
+    pub location: FileSpan,
+    /// The text to replace the code at [`location`](LintFix::location) with.
+    pub replacement: String,
+}
+
+impl LintFix {
+    pub(crate) fn new(
+        codemap: &CodeMap,
+        span: Span,
+        description: String,
+        replacement: String,
+    ) -> Self {
+        Self {
+            description,
+            location: codemap.file_span(span),
+            replacement,
+        }
+    }
+
+    /// Apply `fixes` to `source`, the text of the file they were produced for.
+    ///
+    /// Identical fixes are applied once, and a fix which overlaps one applied before it is
+    /// skipped. Linting the result again will produce the fixes which were skipped.
+    pub fn apply_all<'a>(source: &str, fixes: impl IntoIterator<Item = &'a LintFix>) -> String {
+        let mut edits: Vec<(usize, usize, &str)> = fixes
+            .into_iter()
+            .map(|x| {
+                (
+                    begin(x.location.span),
+                    end(x.location.span),
+                    &*x.replacement,
+                )
+            })
+            .collect();
+        edits.sort();
+        edits.dedup();
+        let mut res = String::with_capacity(source.len());
+        let mut pos = 0;
+        for (begin, end, replacement) in edits {
+            if begin < pos {
+                continue;
+            }
+            res.push_str(&source[pos..begin]);
+            res.push_str(replacement);
+            pos = end;
+        }
+        res.push_str(&source[pos..]);
+        res
+    }
+}
+
+fn begin(span: Span) -> usize {
+    span.begin().get() as usize
+}
+
+fn end(span: Span) -> usize {
+    span.end().get() as usize
+}
+
+fn span(begin: usize, end: usize) -> Span {
+    Span::new(Pos::new(begin as u32), Pos::new(end as u32))
+}
+
+/// The start of the line containing `pos`.
+fn line_start(source: &str, pos: usize) -> usize {
+    source[..pos].rfind('\n').map_or(0, |x| x + 1)
+}
+
+/// The start of the line after the one containing `pos`.
+fn next_line(source: &str, pos: usize) -> usize {
+    source[pos..]
+        .find('\n')
+        .map_or(source.len(), |x| pos + x + 1)
+}
+
+/// The span to delete to remove the statement at `x`. If the statement is on lines of its
+/// own, those lines (including any trailing comment) are removed too.
+pub(crate) fn remove_statement(codemap: &CodeMap, x: Span) -> Span {
+    let source = codemap.source();
+    let (begin, end) = (begin(x), end(x));
+    let after = &source[end..next_line(source, end)];
+    let rest = after.trim_start();
+    if let Some(rest) = rest.strip_prefix(';') {
+        // Followed by another statement on the same line, so remove the separator instead.
+        let next = end + after.len() - rest.trim_start().len();
+        return span(begin, next);
+    }
+    let start = line_start(source, begin);
+    if source[start..begin].trim().is_empty() && (rest.is_empty() || rest.starts_with('#')) {
+        span(start, next_line(source, end))
+    } else {
+        x
+    }
+}
+
+/// The span to delete to remove `items[index]` from a comma separated list of `items`, along
+/// with the separator. If the list is laid out one item per line, the item's line is removed.
+pub(crate) fn remove_item(codemap: &CodeMap, items: &[Span], index: usize) -> Span {
+    let source = codemap.source();
+    let (begin, end) = (begin(items[index]), end(items[index]));
+    let start = line_start(source, begin);
+    let own_line = source[start..begin].trim().is_empty();
+    if let Some(next) = items.get(index + 1) {
+        let next = self::begin(*next);
+        if own_line && source[end..next].contains('\n') {
+            span(start, next_line(source, end))
+        } else {
+            span(begin, next)
+        }
+    } else if let Some(prev) = index.checked_sub(1).map(|i| self::end(items[i])) {
+        if own_line && source[prev..begin].contains('\n') {
+            span(start, next_line(source, end))
+        } else {
+            span(prev, end)
+        }
+    } else {
+        items[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use crate::analysis::LintFix;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    /// Apply all the fixes for `program`, until there are none left.
+    fn fix(program: &str) -> String {
+        let mut program = dedent(program).trim_start_matches('\n').to_owned();
+        loop {
+            let module = AstModule::parse("X", program.clone(), &Dialect::Extended).unwrap();
+            let lints = module.lint(None);
+            let fixed = LintFix::apply_all(&program, lints.iter().filter_map(|x| x.fix()));
+            if fixed == program {
+                return program;
+            }
+            program = fixed;
+        }
+    }
+
+    #[test]
+    fn test_fix_unused_load() {
+        assert_eq!(
+            fix(r#"
+                load("a", "x", "y", "z")
+                load("b", "unused")  # Comment.
+                load("c", c1 = "c")
+                load(
+                    "d",
+                    "used",
+                    "other",
+                )
+                load("e", "e1", "e2"); e = 1
+                print(y, used)
+                "#),
+            dedent(
+                r#"
+                load("a", "y")
+                load(
+                    "d",
+                    "used",
+                )
+                e = 1
+                print(y, used)
+                "#
+            )
+            .trim_start_matches('\n')
+        );
+    }
+
+    #[test]
+    fn test_fix_duplicate_key() {
+        assert_eq!(
+            fix(r#"
+                x = {"a": 1, "b": 2, "a": 3}
+                y = {
+                    "a": 1,
+                    "a": 2,
+                    "a": 3,
+                }
+                z = {"a": f(), "a": 2}
+                "#),
+            dedent(
+                r#"
+                x = {"b": 2, "a": 3}
+                y = {
+                    "a": 3,
+                }
+                z = {"a": f(), "a": 2}
+                "#
+            )
+            .trim_start_matches('\n')
+        );
+    }
+}
@@ -17,10 +17,12 @@
 
 use std::collections::HashSet;
 
+pub use fix::LintFix;
 pub use types::EvalMessage;
 pub use types::EvalSeverity;
 pub use types::Lint;
 
+use crate::analysis::suppressions::Suppressions;
 use crate::analysis::types::LintT;
 use crate::syntax::AstModule;
 
@@ -29,10 +31,12 @@ pub(crate) mod definition;
 mod dubious;
 mod exported;
 mod find_call_name;
+mod fix;
 mod flow;
 mod incompatible;
 mod names;
 mod performance;
+mod suppressions;
 mod types;
 mod underscore;
 
@@ -40,6 +44,10 @@ impl AstModule {
     /// Run a static linter over the module. If the complete set of global variables are known
     /// they can be passed as the `globals` argument, resulting in name-resolution lint errors.
     /// The precise checks run by the linter are not considered stable between versions.
+    ///
+    /// Lints can be disabled with a `# starlark-lint-disable <name>` comment, on the line of
+    /// the lint or on its own line before it, or in the whole file with a
+    /// `# starlark-lint-disable-file <name>` comment.
     pub fn lint(&self, globals: Option<&HashSet<String>>) -> Vec<Lint> {
         let mut res = Vec::new();
         res.extend(flow::lint(self).into_iter().map(LintT::erase));
@@ -48,6 +56,10 @@ impl AstModule {
         res.extend(names::lint(self, globals).into_iter().map(LintT::erase));
         res.extend(underscore::lint(self).into_iter().map(LintT::erase));
         res.extend(performance::lint(self).into_iter().map(LintT::erase));
+        if !res.is_empty() {
+            let suppressions = Suppressions::new(self);
+            res.retain(|x| !suppressions.is_suppressed(x));
+        }
         res
     }
 }
@@ -17,6 +17,7 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::iter;
 
 use thiserror::Error;
 
@@ -24,10 +25,14 @@ use crate::analysis::bind;
 use crate::analysis::bind::Assigner;
 use crate::analysis::bind::Bind;
 use crate::analysis::bind::Scope;
+use crate::analysis::fix::remove_item;
+use crate::analysis::fix::remove_statement;
+use crate::analysis::fix::LintFix;
 use crate::analysis::types::LintT;
 use crate::analysis::types::LintWarning;
 use crate::codemap::CodeMap;
 use crate::codemap::Span;
+use crate::syntax::ast::Stmt;
 use crate::syntax::AstModule;
 
 #[derive(Error, Debug)]
@@ -89,9 +94,66 @@ pub(crate) fn lint(
     if let Some(globals) = globals {
         undefined_variable(&module.codemap, &scope, globals, &mut res);
     }
+    unused_load_fixes(module, &mut res);
     res
 }
 
+/// Attach fixes to the [`NameWarning::UnusedLoad`] lints, removing the unused symbols from
+/// their `load`, or the whole `load` if none of its symbols are used.
+fn unused_load_fixes(module: &AstModule, res: &mut [LintT<NameWarning>]) {
+    let unused: HashMap<Span, usize> = res
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| matches!(x.problem, NameWarning::UnusedLoad(..)))
+        .map(|(i, x)| (x.location.span, i))
+        .collect();
+    if unused.is_empty() {
+        return;
+    }
+    let codemap = &module.codemap;
+    for x in module.top_level_statements() {
+        let load = match &**x {
+            Stmt::Load(load) => load,
+            _ => continue,
+        };
+        let lints: Vec<Option<usize>> = load
+            .args
+            .iter()
+            .map(|(name, _)| unused.get(&name.span).copied())
+            .collect();
+        if lints.iter().all(Option::is_some) {
+            let span = remove_statement(codemap, x.span);
+            for i in lints.into_iter().flatten() {
+                res[i].fix = Some(LintFix::new(
+                    codemap,
+                    span,
+                    format!("Remove unused `load` of `{}`", load.module.node),
+                    String::new(),
+                ));
+            }
+        } else {
+            let items: Vec<Span> = iter::once(load.module.span)
+                .chain(
+                    load.args
+                        .iter()
+                        .map(|(name, sym)| name.span.merge(sym.span)),
+                )
+                .collect();
+            for (arg, i) in lints.iter().enumerate() {
+                if let Some(i) = *i {
+                    let span = remove_item(codemap, &items, arg + 1);
+                    res[i].fix = Some(LintFix::new(
+                        codemap,
+                        span,
+                        format!("Remove unused `{}` from `load`", load.args[arg].0.0),
+                        String::new(),
+                    ));
+                }
+            }
+        }
+    }
+}
+
 fn undefined_variable(
     codemap: &CodeMap,
     scope: &Scope,
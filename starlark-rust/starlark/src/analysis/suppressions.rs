@@ -0,0 +1,130 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Disabling lints with comments.
+//!
+//! A `# starlark-lint-disable name1, name2` comment disables the named lints on its line, or
+//! on the next line of code if the comment is on a line of its own. A
+//! `# starlark-lint-disable-file name1, name2` comment disables them in the whole file.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::analysis::types::Lint;
+use crate::codemap::Pos;
+use crate::syntax::AstModule;
+
+const DISABLE: &str = "starlark-lint-disable";
+const DISABLE_FILE: &str = "starlark-lint-disable-file";
+
+/// The lints disabled by the comments of a module.
+pub(crate) struct Suppressions {
+    file: HashSet<String>,
+    /// Lints disabled on a given (0-based) line.
+    lines: HashMap<usize, HashSet<String>>,
+}
+
+/// If `comment` is the `directive`, the lint names it lists.
+fn parse_directive<'a>(comment: &'a str, directive: &str) -> Option<impl Iterator<Item = &'a str>> {
+    let names = comment
+        .trim_start_matches('#')
+        .trim()
+        .strip_prefix(directive)?;
+    if !names.is_empty() && !names.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(
+        names
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|x| !x.is_empty()),
+    )
+}
+
+impl Suppressions {
+    pub(crate) fn new(module: &AstModule) -> Self {
+        let source = module.codemap.source();
+        let trivia = module.trivia();
+        let mut file = HashSet::new();
+        let mut lines: HashMap<usize, HashSet<String>> = HashMap::new();
+        for comment in trivia.comments {
+            let text = &source[comment.begin..comment.end];
+            if let Some(names) = parse_directive(text, DISABLE_FILE) {
+                file.extend(names.map(str::to_owned));
+            } else if let Some(names) = parse_directive(text, DISABLE) {
+                let pos = if comment.trailing {
+                    comment.begin
+                } else {
+                    match trivia.tokens.iter().find(|x| **x >= comment.end) {
+                        Some(x) => *x,
+                        None => continue,
+                    }
+                };
+                let line = module.codemap.find_line(Pos::new(pos as u32));
+                lines
+                    .entry(line)
+                    .or_default()
+                    .extend(names.map(str::to_owned));
+            }
+        }
+        Self { file, lines }
+    }
+
+    pub(crate) fn is_suppressed(&self, lint: &Lint) -> bool {
+        if self.file.contains(&lint.short_name) {
+            return true;
+        }
+        let line = lint.location.file.find_line(lint.location.span.begin());
+        self.lines
+            .get(&line)
+            .map_or(false, |names| names.contains(&lint.short_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gazebo::prelude::SliceExt;
+
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    fn lint(program: &str) -> Vec<String> {
+        let module = AstModule::parse("X", program.to_owned(), &Dialect::Extended).unwrap();
+        let mut res = module.lint(None).map(|x| x.original.clone());
+        res.sort();
+        res
+    }
+
+    #[test]
+    fn test_suppressions() {
+        let res = lint(
+            r#"
+load("a", "no1")  # starlark-lint-disable unused-load
+load("b", "yes1")  # starlark-lint-disable unused-assign
+# starlark-lint-disable unused-assign, unused-load
+load("c", "no2", "no3")
+# starlark-lint-disable-next unused-load
+load("d", "yes2")
+x = {"no4": 1, "no4": 2}
+def f():
+    yes3 = 1  # starlark-lint-disable-everything
+    no5 = 2  # starlark-lint-disable unused-assign
+# starlark-lint-disable-file duplicate-key
+"#,
+        );
+        assert_eq!(res, &["\"yes1\"", "\"yes2\"", "yes3"]);
+    }
+}
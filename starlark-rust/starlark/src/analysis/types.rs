@@ -25,11 +25,13 @@ use lsp_types::NumberOrString;
 use lsp_types::Range;
 use serde::Serialize;
 
+use crate::analysis::fix::LintFix;
 use crate::codemap::CodeMap;
 use crate::codemap::FileSpan;
 use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
 use crate::errors::Diagnostic;
+use crate::typing::ctx::TypingError;
 
 pub(crate) trait LintWarning: Display {
     fn is_serious(&self) -> bool;
@@ -42,9 +44,14 @@ pub(crate) struct LintT<T> {
     pub location: FileSpan,
     pub original: String,
     pub problem: T,
+    pub fix: Option<LintFix>,
 }
 
 /// A lint produced by [`AstModule::lint`](crate::syntax::AstModule::lint).
+///
+/// Lints made outside this crate, e.g. to report parse errors alongside other lints, are created
+/// with [`Lint::new`]. They used to be created with a struct literal, which is no longer possible
+/// now that lints can carry a fix.
 #[derive(Debug)]
 pub struct Lint {
     /// Which code location does this lint refer to.
@@ -58,6 +65,32 @@ pub struct Lint {
     pub problem: String,
     /// The source code at [`location`](Lint::location).
     pub original: String,
+    fix: Option<LintFix>,
+}
+
+impl Lint {
+    /// A lint without a fix.
+    pub fn new(
+        location: FileSpan,
+        short_name: String,
+        serious: bool,
+        problem: String,
+        original: String,
+    ) -> Self {
+        Self {
+            location,
+            short_name,
+            serious,
+            problem,
+            original,
+            fix: None,
+        }
+    }
+
+    /// An edit which fixes the problem, if one can be made automatically.
+    pub fn fix(&self) -> Option<&LintFix> {
+        self.fix.as_ref()
+    }
 }
 
 impl Display for Lint {
@@ -79,6 +112,7 @@ impl<T: LintWarning> LintT<T> {
             original: location.file.source_span(span).to_owned(),
             location,
             problem,
+            fix: None,
         }
     }
 
@@ -89,6 +123,7 @@ impl<T: LintWarning> LintT<T> {
             serious: self.problem.is_serious(),
             problem: self.problem.to_string(),
             original: self.original,
+            fix: self.fix,
         }
     }
 }
@@ -159,8 +194,23 @@ impl Display for EvalMessage {
 }
 
 impl EvalMessage {
-    /// Convert from an `anyhow::Error`, including some type checking, to an `EvalMessage`
+    /// Convert from an `anyhow::Error`, including some type checking, to an `EvalMessage`.
+    ///
+    /// Errors from [`typecheck`](crate::syntax::AstModule::typecheck) are reported as warnings,
+    /// named after the kind of error.
     pub fn from_anyhow(file: &Path, x: &anyhow::Error) -> Self {
+        if let Some(e) = x.downcast_ref::<TypingError>() {
+            let loc = e.loc();
+            return Self {
+                path: loc.file.clone(),
+                span: Some(loc.span),
+                severity: EvalSeverity::Warning,
+                name: e.short_name().to_owned(),
+                description: e.message(),
+                full_error_with_span: None,
+                original: None,
+            };
+        }
         match x.downcast_ref::<Diagnostic>() {
             Some(
                 d @ Diagnostic {
@@ -35,10 +35,26 @@ use std::sync::Arc;
 use allocative::Allocative;
 use dupe::Dupe;
 use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
 
 /// A small, `Copy`, value representing a position in a `CodeMap`'s file.
 #[derive(
-    Copy, Clone, Dupe, Hash, Eq, PartialEq, PartialOrd, Ord, Debug, Default, Allocative
+    Copy,
+    Clone,
+    Dupe,
+    Hash,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Debug,
+    Default,
+    Allocative,
+    Serialize,
+    Deserialize
 )]
 pub struct Pos(u32);
 
@@ -47,6 +63,11 @@ impl Pos {
     pub const fn new(x: u32) -> Self {
         Self(x)
     }
+
+    /// Get the value.
+    pub const fn get(self) -> u32 {
+        self.0
+    }
 }
 
 impl Add<u32> for Pos {
@@ -57,7 +78,19 @@ impl Add<u32> for Pos {
 }
 
 /// A range of text within a CodeMap.
-#[derive(Copy, Dupe, Clone, Hash, Eq, PartialEq, Debug, Default, Allocative)]
+#[derive(
+    Copy,
+    Dupe,
+    Clone,
+    Hash,
+    Eq,
+    PartialEq,
+    Debug,
+    Default,
+    Allocative,
+    Serialize,
+    Deserialize
+)]
 pub(crate) struct Span {
     /// The position in the codemap representing the first byte of the span.
     begin: Pos,
@@ -111,7 +144,7 @@ impl Span {
 }
 
 /// Associate a Span with a value of arbitrary type (e.g. an AST node).
-#[derive(Clone, PartialEq, Eq, Hash, Debug, Copy)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Copy, Serialize, Deserialize)]
 pub struct Spanned<T> {
     /// Data in the node.
     pub node: T,
@@ -232,6 +265,20 @@ impl PartialEq for CodeMap {
 
 impl Eq for CodeMap {}
 
+/// Only filename and source are serialized, line index is recomputed on deserialization.
+impl Serialize for CodeMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.filename(), self.source()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CodeMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (filename, source) = <(String, String)>::deserialize(deserializer)?;
+        Ok(CodeMap::new(filename, source))
+    }
+}
+
 impl Hash for CodeMap {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.id().hash(state)
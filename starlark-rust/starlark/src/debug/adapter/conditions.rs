@@ -0,0 +1,125 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The options of a breakpoint which decide whether it stops execution.
+
+use dupe::Dupe;
+
+/// A DAP `hitCondition`, deciding to stop based on how many times a breakpoint has been hit.
+///
+/// Written as an operator (one of `==`, `>=`, `>`, `<=`, `<` or `%`) followed by a number,
+/// e.g. `>= 3`. A number on its own means `==`.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub(crate) enum HitCondition {
+    Equal(usize),
+    GreaterOrEqual(usize),
+    Greater(usize),
+    LessOrEqual(usize),
+    Less(usize),
+    /// Every n-th hit.
+    Multiple(usize),
+}
+
+impl HitCondition {
+    pub(crate) fn parse(x: &str) -> Option<Self> {
+        let x = x.trim();
+        // Longer operators first, so `>=` isn't read as `>`.
+        let (op, n) = ["==", ">=", ">", "<=", "<", "%"]
+            .iter()
+            .find_map(|op| x.strip_prefix(*op).map(|n| (*op, n)))
+            .unwrap_or(("==", x));
+        let n = n.trim().parse().ok()?;
+        Some(match op {
+            "==" => Self::Equal(n),
+            ">=" => Self::GreaterOrEqual(n),
+            ">" => Self::Greater(n),
+            "<=" => Self::LessOrEqual(n),
+            "<" => Self::Less(n),
+            _ if n > 0 => Self::Multiple(n),
+            _ => return None,
+        })
+    }
+
+    /// Whether to stop on the `hits`-th hit (counting from 1).
+    pub(crate) fn matches(self, hits: usize) -> bool {
+        match self {
+            Self::Equal(n) => hits == n,
+            Self::GreaterOrEqual(n) => hits >= n,
+            Self::Greater(n) => hits > n,
+            Self::LessOrEqual(n) => hits <= n,
+            Self::Less(n) => hits < n,
+            Self::Multiple(n) => hits % n == 0,
+        }
+    }
+}
+
+/// Expand a DAP `logMessage`, where expressions between braces are replaced by their values,
+/// as given by `eval`.
+pub(crate) fn interpolate(message: &str, mut eval: impl FnMut(&str) -> String) -> String {
+    let mut res = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(open) = rest.find('{') {
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+        res.push_str(&rest[..open]);
+        res.push_str(&eval(&rest[open + 1..close]));
+        rest = &rest[close + 1..];
+    }
+    res.push_str(rest);
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hit_condition() {
+        assert_eq!(Some(HitCondition::Equal(3)), HitCondition::parse("3"));
+        assert_eq!(Some(HitCondition::Equal(3)), HitCondition::parse("== 3"));
+        assert_eq!(
+            Some(HitCondition::GreaterOrEqual(2)),
+            HitCondition::parse(">=2")
+        );
+        assert_eq!(
+            Some(HitCondition::Multiple(2)),
+            HitCondition::parse(" % 2 ")
+        );
+        assert_eq!(None, HitCondition::parse("%0"));
+        assert_eq!(None, HitCondition::parse("x > 2"));
+
+        let hits = |x: &str| {
+            let x = HitCondition::parse(x).unwrap();
+            (1..=6).filter(|i| x.matches(*i)).collect::<Vec<_>>()
+        };
+        assert_eq!(vec![3], hits("3"));
+        assert_eq!(vec![5, 6], hits(">4"));
+        assert_eq!(vec![1, 2], hits("<3"));
+        assert_eq!(vec![3, 6], hits("%3"));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let eval = |x: &str| x.to_uppercase();
+        assert_eq!("x is X", interpolate("x is {x}", eval));
+        assert_eq!("X and Y + 1!", interpolate("{x} and {y + 1}!", eval));
+        assert_eq!("no braces", interpolate("no braces", eval));
+        assert_eq!("A {unclosed", interpolate("{a} {unclosed", eval));
+    }
+}
@@ -16,7 +16,6 @@
  */
 
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::fmt::Debug;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
@@ -29,29 +28,45 @@ use std::sync::Mutex;
 use debugserver_types::*;
 use dupe::Dupe;
 use dupe::OptionDupedExt;
-use gazebo::prelude::SliceExt;
 
 use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
+use crate::codemap::Span;
+use crate::debug::adapter::conditions::interpolate;
+use crate::debug::adapter::conditions::HitCondition;
+use crate::debug::adapter::variables;
 use crate::debug::DapAdapter;
 use crate::debug::DapAdapterClient;
 use crate::debug::DapAdapterEvalHook;
+use crate::debug::InspectVariableInfo;
 use crate::debug::ScopesInfo;
-use crate::debug::Variable;
+use crate::debug::StopReason;
+use crate::debug::VariablePath;
 use crate::debug::VariablesInfo;
 use crate::eval::BeforeStmtFuncDyn;
 use crate::eval::Evaluator;
 use crate::syntax::AstModule;
 use crate::syntax::Dialect;
+use crate::values::Value;
+
+/// Breakpoints which can be shared by the adapters of several evaluations, see
+/// [`prepare_dap_adapter_with_breakpoints`](crate::debug::prepare_dap_adapter_with_breakpoints).
+///
+/// Setting breakpoints through any of the adapters resolves them against the source once,
+/// and applies them to all of the adapters. Hit counts are shared too.
+#[derive(Debug, Clone, Dupe, Default)]
+pub struct DapBreakpoints(Arc<Mutex<HashMap<String, HashMap<Span, BreakpointConfig>>>>);
 
 pub(crate) fn prepare_dap_adapter(
     client: Box<dyn DapAdapterClient>,
+    breakpoints: DapBreakpoints,
 ) -> (impl DapAdapter, impl DapAdapterEvalHook) {
     let (sender, receiver) = std::sync::mpsc::channel::<ToEvalMessage>();
     let state = Arc::new(SharedAdapterState {
         client,
-        breakpoints: Arc::new(Mutex::new(HashMap::new())),
+        breakpoints,
         disable_breakpoints: Arc::new(0usize.into()),
+        step: Mutex::new(None),
     });
 
     (
@@ -79,28 +94,39 @@ struct DapAdapterEvalHookImpl {
 
 impl<'a> BeforeStmtFuncDyn<'a> for DapAdapterEvalHookImpl {
     fn call<'v>(&mut self, span_loc: FileSpanRef, eval: &mut Evaluator<'v, 'a>) {
-        let stop = if self.state.disable_breakpoints.load(Ordering::SeqCst) > 0 {
-            false
+        if self.state.disable_breakpoints.load(Ordering::SeqCst) > 0 {
+            return;
+        }
+        let reason = if self.step_finished(eval) {
+            Some(StopReason::Step)
+        } else if self.breakpoint_hit(span_loc, eval) {
+            Some(StopReason::Breakpoint)
         } else {
-            let breaks = self.state.breakpoints.lock().unwrap();
-            breaks
-                .get(span_loc.filename())
-                .map(|set| set.contains(&span_loc.to_file_span()))
-                .unwrap_or_default()
+            None
         };
-        if stop {
-            self.state.client.event_stopped();
-            loop {
-                let msg = self.receiver.recv().unwrap();
+        if let Some(reason) = reason {
+            *self.state.step.lock().unwrap() = None;
+            self.state.client.event_stopped(reason);
+            let receiver = &self.receiver;
+            self.state.client.while_stopped(&mut || loop {
+                let msg = receiver.recv().unwrap();
                 match msg(span_loc, eval) {
                     Next::Continue => break,
                     Next::RemainPaused => continue,
                 }
-            }
+            });
         }
     }
 }
 
+impl Drop for DapAdapterEvalHookImpl {
+    fn drop(&mut self) {
+        // The hook is dropped with the evaluator, so the evaluation has finished.
+        // A step which did not complete must not apply to whatever is evaluated next.
+        *self.state.step.lock().unwrap() = None;
+    }
+}
+
 impl Debug for DapAdapterEvalHookImpl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DapAdapterEvaluationWrapper").finish()
@@ -111,6 +137,72 @@ impl DapAdapterEvalHookImpl {
     fn new(state: Arc<SharedAdapterState>, receiver: Receiver<ToEvalMessage>) -> Self {
         Self { state, receiver }
     }
+
+    fn step_finished(&self, eval: &Evaluator) -> bool {
+        match *self.state.step.lock().unwrap() {
+            None => false,
+            Some(Step { kind, depth }) => {
+                let count = eval.call_stack_count();
+                match kind {
+                    StepKind::Into => true,
+                    StepKind::Over => count <= depth,
+                    StepKind::Out => count < depth,
+                }
+            }
+        }
+    }
+
+    /// Whether we should stop at a breakpoint here. Logpoints print their message instead.
+    fn breakpoint_hit(&self, span_loc: FileSpanRef, eval: &mut Evaluator) -> bool {
+        let span = span_loc.span;
+        // Don't hold the lock while evaluating, the condition may take a while.
+        let (condition, log_message) = {
+            let breaks = self.state.breakpoints.0.lock().unwrap();
+            match breaks.get(span_loc.filename()).and_then(|x| x.get(&span)) {
+                None => return false,
+                Some(x) => (x.condition.clone(), x.log_message.clone()),
+            }
+        };
+        if let Some(condition) = condition {
+            // If the condition fails to evaluate we stop, so the user gets to see why.
+            match evaluate(eval, &self.state.disable_breakpoints, &condition) {
+                Ok(v) if !v.to_bool() => return false,
+                _ => {}
+            }
+        }
+        {
+            let mut breaks = self.state.breakpoints.0.lock().unwrap();
+            // The breakpoints may have been changed while we were evaluating.
+            match breaks
+                .get_mut(span_loc.filename())
+                .and_then(|x| x.get_mut(&span))
+            {
+                None => return false,
+                Some(x) => {
+                    x.hits += 1;
+                    if let Some(hit_condition) = x.hit_condition {
+                        if !hit_condition.matches(x.hits) {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        match log_message {
+            None => true,
+            Some(message) => {
+                let mut output = interpolate(&message, |expr| {
+                    match evaluate(eval, &self.state.disable_breakpoints, expr) {
+                        Ok(v) => v.to_str(),
+                        Err(e) => format!("<{:#}>", e),
+                    }
+                });
+                output.push('\n');
+                self.state.client.event_output(output);
+                false
+            }
+        }
+    }
 }
 
 impl DapAdapterEvalHook for DapAdapterEvalHookImpl {
@@ -124,9 +216,36 @@ struct SharedAdapterState {
     client: Box<dyn DapAdapterClient>,
     // These breakpoints must all match statements as per before_stmt.
     // Those values for which we abort the execution.
-    breakpoints: Arc<Mutex<HashMap<String, HashSet<FileSpan>>>>,
+    // Keyed by filename then span, rather than by `FileSpan`, since the file the breakpoints
+    // were resolved against is parsed separately from the one being evaluated.
+    breakpoints: DapBreakpoints,
     // Set while we are doing evaluate calls (>= 1 means disable)
     disable_breakpoints: Arc<AtomicUsize>,
+    // The step we are in the middle of, if any.
+    step: Mutex<Option<Step>>,
+}
+
+#[derive(Debug)]
+struct BreakpointConfig {
+    condition: Option<String>,
+    hit_condition: Option<HitCondition>,
+    log_message: Option<String>,
+    // Number of times we reached the breakpoint with the condition true.
+    hits: usize,
+}
+
+#[derive(Debug, Clone, Copy, Dupe)]
+enum StepKind {
+    Into,
+    Over,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, Dupe)]
+struct Step {
+    kind: StepKind,
+    // The call stack size when the step was requested.
+    depth: usize,
 }
 
 enum Next {
@@ -169,33 +288,65 @@ impl DapAdapter for DapAdapterImpl {
         let source = x.source.path.unwrap();
 
         if breakpoints.is_empty() {
-            self.state.breakpoints.lock().unwrap().remove(&source);
+            self.state.breakpoints.0.lock().unwrap().remove(&source);
             Ok(SetBreakpointsResponseBody {
                 breakpoints: Vec::new(),
             })
         } else {
             match self.state.client.get_ast(&source) {
                 Err(_) => {
-                    self.state.breakpoints.lock().unwrap().remove(&source);
+                    self.state.breakpoints.0.lock().unwrap().remove(&source);
                     Ok(SetBreakpointsResponseBody {
-                        breakpoints: vec![breakpoint(false); breakpoints.len()],
+                        breakpoints: vec![breakpoint(false, None); breakpoints.len()],
                     })
                 }
                 Ok(ast) => {
-                    let poss: HashMap<usize, FileSpan> = ast
+                    let poss: HashMap<usize, Span> = ast
                         .stmt_locations()
                         .iter()
-                        .map(|span| (span.resolve_span().begin_line, span.dupe()))
+                        .map(|span| (span.resolve_span().begin_line, span.span))
                         .collect();
-                    let list = breakpoints.map(|x| poss.get(&(x.line as usize - 1)));
+                    let mut configs = HashMap::new();
+                    let mut res = Vec::with_capacity(breakpoints.len());
+                    for x in breakpoints {
+                        let span = match poss.get(&(x.line as usize - 1)).duped() {
+                            None => {
+                                res.push(breakpoint(false, None));
+                                continue;
+                            }
+                            Some(span) => span,
+                        };
+                        let hit_condition = match &x.hit_condition {
+                            None => None,
+                            Some(h) => match HitCondition::parse(h) {
+                                None => {
+                                    res.push(breakpoint(
+                                        false,
+                                        Some(format!("Invalid hit condition `{}`", h)),
+                                    ));
+                                    continue;
+                                }
+                                Some(h) => Some(h),
+                            },
+                        };
+                        configs.insert(
+                            span,
+                            BreakpointConfig {
+                                condition: x.condition,
+                                hit_condition,
+                                log_message: x.log_message,
+                                hits: 0,
+                            },
+                        );
+                        res.push(breakpoint(true, None));
+                    }
                     self.state
                         .breakpoints
+                        .0
                         .lock()
                         .unwrap()
-                        .insert(source, list.iter().filter_map(|x| x.duped()).collect());
-                    Ok(SetBreakpointsResponseBody {
-                        breakpoints: list.map(|x| breakpoint(x.is_some())),
-                    })
+                        .insert(source, configs);
+                    Ok(SetBreakpointsResponseBody { breakpoints: res })
                 }
             }
         }
@@ -244,33 +395,57 @@ impl DapAdapter for DapAdapterImpl {
             Ok(VariablesInfo {
                 locals: vars
                     .into_iter()
-                    .map(|(name, value)| Variable {
-                        name,
-                        value: value.to_string(),
-                        type_: value.get_type().to_owned(),
+                    .map(|(name, value)| {
+                        let path = VariablePath::new_local(&name);
+                        variables::variable(name, value, path)
                     })
                     .collect(),
             })
         }))
     }
 
+    fn inspect_variable(&self, path: VariablePath) -> anyhow::Result<InspectVariableInfo> {
+        self.with_ctx(Box::new(move |_, eval| {
+            let local = eval
+                .local_variables()
+                .get(&path.name)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("Unknown local variable `{}`", path.name))?;
+            let value = variables::resolve(local, &path)?;
+            Ok(InspectVariableInfo {
+                sub_values: variables::inspect(value, &path),
+            })
+        }))
+    }
+
     fn continue_(&self, _: ContinueArguments) -> anyhow::Result<ContinueResponseBody> {
+        *self.state.step.lock().unwrap() = None;
         self.inject_continue();
         Ok(ContinueResponseBody::default())
     }
 
+    fn step_over(&self, _: NextArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Over);
+        Ok(())
+    }
+
+    fn step_in(&self, _: StepInArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Into);
+        Ok(())
+    }
+
+    fn step_out(&self, _: StepOutArguments) -> anyhow::Result<()> {
+        self.inject_step(StepKind::Out);
+        Ok(())
+    }
+
     fn evaluate(&self, x: EvaluateArguments) -> anyhow::Result<EvaluateResponseBody> {
         let disable_breakpoints = self.state.disable_breakpoints.dupe();
         self.with_ctx(Box::new(move |_, eval| {
-            // We don't want to trigger breakpoints during an evaluate,
-            // not least because we currently don't allow reenterant evaluate
-            disable_breakpoints.fetch_add(1, Ordering::SeqCst);
-            let ast = AstModule::parse("interactive", x.expression.clone(), &Dialect::Extended);
-            let s = match ast.and_then(|ast| eval.eval_statements(ast)) {
+            let s = match evaluate(eval, &disable_breakpoints, &x.expression) {
                 Err(e) => format!("{:#}", e),
                 Ok(v) => v.to_string(),
             };
-            disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
             Ok(EvaluateResponseBody {
                 indexed_variables: None,
                 named_variables: None,
@@ -303,6 +478,17 @@ impl DapAdapterImpl {
         self.inject(Box::new(|_, _| (Next::Continue, ())))
     }
 
+    fn inject_step(&self, kind: StepKind) {
+        let state = self.state.dupe();
+        self.inject(Box::new(move |_, eval| {
+            *state.step.lock().unwrap() = Some(Step {
+                kind,
+                depth: eval.call_stack_count(),
+            });
+            (Next::Continue, ())
+        }))
+    }
+
     fn with_ctx<T: 'static + Send>(
         &self,
         f: Box<dyn Fn(FileSpanRef, &mut Evaluator) -> T + Send>,
@@ -313,15 +499,241 @@ impl DapAdapterImpl {
     }
 }
 
-fn breakpoint(verified: bool) -> Breakpoint {
+/// Evaluate `expression` in the context of the paused evaluator.
+fn evaluate<'v>(
+    eval: &mut Evaluator<'v, '_>,
+    disable_breakpoints: &AtomicUsize,
+    expression: &str,
+) -> anyhow::Result<Value<'v>> {
+    // We don't want to trigger breakpoints during an evaluate,
+    // not least because we currently don't allow reenterant evaluate
+    disable_breakpoints.fetch_add(1, Ordering::SeqCst);
+    let ast = AstModule::parse("interactive", expression.to_owned(), &Dialect::Extended);
+    let res = ast.and_then(|ast| eval.eval_statements(ast));
+    disable_breakpoints.fetch_sub(1, Ordering::SeqCst);
+    res
+}
+
+fn breakpoint(verified: bool, message: Option<String>) -> Breakpoint {
     Breakpoint {
         column: None,
         end_column: None,
         end_line: None,
         id: None,
         line: None,
-        message: None,
+        message,
         source: None,
         verified,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::thread::JoinHandle;
+
+    use super::*;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+
+    const PROGRAM: &str = "\
+def f():
+    x = 1
+    return x
+y = f()
+z = y
+";
+
+    #[derive(Debug)]
+    enum Event {
+        Stopped(StopReason),
+        Output(String),
+    }
+
+    #[derive(Debug)]
+    struct TestClient {
+        events: Mutex<Sender<Event>>,
+    }
+
+    impl DapAdapterClient for TestClient {
+        fn event_stopped(&self, reason: StopReason) {
+            self.events
+                .lock()
+                .unwrap()
+                .send(Event::Stopped(reason))
+                .unwrap();
+        }
+
+        fn event_output(&self, output: String) {
+            self.events
+                .lock()
+                .unwrap()
+                .send(Event::Output(output))
+                .unwrap();
+        }
+
+        fn get_ast(&self, source: &str) -> anyhow::Result<AstModule> {
+            AstModule::parse(source, PROGRAM.to_owned(), &Dialect::Extended)
+        }
+    }
+
+    struct TestEval {
+        adapter: DapAdapterImpl,
+        events: Receiver<Event>,
+        thread: JoinHandle<()>,
+    }
+
+    impl TestEval {
+        /// Evaluate `PROGRAM` on another thread, with the given breakpoints set.
+        fn start(breakpoints: Vec<SourceBreakpoint>) -> TestEval {
+            let (sender, events) = channel();
+            let (sender_to_eval, receiver) = channel();
+            let state = Arc::new(SharedAdapterState {
+                client: Box::new(TestClient {
+                    events: Mutex::new(sender),
+                }),
+                breakpoints: DapBreakpoints::default(),
+                disable_breakpoints: Arc::new(0usize.into()),
+                step: Mutex::new(None),
+            });
+            let adapter = DapAdapterImpl {
+                state: state.dupe(),
+                sender: sender_to_eval,
+            };
+            let hook = DapAdapterEvalHookImpl::new(state, receiver);
+            adapter
+                .set_breakpoints(SetBreakpointsArguments {
+                    breakpoints: Some(breakpoints),
+                    lines: None,
+                    source: Source {
+                        path: Some("test.star".to_owned()),
+                        ..Source::default()
+                    },
+                    source_modified: None,
+                })
+                .unwrap();
+            let thread = thread::spawn(move || {
+                let module = Module::new();
+                let mut eval = Evaluator::new(&module);
+                Box::new(hook).add_dap_hooks(&mut eval);
+                let ast =
+                    AstModule::parse("test.star", PROGRAM.to_owned(), &Dialect::Extended).unwrap();
+                eval.eval_module(ast, &Globals::standard()).unwrap();
+            });
+            TestEval {
+                adapter,
+                events,
+                thread,
+            }
+        }
+
+        /// Wait until the evaluation stops, and return the line it stopped at.
+        fn stopped(&self, reason: StopReason) -> i64 {
+            match self.events.recv().unwrap() {
+                Event::Stopped(r) => assert_eq!(reason, r),
+                Event::Output(output) => panic!("Unexpected output `{}`", output),
+            }
+            self.adapter.top_frame().unwrap().unwrap().line
+        }
+
+        fn continue_(&self) {
+            self.adapter
+                .continue_(ContinueArguments { thread_id: 0 })
+                .unwrap();
+        }
+
+        /// Wait for the evaluation to finish, and return the output it printed.
+        fn finish(self) -> Vec<String> {
+            self.thread.join().unwrap();
+            assert!(self.adapter.state.step.lock().unwrap().is_none());
+            self.events
+                .try_iter()
+                .map(|event| match event {
+                    Event::Output(output) => output,
+                    Event::Stopped(reason) => panic!("Unexpected stop {:?}", reason),
+                })
+                .collect()
+        }
+    }
+
+    fn breakpoint(line: i64) -> SourceBreakpoint {
+        SourceBreakpoint {
+            column: None,
+            condition: None,
+            hit_condition: None,
+            line,
+            log_message: None,
+        }
+    }
+
+    #[test]
+    fn test_step_over_skips_callee() {
+        let eval = TestEval::start(vec![breakpoint(4)]);
+        assert_eq!(4, eval.stopped(StopReason::Breakpoint));
+        eval.adapter
+            .step_over(NextArguments { thread_id: 0 })
+            .unwrap();
+        assert_eq!(5, eval.stopped(StopReason::Step));
+        eval.continue_();
+        assert!(eval.finish().is_empty());
+    }
+
+    #[test]
+    fn test_step_in_and_out() {
+        let eval = TestEval::start(vec![breakpoint(4)]);
+        assert_eq!(4, eval.stopped(StopReason::Breakpoint));
+        eval.adapter
+            .step_in(StepInArguments {
+                target_id: None,
+                thread_id: 0,
+            })
+            .unwrap();
+        assert_eq!(2, eval.stopped(StopReason::Step));
+        eval.adapter
+            .step_out(StepOutArguments { thread_id: 0 })
+            .unwrap();
+        assert_eq!(5, eval.stopped(StopReason::Step));
+        eval.continue_();
+        assert!(eval.finish().is_empty());
+    }
+
+    #[test]
+    fn test_false_condition() {
+        let eval = TestEval::start(vec![SourceBreakpoint {
+            condition: Some("y == 2".to_owned()),
+            ..breakpoint(5)
+        }]);
+        assert!(eval.finish().is_empty());
+    }
+
+    #[test]
+    fn test_true_condition() {
+        let eval = TestEval::start(vec![SourceBreakpoint {
+            condition: Some("y == 1".to_owned()),
+            ..breakpoint(5)
+        }]);
+        assert_eq!(5, eval.stopped(StopReason::Breakpoint));
+        eval.continue_();
+        assert!(eval.finish().is_empty());
+    }
+
+    #[test]
+    fn test_logpoint() {
+        let eval = TestEval::start(vec![SourceBreakpoint {
+            log_message: Some("y is {y}".to_owned()),
+            ..breakpoint(5)
+        }]);
+        assert_eq!(vec!["y is 1\n".to_owned()], eval.finish());
+    }
+
+    #[test]
+    fn test_step_cleared_at_end_of_evaluation() {
+        let eval = TestEval::start(vec![breakpoint(5)]);
+        assert_eq!(5, eval.stopped(StopReason::Breakpoint));
+        // There is no statement to stop at after the last one.
+        eval.adapter
+            .step_over(NextArguments { thread_id: 0 })
+            .unwrap();
+        assert!(eval.finish().is_empty());
+    }
+}
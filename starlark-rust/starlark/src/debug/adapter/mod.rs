@@ -22,19 +22,54 @@
 use std::fmt::Debug;
 
 use debugserver_types::*;
+use dupe::Dupe;
 
 use crate::eval::Evaluator;
 use crate::syntax::AstModule;
 
+mod conditions;
 mod implementation;
+mod variables;
+
+pub use implementation::DapBreakpoints;
+
+/// Why the evaluation stopped.
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum StopReason {
+    /// A breakpoint was hit.
+    Breakpoint,
+    /// A step request finished.
+    Step,
+}
+
+impl StopReason {
+    /// The DAP `reason` of a stopped event.
+    pub fn to_dap(self) -> String {
+        match self {
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Step => "step",
+        }
+        .to_owned()
+    }
+}
 
 /// The DapAdapterClient is implemented by the user and provides functionality required by the DapAdapter.
 pub trait DapAdapterClient: Debug + Send + Sync + 'static {
-    /// Indicates that the evaluation stopped at a breakpoint.
-    fn event_stopped(&self);
+    /// Indicates that the evaluation stopped.
+    fn event_stopped(&self, reason: StopReason);
+
+    /// Prints the output of a logpoint, which includes the trailing newline.
+    fn event_output(&self, output: String);
 
     /// Gets the ast for source.
     fn get_ast(&self, source: &str) -> anyhow::Result<AstModule>;
+
+    /// Called on the evaluating thread when it stops, with a function which blocks that thread
+    /// until the evaluation resumes. Override this to tell e.g. an async runtime that the
+    /// thread is going to block.
+    fn while_stopped(&self, wait: &mut dyn FnMut()) {
+        wait()
+    }
 }
 
 /// Information about the variables scopes
@@ -43,6 +78,44 @@ pub struct ScopesInfo {
     pub num_locals: usize,
 }
 
+/// A step from a value to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// The element at an index of a list or tuple, or the value at an index of a dict.
+    Index(usize),
+    /// A field of a struct.
+    Attr(String),
+}
+
+/// The path from a local variable to a value inside it, e.g. `x[1].y`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VariablePath {
+    /// The name of the local variable.
+    pub name: String,
+    /// How to get from the variable to the value.
+    pub access: Vec<PathSegment>,
+}
+
+impl VariablePath {
+    /// The path to a local variable itself.
+    pub fn new_local(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            access: Vec::new(),
+        }
+    }
+
+    /// The path to a child of this value.
+    pub fn child(&self, segment: PathSegment) -> Self {
+        let mut access = self.access.clone();
+        access.push(segment);
+        Self {
+            name: self.name.clone(),
+            access,
+        }
+    }
+}
+
 /// Information about a variable.
 pub struct Variable {
     /// Name of the variable.
@@ -51,11 +124,15 @@ pub struct Variable {
     pub value: String,
     /// The variables type.
     pub type_: String,
+    /// If the value has children, such as the elements of a list, the path to pass to
+    /// [`DapAdapter::inspect_variable`] to get them.
+    pub children: Option<VariablePath>,
 }
 
 impl Variable {
-    /// Helper to convert to the DAP Variable type.
-    pub fn to_dap(self) -> debugserver_types::Variable {
+    /// Helper to convert to the DAP Variable type, where `variables_reference` is how the
+    /// client asks for the children (0 if there are none).
+    pub fn to_dap(self, variables_reference: i64) -> debugserver_types::Variable {
         debugserver_types::Variable {
             name: self.name,
             value: self.value,
@@ -64,7 +141,7 @@ impl Variable {
             indexed_variables: None,
             named_variables: None,
             presentation_hint: None,
-            variables_reference: 0,
+            variables_reference,
         }
     }
 }
@@ -75,6 +152,12 @@ pub struct VariablesInfo {
     pub locals: Vec<Variable>,
 }
 
+/// Information about the children of a variable.
+pub struct InspectVariableInfo {
+    /// The children, e.g. the elements of a list or the fields of a struct.
+    pub sub_values: Vec<Variable>,
+}
+
 /// The DapAdapter accepts DAP requests and updates the hooks in the running evaluator.
 pub trait DapAdapter: Debug + Send + 'static {
     /// Sets multiple breakpoints for a file (and clears existing ones).
@@ -103,11 +186,34 @@ pub trait DapAdapter: Debug + Send + 'static {
     /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_Variables>
     fn variables(&self) -> anyhow::Result<VariablesInfo>;
 
+    /// Gets the children of a value reachable from a local variable, as given by
+    /// [`Variable::children`] while stopped at the same point.
+    ///
+    /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_Variables>
+    fn inspect_variable(&self, path: VariablePath) -> anyhow::Result<InspectVariableInfo>;
+
     /// Resumes execution.
     ///
     /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_Continue>
     fn continue_(&self, args: ContinueArguments) -> anyhow::Result<ContinueResponseBody>;
 
+    /// Resumes execution until the next statement in the current function, or in its caller
+    /// if it returns.
+    ///
+    /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_Next>
+    fn step_over(&self, args: NextArguments) -> anyhow::Result<()>;
+
+    /// Resumes execution until the next statement, including in functions called by the
+    /// current one.
+    ///
+    /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_StepIn>
+    fn step_in(&self, args: StepInArguments) -> anyhow::Result<()>;
+
+    /// Resumes execution until the current function returns.
+    ///
+    /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_StepOut>
+    fn step_out(&self, args: StepOutArguments) -> anyhow::Result<()>;
+
     /// Evaluates in expression in the context of the top-most frame.
     ///
     /// See <https://microsoft.github.io/debug-adapter-protocol/specification#Requests_Evaluate>
@@ -127,6 +233,9 @@ pub fn dap_capabilities() -> Capabilities {
     Capabilities {
         supports_configuration_done_request: Some(true),
         supports_evaluate_for_hovers: Some(true),
+        supports_conditional_breakpoints: Some(true),
+        supports_hit_conditional_breakpoints: Some(true),
+        supports_log_points: Some(true),
         supports_set_variable: Some(true),
         supports_step_in_targets_request: Some(true),
         ..Capabilities::default()
@@ -137,5 +246,14 @@ pub fn dap_capabilities() -> Capabilities {
 pub fn prepare_dap_adapter(
     client: Box<dyn DapAdapterClient>,
 ) -> (impl DapAdapter, impl DapAdapterEvalHook) {
-    implementation::prepare_dap_adapter(client)
+    implementation::prepare_dap_adapter(client, DapBreakpoints::default())
+}
+
+/// Like [`prepare_dap_adapter`], but the breakpoints are shared with every other adapter
+/// created with the same [`DapBreakpoints`].
+pub fn prepare_dap_adapter_with_breakpoints(
+    client: Box<dyn DapAdapterClient>,
+    breakpoints: DapBreakpoints,
+) -> (impl DapAdapter, impl DapAdapterEvalHook) {
+    implementation::prepare_dap_adapter(client, breakpoints)
 }
@@ -0,0 +1,154 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Expanding compound values (lists, tuples, dicts and structs) in the variables view.
+
+use crate::debug::PathSegment;
+use crate::debug::Variable;
+use crate::debug::VariablePath;
+use crate::values::dict::DictRef;
+use crate::values::list::ListRef;
+use crate::values::structs::StructRef;
+use crate::values::tuple::TupleRef;
+use crate::values::Value;
+
+/// The children of a value, with their names and how to reach them, in display order.
+fn children<'v>(x: Value<'v>) -> Vec<(String, PathSegment, Value<'v>)> {
+    fn indexed<'v>(xs: &[Value<'v>]) -> Vec<(String, PathSegment, Value<'v>)> {
+        xs.iter()
+            .enumerate()
+            .map(|(i, x)| (i.to_string(), PathSegment::Index(i), *x))
+            .collect()
+    }
+
+    if let Some(xs) = ListRef::from_value(x) {
+        indexed(xs.content())
+    } else if let Some(xs) = TupleRef::from_value(x) {
+        indexed(xs.content())
+    } else if let Some(xs) = DictRef::from_value(x) {
+        xs.iter()
+            .enumerate()
+            .map(|(i, (k, v))| (k.to_repr(), PathSegment::Index(i), v))
+            .collect()
+    } else if let Some(xs) = StructRef::from_value(x) {
+        xs.iter()
+            .map(|(k, v)| {
+                (
+                    k.as_str().to_owned(),
+                    PathSegment::Attr(k.as_str().to_owned()),
+                    v,
+                )
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+fn has_children(x: Value) -> bool {
+    if let Some(xs) = ListRef::from_value(x) {
+        !xs.content().is_empty()
+    } else if let Some(xs) = TupleRef::from_value(x) {
+        !xs.content().is_empty()
+    } else if let Some(xs) = DictRef::from_value(x) {
+        xs.len() != 0
+    } else if let Some(xs) = StructRef::from_value(x) {
+        xs.iter().len() != 0
+    } else {
+        false
+    }
+}
+
+/// Describe the value at `path`, reporting its `children` if it has any.
+pub(crate) fn variable(name: String, x: Value, path: VariablePath) -> Variable {
+    Variable {
+        name,
+        value: x.to_string(),
+        type_: x.get_type().to_owned(),
+        children: has_children(x).then_some(path),
+    }
+}
+
+/// Follow `path` from the value of the local variable it starts at.
+pub(crate) fn resolve<'v>(local: Value<'v>, path: &VariablePath) -> anyhow::Result<Value<'v>> {
+    let mut x = local;
+    for segment in &path.access {
+        x = children(x)
+            .into_iter()
+            .find_map(|(_, s, v)| (s == *segment).then_some(v))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Value `{}` has no child at `{:?}`", x.to_repr(), segment)
+            })?;
+    }
+    Ok(x)
+}
+
+/// The children of the value at `path`.
+pub(crate) fn inspect(x: Value, path: &VariablePath) -> Vec<Variable> {
+    children(x)
+        .into_iter()
+        .map(|(name, segment, v)| variable(name, v, path.child(segment)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use gazebo::prelude::SliceExt;
+
+    use super::*;
+    use crate::environment::Globals;
+    use crate::environment::Module;
+    use crate::eval::Evaluator;
+    use crate::syntax::AstModule;
+    use crate::syntax::Dialect;
+
+    #[test]
+    fn test_inspect() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        let ast = AstModule::parse(
+            "x.star",
+            "struct(a = [1, (2, 3)], b = {'k': 'v'}, c = None)".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let x = eval.eval_module(ast, &Globals::extended()).unwrap();
+
+        let describe = |xs: Vec<Variable>| {
+            xs.map(|x| format!("{} = {} ({})", x.name, x.value, x.children.is_some()))
+        };
+        let root = VariablePath::new_local("x");
+        assert_eq!(
+            describe(inspect(x, &root)),
+            &[
+                "a = [1, (2, 3)] (true)",
+                "b = {\"k\": \"v\"} (true)",
+                "c = None (false)"
+            ]
+        );
+
+        let path = root
+            .child(PathSegment::Attr("a".to_owned()))
+            .child(PathSegment::Index(1));
+        let child = resolve(x, &path).unwrap();
+        assert_eq!(
+            describe(inspect(child, &path)),
+            &["0 = 2 (false)", "1 = 3 (false)"]
+        );
+        assert!(resolve(x, &path.child(PathSegment::Index(5))).is_err());
+    }
+}
@@ -50,6 +50,7 @@ use crate::eval::compiler::scope::ScopeId;
 use crate::eval::compiler::Compiler;
 use crate::eval::runtime::arguments::ArgNames;
 use crate::eval::runtime::arguments::ArgumentsFull;
+use crate::eval::runtime::profile::or_instrumentation::ProfileOrInstrumentationMode;
 use crate::hint::unlikely;
 use crate::syntax::ast::AstModule;
 use crate::syntax::DialectTypes;
@@ -61,6 +62,12 @@ impl<'v, 'a> Evaluator<'v, 'a> {
     pub fn eval_module(&mut self, ast: AstModule, globals: &Globals) -> anyhow::Result<Value<'v>> {
         let start = Instant::now();
 
+        if self.profile_or_instrumentation_mode
+            == ProfileOrInstrumentationMode::Profile(ProfileMode::Coverage)
+        {
+            self.stmt_profile.add_module(&ast.statement, &ast.codemap);
+        }
+
         let AstModule {
             codemap,
             statement,
@@ -92,10 +92,6 @@ pub(crate) enum EvaluatorError {
     ProfileOrInstrumentationAlreadyEnabled,
     #[error("Top frame is not def (internal error)")]
     TopFrameNotDef,
-    #[error(
-        "Coverage profile generation not implemented (but can be obtained with `.coverage()` function)"
-    )]
-    CoverageNotImplemented,
     #[error("Coverage not enabled")]
     CoverageNotEnabled,
 }
@@ -136,7 +132,7 @@ pub struct Evaluator<'v, 'a> {
     // Extra functions to run on each statement, usually empty
     pub(crate) before_stmt: BeforeStmt<'a>,
     // Used for line profiling
-    stmt_profile: StmtProfile,
+    pub(crate) stmt_profile: StmtProfile,
     // Bytecode profile.
     pub(crate) bc_profile: BcProfile,
     // Total time spent in runtime typechecking.
@@ -331,7 +327,7 @@ impl<'v, 'a> Evaluator<'v, 'a> {
                 Err(EvaluatorError::RetainedMemoryProfilingCannotBeObtainedFromEvaluator.into())
             }
             ProfileMode::Statement => self.stmt_profile.gen(),
-            ProfileMode::Coverage => Err(EvaluatorError::CoverageNotImplemented.into()),
+            ProfileMode::Coverage => self.stmt_profile.gen_coverage(),
             ProfileMode::Bytecode => self.bc_profile.gen_bc_profile(),
             ProfileMode::BytecodePairs => self.bc_profile.gen_bc_pairs_profile(),
             ProfileMode::TimeFlame => self.flame_profile.gen(),
@@ -0,0 +1,110 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::codemap::CodeMap;
+use crate::codemap::Span;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::Expr;
+use crate::syntax::ast::Stmt;
+
+/// Line coverage, collected with [`ProfileMode::Coverage`](crate::eval::ProfileMode::Coverage).
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CoverageProfileData {
+    /// For each file, how many times the statements starting on each line were executed.
+    /// Lines are 1-based, and lines with statements which were never executed have a count of 0.
+    files: BTreeMap<String, BTreeMap<usize, usize>>,
+}
+
+/// The 1-based lines on which the statements of a module start.
+pub(crate) fn statement_lines(statement: &AstStmt, codemap: &CodeMap) -> Vec<usize> {
+    fn visit(x: &AstStmt, codemap: &CodeMap, res: &mut Vec<usize>) {
+        match &**x {
+            // Either not statements in their own right, or never reach the bytecode.
+            Stmt::Statements(_) | Stmt::Pass | Stmt::Load(_) => {}
+            Stmt::Expression(e) if matches!(&**e, Expr::Literal(AstLiteral::String(_))) => {}
+            _ => res.push(codemap.find_line(x.span.begin()) + 1),
+        }
+        x.visit_stmt(|x| visit(x, codemap, res));
+    }
+
+    let mut res = Vec::new();
+    visit(statement, codemap, &mut res);
+    res
+}
+
+impl CoverageProfileData {
+    /// Record the lines of a file which have statements on them, even if never executed.
+    /// Files which were not evaluated with coverage enabled, e.g. `.bzl` files whose functions
+    /// were only called, only report the lines which were executed.
+    pub(crate) fn add_lines(&mut self, file: &str, statement_lines: &[usize]) {
+        let lines = self.files.entry(file.to_owned()).or_default();
+        for line in statement_lines {
+            lines.entry(*line).or_insert(0);
+        }
+    }
+
+    /// Record that the statement at `span` was executed `count` times.
+    pub(crate) fn add_hits(&mut self, codemap: &CodeMap, span: Span, count: usize) {
+        let line = codemap.find_line(span.begin()) + 1;
+        *self
+            .files
+            .entry(codemap.filename().to_owned())
+            .or_default()
+            .entry(line)
+            .or_insert(0) += count;
+    }
+
+    pub(crate) fn merge<'a>(
+        profiles: impl IntoIterator<Item = &'a CoverageProfileData>,
+    ) -> CoverageProfileData {
+        let mut res = CoverageProfileData::default();
+        for profile in profiles {
+            for (file, lines) in &profile.files {
+                let res = res.files.entry(file.clone()).or_default();
+                for (line, count) in lines {
+                    *res.entry(*line).or_insert(0) += count;
+                }
+            }
+        }
+        res
+    }
+
+    /// Generate the coverage in the [LCOV](https://github.com/linux-test-project/lcov) tracefile format.
+    /// Each file is written under the path returned by `path`, or left out if it returns `None`.
+    pub(crate) fn gen_lcov(&self, path: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut res = String::new();
+        for (file, lines) in &self.files {
+            let file = match path(file) {
+                Some(file) => file,
+                None => continue,
+            };
+            writeln!(res, "TN:").unwrap();
+            writeln!(res, "SF:{}", file).unwrap();
+            for (line, count) in lines {
+                writeln!(res, "DA:{},{}", line, count).unwrap();
+            }
+            writeln!(res, "LF:{}", lines.len()).unwrap();
+            writeln!(res, "LH:{}", lines.values().filter(|x| **x > 0).count()).unwrap();
+            writeln!(res, "end_of_record").unwrap();
+        }
+        res
+    }
+}
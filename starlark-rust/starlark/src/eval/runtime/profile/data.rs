@@ -24,7 +24,9 @@ use gazebo::prelude::SliceExt;
 
 use crate::eval::runtime::profile::bc::BcPairsProfileData;
 use crate::eval::runtime::profile::bc::BcProfileData;
+use crate::eval::runtime::profile::coverage::CoverageProfileData;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
+use crate::eval::runtime::profile::stmt::StmtProfileStats;
 use crate::eval::ProfileMode;
 use crate::values::AggregateHeapProfileInfo;
 
@@ -38,6 +40,10 @@ enum ProfileDataError {
     DifferentProfileModes,
     #[error("Merge of profile data for profile mode `{0}` is not implemented")]
     MergeNotImplemented(ProfileMode),
+    #[error("Top functions are only available for flame graph profile modes, not `{0}`")]
+    TopFunctionsNotFlameGraph(ProfileMode),
+    #[error("LCOV is only available for the coverage profile mode, not `{0}`")]
+    LcovNotCoverage(ProfileMode),
 }
 
 #[derive(Clone, Debug)]
@@ -47,6 +53,8 @@ pub(crate) enum ProfileDataImpl {
     AggregateHeapProfileInfo(Box<AggregateHeapProfileInfo>),
     /// Flame graph data is in milliseconds.
     TimeFlameProfile(FlameGraphData),
+    Statement(Box<StmtProfileStats>),
+    Coverage(Box<CoverageProfileData>),
     Other(String),
 }
 
@@ -87,6 +95,41 @@ impl ProfileData {
             (ProfileDataImpl::TimeFlameProfile(_), _) => {
                 Err(ProfileDataError::ProfileDataNotConsistent.into())
             }
+            (ProfileDataImpl::Statement(data), ProfileMode::Statement) => Ok(data.gen_csv()),
+            (ProfileDataImpl::Statement(_), _) => {
+                Err(ProfileDataError::ProfileDataNotConsistent.into())
+            }
+            (ProfileDataImpl::Coverage(data), ProfileMode::Coverage) => {
+                Ok(data.gen_lcov(&|file| Some(file.to_owned())))
+            }
+            (ProfileDataImpl::Coverage(_), _) => {
+                Err(ProfileDataError::ProfileDataNotConsistent.into())
+            }
+        }
+    }
+
+    /// For the flame graph profile modes, generate a CSV table of the `n` functions with
+    /// the largest values (e.g. time or allocations), including the values of the functions they call.
+    pub fn gen_top_functions_csv(&self, n: usize) -> anyhow::Result<String> {
+        match (&self.profile, &self.profile_mode) {
+            (ProfileDataImpl::TimeFlameProfile(data), ProfileMode::TimeFlame) => {
+                Ok(data.gen_top_csv(n))
+            }
+            (
+                ProfileDataImpl::AggregateHeapProfileInfo(profile),
+                ProfileMode::HeapFlameRetained | ProfileMode::HeapFlameAllocated,
+            ) => Ok(profile.flame_graph_data().gen_top_csv(n)),
+            _ => Err(ProfileDataError::TopFunctionsNotFlameGraph(self.profile_mode.dupe()).into()),
+        }
+    }
+
+    /// For the coverage profile mode, generate the LCOV tracefile with each file under the path
+    /// returned by `path`, leaving out the files for which it returns `None`. Use this when the
+    /// names files were evaluated with are not the paths other tools know them by.
+    pub fn gen_lcov(&self, path: impl Fn(&str) -> Option<String>) -> anyhow::Result<String> {
+        match (&self.profile, &self.profile_mode) {
+            (ProfileDataImpl::Coverage(data), ProfileMode::Coverage) => Ok(data.gen_lcov(&path)),
+            _ => Err(ProfileDataError::LcovNotCoverage(self.profile_mode.dupe()).into()),
         }
     }
 
@@ -152,6 +195,22 @@ impl ProfileData {
                 let profile = FlameGraphData::merge(profiles);
                 ProfileDataImpl::TimeFlameProfile(profile)
             }
+            ProfileMode::Statement => {
+                let profiles = profiles.try_map(|p| match &p.profile {
+                    ProfileDataImpl::Statement(data) => Ok(&**data),
+                    _ => Err(ProfileDataError::ProfileDataNotConsistent),
+                })?;
+                let profile = StmtProfileStats::merge(profiles);
+                ProfileDataImpl::Statement(Box::new(profile))
+            }
+            ProfileMode::Coverage => {
+                let profiles = profiles.try_map(|p| match &p.profile {
+                    ProfileDataImpl::Coverage(data) => Ok(&**data),
+                    _ => Err(ProfileDataError::ProfileDataNotConsistent),
+                })?;
+                let profile = CoverageProfileData::merge(profiles);
+                ProfileDataImpl::Coverage(Box::new(profile))
+            }
             profile_mode => {
                 return Err(ProfileDataError::MergeNotImplemented(profile_mode.dupe()).into());
             }
@@ -167,7 +226,11 @@ impl ProfileData {
 mod tests {
     use dupe::Dupe;
 
+    use crate::codemap::CodeMap;
+    use crate::codemap::Pos;
+    use crate::codemap::Span;
     use crate::eval::runtime::profile::bc::BcPairsProfileData;
+    use crate::eval::runtime::profile::coverage::CoverageProfileData;
     use crate::eval::runtime::profile::data::ProfileDataImpl;
     use crate::eval::runtime::profile::flamegraph::FlameGraphData;
     use crate::eval::ProfileData;
@@ -219,4 +282,38 @@ mod tests {
         // Smoke.
         ProfileData::merge([&profile, &profile]).unwrap();
     }
+
+    #[test]
+    fn merge_statement() {
+        let profile = ProfileData {
+            profile_mode: ProfileMode::Statement,
+            profile: ProfileDataImpl::Statement(Box::default()),
+        };
+        // Smoke.
+        ProfileData::merge([&profile, &profile]).unwrap();
+    }
+
+    #[test]
+    fn merge_coverage() {
+        let codemap = CodeMap::new("x.star".to_owned(), "x = 1\ny = 2\n".to_owned());
+        let mut data = CoverageProfileData::default();
+        data.add_lines("x.star", &[1, 2]);
+        data.add_hits(&codemap, Span::new(Pos::new(0), Pos::new(5)), 1);
+        let profile = ProfileData {
+            profile_mode: ProfileMode::Coverage,
+            profile: ProfileDataImpl::Coverage(Box::new(data)),
+        };
+        let merged = ProfileData::merge([&profile, &profile]).unwrap();
+        assert_eq!(
+            "TN:\nSF:x.star\nDA:1,2\nDA:2,0\nLF:2\nLH:1\nend_of_record\n",
+            merged.gen().unwrap()
+        );
+        assert_eq!(
+            "TN:\nSF:src/x.star\nDA:1,2\nDA:2,0\nLF:2\nLH:1\nend_of_record\n",
+            merged
+                .gen_lcov(|file| Some(format!("src/{}", file)))
+                .unwrap()
+        );
+        assert_eq!("", merged.gen_lcov(|_| None).unwrap());
+    }
 }
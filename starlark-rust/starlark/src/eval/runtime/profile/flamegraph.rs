@@ -17,12 +17,31 @@
 
 //! Utility to write files in formats understood by `flamegraph.pl`.
 
+use std::cmp::Reverse;
 use std::fmt::Write;
 
 use dupe::Dupe;
 use starlark_map::small_map::SmallMap;
 
+use crate::eval::compiler::def::Def;
+use crate::eval::compiler::def::FrozenDef;
+use crate::eval::runtime::profile::csv::CsvWriter;
 use crate::values::layout::heap::profile::arc_str::ArcStr;
+use crate::values::Value;
+
+/// Qualify the `name` of a function with the file it is defined in, if it is defined in
+/// Starlark, so functions with the same name in different files (e.g. the `_impl` of many
+/// rules) are counted separately.
+pub(crate) fn qualified_function_name(function: Value, name: String) -> String {
+    let codemap = if let Some(def) = function.downcast_ref::<FrozenDef>() {
+        &def.def_info.codemap
+    } else if let Some(def) = function.downcast_ref::<Def>() {
+        &def.def_info.codemap
+    } else {
+        return name;
+    };
+    format!("{} ({})", name, codemap.filename())
+}
 
 /// Node in flamegraph tree.
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
@@ -51,6 +70,30 @@ impl FlameGraphNode {
         }
     }
 
+    /// Add the total (including children) and self values of the children of this node
+    /// to `totals`, returning the total of this node. Functions which recurse are only
+    /// counted once for their total.
+    fn add_totals<'a>(
+        &'a self,
+        stack: &mut Vec<&'a str>,
+        totals: &mut SmallMap<&'a str, (u64, u64)>,
+    ) -> u64 {
+        let mut total = self.value.unwrap_or(0);
+        for (k, v) in self.children.iter() {
+            let recursive = stack.contains(&&**k);
+            stack.push(k);
+            let child = v.add_totals(stack, totals);
+            stack.pop().unwrap();
+            let entry = totals.entry(&**k).or_default();
+            if !recursive {
+                entry.0 += child;
+            }
+            entry.1 += v.value.unwrap_or(0);
+            total += child;
+        }
+        total
+    }
+
     /// Add value to the node.
     pub(crate) fn add(&mut self, value: u64) {
         match &mut self.value {
@@ -84,6 +127,24 @@ impl FlameGraphData {
         writer.finish()
     }
 
+    /// A CSV table of the `n` functions with the largest total values,
+    /// where the total includes the values of the functions they call.
+    pub(crate) fn gen_top_csv(&self, n: usize) -> String {
+        let mut totals = SmallMap::new();
+        self.root.add_totals(&mut Vec::new(), &mut totals);
+        let mut totals = Vec::from_iter(totals);
+        totals.sort_by_key(|(name, (total, _))| (Reverse(*total), *name));
+
+        let mut csv = CsvWriter::new(["Function", "Total", "Self"]);
+        for (name, (total, self_value)) in totals.into_iter().take(n) {
+            csv.write_value(name);
+            csv.write_value(total);
+            csv.write_value(self_value);
+            csv.finish_row();
+        }
+        csv.finish()
+    }
+
     pub(crate) fn root(&mut self) -> &mut FlameGraphNode {
         &mut self.root
     }
@@ -167,4 +228,21 @@ mod tests {
 
         assert_eq!(expected, c);
     }
+
+    #[test]
+    fn test_gen_top_csv() {
+        let mut data = FlameGraphData::default();
+        data.root().child("a".into()).add(10);
+        data.root().child("a".into()).child("b".into()).add(20);
+        data.root()
+            .child("a".into())
+            .child("b".into())
+            .child("a".into())
+            .add(5);
+        data.root().child("c".into()).add(1);
+        assert_eq!(
+            "Function,Total,Self\n\"a\",35,15\n\"b\",25,20\n",
+            data.gen_top_csv(2)
+        );
+    }
 }
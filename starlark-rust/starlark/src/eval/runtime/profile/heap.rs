@@ -180,4 +180,34 @@ f
 
         Ok(())
     }
+
+    #[test]
+    fn test_top_functions_by_file() -> anyhow::Result<()> {
+        let globals = Globals::standard();
+        let a = Module::new();
+        let b = Module::new();
+        let mut fs = Vec::new();
+        for (file, module) in [("a.bzl", &a), ("b.bzl", &b)] {
+            let ast = AstModule::parse(
+                file,
+                "def f():\n    return [1, 2]\nf\n".to_owned(),
+                &Dialect::Extended,
+            )?;
+            fs.push(Evaluator::new(module).eval_module(ast, &globals)?);
+        }
+
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+        eval.enable_profile(&ProfileMode::HeapFlameAllocated)
+            .unwrap();
+        for f in fs {
+            eval.eval_function(f, &[], &[])?;
+        }
+
+        // Both functions are called `f`, but are different functions.
+        let top = eval.gen_profile()?.gen_top_functions_csv(10)?;
+        assert!(top.contains("\"f (a.bzl)\""), "{}", top);
+        assert!(top.contains("\"f (b.bzl)\""), "{}", top);
+        Ok(())
+    }
 }
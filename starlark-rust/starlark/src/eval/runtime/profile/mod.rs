@@ -22,6 +22,7 @@ use allocative::Allocative;
 use dupe::Dupe;
 
 pub(crate) mod bc;
+pub(crate) mod coverage;
 pub(crate) mod csv;
 pub(crate) mod data;
 pub(crate) mod flamegraph;
@@ -48,7 +49,7 @@ pub enum ProfileMode {
     HeapFlameRetained,
     /// The statement profile mode provides information about time spent in each statement.
     Statement,
-    /// Code coverage.
+    /// Line coverage, written in the LCOV tracefile format.
     Coverage,
     /// The bytecode profile mode provides information about bytecode instructions.
     Bytecode,
@@ -25,14 +25,18 @@ use dupe::Dupe;
 
 use crate::codemap::CodeMap;
 use crate::codemap::CodeMapId;
-use crate::codemap::FileSpan;
 use crate::codemap::FileSpanRef;
 use crate::codemap::ResolvedFileSpan;
+use crate::codemap::ResolvedSpan;
 use crate::codemap::Span;
+use crate::eval::runtime::profile::coverage::statement_lines;
+use crate::eval::runtime::profile::coverage::CoverageProfileData;
 use crate::eval::runtime::profile::csv::CsvWriter;
 use crate::eval::runtime::profile::data::ProfileData;
+use crate::eval::runtime::profile::data::ProfileDataImpl;
 use crate::eval::runtime::small_duration::SmallDuration;
 use crate::eval::ProfileMode;
+use crate::syntax::ast::AstStmt;
 
 #[derive(Debug, thiserror::Error)]
 enum StmtProfileError {
@@ -52,6 +56,8 @@ struct StmtProfileData {
     next_file: CodeMapId,
     last_span: (CodeMapId, Span),
     last_start: Instant,
+    /// The lines with statements of the modules evaluated, for coverage.
+    module_lines: Vec<(String, Vec<usize>)>,
 }
 
 impl StmtProfileData {
@@ -62,6 +68,7 @@ impl StmtProfileData {
             next_file: CodeMapId::EMPTY,
             last_span: (CodeMapId::EMPTY, Span::default()),
             last_start: Instant::now(),
+            module_lines: Vec::new(),
         }
     }
 
@@ -103,7 +110,7 @@ impl StmtProfileData {
         }
     }
 
-    fn write_to_string(&self, now: Instant) -> String {
+    fn stats(&self, now: Instant) -> StmtProfileStats {
         // The statement that was running last won't have been properly updated.
         // However, at this point, we have probably run some post-execution code,
         // so it probably wouldn't have a "fair" timing anyway.
@@ -113,42 +120,38 @@ impl StmtProfileData {
         let mut data = self.clone();
         data.add_last(now);
 
-        struct Item {
-            span: FileSpan,
-            time: SmallDuration,
-            count: usize,
-        }
-        // There should be one EMPTY span entry
-        let mut items = Vec::with_capacity(data.stmts.len() - 1);
-        let mut total_time = SmallDuration::default();
-        let mut total_count = 0;
+        let mut stats = StmtProfileStats::default();
         for ((file, span), (count, time)) in data.stmts {
             // EMPTY represents the first time special-case
             if file != CodeMapId::EMPTY {
                 let span = data.files[&file].file_span(span);
-                total_time += time;
-                total_count += count;
-                items.push(Item { span, time, count })
+                stats.add(
+                    span.file.filename().to_owned(),
+                    span.resolve_span(),
+                    count,
+                    time,
+                );
             }
         }
-        items.sort_by_key(|x| -(x.time.nanos as i128));
+        stats
+    }
 
-        let mut csv = CsvWriter::new(["File", "Span", "Duration(s)", "Count"]);
-        csv.write_value("TOTAL");
-        csv.write_value("");
-        csv.write_value(total_time);
-        csv.write_value(total_count);
-        csv.finish_row();
+    fn coverage_data(&self, now: Instant) -> CoverageProfileData {
+        // Like `write_to_string`, the last statement hasn't been recorded yet.
+        let mut data = self.clone();
+        data.add_last(now);
 
-        for x in items {
-            csv.write_value(x.span.file.filename());
-            csv.write_display(x.span.file.resolve_span(x.span.span));
-            csv.write_value(x.time);
-            csv.write_value(x.count);
-            csv.finish_row();
+        let mut res = CoverageProfileData::default();
+        for (file, lines) in &data.module_lines {
+            res.add_lines(file, lines);
         }
-
-        csv.finish()
+        for ((file, span), (count, _)) in data.stmts {
+            // EMPTY represents the first time special-case
+            if file != CodeMapId::EMPTY {
+                res.add_hits(&data.files[&file], span, count);
+            }
+        }
+        res
     }
 
     fn coverage(&self) -> HashSet<ResolvedFileSpan> {
@@ -167,6 +170,60 @@ impl StmtProfileData {
     }
 }
 
+/// Time spent and number of executions per statement, which can be merged across evaluations.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StmtProfileStats {
+    stmts: HashMap<(String, ResolvedSpan), (usize, SmallDuration)>,
+}
+
+impl StmtProfileStats {
+    fn add(&mut self, file: String, span: ResolvedSpan, count: usize, time: SmallDuration) {
+        let v = self.stmts.entry((file, span)).or_default();
+        v.0 += count;
+        v.1 += time;
+    }
+
+    pub(crate) fn merge<'a>(
+        profiles: impl IntoIterator<Item = &'a StmtProfileStats>,
+    ) -> StmtProfileStats {
+        let mut res = StmtProfileStats::default();
+        for profile in profiles {
+            for ((file, span), (count, time)) in &profile.stmts {
+                res.add(file.clone(), *span, *count, *time);
+            }
+        }
+        res
+    }
+
+    pub(crate) fn gen_csv(&self) -> String {
+        let mut items = Vec::from_iter(&self.stmts);
+        let mut total_time = SmallDuration::default();
+        let mut total_count = 0;
+        for (_, (count, time)) in &items {
+            total_time += *time;
+            total_count += count;
+        }
+        items.sort_by_key(|(_, (_, time))| -(time.nanos as i128));
+
+        let mut csv = CsvWriter::new(["File", "Span", "Duration(s)", "Count"]);
+        csv.write_value("TOTAL");
+        csv.write_value("");
+        csv.write_value(total_time);
+        csv.write_value(total_count);
+        csv.finish_row();
+
+        for ((file, span), (count, time)) in items {
+            csv.write_value(file.as_str());
+            csv.write_display(span);
+            csv.write_value(*time);
+            csv.write_value(*count);
+            csv.finish_row();
+        }
+
+        csv.finish()
+    }
+}
+
 impl StmtProfile {
     pub(crate) fn new() -> Self {
         Self(None)
@@ -182,14 +239,36 @@ impl StmtProfile {
         }
     }
 
+    /// Record the statements of a module about to be evaluated, so coverage includes those
+    /// which never run. Uses the AST we already have, rather than parsing the file again.
+    pub(crate) fn add_module(&mut self, statement: &AstStmt, codemap: &CodeMap) {
+        if let Some(data) = &mut self.0 {
+            data.module_lines.push((
+                codemap.filename().to_owned(),
+                statement_lines(statement, codemap),
+            ));
+        }
+    }
+
     // None = not applicable because not enabled
     pub(crate) fn gen(&self) -> anyhow::Result<ProfileData> {
         let now = Instant::now();
         match &self.0 {
-            Some(data) => Ok(ProfileData::new(
-                ProfileMode::Statement,
-                data.write_to_string(now),
-            )),
+            Some(data) => Ok(ProfileData {
+                profile_mode: ProfileMode::Statement,
+                profile: ProfileDataImpl::Statement(Box::new(data.stats(now))),
+            }),
+            None => Err(StmtProfileError::NotEnabled.into()),
+        }
+    }
+
+    pub(crate) fn gen_coverage(&self) -> anyhow::Result<ProfileData> {
+        let now = Instant::now();
+        match &self.0 {
+            Some(data) => Ok(ProfileData {
+                profile_mode: ProfileMode::Coverage,
+                profile: ProfileDataImpl::Coverage(Box::new(data.coverage_data(now))),
+            }),
             None => Err(StmtProfileError::NotEnabled.into()),
         }
     }
@@ -205,12 +284,14 @@ impl StmtProfile {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
 
     use crate::assert::test_functions;
     use crate::environment::GlobalsBuilder;
     use crate::environment::Module;
     use crate::eval::Evaluator;
     use crate::eval::ProfileMode;
+    use crate::eval::ReturnFileLoader;
     use crate::syntax::AstModule;
     use crate::syntax::Dialect;
 
@@ -255,4 +336,110 @@ xx(*[2])
             coverage
         );
     }
+
+    #[test]
+    fn test_coverage_lcov() {
+        let module = Module::new();
+        let mut eval = Evaluator::new(&module);
+
+        let module = AstModule::parse(
+            "cov.star",
+            r#"
+def xx(x):
+    return noop(x)
+
+def yy():
+    return 1
+
+xx(*[1])
+xx(*[2])
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        eval.enable_profile(&ProfileMode::Coverage).unwrap();
+        let mut globals = GlobalsBuilder::standard();
+        test_functions(&mut globals);
+        eval.eval_module(module, &globals.build()).unwrap();
+
+        assert_eq!(
+            "TN:\n\
+             SF:cov.star\n\
+             DA:2,1\n\
+             DA:3,2\n\
+             DA:5,1\n\
+             DA:6,0\n\
+             DA:8,1\n\
+             DA:9,1\n\
+             LF:6\n\
+             LH:5\n\
+             end_of_record\n",
+            eval.gen_profile().unwrap().gen().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_coverage_lcov_of_loaded_module() {
+        let globals = GlobalsBuilder::standard().build();
+
+        // Only instrumented, so the statements run from the module loading it are recorded.
+        let lib = Module::new();
+        {
+            let mut eval = Evaluator::new(&lib);
+            eval.enable_profile_instrumentation(&ProfileMode::Coverage)
+                .unwrap();
+            let ast = AstModule::parse(
+                "lib.star",
+                r#"
+def xx(x):
+    return x
+
+def yy():
+    return 1
+"#
+                .to_owned(),
+                &Dialect::Extended,
+            )
+            .unwrap();
+            eval.eval_module(ast, &globals).unwrap();
+        }
+        let lib = lib.freeze().unwrap();
+
+        let module = Module::new();
+        let modules = HashMap::from([("lib.star", &lib)]);
+        let loader = ReturnFileLoader { modules: &modules };
+        let mut eval = Evaluator::new(&module);
+        eval.set_loader(&loader);
+        eval.enable_profile(&ProfileMode::Coverage).unwrap();
+        let ast = AstModule::parse(
+            "main.star",
+            r#"
+load("lib.star", "xx")
+xx(1)
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        eval.eval_module(ast, &globals).unwrap();
+
+        // We don't know which lines of `lib.star` were not run, since it wasn't evaluated
+        // with coverage, rather than reporting its top-level statements as not run.
+        assert_eq!(
+            "TN:\n\
+             SF:lib.star\n\
+             DA:3,1\n\
+             LF:1\n\
+             LH:1\n\
+             end_of_record\n\
+             TN:\n\
+             SF:main.star\n\
+             DA:3,1\n\
+             LF:1\n\
+             LH:1\n\
+             end_of_record\n",
+            eval.gen_profile().unwrap().gen().unwrap()
+        );
+    }
 }
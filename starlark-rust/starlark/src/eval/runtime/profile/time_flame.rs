@@ -26,6 +26,7 @@ use gazebo::prelude::SliceExt;
 use crate as starlark;
 use crate::eval::runtime::profile::data::ProfileData;
 use crate::eval::runtime::profile::data::ProfileDataImpl;
+use crate::eval::runtime::profile::flamegraph::qualified_function_name;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
 use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
 use crate::eval::runtime::small_duration::SmallDuration;
@@ -188,7 +189,7 @@ impl<'v> FlameProfile<'v> {
         // Need to write out lines which look like:
         // root;calls1;calls2 1
         // All the numbers at the end must be whole numbers (we use milliseconds)
-        let names = x.values.map(|x| x.to_repr());
+        let names = x.values.map(|x| qualified_function_name(*x, x.to_repr()));
         ProfileData {
             profile_mode: ProfileMode::TimeFlame,
             profile: ProfileDataImpl::TimeFlameProfile(Stacks::new(&names, &x.frames).render()),
@@ -40,12 +40,21 @@ use lsp_types::notification::DidCloseTextDocument;
 use lsp_types::notification::DidOpenTextDocument;
 use lsp_types::notification::LogMessage;
 use lsp_types::notification::PublishDiagnostics;
+use lsp_types::request::CodeActionRequest;
+use lsp_types::request::Formatting;
 use lsp_types::request::GotoDefinition;
+use lsp_types::CodeAction;
+use lsp_types::CodeActionKind;
+use lsp_types::CodeActionOrCommand;
+use lsp_types::CodeActionParams;
+use lsp_types::CodeActionProviderCapability;
+use lsp_types::CodeActionResponse;
 use lsp_types::DefinitionOptions;
 use lsp_types::Diagnostic;
 use lsp_types::DidChangeTextDocumentParams;
 use lsp_types::DidCloseTextDocumentParams;
 use lsp_types::DidOpenTextDocumentParams;
+use lsp_types::DocumentFormattingParams;
 use lsp_types::GotoDefinitionParams;
 use lsp_types::GotoDefinitionResponse;
 use lsp_types::InitializeParams;
@@ -53,13 +62,16 @@ use lsp_types::LocationLink;
 use lsp_types::LogMessageParams;
 use lsp_types::MessageType;
 use lsp_types::OneOf;
+use lsp_types::Position;
 use lsp_types::PublishDiagnosticsParams;
 use lsp_types::Range;
 use lsp_types::ServerCapabilities;
 use lsp_types::TextDocumentSyncCapability;
 use lsp_types::TextDocumentSyncKind;
+use lsp_types::TextEdit;
 use lsp_types::Url;
 use lsp_types::WorkDoneProgressOptions;
+use lsp_types::WorkspaceEdit;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Deserializer;
@@ -317,6 +329,9 @@ struct Backend<T: LspContext> {
     /// The `AstModule` from the last time that a file was opened / changed and parsed successfully.
     /// Entries are evicted when the file is closed.
     last_valid_parse: RwLock<HashMap<LspUrl, Arc<LspModule>>>,
+    /// The current contents of open files, which may not parse.
+    /// Entries are evicted when the file is closed.
+    open_files: RwLock<HashMap<LspUrl, String>>,
 }
 
 /// The logic implementations of stuff
@@ -332,6 +347,8 @@ impl<T: LspContext> Backend<T> {
         ServerCapabilities {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
             definition_provider,
+            document_formatting_provider: Some(OneOf::Left(true)),
+            code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
             ..ServerCapabilities::default()
         }
     }
@@ -353,7 +370,11 @@ impl<T: LspContext> Backend<T> {
     }
 
     fn validate(&self, uri: Url, version: Option<i64>, text: String) -> anyhow::Result<()> {
-        let uri = uri.try_into()?;
+        let uri: LspUrl = uri.try_into()?;
+        {
+            let mut open_files = self.open_files.write().unwrap();
+            open_files.insert(uri.clone(), text.clone());
+        }
         let eval_result = self.context.parse_file_with_contents(&uri, text);
         if let Some(ast) = eval_result.ast {
             let module = Arc::new(LspModule::new(ast));
@@ -384,8 +405,11 @@ impl<T: LspContext> Backend<T> {
 
     fn did_close(&self, params: DidCloseTextDocumentParams) -> anyhow::Result<()> {
         {
+            let uri: LspUrl = params.text_document.uri.clone().try_into()?;
             let mut last_valid_parse = self.last_valid_parse.write().unwrap();
-            last_valid_parse.remove(&params.text_document.uri.clone().try_into()?);
+            last_valid_parse.remove(&uri);
+            let mut open_files = self.open_files.write().unwrap();
+            open_files.remove(&uri);
         }
         self.publish_diagnostics(params.text_document.uri, Vec::new(), None);
         Ok(())
@@ -401,6 +425,84 @@ impl<T: LspContext> Backend<T> {
         self.send_response(new_response(id, self.find_definition(params)));
     }
 
+    /// Format an open file. Files which don't parse are left unchanged.
+    fn formatting(&self, id: RequestId, params: DocumentFormattingParams) {
+        self.send_response(new_response(id, self.format_document(params)));
+    }
+
+    fn format_document(
+        &self,
+        params: DocumentFormattingParams,
+    ) -> anyhow::Result<Option<Vec<TextEdit>>> {
+        let uri: LspUrl = params.text_document.uri.try_into()?;
+        let text = match self.open_files.read().unwrap().get(&uri) {
+            Some(text) => text.clone(),
+            None => return Ok(None),
+        };
+        let eval_result = self.context.parse_file_with_contents(&uri, text.clone());
+        let ast = match eval_result.ast {
+            Some(ast) => ast,
+            None => return Ok(None),
+        };
+        let formatted = ast.format();
+        if formatted == text {
+            return Ok(Some(Vec::new()));
+        }
+        // Replace the whole file, positions are counted in UTF-16 code units.
+        let last_line = text.rsplit('\n').next().unwrap_or_default();
+        let end = Position::new(
+            text.matches('\n').count() as u32,
+            last_line.encode_utf16().count() as u32,
+        );
+        Ok(Some(vec![TextEdit::new(
+            Range::new(Position::new(0, 0), end),
+            formatted,
+        )]))
+    }
+
+    /// Offer the fixes for lints in the requested range of an open file as quick fixes.
+    fn code_action(&self, id: RequestId, params: CodeActionParams) {
+        self.send_response(new_response(id, self.find_code_actions(params)));
+    }
+
+    fn find_code_actions(
+        &self,
+        params: CodeActionParams,
+    ) -> anyhow::Result<Option<CodeActionResponse>> {
+        let url = params.text_document.uri;
+        let uri: LspUrl = url.clone().try_into()?;
+        let text = match self.open_files.read().unwrap().get(&uri) {
+            Some(text) => text.clone(),
+            None => return Ok(None),
+        };
+        let ast = match self.context.parse_file_with_contents(&uri, text).ast {
+            Some(ast) => ast,
+            None => return Ok(None),
+        };
+        let mut actions = Vec::new();
+        for lint in ast.lint(None) {
+            let fix = match lint.fix() {
+                Some(fix) => fix,
+                None => continue,
+            };
+            let range: Range = lint.location.resolve_span().into();
+            if range.end < params.range.start || params.range.end < range.start {
+                continue;
+            }
+            let edit = TextEdit::new(fix.location.resolve_span().into(), fix.replacement.clone());
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: fix.description.clone(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    url.clone(),
+                    vec![edit],
+                )]))),
+                ..CodeAction::default()
+            }));
+        }
+        Ok(Some(actions))
+    }
+
     /// Get the file contents of a starlark: URI.
     fn get_starlark_file_contents(&self, id: RequestId, params: StarlarkFileContentsParams) {
         let response: anyhow::Result<_> = match params.uri {
@@ -626,6 +728,10 @@ impl<T: LspContext> Backend<T> {
                         self.goto_definition(req.id, params);
                     } else if let Some(params) = as_request::<StarlarkFileContentsRequest>(&req) {
                         self.get_starlark_file_contents(req.id, params);
+                    } else if let Some(params) = as_request::<Formatting>(&req) {
+                        self.formatting(req.id, params);
+                    } else if let Some(params) = as_request::<CodeActionRequest>(&req) {
+                        self.code_action(req.id, params);
                     } else if self.connection.handle_shutdown(&req)? {
                         return Ok(());
                     }
@@ -689,6 +795,7 @@ pub fn server_with_connection<T: LspContext>(
         connection,
         context,
         last_valid_parse: RwLock::default(),
+        open_files: RwLock::default(),
     }
     .main_loop(initialization_params)?;
 
@@ -774,7 +881,14 @@ mod test {
     use anyhow::Context;
     use lsp_server::Request;
     use lsp_server::RequestId;
+    use lsp_types::request::CodeActionRequest;
+    use lsp_types::request::Formatting;
     use lsp_types::request::GotoDefinition;
+    use lsp_types::CodeActionContext;
+    use lsp_types::CodeActionOrCommand;
+    use lsp_types::CodeActionParams;
+    use lsp_types::DocumentFormattingParams;
+    use lsp_types::FormattingOptions;
     use lsp_types::GotoDefinitionParams;
     use lsp_types::GotoDefinitionResponse;
     use lsp_types::LocationLink;
@@ -782,6 +896,7 @@ mod test {
     use lsp_types::Range;
     use lsp_types::TextDocumentIdentifier;
     use lsp_types::TextDocumentPositionParams;
+    use lsp_types::TextEdit;
     use lsp_types::Url;
     use textwrap::dedent;
 
@@ -1596,6 +1711,78 @@ mod test {
         Ok(())
     }
 
+    fn formatting_request(server: &mut TestServer, uri: Url) -> Request {
+        server.new_request::<Formatting>(DocumentFormattingParams {
+            text_document: TextDocumentIdentifier { uri },
+            options: FormattingOptions::default(),
+            work_done_progress_params: Default::default(),
+        })
+    }
+
+    #[test]
+    fn formats_open_files() -> anyhow::Result<()> {
+        let mut server = TestServer::new()?;
+        let uri = temp_file_uri("file.star");
+        server.open_file(uri.clone(), "def f(a,b):\n  return a+b\n".to_owned())?;
+
+        let req = formatting_request(&mut server, uri.clone());
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        let expected = TextEdit {
+            range: Range::new(Position::new(0, 0), Position::new(2, 0)),
+            new_text: "def f(a, b):\n    return a + b\n".to_owned(),
+        };
+        assert_eq!(Some(vec![expected]), response);
+
+        server.change_file(uri.clone(), "x = 1\n".to_owned())?;
+        let req = formatting_request(&mut server, uri.clone());
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        assert_eq!(Some(vec![]), response);
+
+        // Files which don't parse are left alone.
+        server.change_file(uri.clone(), "x = (\n".to_owned())?;
+        let req = formatting_request(&mut server, uri);
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<TextEdit>>>(request_id)?;
+        assert_eq!(None, response);
+        Ok(())
+    }
+
+    #[test]
+    fn offers_lint_fixes_as_code_actions() -> anyhow::Result<()> {
+        let mut server = TestServer::new()?;
+        let uri = temp_file_uri("file.star");
+        server.open_file(uri.clone(), "x = 1\n".to_owned())?;
+        server.change_file(
+            uri.clone(),
+            "load(\"a\", \"b\", \"c\")\nx = c\ny = {1: 2, 1: 3}\n".to_owned(),
+        )?;
+
+        let req = server.new_request::<CodeActionRequest>(CodeActionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            range: Range::new(Position::new(0, 0), Position::new(0, 20)),
+            context: CodeActionContext::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        });
+        let request_id = server.send_request(req)?;
+        let response = server.get_response::<Option<Vec<CodeActionOrCommand>>>(request_id)?;
+        // Only the fixes for lints in the requested range are offered.
+        let action = match response.as_deref() {
+            Some([CodeActionOrCommand::CodeAction(action)]) => action.clone(),
+            _ => panic!("Expected a single code action, got {:?}", response),
+        };
+        assert_eq!("Remove unused `b` from `load`", action.title);
+        let edits = action.edit.unwrap().changes.unwrap().remove(&uri).unwrap();
+        let expected = TextEdit {
+            range: Range::new(Position::new(0, 10), Position::new(0, 15)),
+            new_text: String::new(),
+        };
+        assert_eq!(vec![expected], edits);
+        Ok(())
+    }
+
     #[test]
     fn returns_starlark_file_contents() -> anyhow::Result<()> {
         let mut server = TestServer::new()?;
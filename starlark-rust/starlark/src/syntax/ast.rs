@@ -26,6 +26,8 @@ use std::mem;
 use allocative::Allocative;
 use derivative::Derivative;
 use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
 use static_assertions::assert_eq_size;
 
 use crate::codemap::CodeMap;
@@ -52,6 +54,17 @@ impl AstPayload for AstNoPayload {
     type DefPayload = ();
 }
 
+/// Payload of the AST which can be serialized.
+///
+/// Only payload-free AST is serialized, so this is the single bound
+/// for serde derives of generic AST types.
+pub(crate) trait AstPayloadSerde:
+    AstPayload<IdentPayload = (), IdentAssignPayload = (), DefPayload = ()>
+{
+}
+
+impl AstPayloadSerde for AstNoPayload {}
+
 pub(crate) type Expr = ExprP<AstNoPayload>;
 pub(crate) type Assign = AssignP<AstNoPayload>;
 pub(crate) type AssignIdent = AssignIdentP<AstNoPayload>;
@@ -102,7 +115,11 @@ assert_eq_size!(AstAssign, [usize; 7]);
 ///
 /// The internal details (statements/expressions) are deliberately omitted, as they change
 /// more regularly. A few methods to obtain information about the AST are provided.
-#[derive(Derivative)]
+///
+/// `AstModule` can be serialized to cache parse results (e.g. on disk).
+/// Serialized representation is not stable across versions of this crate,
+/// so the cache must be invalidated when the crate changes.
+#[derive(Derivative, Serialize, Deserialize)]
 #[derivative(Debug)]
 pub struct AstModule {
     #[derivative(Debug = "ignore")]
@@ -143,7 +160,8 @@ pub(crate) trait ToAst: Sized {
 
 impl<T> ToAst for T {}
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) enum ArgumentP<P: AstPayload> {
     Positional(AstExprP<P>),
     Named(AstString, AstExprP<P>),
@@ -151,7 +169,8 @@ pub(crate) enum ArgumentP<P: AstPayload> {
     KwArgs(AstExprP<P>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) enum ParameterP<P: AstPayload> {
     Normal(AstAssignIdentP<P>, Option<Box<AstExprP<P>>>),
     WithDefaultValue(
@@ -164,14 +183,15 @@ pub(crate) enum ParameterP<P: AstPayload> {
     KwArgs(AstAssignIdentP<P>, Option<Box<AstExprP<P>>>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum AstLiteral {
     Int(AstInt),
     Float(AstFloat),
     String(AstString),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) struct LambdaP<P: AstPayload> {
     pub(crate) params: Vec<AstParameterP<P>>,
     pub(crate) body: Box<AstExprP<P>>,
@@ -191,7 +211,8 @@ impl<P: AstPayload> LambdaP<P> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) enum ExprP<P: AstPayload> {
     Tuple(Vec<AstExprP<P>>),
     Dot(Box<AstExprP<P>>, AstString),
@@ -223,7 +244,8 @@ pub(crate) enum ExprP<P: AstPayload> {
 }
 
 /// In some places e.g. AssignModify, the Tuple case is not allowed.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) enum AssignP<P: AstPayload> {
     // We use Tuple for both Tuple and List,
     // as these have the same semantics in Starlark.
@@ -234,29 +256,33 @@ pub(crate) enum AssignP<P: AstPayload> {
 }
 
 /// Identifier in assign position.
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) struct AssignIdentP<P: AstPayload>(pub String, pub P::IdentAssignPayload);
 
 /// `load` statement.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) struct LoadP<P: AstPayload> {
     pub module: AstString,
     pub args: Vec<(AstAssignIdentP<P>, AstString)>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) struct ForClauseP<P: AstPayload> {
     pub(crate) var: AstAssignP<P>,
     pub(crate) over: AstExprP<P>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) enum ClauseP<P: AstPayload> {
     For(ForClauseP<P>),
     If(AstExprP<P>),
 }
 
-#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq, Serialize, Deserialize)]
 pub(crate) enum BinOp {
     Or,
     And,
@@ -281,7 +307,7 @@ pub(crate) enum BinOp {
     RightShift,
 }
 
-#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) enum AssignOp {
     Add,         // +=
     Subtract,    // -=
@@ -302,7 +328,8 @@ pub enum Visibility {
     Public,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) struct DefP<P: AstPayload> {
     pub(crate) name: AstAssignIdentP<P>,
     pub(crate) params: Vec<AstParameterP<P>>,
@@ -324,7 +351,8 @@ impl<P: AstPayload> DefP<P> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "P: AstPayloadSerde")]
 pub(crate) enum StmtP<P: AstPayload> {
     Break,
     Continue,
@@ -16,6 +16,8 @@
  */
 
 use dupe::Dupe;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 
 use crate::codemap::CodeMap;
@@ -40,7 +42,7 @@ pub(crate) enum DialectError {
 }
 
 /// How to handle type annotations in Starlark.
-#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Dupe, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum DialectTypes {
     /// Prohibit types at parse time.
     Disable,
@@ -51,7 +53,7 @@ pub enum DialectTypes {
 }
 
 /// Starlark language features to enable, e.g. [`Standard`](Dialect::Standard) to follow the Starlark standard.
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Dialect {
     /// Are `def` statements permitted.
     /// Enabled in both [`Standard`](Dialect::Standard) and [`Extended`](Dialect::Extended).
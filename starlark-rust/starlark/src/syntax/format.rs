@@ -0,0 +1,881 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Canonical formatting of Starlark source code.
+//!
+//! The AST doesn't record comments, so we recover them from the [`Trivia`] of the module.
+//! Comments are emitted in source order at the first line break after them, so none are
+//! ever lost.
+//!
+//! Layout only depends on the source, not on line widths: a collection, call, parameter list
+//! or `load` is laid out one item per line if it spans multiple lines in the source, and on
+//! a single line otherwise. That makes formatting idempotent and keeps diffs small.
+
+use std::cmp;
+
+use crate::codemap::Span;
+use crate::syntax::ast::ArgumentP;
+use crate::syntax::ast::AssignP;
+use crate::syntax::ast::AstArgument;
+use crate::syntax::ast::AstAssign;
+use crate::syntax::ast::AstAssignIdent;
+use crate::syntax::ast::AstExpr;
+use crate::syntax::ast::AstLiteral;
+use crate::syntax::ast::AstModule;
+use crate::syntax::ast::AstParameter;
+use crate::syntax::ast::AstStmt;
+use crate::syntax::ast::AstString;
+use crate::syntax::ast::BinOp;
+use crate::syntax::ast::Clause;
+use crate::syntax::ast::ClauseP;
+use crate::syntax::ast::ExprP;
+use crate::syntax::ast::ForClause;
+use crate::syntax::ast::ParameterP;
+use crate::syntax::ast::StmtP;
+use crate::syntax::trivia::Comment;
+use crate::syntax::trivia::Trivia;
+
+const INDENT: &str = "    ";
+
+// Precedence of expressions, following the grammar.
+const PREC_TEST: u8 = 1;
+const PREC_OR: u8 = 2;
+const PREC_AND: u8 = 3;
+const PREC_NOT: u8 = 4;
+const PREC_COMPARE: u8 = 5;
+const PREC_BIT_OR: u8 = 6;
+const PREC_BIT_XOR: u8 = 7;
+const PREC_BIT_AND: u8 = 8;
+const PREC_SHIFT: u8 = 9;
+const PREC_ARITH: u8 = 10;
+const PREC_PRODUCT: u8 = 11;
+const PREC_UNARY: u8 = 12;
+const PREC_PRIMARY: u8 = 13;
+const PREC_ATOM: u8 = 14;
+
+fn binop_precedence(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => PREC_OR,
+        BinOp::And => PREC_AND,
+        BinOp::Equal
+        | BinOp::NotEqual
+        | BinOp::Less
+        | BinOp::Greater
+        | BinOp::LessOrEqual
+        | BinOp::GreaterOrEqual
+        | BinOp::In
+        | BinOp::NotIn => PREC_COMPARE,
+        BinOp::BitOr => PREC_BIT_OR,
+        BinOp::BitXor => PREC_BIT_XOR,
+        BinOp::BitAnd => PREC_BIT_AND,
+        BinOp::LeftShift | BinOp::RightShift => PREC_SHIFT,
+        BinOp::Add | BinOp::Subtract => PREC_ARITH,
+        BinOp::Multiply | BinOp::Percent | BinOp::Divide | BinOp::FloorDivide => PREC_PRODUCT,
+    }
+}
+
+fn precedence(x: &AstExpr) -> u8 {
+    match &x.node {
+        ExprP::Lambda(..) | ExprP::If(..) => PREC_TEST,
+        ExprP::Op(_, op, _) => binop_precedence(*op),
+        ExprP::Not(..) => PREC_NOT,
+        ExprP::Minus(..) | ExprP::Plus(..) | ExprP::BitNot(..) => PREC_UNARY,
+        ExprP::Dot(..) | ExprP::Call(..) | ExprP::ArrayIndirection(..) | ExprP::Slice(..) => {
+            PREC_PRIMARY
+        }
+        // Tuples always come with their own parentheses.
+        ExprP::Tuple(..)
+        | ExprP::Identifier(..)
+        | ExprP::Literal(..)
+        | ExprP::List(..)
+        | ExprP::Dict(..)
+        | ExprP::ListComprehension(..)
+        | ExprP::DictComprehension(..) => PREC_ATOM,
+    }
+}
+
+/// Whether there is an empty line between `from` and `to`, not counting the lines they are on.
+fn has_blank_line(source: &str, from: usize, to: usize) -> bool {
+    let lines: Vec<&str> = source[from..to].split('\n').collect();
+    lines.len() > 2
+        && lines[1..lines.len() - 1]
+            .iter()
+            .any(|x| x.trim().is_empty())
+}
+
+fn flatten_statements<'a>(x: &'a AstStmt, res: &mut Vec<&'a AstStmt>) {
+    match &x.node {
+        StmtP::Statements(xs) => {
+            for x in xs {
+                flatten_statements(x, res);
+            }
+        }
+        _ => res.push(x),
+    }
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    comments: Vec<Comment>,
+    /// Index of the first comment not written yet.
+    next_comment: usize,
+    /// Start of every token, in order.
+    tokens: Vec<usize>,
+    /// End of the last statement or comment written.
+    last_end: usize,
+    out: String,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(module: &'a AstModule) -> Self {
+        let source = module.codemap.source();
+        let Trivia { comments, tokens } = module.trivia();
+        Formatter {
+            source,
+            comments,
+            next_comment: 0,
+            tokens,
+            last_end: 0,
+            out: String::new(),
+        }
+    }
+
+    fn begin(span: Span) -> usize {
+        span.begin().get() as usize
+    }
+
+    fn end(span: Span) -> usize {
+        span.end().get() as usize
+    }
+
+    fn is_multiline(&self, from: usize, to: usize) -> bool {
+        self.source[from..to].contains('\n')
+    }
+
+    fn column(&self, pos: usize) -> usize {
+        pos - self.source[..pos].rfind('\n').map_or(0, |x| x + 1)
+    }
+
+    /// The start of the first token at or after `pos`.
+    fn next_token(&self, pos: usize) -> usize {
+        let i = self.tokens.partition_point(|x| *x < pos);
+        self.tokens.get(i).copied().unwrap_or(self.source.len())
+    }
+
+    /// The position after the `close` bracket following `pos`, skipping over whitespace,
+    /// comments and a trailing comma. Returns `pos` if there is no such bracket.
+    fn closing(&self, pos: usize, close: char) -> usize {
+        let mut chars = self.source[pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == close => return pos + i + 1,
+                '#' => {
+                    for (_, c) in chars.by_ref() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                c if c.is_whitespace() || c == ',' || c == '\\' => {}
+                _ => break,
+            }
+        }
+        pos
+    }
+
+    fn indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        self.out.push('\n');
+        self.indent(indent);
+    }
+
+    fn comment_text(&self, comment: Comment) -> &'a str {
+        self.source[comment.begin..comment.end].trim_end()
+    }
+
+    /// Take the next comment, if it starts before `upto`.
+    fn take_comment(&mut self, upto: usize) -> Option<Comment> {
+        let comment = *self.comments.get(self.next_comment)?;
+        if comment.begin >= upto {
+            return None;
+        }
+        self.next_comment += 1;
+        self.last_end = cmp::max(self.last_end, comment.end);
+        Some(comment)
+    }
+
+    /// Append the next comment to the current line, if it is a trailing comment before `upto`.
+    fn trailing_comment(&mut self, upto: usize) -> Option<usize> {
+        let comment = self.comments.get(self.next_comment)?;
+        if !comment.trailing {
+            return None;
+        }
+        let comment = self.take_comment(upto)?;
+        self.out.push_str("  ");
+        self.out.push_str(self.comment_text(comment));
+        Some(comment.end)
+    }
+
+    /// Start a new line inside an expression, before the item at `upto`, writing the
+    /// comments which come before it. `prev` is the end of the previous item, used to
+    /// preserve blank lines between items.
+    fn break_line(&mut self, mut prev: Option<usize>, upto: usize, blank: bool, indent: usize) {
+        if let Some(end) = self.trailing_comment(upto) {
+            prev = Some(end);
+        }
+        while let Some(comment) = self.take_comment(upto) {
+            self.blank_line(prev, comment.begin);
+            self.newline(indent);
+            self.out.push_str(self.comment_text(comment));
+            prev = Some(comment.end);
+        }
+        if blank {
+            self.blank_line(prev, upto);
+        }
+        self.newline(indent);
+    }
+
+    fn blank_line(&mut self, prev: Option<usize>, next: usize) {
+        if let Some(prev) = prev {
+            if !self.out.is_empty() && has_blank_line(self.source, prev, next) {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Write a block of statements, each starting on a new line.
+    fn block(&mut self, x: &AstStmt, indent: usize) {
+        let mut stmts = Vec::new();
+        flatten_statements(x, &mut stmts);
+        for (i, stmt) in stmts.iter().enumerate() {
+            self.comment_lines_before(Self::begin(stmt.span), i != 0, indent);
+            self.stmt(stmt, indent);
+        }
+
+        // Comments after the last statement belong to this block if they are indented as much
+        // as its statements, and to the enclosing one otherwise.
+        let (upto, column) = match stmts.last() {
+            Some(last) if indent != 0 => (
+                self.next_token(Self::end(last.span)),
+                Some(self.column(Self::begin(stmts[0].span))),
+            ),
+            _ => (self.source.len(), None),
+        };
+        while let Some(comment) = self.comments.get(self.next_comment).copied() {
+            if comment.begin >= upto {
+                break;
+            }
+            if let Some(column) = column {
+                if comment.trailing || self.column(comment.begin) < column {
+                    break;
+                }
+            }
+            self.comment_lines_before(comment.end, true, indent);
+        }
+    }
+
+    /// Write the comments which start before `upto` on their own lines. Blank lines before
+    /// them and before `upto` are preserved, except at the start of a block.
+    fn comment_lines_before(&mut self, upto: usize, mut blank: bool, indent: usize) {
+        loop {
+            let next = match self.comments.get(self.next_comment) {
+                Some(comment) if comment.begin < upto => comment.begin,
+                _ => upto,
+            };
+            if blank {
+                self.blank_line(Some(self.last_end), next);
+            }
+            blank = true;
+            match self.take_comment(upto) {
+                Some(comment) => {
+                    self.indent(indent);
+                    self.out.push_str(self.comment_text(comment));
+                    self.out.push('\n');
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Finish the line of a simple statement ending at `end`.
+    fn end_statement(&mut self, end: usize, indent: usize) {
+        let mut line_end = self.source[end..]
+            .find('\n')
+            .map_or(self.source.len(), |x| end + x);
+        // With several statements on a line, the comment belongs to the last one.
+        let next = self.next_token(end);
+        if self.source[next..].starts_with(';') {
+            line_end = cmp::min(line_end, self.next_token(next + 1));
+        }
+        self.trailing_comment(line_end);
+        self.out.push('\n');
+        self.last_end = cmp::max(self.last_end, end);
+        while let Some(comment) = self.take_comment(line_end) {
+            self.indent(indent);
+            self.out.push_str(self.comment_text(comment));
+            self.out.push('\n');
+        }
+    }
+
+    /// Finish the header of a compound statement, whose body starts at `body`.
+    fn end_header(&mut self, body: &AstStmt) {
+        self.trailing_comment(Self::begin(body.span));
+        self.out.push('\n');
+    }
+
+    fn stmt(&mut self, x: &AstStmt, indent: usize) {
+        self.indent(indent);
+        match &x.node {
+            StmtP::Break => self.out.push_str("break"),
+            StmtP::Continue => self.out.push_str("continue"),
+            StmtP::Pass => self.out.push_str("pass"),
+            StmtP::Return(None) => self.out.push_str("return"),
+            StmtP::Return(Some(e)) => {
+                self.out.push_str("return ");
+                self.expr_list(e, indent);
+            }
+            StmtP::Expression(e) => self.expr(e, PREC_TEST, indent),
+            StmtP::Assign(lhs, ty_rhs) => {
+                let (ty, rhs) = &**ty_rhs;
+                self.assign(lhs, true, indent);
+                if let Some(ty) = ty {
+                    self.out.push_str(": ");
+                    self.expr(ty, PREC_TEST, indent);
+                }
+                self.out.push_str(" = ");
+                self.expr_list(rhs, indent);
+            }
+            StmtP::AssignModify(lhs, op, rhs) => {
+                self.assign(lhs, true, indent);
+                self.out.push_str(&op.to_string());
+                self.expr_list(rhs, indent);
+            }
+            StmtP::Load(load) => {
+                let mut items: Vec<(Option<&AstAssignIdent>, &AstString)> =
+                    vec![(None, &load.module)];
+                for (local, their) in &load.args {
+                    // Without an alias, both have the span of the string.
+                    let local = if local.span == their.span {
+                        None
+                    } else {
+                        Some(local)
+                    };
+                    items.push((local, their));
+                }
+                self.out.push_str("load");
+                let end = Self::end(x.span);
+                self.items(
+                    "(",
+                    &items,
+                    ")",
+                    end,
+                    self.is_multiline(Self::begin(x.span), end),
+                    indent,
+                    |(local, their)| local.map_or(their.span, |x| x.span.merge(their.span)),
+                    |f, (local, their), _| {
+                        if let Some(local) = local {
+                            f.out.push_str(&local.node.0);
+                            f.out.push_str(" = ");
+                        }
+                        f.string(their.span);
+                    },
+                );
+            }
+            StmtP::Statements(_) => unreachable!("statements are flattened by block"),
+            StmtP::If(cond, then) => {
+                self.if_stmt("if", cond, then, None, indent);
+                return;
+            }
+            StmtP::IfElse(cond, then_else) => {
+                let (then, els) = &**then_else;
+                self.if_stmt("if", cond, then, Some(els), indent);
+                return;
+            }
+            StmtP::For(var, over_body) => {
+                let (over, body) = &**over_body;
+                self.out.push_str("for ");
+                self.assign(var, true, indent);
+                self.out.push_str(" in ");
+                self.expr(over, PREC_TEST, indent);
+                self.out.push(':');
+                self.end_header(body);
+                self.block(body, indent + 1);
+                return;
+            }
+            StmtP::Def(def) => {
+                self.out.push_str("def ");
+                self.out.push_str(&def.name.node.0);
+                self.parameters(&def.params, Self::end(def.name.span), indent);
+                if let Some(return_type) = &def.return_type {
+                    self.out.push_str(" -> ");
+                    self.expr(return_type, PREC_TEST, indent);
+                }
+                self.out.push(':');
+                self.end_header(&def.body);
+                self.block(&def.body, indent + 1);
+                return;
+            }
+        }
+        self.end_statement(Self::end(x.span), indent);
+    }
+
+    fn if_stmt(
+        &mut self,
+        keyword: &str,
+        cond: &AstExpr,
+        then: &AstStmt,
+        els: Option<&AstStmt>,
+        indent: usize,
+    ) {
+        self.out.push_str(keyword);
+        self.out.push(' ');
+        self.expr(cond, PREC_TEST, indent);
+        self.out.push(':');
+        self.end_header(then);
+        self.block(then, indent + 1);
+
+        let els = match els {
+            Some(els) => els,
+            None => return,
+        };
+        self.comment_lines_before(self.next_token(self.last_end), false, indent);
+        self.indent(indent);
+        // An `elif` is parsed as an `if` in the `else` branch, whose span starts after the
+        // keyword. An `if` alone in an `else` block starts with the keyword.
+        let source = &self.source[Self::begin(els.span)..];
+        let is_elif = !(source.starts_with("if")
+            && !source[2..].starts_with(|c: char| c.is_alphanumeric() || c == '_'));
+        match &els.node {
+            StmtP::If(cond, then) if is_elif => self.if_stmt("elif", cond, then, None, indent),
+            StmtP::IfElse(cond, then_else) if is_elif => {
+                let (then, els) = &**then_else;
+                self.if_stmt("elif", cond, then, Some(els), indent)
+            }
+            _ => {
+                self.out.push_str("else:");
+                self.end_header(els);
+                self.block(els, indent + 1);
+            }
+        }
+    }
+
+    /// Write items between brackets, either on a single line or one per line.
+    fn items<T>(
+        &mut self,
+        open: &str,
+        items: &[T],
+        close: &str,
+        close_pos: usize,
+        multiline: bool,
+        indent: usize,
+        span_of: impl Fn(&T) -> Span,
+        write_item: impl Fn(&mut Self, &T, usize),
+    ) {
+        self.out.push_str(open);
+        if multiline && !items.is_empty() {
+            let mut prev = None;
+            for x in items {
+                let span = span_of(x);
+                self.break_line(prev, Self::begin(span), prev.is_some(), indent + 1);
+                write_item(self, x, indent + 1);
+                self.out.push(',');
+                prev = Some(Self::end(span));
+            }
+            self.break_line(prev, close_pos, false, indent);
+        } else {
+            for (i, x) in items.iter().enumerate() {
+                if i != 0 {
+                    self.out.push_str(", ");
+                }
+                write_item(self, x, indent);
+            }
+        }
+        self.out.push_str(close);
+    }
+
+    fn parameters(&mut self, params: &[AstParameter], open: usize, indent: usize) {
+        let close = match params.last() {
+            Some(last) => self.closing(Self::end(last.span), ')'),
+            None => open,
+        };
+        self.items(
+            "(",
+            params,
+            ")",
+            close,
+            self.is_multiline(open, close),
+            indent,
+            |x| x.span,
+            |f, x, indent| f.parameter(x, indent),
+        );
+    }
+
+    fn parameter(&mut self, x: &AstParameter, indent: usize) {
+        let (prefix, name, ty, default) = match &x.node {
+            ParameterP::Normal(name, ty) => ("", name, ty, None),
+            ParameterP::WithDefaultValue(name, ty, default) => ("", name, ty, Some(default)),
+            ParameterP::NoArgs => {
+                self.out.push('*');
+                return;
+            }
+            ParameterP::Args(name, ty) => ("*", name, ty, None),
+            ParameterP::KwArgs(name, ty) => ("**", name, ty, None),
+        };
+        self.out.push_str(prefix);
+        self.out.push_str(&name.node.0);
+        if let Some(ty) = ty {
+            self.out.push_str(": ");
+            self.expr(ty, PREC_TEST, indent);
+        }
+        if let Some(default) = default {
+            self.out.push_str(" = ");
+            self.expr(default, PREC_TEST, indent);
+        }
+    }
+
+    fn argument(&mut self, x: &AstArgument, indent: usize) {
+        match &x.node {
+            ArgumentP::Positional(e) => self.expr(e, PREC_TEST, indent),
+            ArgumentP::Named(name, e) => {
+                self.out.push_str(&name.node);
+                self.out.push_str(" = ");
+                self.expr(e, PREC_TEST, indent);
+            }
+            ArgumentP::Args(e) => {
+                self.out.push('*');
+                self.expr(e, PREC_TEST, indent);
+            }
+            ArgumentP::KwArgs(e) => {
+                self.out.push_str("**");
+                self.expr(e, PREC_TEST, indent);
+            }
+        }
+    }
+
+    /// Write an assignment target. At the top level, tuples don't need parentheses.
+    fn assign(&mut self, x: &AstAssign, top: bool, indent: usize) {
+        match &x.node {
+            AssignP::Tuple(xs) => {
+                let bare = top && xs.len() > 1;
+                if !bare {
+                    self.out.push('(');
+                }
+                for (i, x) in xs.iter().enumerate() {
+                    if i != 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.assign(x, false, indent);
+                }
+                if xs.len() == 1 {
+                    self.out.push(',');
+                }
+                if !bare {
+                    self.out.push(')');
+                }
+            }
+            AssignP::ArrayIndirection(array_index) => {
+                let (array, index) = &**array_index;
+                self.expr(array, PREC_PRIMARY, indent);
+                self.out.push('[');
+                self.expr_list(index, indent);
+                self.out.push(']');
+            }
+            AssignP::Dot(e, name) => {
+                self.expr(e, PREC_PRIMARY, indent);
+                self.out.push('.');
+                self.out.push_str(&name.node);
+            }
+            AssignP::Identifier(name) => self.out.push_str(&name.node.0),
+        }
+    }
+
+    /// Write an expression where a tuple doesn't need parentheses.
+    fn expr_list(&mut self, x: &AstExpr, indent: usize) {
+        match &x.node {
+            ExprP::Tuple(xs) if xs.len() > 1 && !self.is_multiline_span(x.span) => {
+                for (i, x) in xs.iter().enumerate() {
+                    if i != 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.expr(x, PREC_TEST, indent);
+                }
+            }
+            _ => self.expr(x, PREC_TEST, indent),
+        }
+    }
+
+    fn is_multiline_span(&self, span: Span) -> bool {
+        self.is_multiline(Self::begin(span), Self::end(span))
+    }
+
+    /// Write a string literal as in the source, but prefer double quotes.
+    fn string(&mut self, span: Span) {
+        let text = &self.source[Self::begin(span)..Self::end(span)];
+        if text.len() >= 2 && text.starts_with('\'') && !text.starts_with("'''") {
+            let inner = &text[1..text.len() - 1];
+            if !inner.contains('"') && !inner.contains('\\') {
+                self.out.push('"');
+                self.out.push_str(inner);
+                self.out.push('"');
+                return;
+            }
+        }
+        self.out.push_str(text);
+    }
+
+    /// Write an expression, in parentheses if its precedence is lower than `min`.
+    fn expr(&mut self, x: &AstExpr, min: u8, indent: usize) {
+        let parens = precedence(x) < min;
+        if parens {
+            self.out.push('(');
+        }
+        self.expr_inner(x, indent);
+        if parens {
+            self.out.push(')');
+        }
+    }
+
+    fn expr_inner(&mut self, x: &AstExpr, indent: usize) {
+        match &x.node {
+            ExprP::Tuple(xs) => {
+                let multiline = self.is_multiline_span(x.span);
+                if xs.len() == 1 && !multiline {
+                    self.out.push('(');
+                    self.expr(&xs[0], PREC_TEST, indent);
+                    self.out.push_str(",)");
+                } else {
+                    // The span of a tuple doesn't include its parentheses.
+                    let close = self.closing(Self::end(x.span), ')');
+                    self.items(
+                        "(",
+                        xs,
+                        ")",
+                        close,
+                        multiline,
+                        indent,
+                        |x| x.span,
+                        |f, x, indent| f.expr(x, PREC_TEST, indent),
+                    );
+                }
+            }
+            ExprP::Dot(e, name) => {
+                self.expr(e, PREC_PRIMARY, indent);
+                self.out.push('.');
+                self.out.push_str(&name.node);
+            }
+            ExprP::Call(f, args) => {
+                self.expr(f, PREC_PRIMARY, indent);
+                let end = Self::end(x.span);
+                self.items(
+                    "(",
+                    args,
+                    ")",
+                    end,
+                    self.is_multiline(Self::end(f.span), end),
+                    indent,
+                    |x| x.span,
+                    |f, x, indent| f.argument(x, indent),
+                );
+            }
+            ExprP::ArrayIndirection(array_index) => {
+                let (array, index) = &**array_index;
+                self.expr(array, PREC_PRIMARY, indent);
+                self.out.push('[');
+                self.expr_list(index, indent);
+                self.out.push(']');
+            }
+            ExprP::Slice(e, start, stop, step) => {
+                self.expr(e, PREC_PRIMARY, indent);
+                self.out.push('[');
+                if let Some(start) = start {
+                    self.expr(start, PREC_TEST, indent);
+                }
+                self.out.push(':');
+                if let Some(stop) = stop {
+                    self.expr(stop, PREC_TEST, indent);
+                }
+                if let Some(step) = step {
+                    self.out.push(':');
+                    self.expr(step, PREC_TEST, indent);
+                }
+                self.out.push(']');
+            }
+            ExprP::Identifier(name, _) => self.out.push_str(&name.node),
+            ExprP::Lambda(lambda) => {
+                self.out.push_str("lambda");
+                for (i, param) in lambda.params.iter().enumerate() {
+                    self.out.push_str(if i == 0 { " " } else { ", " });
+                    self.parameter(param, indent);
+                }
+                self.out.push_str(": ");
+                self.expr(&lambda.body, PREC_TEST, indent);
+            }
+            ExprP::Literal(AstLiteral::String(s)) => self.string(s.span),
+            ExprP::Literal(AstLiteral::Int(_) | AstLiteral::Float(_)) => {
+                self.out
+                    .push_str(&self.source[Self::begin(x.span)..Self::end(x.span)]);
+            }
+            ExprP::Not(e) => {
+                self.out.push_str("not ");
+                self.expr(e, PREC_NOT, indent);
+            }
+            ExprP::Minus(e) => {
+                self.out.push('-');
+                self.expr(e, PREC_UNARY, indent);
+            }
+            ExprP::Plus(e) => {
+                self.out.push('+');
+                self.expr(e, PREC_UNARY, indent);
+            }
+            ExprP::BitNot(e) => {
+                self.out.push('~');
+                self.expr(e, PREC_UNARY, indent);
+            }
+            ExprP::Op(l, op, r) => {
+                let prec = binop_precedence(*op);
+                // Comparisons don't chain.
+                let (l_min, r_min) = if prec == PREC_COMPARE {
+                    (PREC_BIT_OR, PREC_BIT_OR)
+                } else {
+                    (prec, prec + 1)
+                };
+                self.expr(l, l_min, indent);
+                self.out.push_str(&op.to_string());
+                self.expr(r, r_min, indent);
+            }
+            ExprP::If(cond_then_else) => {
+                let (cond, then, els) = &**cond_then_else;
+                self.expr(then, PREC_OR, indent);
+                self.out.push_str(" if ");
+                self.expr(cond, PREC_OR, indent);
+                self.out.push_str(" else ");
+                self.expr(els, PREC_TEST, indent);
+            }
+            ExprP::List(xs) => self.items(
+                "[",
+                xs,
+                "]",
+                Self::end(x.span),
+                self.is_multiline_span(x.span),
+                indent,
+                |x| x.span,
+                |f, x, indent| f.expr(x, PREC_TEST, indent),
+            ),
+            ExprP::Dict(xs) => self.items(
+                "{",
+                xs,
+                "}",
+                Self::end(x.span),
+                self.is_multiline_span(x.span),
+                indent,
+                |(k, v)| k.span.merge(v.span),
+                |f, (k, v), indent| {
+                    f.expr(k, PREC_TEST, indent);
+                    f.out.push_str(": ");
+                    f.expr(v, PREC_TEST, indent);
+                },
+            ),
+            ExprP::ListComprehension(body, for_, clauses) => {
+                self.comprehension("[", "]", x.span, body.span, indent, for_, clauses, |f| {
+                    f.expr(body, PREC_TEST, indent + 1)
+                });
+            }
+            ExprP::DictComprehension(kv, for_, clauses) => {
+                let (k, v) = &**kv;
+                let body = k.span.merge(v.span);
+                self.comprehension("{", "}", x.span, body, indent, for_, clauses, |f| {
+                    f.expr(k, PREC_TEST, indent + 1);
+                    f.out.push_str(": ");
+                    f.expr(v, PREC_TEST, indent + 1);
+                });
+            }
+        }
+    }
+
+    fn comprehension(
+        &mut self,
+        open: &str,
+        close: &str,
+        span: Span,
+        body_span: Span,
+        indent: usize,
+        for_: &ForClause,
+        clauses: &[Clause],
+        body: impl Fn(&mut Self),
+    ) {
+        let multiline = self.is_multiline_span(span);
+        // On a single line, the body and clauses are indented as if they were on their own
+        // line, which doesn't matter since they can't contain line breaks.
+        let inner = indent + 1;
+        self.out.push_str(open);
+        if multiline {
+            self.break_line(None, Self::begin(body_span), false, inner);
+        }
+        body(self);
+        let mut prev = Self::end(body_span);
+
+        let for_clause = |f: &mut Self, for_: &ForClause, prev: &mut usize| {
+            let begin = Self::begin(for_.var.span);
+            if multiline {
+                f.break_line(Some(*prev), begin, false, inner);
+            } else {
+                f.out.push(' ');
+            }
+            f.out.push_str("for ");
+            f.assign(&for_.var, true, inner);
+            f.out.push_str(" in ");
+            f.expr(&for_.over, PREC_OR, inner);
+            *prev = Self::end(for_.over.span);
+        };
+        for_clause(self, for_, &mut prev);
+        for clause in clauses {
+            match clause {
+                ClauseP::For(for_) => for_clause(self, for_, &mut prev),
+                ClauseP::If(cond) => {
+                    if multiline {
+                        self.break_line(Some(prev), Self::begin(cond.span), false, inner);
+                    } else {
+                        self.out.push(' ');
+                    }
+                    self.out.push_str("if ");
+                    self.expr(cond, PREC_OR, inner);
+                    prev = Self::end(cond.span);
+                }
+            }
+        }
+        if multiline {
+            self.break_line(Some(prev), Self::end(span), false, indent);
+        }
+        self.out.push_str(close);
+    }
+}
+
+impl AstModule {
+    /// Format the module in the canonical style, preserving comments.
+    ///
+    /// Formatting is idempotent, and the result parses to the same AST as this module.
+    pub fn format(&self) -> String {
+        let mut formatter = Formatter::new(self);
+        formatter.block(&self.statement, 0);
+        formatter.out
+    }
+}
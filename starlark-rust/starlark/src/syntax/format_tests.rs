@@ -0,0 +1,233 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use textwrap::dedent;
+
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
+
+fn format(program: &str) -> String {
+    AstModule::parse("format.star", program.to_owned(), &Dialect::Extended)
+        .unwrap()
+        .format()
+}
+
+/// Check that `program` formats to `expected`, and that formatting is idempotent.
+fn assert_format(program: &str, expected: &str) {
+    let program = dedent(program);
+    let expected = dedent(expected);
+    let expected = expected.trim_start_matches('\n');
+    assert_eq!(expected, format(program.trim_start_matches('\n')));
+    assert_eq!(expected, format(expected));
+}
+
+#[test]
+fn test_format_spacing() {
+    assert_format(
+        r#"
+        x=[1,2]
+        y={'a':1}
+        z=f(a,b=1,*c,**d)[0][1:2]
+        z+=1
+        "#,
+        r#"
+        x = [1, 2]
+        y = {"a": 1}
+        z = f(a, b = 1, *c, **d)[0][1:2]
+        z += 1
+        "#,
+    );
+}
+
+#[test]
+fn test_format_multiline() {
+    assert_format(
+        r#"
+        deps = [
+          ":a", # first
+
+          # second
+          ":b"]
+        f(
+          x, y = {1: 2,
+            3: 4})
+        "#,
+        r#"
+        deps = [
+            ":a",  # first
+
+            # second
+            ":b",
+        ]
+        f(
+            x,
+            y = {
+                1: 2,
+                3: 4,
+            },
+        )
+        "#,
+    );
+}
+
+#[test]
+fn test_format_statements() {
+    assert_format(
+        r#"
+        def f(x):
+          if x: return 1
+          elif x > 2:
+            return 2
+          else:
+            if x:
+              pass
+          for k, v in x.items(): print(k); print(v)
+          return (x + 1) * 2, x
+        "#,
+        r#"
+        def f(x):
+            if x:
+                return 1
+            elif x > 2:
+                return 2
+            else:
+                if x:
+                    pass
+            for k, v in x.items():
+                print(k)
+                print(v)
+            return (x + 1) * 2, x
+        "#,
+    );
+}
+
+#[test]
+fn test_format_comments() {
+    assert_format(
+        r#"
+        # Header.
+
+
+        load(":defs.bzl", "rule")  # Trailing.
+        x = 1
+
+
+
+        # Leading.
+        def f(): # After header.
+            pass
+            # Inside.
+        # Outside.
+        rule(name = "a")
+        # End.
+        "#,
+        r#"
+        # Header.
+
+        load(":defs.bzl", "rule")  # Trailing.
+        x = 1
+
+        # Leading.
+        def f():  # After header.
+            pass
+            # Inside.
+        # Outside.
+        rule(name = "a")
+        # End.
+        "#,
+    );
+}
+
+#[test]
+fn test_format_collapsed_comments() {
+    // Expressions which are not laid out over multiple lines keep their comments after them.
+    assert_format(
+        r#"
+        x = (a +  # Why a.
+             b)
+        "#,
+        r#"
+        x = a + b  # Why a.
+        "#,
+    );
+}
+
+#[test]
+fn test_format_parentheses() {
+    assert_format(
+        r#"
+        a = (b + c)
+        a = -(b + c)
+        a = (b if c else d).e
+        a = not (b and c)
+        a = (b or c) and d
+        a = b - (c - d)
+        a = (1,)
+        a = (lambda x, y = 1: x + y)(1)
+        "#,
+        r#"
+        a = b + c
+        a = -(b + c)
+        a = (b if c else d).e
+        a = not (b and c)
+        a = (b or c) and d
+        a = b - (c - d)
+        a = (1,)
+        a = (lambda x, y = 1: x + y)(1)
+        "#,
+    );
+}
+
+#[test]
+fn test_format_strings() {
+    assert_format(
+        r#"
+        a = 'b'
+        a = 'say "hi"'
+        a = r'\d'
+        a = '''doc'''
+        "#,
+        r#"
+        a = "b"
+        a = 'say "hi"'
+        a = r'\d'
+        a = '''doc'''
+        "#,
+    );
+}
+
+#[test]
+fn test_format_types() {
+    assert_format(
+        r#"
+        def f(x:int,*args:str,y:list[str]=[],**kwargs)->bool:
+            z: int = 1
+            return [a for a in args if a]
+        "#,
+        r#"
+        def f(x: int, *args: str, y: list[str] = [], **kwargs) -> bool:
+            z: int = 1
+            return [a for a in args if a]
+        "#,
+    );
+}
+
+#[test]
+fn test_format_empty() {
+    assert_eq!("", format(""));
+    assert_eq!("# Only a comment.\n", format("\n# Only a comment.\n\n"));
+}
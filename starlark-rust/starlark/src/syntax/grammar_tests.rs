@@ -20,6 +20,7 @@ use gazebo::prelude::SliceExt;
 use crate::assert;
 use crate::assert::Assert;
 use crate::syntax::ast::Stmt;
+use crate::syntax::AstModule;
 
 #[test]
 fn test_empty() {
@@ -317,3 +318,17 @@ fn test_bad_assignment() {
     assert::parse_fail("[!x or y!] = 1");
     assert::parse_fail("![x]! += 1");
 }
+
+#[test]
+fn test_ast_serialization_roundtrip() {
+    let program = "load(\"a.bzl\", \"b\")\ndef f(x, *, y = 99999999999999999999):\n    return [x + i for i in y if i]\nz = lambda: 1.5\n";
+    let ast = assert::parse_ast(program);
+    let serialized = serde_json::to_string(&ast).unwrap();
+    let deserialized: AstModule = serde_json::from_str(&serialized).unwrap();
+    assert_eq!(
+        ast.statement.to_string(),
+        deserialized.statement.to_string()
+    );
+    assert_eq!(ast.codemap.source(), deserialized.codemap.source());
+    assert_eq!(ast.dialect, deserialized.dialect);
+}
@@ -24,6 +24,8 @@ use derive_more::Display;
 use logos::Logos;
 use num_bigint::BigInt;
 use num_traits::Num;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 
 use crate::codemap::CodeMap;
@@ -504,12 +506,40 @@ impl<'a> Lexer<'a> {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Display)]
+#[derive(Debug, Clone, Eq, PartialEq, Display, Serialize, Deserialize)]
+#[serde(into = "TokenIntRepr", try_from = "TokenIntRepr")]
 pub enum TokenInt {
     I32(i32),
     BigInt(BigInt),
 }
 
+/// Serialized `TokenInt`: `BigInt` is stored as decimal string.
+#[derive(Serialize, Deserialize)]
+enum TokenIntRepr {
+    I32(i32),
+    BigInt(String),
+}
+
+impl From<TokenInt> for TokenIntRepr {
+    fn from(i: TokenInt) -> Self {
+        match i {
+            TokenInt::I32(i) => TokenIntRepr::I32(i),
+            TokenInt::BigInt(i) => TokenIntRepr::BigInt(i.to_string()),
+        }
+    }
+}
+
+impl TryFrom<TokenIntRepr> for TokenInt {
+    type Error = num_bigint::ParseBigIntError;
+
+    fn try_from(i: TokenIntRepr) -> Result<Self, Self::Error> {
+        match i {
+            TokenIntRepr::I32(i) => Ok(TokenInt::I32(i)),
+            TokenIntRepr::BigInt(i) => Ok(TokenInt::BigInt(i.parse()?)),
+        }
+    }
+}
+
 /// All token that can be generated by the lexer
 #[derive(Logos, Debug, Clone, PartialEq)]
 pub enum Token {
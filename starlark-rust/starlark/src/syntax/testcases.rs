@@ -16,6 +16,8 @@
  */
 
 use crate::assert;
+use crate::syntax::AstModule;
+use crate::syntax::Dialect;
 
 macro_rules! testcases_parse {
     ($($x:expr)*) => {
@@ -215,3 +217,20 @@ fn parsing_testcases() {
         assert::parse(content);
     }
 }
+
+#[test]
+fn formatting_testcases() {
+    for (name, content) in TESTCASE_FILES {
+        let ast = AstModule::parse(name, (*content).to_owned(), &Dialect::Extended).unwrap();
+        let formatted = ast.format();
+        let reparsed = AstModule::parse(name, formatted.clone(), &Dialect::Extended).unwrap();
+        // Formatting doesn't change the AST, and is idempotent.
+        assert_eq!(
+            ast.statement.to_string(),
+            reparsed.statement.to_string(),
+            "{}",
+            name
+        );
+        assert_eq!(formatted, reparsed.format(), "{}", name);
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Recovering the parts of the source the AST doesn't record, namely comments.
+//!
+//! We lex the source again: anything between two tokens is whitespace, a line continuation
+//! or a comment.
+
+use dupe::Dupe;
+
+use crate::syntax::ast::AstModule;
+use crate::syntax::lexer::Lexer;
+use crate::syntax::lexer::Token;
+
+/// A comment, from the `#` to the end of its line (excluding the newline).
+#[derive(Clone, Copy, Dupe, Debug)]
+pub(crate) struct Comment {
+    pub(crate) begin: usize,
+    pub(crate) end: usize,
+    /// Whether there is code before the comment on its line.
+    pub(crate) trailing: bool,
+}
+
+/// The comments of a module, along with where its tokens start.
+pub(crate) struct Trivia {
+    /// All the comments, in order.
+    pub(crate) comments: Vec<Comment>,
+    /// Start of every token, in order, ignoring the synthetic indentation and newline tokens.
+    pub(crate) tokens: Vec<usize>,
+}
+
+/// Find the comments in `source[from..to]`, which lies between two tokens.
+fn comments_between(source: &str, from: usize, to: usize, res: &mut Vec<Comment>) {
+    let mut pos = from;
+    while let Some(offset) = source[pos..to].find('#') {
+        let begin = pos + offset;
+        let end = source[begin..to].find('\n').map_or(to, |x| begin + x);
+        let line_start = source[..begin].rfind('\n').map_or(0, |x| x + 1);
+        res.push(Comment {
+            begin,
+            end,
+            trailing: !source[line_start..begin].trim().is_empty(),
+        });
+        pos = end;
+    }
+}
+
+impl AstModule {
+    pub(crate) fn trivia(&self) -> Trivia {
+        let source = self.codemap.source();
+        let mut comments = Vec::new();
+        let mut tokens = Vec::new();
+        let mut prev = 0;
+        for lexeme in Lexer::new(source, &self.dialect, self.codemap.dupe()) {
+            // The module was parsed from this source, so the lexer can't fail.
+            let (begin, token, end) = match lexeme {
+                Ok(x) => x,
+                Err(_) => break,
+            };
+            if matches!(token, Token::Newline | Token::Indent | Token::Dedent) {
+                continue;
+            }
+            comments_between(source, prev, begin, &mut comments);
+            tokens.push(begin);
+            prev = end;
+        }
+        comments_between(source, prev, source.len(), &mut comments);
+        Trivia { comments, tokens }
+    }
+}
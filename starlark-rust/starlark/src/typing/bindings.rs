@@ -16,6 +16,7 @@
  */
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use dupe::Dupe;
@@ -97,6 +98,17 @@ impl Interface {
     pub fn get(&self, name: &str) -> Option<&Ty> {
         self.0.get(name)
     }
+
+    /// Only keep the bindings for the given names.
+    pub(crate) fn retain(&self, names: &HashSet<String>) -> Self {
+        Self::new(
+            self.0
+                .iter()
+                .filter(|(name, _)| names.contains(name.as_str()))
+                .map(|(name, ty)| (name.clone(), ty.clone()))
+                .collect(),
+        )
+    }
 }
 
 pub type Loads = HashMap<String, Interface>;
@@ -79,6 +79,46 @@ pub(crate) enum TypingError {
     TooManyPositionalArguments { loc: ResolvedFileSpan },
 }
 
+impl TypingError {
+    /// Where the error occurred.
+    pub(crate) fn loc(&self) -> &ResolvedFileSpan {
+        match self {
+            Self::AttributeNotAvailable { loc, .. }
+            | Self::UnknownBuiltin { loc, .. }
+            | Self::InvalidBuiltinCall { loc, .. }
+            | Self::IncompatibleType { loc, .. }
+            | Self::CallToNonCallable { loc, .. }
+            | Self::MissingRequiredParameter { loc, .. }
+            | Self::UnexpectedNamedArgument { loc, .. }
+            | Self::TooManyPositionalArguments { loc } => loc,
+        }
+    }
+
+    /// kebab-case constant describing this error, e.g. `incompatible-type`.
+    pub(crate) fn short_name(&self) -> &'static str {
+        match self {
+            Self::AttributeNotAvailable { .. } => "attribute-not-available",
+            Self::UnknownBuiltin { .. } => "unknown-builtin",
+            Self::InvalidBuiltinCall { .. } => "invalid-builtin-call",
+            Self::IncompatibleType { .. } => "incompatible-type",
+            Self::CallToNonCallable { .. } => "call-to-non-callable",
+            Self::MissingRequiredParameter { .. } => "missing-required-parameter",
+            Self::UnexpectedNamedArgument { .. } => "unexpected-named-argument",
+            Self::TooManyPositionalArguments { .. } => "too-many-positional-arguments",
+        }
+    }
+
+    /// The error message, without the location.
+    pub(crate) fn message(&self) -> String {
+        let message = self.to_string();
+        let location = format!(", at {}", self.loc());
+        match message.strip_suffix(&location) {
+            Some(x) => x.to_owned(),
+            None => message,
+        }
+    }
+}
+
 pub(crate) struct TypingContext<'a> {
     pub(crate) codemap: CodeMap,
     pub(crate) oracle: &'a dyn TypingOracle,
@@ -26,6 +26,7 @@
 pub(crate) mod bindings;
 pub(crate) mod ctx;
 pub(crate) mod oracle;
+pub(crate) mod program;
 pub(crate) mod ty;
 pub(crate) mod typecheck;
 
@@ -38,6 +39,8 @@ pub use oracle::standard::OracleStandard;
 pub use oracle::traits::OracleNoBuiltins;
 pub use oracle::traits::OracleNone;
 pub use oracle::traits::TypingOracle;
+pub use program::typecheck_modules;
+pub use program::ModuleTypecheck;
 pub use ty::Approximation;
 pub use ty::Arg;
 pub use ty::Param;
@@ -0,0 +1,124 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use dupe::Dupe;
+
+use crate::collections::SmallMap;
+use crate::syntax::AstModule;
+use crate::typing::bindings::Interface;
+use crate::typing::oracle::traits::TypingOracle;
+use crate::typing::ty::Approximation;
+use crate::typing::typecheck::TypeMap;
+
+/// The result of typechecking one module with [`typecheck_modules`].
+#[derive(Debug)]
+pub struct ModuleTypecheck {
+    /// Type errors found in this module.
+    pub errors: Vec<anyhow::Error>,
+    /// The inferred types of the bindings in this module.
+    pub typemap: TypeMap,
+    /// The types of the symbols this module exports.
+    pub interface: Interface,
+    /// Places where the typechecker had to approximate.
+    pub approximations: Vec<Approximation>,
+}
+
+struct ProgramChecker<'a, F> {
+    oracle: &'a dyn TypingOracle,
+    resolve_load: F,
+    /// Modules we have not started checking yet.
+    pending: HashMap<String, AstModule>,
+    done: HashMap<String, ModuleTypecheck>,
+}
+
+impl<'a, F: Fn(&str, &str) -> Option<String>> ProgramChecker<'a, F> {
+    fn check(&mut self, name: &str) {
+        // If the module isn't pending it is either done, unknown, or part of a load cycle
+        // we are in the middle of checking. In all cases there is nothing to do.
+        let ast = match self.pending.remove(name) {
+            None => return,
+            Some(ast) => ast,
+        };
+
+        let mut loads = HashMap::new();
+        for load in ast.loads() {
+            if let Some(dep) = (self.resolve_load)(name, load.module_id) {
+                self.check(&dep);
+                // Missing if the load is part of a cycle, in which case we know nothing about it.
+                if let Some(res) = self.done.get(&dep) {
+                    loads.insert(load.module_id.to_owned(), res.interface.dupe());
+                }
+            }
+        }
+
+        let exported: HashSet<String> = ast
+            .exported_symbols()
+            .into_iter()
+            .map(|(_, name)| name.to_owned())
+            .collect();
+        let (errors, typemap, interface, approximations) = ast.typecheck(self.oracle, &loads);
+        self.done.insert(
+            name.to_owned(),
+            ModuleTypecheck {
+                errors,
+                typemap,
+                interface: interface.retain(&exported),
+                approximations,
+            },
+        );
+    }
+}
+
+/// Typecheck a set of modules together, so that the types of the symbols a module exports
+/// (see [`exported_symbols`](AstModule::exported_symbols)) are known in the modules which `load()` it.
+///
+/// Modules are identified by their filename. The `resolve_load` function is given the filename of
+/// a module and the string it passes to `load()`, and returns the filename of the loaded module,
+/// or [`None`] if it isn't available, in which case the loaded symbols are untyped.
+/// Modules are checked in dependency order. A load cycle is broken by treating the module
+/// which closes the cycle as unavailable.
+///
+/// The results are in the same order as the modules that were passed in.
+pub fn typecheck_modules(
+    oracle: &dyn TypingOracle,
+    modules: Vec<AstModule>,
+    resolve_load: impl Fn(&str, &str) -> Option<String>,
+) -> SmallMap<String, ModuleTypecheck> {
+    let names: Vec<String> = modules
+        .iter()
+        .map(|x| x.codemap.filename().to_owned())
+        .collect();
+    let mut checker = ProgramChecker {
+        oracle,
+        resolve_load,
+        pending: names.iter().cloned().zip(modules).collect(),
+        done: HashMap::new(),
+    };
+    for name in &names {
+        checker.check(name);
+    }
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let res = checker.done.remove(&name)?;
+            Some((name, res))
+        })
+        .collect()
+}
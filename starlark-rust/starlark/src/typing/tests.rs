@@ -16,12 +16,16 @@
  */
 
 use std::collections::HashMap;
+use std::path::Path;
 
 use once_cell::sync::Lazy;
 
+use crate::errors::EvalMessage;
+use crate::errors::EvalSeverity;
 use crate::stdlib::LibraryExtension;
 use crate::syntax::AstModule;
 use crate::syntax::Dialect;
+use crate::typing::typecheck_modules;
 use crate::typing::Approximation;
 use crate::typing::Interface;
 use crate::typing::OracleNoBuiltins;
@@ -98,6 +102,15 @@ hash(1)
         format!("{:#}", errs[0]),
         r#"Expected type `"string"` but got `"int"`, at filename:2:1-8"#
     );
+
+    let message = EvalMessage::from_anyhow(Path::new("filename"), &errs[0]);
+    assert!(matches!(message.severity, EvalSeverity::Warning));
+    assert_eq!(message.name, "incompatible-type");
+    assert_eq!(
+        message.description,
+        r#"Expected type `"string"` but got `"int"`"#
+    );
+    assert_eq!(message.span.unwrap().to_string(), "2:1-8");
 }
 
 #[test]
@@ -124,6 +137,76 @@ res = [foo([])]
     assert_eq!(interface.get("res").unwrap(), &Ty::list(Ty::string()));
 }
 
+#[test]
+fn test_typecheck_modules() {
+    let module = |name: &str, code: &str| {
+        AstModule::parse(name, code.to_owned(), &Dialect::Extended).unwrap()
+    };
+    let res = typecheck_modules(
+        &mk_oracle(),
+        vec![
+            module(
+                "main.bzl",
+                r#"
+load("helpers.bzl", "greet", "config", "_private")
+greet(1)
+count = config.count
+_private("x")
+"#,
+            ),
+            module(
+                "helpers.bzl",
+                r#"
+def greet(name: str.type) -> str.type:
+    return "Hello " + name
+config = struct(count = 1)
+def _private(x: int.type):
+    pass
+"#,
+            ),
+        ],
+        |_, load| Some(load.to_owned()),
+    );
+    assert_eq!(
+        res.keys().map(|x| x.as_str()).collect::<Vec<_>>(),
+        &["main.bzl", "helpers.bzl"]
+    );
+
+    let helpers = res.get("helpers.bzl").unwrap();
+    assert!(helpers.errors.is_empty());
+    assert!(helpers.interface.get("greet").is_some());
+    assert!(helpers.interface.get("_private").is_none());
+
+    let main = res.get("main.bzl").unwrap();
+    assert_eq!(main.errors.len(), 1);
+    assert_eq!(
+        format!("{:#}", main.errors[0]),
+        r#"Expected type `"string"` but got `"int"`, at main.bzl:3:1-9"#
+    );
+    assert_eq!(main.interface.get("count").unwrap(), &Ty::int());
+}
+
+#[test]
+fn test_typecheck_modules_cycle() {
+    let module = |name: &str, code: &str| {
+        AstModule::parse(name, code.to_owned(), &Dialect::Extended).unwrap()
+    };
+    let res = typecheck_modules(
+        &mk_oracle(),
+        vec![
+            module("a.bzl", "load(\"b.bzl\", \"b\")\na = 1\n"),
+            module("b.bzl", "load(\"a.bzl\", \"a\")\nb = a\n"),
+        ],
+        |_, load| Some(load.to_owned()),
+    );
+    assert!(res.values().all(|x| x.errors.is_empty()));
+    // `a.bzl` is checked first, so when `b.bzl` loads it we know nothing about it.
+    assert_eq!(
+        res.get("b.bzl").unwrap().interface.get("b").unwrap(),
+        &Ty::Any
+    );
+}
+
 /// Test things that have previous claimed incorrectly they were type errors
 #[test]
 fn test_false_negative() {
@@ -514,6 +514,18 @@ impl Ty {
                     Ok(Ty::unions(rs))
                 }
             }
+            Ty::Struct { fields, extra } => {
+                if let Some(field) = fields.get(attr) {
+                    return Ok(field.clone());
+                }
+                match ctx.oracle.attribute(self, attr) {
+                    Some(Ok(r)) => Ok(r),
+                    // The field might be one of those we don't know about
+                    _ if *extra => Ok(Ty::Any),
+                    Some(Err(())) => Err(()),
+                    None => Ok(ctx.approximation("oracle.attribute", format!("{}.{}", self, attr))),
+                }
+            }
             _ => match ctx.oracle.attribute(self, attr) {
                 Some(r) => r,
                 None => Ok(ctx.approximation("oracle.attribute", format!("{}.{}", self, attr))),
@@ -32,6 +32,7 @@ use either::Either;
 use starlark_map::small_map::SmallMap;
 
 use crate::eval::runtime::profile::data::ProfileDataImpl;
+use crate::eval::runtime::profile::flamegraph::qualified_function_name;
 use crate::eval::runtime::profile::flamegraph::FlameGraphData;
 use crate::eval::runtime::profile::flamegraph::FlameGraphNode;
 use crate::eval::runtime::profile::heap::RetainedHeapProfileMode;
@@ -62,7 +63,7 @@ impl FunctionIds {
         match self.values.entry(x.ptr_value()) {
             hash_map::Entry::Occupied(v) => *v.get(),
             hash_map::Entry::Vacant(outer) => {
-                let function_id = self.strings.index(&x.to_str());
+                let function_id = self.strings.index(&qualified_function_name(x, x.to_str()));
                 outer.insert(function_id);
                 function_id
             }
@@ -390,14 +391,18 @@ impl AggregateHeapProfileInfo {
         }
     }
 
-    /// Write this out recursively to a file.
-    pub fn gen_flame_graph(&self) -> String {
+    pub(crate) fn flame_graph_data(&self) -> FlameGraphData {
         let mut data = FlameGraphData::default();
         self.root().write_flame_graph(data.root());
         data.root()
             .child(ArcStr::new_static("unused_capacity"))
             .add(self.unused_capacity.get() as u64);
-        data.write()
+        data
+    }
+
+    /// Write this out recursively to a file.
+    pub fn gen_flame_graph(&self) -> String {
+        self.flame_graph_data().write()
     }
 
     /// Write per-function summary in CSV format.
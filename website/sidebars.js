@@ -155,6 +155,7 @@ const manualSidebar = [
       isInternal() ? 'developers/heap_profiling' : [],
       'developers/parity_script',
       'developers/what-ran',
+      'developers/audit_json',
       {
         type: 'category',
         label: 'Starlark Language',